@@ -262,8 +262,8 @@ impl<R: BufRead> TsvSolutionsReader<R> {
 mod tests {
     use super::*;
     use std::io::Cursor;
-    use std::rc::Rc;
     use std::str;
+    use std::sync::Arc;
 
     fn build_example() -> (Vec<Variable>, Vec<Vec<Option<Term>>>) {
         (
@@ -312,7 +312,7 @@ mod tests {
     fn test_csv_serialization() -> io::Result<()> {
         let (variables, solutions) = build_example();
         let mut writer = CsvSolutionsWriter::start(Vec::new(), variables.clone())?;
-        let variables = Rc::new(variables);
+        let variables = Arc::new(variables);
         for solution in solutions {
             writer.write(
                 variables
@@ -330,7 +330,7 @@ mod tests {
     fn test_tsv_serialization() -> io::Result<()> {
         let (variables, solutions) = build_example();
         let mut writer = TsvSolutionsWriter::start(Vec::new(), variables.clone())?;
-        let variables = Rc::new(variables);
+        let variables = Arc::new(variables);
         for solution in solutions {
             writer.write(
                 variables
@@ -17,7 +17,7 @@ pub use crate::solution::QuerySolution;
 use crate::xml::*;
 use oxrdf::{TermRef, Variable, VariableRef};
 use std::io::{self, BufRead, Write};
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// [SPARQL query](https://www.w3.org/TR/sparql11-query/) results serialization formats.
 #[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
@@ -198,7 +198,7 @@ impl QueryResultsParser {
                     solutions,
                     variables,
                 } => QueryResultsReader::Solutions(SolutionsReader {
-                    variables: Rc::new(variables),
+                    variables: Arc::new(variables),
                     solutions: SolutionsReaderKind::Xml(solutions),
                 }),
             },
@@ -208,7 +208,7 @@ impl QueryResultsParser {
                     solutions,
                     variables,
                 } => QueryResultsReader::Solutions(SolutionsReader {
-                    variables: Rc::new(variables),
+                    variables: Arc::new(variables),
                     solutions: SolutionsReaderKind::Json(solutions),
                 }),
             },
@@ -219,7 +219,7 @@ impl QueryResultsParser {
                     solutions,
                     variables,
                 } => QueryResultsReader::Solutions(SolutionsReader {
-                    variables: Rc::new(variables),
+                    variables: Arc::new(variables),
                     solutions: SolutionsReaderKind::Tsv(solutions),
                 }),
             },
@@ -276,7 +276,7 @@ pub enum QueryResultsReader<R: BufRead> {
 /// # Result::<(),sparesults::ParseError>::Ok(())
 /// ```
 pub struct SolutionsReader<R: BufRead> {
-    variables: Rc<Vec<Variable>>,
+    variables: Arc<Vec<Variable>>,
     solutions: SolutionsReaderKind<R>,
 }
 
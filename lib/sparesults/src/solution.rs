@@ -3,7 +3,7 @@
 use oxrdf::{Term, Variable, VariableRef};
 use std::iter::Zip;
 use std::ops::Index;
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// Tuple associating variables and terms that are the result of a SPARQL query.
 ///
@@ -18,7 +18,7 @@ use std::rc::Rc;
 /// assert_eq!(solution.get(1), None); // Get the value of the second column if it exists (here no).
 /// ```
 pub struct QuerySolution {
-    variables: Rc<Vec<Variable>>,
+    variables: Arc<Vec<Variable>>,
     values: Vec<Option<Term>>,
 }
 
@@ -116,7 +116,7 @@ impl QuerySolution {
     }
 }
 
-impl<V: Into<Rc<Vec<Variable>>>, S: Into<Vec<Option<Term>>>> From<(V, S)> for QuerySolution {
+impl<V: Into<Arc<Vec<Variable>>>, S: Into<Vec<Option<Term>>>> From<(V, S)> for QuerySolution {
     #[inline]
     fn from((v, s): (V, S)) -> Self {
         QuerySolution {
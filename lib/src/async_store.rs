@@ -0,0 +1,382 @@
+//! An asynchronous API for [`Store`] based on [Tokio](https://tokio.rs/).
+
+use crate::io::{DatasetFormat, GraphFormat};
+use crate::model::{GraphName, Triple, Variable};
+use crate::sparql::{EvaluationError, Query, QueryOptions, QueryResults, QuerySolution, Update};
+use crate::store::{LoaderError, SerializerError, StorageError, Store};
+use futures_core::Stream;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::panic::resume_unwind;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{spawn_blocking, JoinHandle};
+
+/// Size of the chunks of data exchanged between the async API and the blocking store.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Number of chunks or results buffered between the blocking store and the async API.
+const CHANNEL_BUFFER: usize = 16;
+
+/// An asynchronous wrapper around a [`Store`] for [Tokio](https://tokio.rs/) based applications.
+///
+/// The blocking store operations are executed on Tokio blocking thread pool.
+/// The parsers and serializers read from [`AsyncRead`] and write to [`AsyncWrite`]
+/// and query results are returned as [`Stream`]s.
+///
+/// It requires the `async` feature and should be used from inside of a Tokio runtime.
+///
+/// Usage example:
+/// ```
+/// use futures::StreamExt;
+/// use oxigraph::model::*;
+/// use oxigraph::store::{AsyncQueryResults, AsyncStore, Store};
+///
+/// # tokio::runtime::Runtime::new()?.block_on(async {
+/// let store = AsyncStore::from(Store::new()?);
+///
+/// // insertion
+/// let file = b"<http://example.com> <http://example.com> <http://example.com> .";
+/// store.load_graph(file.as_ref(), GraphFormat::NTriples, GraphName::DefaultGraph, None).await?;
+///
+/// // SPARQL query
+/// if let AsyncQueryResults::Solutions(mut solutions) = store.query("SELECT ?s WHERE { ?s ?p ?o }").await? {
+///     assert_eq!(solutions.next().await.unwrap()?.get("s"), Some(&NamedNode::new("http://example.com")?.into()));
+/// }
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// # })?;
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[derive(Clone)]
+pub struct AsyncStore {
+    store: Store,
+}
+
+impl AsyncStore {
+    /// Opens a [`Store`] and creates it if it does not exist yet.
+    ///
+    /// See [`Store::open`].
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref().to_owned();
+        Ok(join(spawn_blocking(move || Store::open(path)))
+            .await??
+            .into())
+    }
+
+    /// Returns the underlying blocking store.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Executes a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/).
+    ///
+    /// The query is evaluated lazily on the blocking thread pool while the returned results are consumed.
+    ///
+    /// See [`Store::query`].
+    pub async fn query(
+        &self,
+        query: impl TryInto<Query, Error = impl Into<EvaluationError>>,
+    ) -> Result<AsyncQueryResults, EvaluationError> {
+        self.query_opt(query, QueryOptions::default()).await
+    }
+
+    /// Executes a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) with some options.
+    ///
+    /// See [`Store::query_opt`].
+    pub async fn query_opt(
+        &self,
+        query: impl TryInto<Query, Error = impl Into<EvaluationError>>,
+        options: QueryOptions,
+    ) -> Result<AsyncQueryResults, EvaluationError> {
+        let query = query.try_into().map_err(Into::into)?;
+        let store = self.store.clone();
+        let (results_sender, results_receiver) = oneshot::channel();
+        spawn_blocking(move || {
+            let results = match store.query_opt(query, options) {
+                Ok(results) => results,
+                Err(e) => {
+                    results_sender.send(Err(e)).ok();
+                    return;
+                }
+            };
+            match results {
+                QueryResults::Boolean(value) => {
+                    results_sender
+                        .send(Ok(AsyncQueryResults::Boolean(value)))
+                        .ok();
+                }
+                QueryResults::Solutions(solutions) => {
+                    let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER);
+                    let stream = QuerySolutionStream {
+                        variables: solutions.variables().to_vec(),
+                        receiver,
+                    };
+                    if results_sender
+                        .send(Ok(AsyncQueryResults::Solutions(stream)))
+                        .is_err()
+                    {
+                        return;
+                    }
+                    for solution in solutions {
+                        if sender.blocking_send(solution).is_err() {
+                            return; // The stream has been dropped
+                        }
+                    }
+                }
+                QueryResults::Graph(triples) => {
+                    let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER);
+                    if results_sender
+                        .send(Ok(AsyncQueryResults::Graph(QueryTripleStream { receiver })))
+                        .is_err()
+                    {
+                        return;
+                    }
+                    for triple in triples {
+                        if sender.blocking_send(triple).is_err() {
+                            return; // The stream has been dropped
+                        }
+                    }
+                }
+            }
+        });
+        results_receiver.await.map_err(|_| {
+            EvaluationError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "The query evaluation has been interrupted",
+            ))
+        })?
+    }
+
+    /// Executes a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/).
+    ///
+    /// See [`Store::update`].
+    pub async fn update(
+        &self,
+        update: impl TryInto<Update, Error = impl Into<EvaluationError>>,
+    ) -> Result<(), EvaluationError> {
+        let update = update.try_into().map_err(Into::into)?;
+        let store = self.store.clone();
+        join(spawn_blocking(move || store.update(update))).await?
+    }
+
+    /// Loads a graph file (i.e. triples) into the store.
+    ///
+    /// See [`Store::load_graph`].
+    pub async fn load_graph(
+        &self,
+        reader: impl AsyncRead + Unpin,
+        format: GraphFormat,
+        to_graph_name: impl Into<GraphName>,
+        base_iri: Option<&str>,
+    ) -> Result<(), LoaderError> {
+        let store = self.store.clone();
+        let to_graph_name = to_graph_name.into();
+        let base_iri = base_iri.map(ToOwned::to_owned);
+        load_from_async_read(reader, move |reader| {
+            store.load_graph(reader, format, &to_graph_name, base_iri.as_deref())
+        })
+        .await
+    }
+
+    /// Loads a dataset file (i.e. quads) into the store.
+    ///
+    /// See [`Store::load_dataset`].
+    pub async fn load_dataset(
+        &self,
+        reader: impl AsyncRead + Unpin,
+        format: DatasetFormat,
+        base_iri: Option<&str>,
+    ) -> Result<(), LoaderError> {
+        let store = self.store.clone();
+        let base_iri = base_iri.map(ToOwned::to_owned);
+        load_from_async_read(reader, move |reader| {
+            store.load_dataset(reader, format, base_iri.as_deref())
+        })
+        .await
+    }
+
+    /// Dumps a store graph into a file.
+    ///
+    /// See [`Store::dump_graph`].
+    pub async fn dump_graph(
+        &self,
+        writer: impl AsyncWrite + Unpin,
+        format: GraphFormat,
+        from_graph_name: impl Into<GraphName>,
+    ) -> Result<(), SerializerError> {
+        let store = self.store.clone();
+        let from_graph_name = from_graph_name.into();
+        dump_to_async_write(writer, move |writer| {
+            store.dump_graph(writer, format, &from_graph_name)
+        })
+        .await
+    }
+
+    /// Dumps the store into a file.
+    ///
+    /// See [`Store::dump_dataset`].
+    pub async fn dump_dataset(
+        &self,
+        writer: impl AsyncWrite + Unpin,
+        format: DatasetFormat,
+    ) -> Result<(), SerializerError> {
+        let store = self.store.clone();
+        dump_to_async_write(writer, move |writer| store.dump_dataset(writer, format)).await
+    }
+}
+
+impl From<Store> for AsyncStore {
+    fn from(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+/// Results of a [SPARQL query](https://www.w3.org/TR/sparql11-query/) evaluated using [`AsyncStore::query`].
+pub enum AsyncQueryResults {
+    /// Results of a [SELECT](https://www.w3.org/TR/sparql11-query/#select) query.
+    Solutions(QuerySolutionStream),
+    /// Result of a [ASK](https://www.w3.org/TR/sparql11-query/#ask) query.
+    Boolean(bool),
+    /// Results of a [CONSTRUCT](https://www.w3.org/TR/sparql11-query/#construct) or [DESCRIBE](https://www.w3.org/TR/sparql11-query/#describe) query.
+    Graph(QueryTripleStream),
+}
+
+/// A [`Stream`] over [`QuerySolution`]s.
+pub struct QuerySolutionStream {
+    variables: Vec<Variable>,
+    receiver: mpsc::Receiver<Result<QuerySolution, EvaluationError>>,
+}
+
+impl QuerySolutionStream {
+    /// The variables used in the solutions.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+}
+
+impl Stream for QuerySolutionStream {
+    type Item = Result<QuerySolution, EvaluationError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// A [`Stream`] over the triples returned by a CONSTRUCT or DESCRIBE query.
+pub struct QueryTripleStream {
+    receiver: mpsc::Receiver<Result<Triple, EvaluationError>>,
+}
+
+impl Stream for QueryTripleStream {
+    type Item = Result<Triple, EvaluationError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Waits for a blocking task and propagates its panics.
+async fn join<T>(task: JoinHandle<T>) -> io::Result<T> {
+    task.await.map_err(|e| {
+        if e.is_panic() {
+            resume_unwind(e.into_panic())
+        } else {
+            io::Error::new(io::ErrorKind::Interrupted, e)
+        }
+    })
+}
+
+/// Streams the content of `reader` to the `load` function running on the blocking thread pool.
+async fn load_from_async_read(
+    mut reader: impl AsyncRead + Unpin,
+    load: impl FnOnce(BufReader<ChannelReader>) -> Result<(), LoaderError> + Send + 'static,
+) -> Result<(), LoaderError> {
+    let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER);
+    let task = spawn_blocking(move || {
+        load(BufReader::new(ChannelReader {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }))
+    });
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let chunk = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(size) => Ok(buffer[..size].to_vec()),
+            Err(e) => Err(e),
+        };
+        let is_error = chunk.is_err();
+        if sender.send(chunk).await.is_err() || is_error {
+            break; // The loader has stopped or is going to fail
+        }
+    }
+    drop(sender);
+    join(task).await.map_err(StorageError::from)?
+}
+
+/// Streams the output of the `dump` function running on the blocking thread pool to `writer`.
+async fn dump_to_async_write(
+    mut writer: impl AsyncWrite + Unpin,
+    dump: impl FnOnce(&mut BufWriter<ChannelWriter>) -> Result<(), SerializerError> + Send + 'static,
+) -> Result<(), SerializerError> {
+    let (sender, mut receiver) = mpsc::channel(CHANNEL_BUFFER);
+    let task = spawn_blocking(move || -> Result<(), SerializerError> {
+        let mut writer = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter { sender });
+        dump(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    });
+    while let Some(chunk) = receiver.recv().await {
+        writer.write_all(&chunk).await?;
+    }
+    writer.flush().await?;
+    join(task).await?
+}
+
+/// A blocking reader of the chunks sent by the async API.
+struct ChannelReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let size = buf.len().min(self.chunk.len() - self.position);
+        buf[..size].copy_from_slice(&self.chunk[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+}
+
+/// A blocking writer sending its output to the async API.
+struct ChannelWriter {
+    sender: mpsc::Sender<Vec<u8>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender.blocking_send(buf.to_vec()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The output of the dump has been closed",
+            )
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -3,13 +3,46 @@
 pub use crate::io::error::{ParseError, SyntaxError};
 use crate::io::{DatasetFormat, GraphFormat};
 use crate::model::*;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sparql::parallel::{TaskHandle, WorkerPool};
+#[cfg(not(target_arch = "wasm32"))]
+use flate2::bufread::MultiGzDecoder;
 use oxiri::{Iri, IriParseError};
+#[cfg(not(target_arch = "wasm32"))]
+use rand::random;
 use rio_api::model as rio;
 use rio_api::parser::{QuadsParser, TriplesParser};
 use rio_turtle::{NQuadsParser, NTriplesParser, TriGParser, TurtleParser};
 use rio_xml::RdfXmlParser;
+use siphasher::sip128::{Hasher128, SipHasher24};
 use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::VecDeque;
+use std::hash::Hasher;
 use std::io::BufRead;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufReader, Read};
+#[cfg(not(target_arch = "wasm32"))]
+use std::mem::{replace, take};
+#[cfg(not(target_arch = "wasm32"))]
+use std::panic::resume_unwind;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io, vec};
+
+/// Approximate size of the chunks parsed in parallel.
+#[cfg(not(target_arch = "wasm32"))]
+const PARALLEL_PARSING_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+/// Approximate size of the compressed chunks decompressed and parsed in parallel.
+#[cfg(not(target_arch = "wasm32"))]
+const PARALLEL_DECOMPRESSION_CHUNK_SIZE: usize = 1024 * 1024;
+/// The two first bytes of a gzip member.
+#[cfg(not(target_arch = "wasm32"))]
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+/// The size of the gzip member header up to the length of its extra field.
+#[cfg(not(target_arch = "wasm32"))]
+const GZIP_HEADER_SIZE: usize = 12;
 
 /// Parsers for RDF graph serialization formats.
 ///
@@ -34,6 +67,7 @@ use std::io::BufRead;
 pub struct GraphParser {
     format: GraphFormat,
     base_iri: Option<Iri<String>>,
+    bnode_key: Option<(u64, u64)>,
 }
 
 impl GraphParser {
@@ -43,6 +77,7 @@ impl GraphParser {
         Self {
             format,
             base_iri: None,
+            bnode_key: None,
         }
     }
 
@@ -67,11 +102,22 @@ impl GraphParser {
         Ok(self)
     }
 
+    /// Derives the blank node identifiers from their labels using the given key
+    /// instead of generating new random identifiers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn with_blank_node_key(mut self, key: (u64, u64)) -> Self {
+        self.bnode_key = Some(key);
+        self
+    }
+
     /// Executes the parsing itself on a [`BufRead`](std::io::BufRead) implementation and returns an iterator of triples.
     #[allow(clippy::unnecessary_wraps)]
     pub fn read_triples<R: BufRead>(&self, reader: R) -> Result<TripleReader<R>, ParseError> {
         Ok(TripleReader {
-            mapper: RioMapper::default(),
+            mapper: RioMapper {
+                bnode_map: HashMap::new(),
+                bnode_key: self.bnode_key,
+            },
             parser: match self.format {
                 GraphFormat::NTriples => TripleReaderKind::NTriples(NTriplesParser::new(reader)),
                 GraphFormat::Turtle => {
@@ -185,6 +231,7 @@ impl<R: BufRead> TripleReader<R> {
 pub struct DatasetParser {
     format: DatasetFormat,
     base_iri: Option<Iri<String>>,
+    bnode_key: Option<(u64, u64)>,
 }
 
 impl DatasetParser {
@@ -194,6 +241,7 @@ impl DatasetParser {
         Self {
             format,
             base_iri: None,
+            bnode_key: None,
         }
     }
 
@@ -218,11 +266,22 @@ impl DatasetParser {
         Ok(self)
     }
 
+    /// Derives the blank node identifiers from their labels using the given key
+    /// instead of generating new random identifiers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn with_blank_node_key(mut self, key: (u64, u64)) -> Self {
+        self.bnode_key = Some(key);
+        self
+    }
+
     /// Executes the parsing itself on a [`BufRead`](std::io::BufRead) implementation and returns an iterator of quads.
     #[allow(clippy::unnecessary_wraps)]
     pub fn read_quads<R: BufRead>(&self, reader: R) -> Result<QuadReader<R>, ParseError> {
         Ok(QuadReader {
-            mapper: RioMapper::default(),
+            mapper: RioMapper {
+                bnode_map: HashMap::new(),
+                bnode_key: self.bnode_key,
+            },
             parser: match self.format {
                 DatasetFormat::NQuads => QuadReaderKind::NQuads(NQuadsParser::new(reader)),
                 DatasetFormat::TriG => {
@@ -307,9 +366,347 @@ impl<R: BufRead> QuadReader<R> {
     }
 }
 
-#[derive(Default)]
+/// A line based format that could be parsed in parallel by [`ParallelQuadReader`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub(crate) enum LineFormat {
+    /// N-Triples with the graph the triples should be put in.
+    NTriples(GraphName),
+    NQuads,
+}
+
+/// An iterator yielding the quads of a line based file.
+///
+/// The file is split into chunks at line boundaries on the calling thread and the chunks are parsed in parallel
+/// by the threads of a [`WorkerPool`].
+/// The quads are returned in the file order.
+/// The positions in the syntax errors are relative to the beginning of the chunk.
+///
+/// Gzip compressed files are decompressed.
+/// If their members give their compressed size, like the ones written by bgzip, they are also decompressed in parallel.
+#[cfg(not(target_arch = "wasm32"))]
+#[must_use]
+pub(crate) struct ParallelQuadReader<R: BufRead> {
+    input: ParallelInput<R>,
+    format: LineFormat,
+    bnode_key: (u64, u64),
+    num_threads: usize,
+    workers: Arc<WorkerPool>,
+    chunks: VecDeque<PendingChunk>,
+    /// The end of the already parsed chunks that is not a complete line yet
+    incomplete_line: Vec<u8>,
+    current: vec::IntoIter<Result<Quad, ParseError>>,
+    error: Option<io::Error>,
+    is_end: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum ParallelInput<R: BufRead> {
+    Plain(R),
+    /// Gzip members decompressed on the calling thread
+    Gzip(BufReader<MultiGzDecoder<R>>),
+    /// Gzip members with their compressed size, decompressed by the workers
+    GzipBlocks(R),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<R: BufRead> ParallelQuadReader<R> {
+    /// If no `bnode_key` is given a random one is used.
+    pub fn new(
+        mut reader: R,
+        format: LineFormat,
+        num_threads: usize,
+        bnode_key: Option<(u64, u64)>,
+    ) -> Self {
+        let num_threads = num_threads.max(1);
+        let start = reader.fill_buf().map(|start| {
+            (
+                gzip_block_size(start).is_some(),
+                start.starts_with(&GZIP_MAGIC),
+            )
+        });
+        let (input, error) = match start {
+            Ok((true, _)) => (ParallelInput::GzipBlocks(reader), None),
+            Ok((false, true)) => (
+                ParallelInput::Gzip(BufReader::new(MultiGzDecoder::new(reader))),
+                None,
+            ),
+            Ok((false, false)) => (ParallelInput::Plain(reader), None),
+            Err(e) => (ParallelInput::Plain(reader), Some(e)),
+        };
+        Self {
+            input,
+            format,
+            bnode_key: bnode_key.unwrap_or_else(|| (random(), random())),
+            num_threads,
+            workers: Arc::new(WorkerPool::new(num_threads)),
+            chunks: VecDeque::new(),
+            incomplete_line: Vec::new(),
+            current: Vec::new().into_iter(),
+            is_end: error.is_some(),
+            error,
+        }
+    }
+
+    fn read_chunk(&mut self) -> io::Result<Option<RawChunk>> {
+        Ok(match &mut self.input {
+            ParallelInput::Plain(reader) => read_lines(reader)?.map(RawChunk::Lines),
+            ParallelInput::Gzip(reader) => read_lines(reader)?.map(RawChunk::Lines),
+            ParallelInput::GzipBlocks(reader) => {
+                let mut chunk = Vec::new();
+                while chunk.len() < PARALLEL_DECOMPRESSION_CHUNK_SIZE
+                    && read_gzip_block(reader, &mut chunk)?
+                {}
+                if chunk.is_empty() {
+                    None
+                } else {
+                    Some(RawChunk::GzipBlocks(chunk))
+                }
+            }
+        })
+    }
+
+    /// Parses the chunk using a worker or, if none is available, the current thread.
+    fn parse_chunk(&mut self, chunk: RawChunk) -> io::Result<()> {
+        let format = self.format.clone();
+        let bnode_key = self.bnode_key;
+        self.chunks
+            .push_back(if let Some(worker) = self.workers.reserve(1).pop() {
+                PendingChunk::Parsing(worker.spawn(move || chunk.parse(&format, bnode_key))?)
+            } else {
+                PendingChunk::Parsed(chunk.parse(&format, bnode_key))
+            });
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<R: BufRead> Iterator for ParallelQuadReader<R> {
+    type Item = Result<Quad, ParseError>;
+
+    fn next(&mut self) -> Option<Result<Quad, ParseError>> {
+        loop {
+            if let Some(r) = self.current.next() {
+                return Some(r);
+            }
+            while !self.is_end && self.chunks.len() < self.num_threads {
+                if let Err(e) = self.read_chunk().and_then(|chunk| match chunk {
+                    Some(chunk) => self.parse_chunk(chunk),
+                    None => {
+                        self.is_end = true;
+                        Ok(())
+                    }
+                }) {
+                    // The error is returned after the quads of the chunks already read
+                    self.error = Some(e);
+                    self.is_end = true;
+                }
+            }
+            if let Some(chunk) = self.chunks.pop_front() {
+                let chunk = match chunk {
+                    PendingChunk::Parsing(task) => {
+                        task.join().unwrap_or_else(|panic| resume_unwind(panic))
+                    }
+                    PendingChunk::Parsed(chunk) => chunk,
+                };
+                self.incomplete_line.extend_from_slice(&chunk.head);
+                if let Some(tail) = chunk.tail {
+                    // The line started in the previous chunks is now complete
+                    let line = replace(&mut self.incomplete_line, tail);
+                    let mut quads = parse_lines(&line, &self.format, self.bnode_key);
+                    quads.extend(chunk.quads);
+                    self.current = quads.into_iter();
+                }
+            } else if !self.incomplete_line.is_empty() {
+                let line = take(&mut self.incomplete_line);
+                self.current = parse_lines(&line, &self.format, self.bnode_key).into_iter();
+            } else {
+                return self.error.take().map(|e| Err(e.into()));
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum PendingChunk {
+    Parsing(TaskHandle<ParsedChunk>),
+    Parsed(ParsedChunk),
+}
+
+/// A chunk of the input of a [`ParallelQuadReader`].
+#[cfg(not(target_arch = "wasm32"))]
+enum RawChunk {
+    /// Complete lines
+    Lines(Vec<u8>),
+    /// Complete gzip members, their content might start and end in the middle of a line
+    GzipBlocks(Vec<u8>),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RawChunk {
+    fn parse(self, format: &LineFormat, bnode_key: (u64, u64)) -> ParsedChunk {
+        match self {
+            Self::Lines(lines) => ParsedChunk {
+                head: Vec::new(),
+                quads: parse_lines(&lines, format, bnode_key),
+                tail: Some(Vec::new()),
+            },
+            Self::GzipBlocks(blocks) => {
+                let mut content = Vec::new();
+                if let Err(e) = MultiGzDecoder::new(blocks.as_slice()).read_to_end(&mut content) {
+                    return ParsedChunk {
+                        head: Vec::new(),
+                        quads: vec![Err(e.into())],
+                        tail: Some(Vec::new()),
+                    };
+                }
+                let (first_end, last_end) = match (
+                    content.iter().position(|b| *b == b'\n'),
+                    content.iter().rposition(|b| *b == b'\n'),
+                ) {
+                    (Some(first_end), Some(last_end)) => (first_end, last_end),
+                    _ => {
+                        return ParsedChunk {
+                            head: content,
+                            quads: Vec::new(),
+                            tail: None,
+                        }
+                    }
+                };
+                ParsedChunk {
+                    head: content[..=first_end].to_vec(),
+                    quads: parse_lines(&content[first_end + 1..=last_end], format, bnode_key),
+                    tail: Some(content[last_end + 1..].to_vec()),
+                }
+            }
+        }
+    }
+}
+
+/// A chunk of the input of a [`ParallelQuadReader`] with its complete lines parsed.
+#[cfg(not(target_arch = "wasm32"))]
+struct ParsedChunk {
+    /// The end of the line started in the previous chunk
+    head: Vec<u8>,
+    quads: Vec<Result<Quad, ParseError>>,
+    /// The beginning of the line continued in the next chunk.
+    /// `None` if the chunk does not contain any line end i.e. if it is all in `head`.
+    tail: Option<Vec<u8>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_lines(
+    lines: &[u8],
+    format: &LineFormat,
+    bnode_key: (u64, u64),
+) -> Vec<Result<Quad, ParseError>> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let mapper = RioMapper {
+        bnode_map: HashMap::new(),
+        bnode_key: Some(bnode_key),
+    };
+    match format {
+        LineFormat::NTriples(graph_name) => TripleReader {
+            mapper,
+            parser: TripleReaderKind::NTriples(NTriplesParser::new(lines)),
+            buffer: Vec::new(),
+        }
+        .map(|t| t.map(|t| t.in_graph(graph_name.clone())))
+        .collect(),
+        LineFormat::NQuads => QuadReader {
+            mapper,
+            parser: QuadReaderKind::NQuads(NQuadsParser::new(lines)),
+            buffer: Vec::new(),
+        }
+        .collect(),
+    }
+}
+
+/// Reads a chunk of about [`PARALLEL_PARSING_CHUNK_SIZE`] bytes ending at the end of a line.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_lines(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut chunk = Vec::with_capacity(PARALLEL_PARSING_CHUNK_SIZE);
+    while chunk.len() < PARALLEL_PARSING_CHUNK_SIZE {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        let len = buffer.len().min(PARALLEL_PARSING_CHUNK_SIZE - chunk.len());
+        chunk.extend_from_slice(&buffer[..len]);
+        reader.consume(len);
+    }
+    if chunk.last() != Some(&b'\n') {
+        // We complete the current line
+        reader.read_until(b'\n', &mut chunk)?;
+    }
+    Ok(if chunk.is_empty() { None } else { Some(chunk) })
+}
+
+/// Appends the next gzip member to `chunk`. Returns `false` if the end of the input is reached.
+///
+/// The members must give their compressed size in the same way as [BGZF](https://samtools.github.io/hts-specs/SAMv1.pdf) does.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_gzip_block(reader: &mut impl BufRead, chunk: &mut Vec<u8>) -> io::Result<bool> {
+    if reader.fill_buf()?.is_empty() {
+        return Ok(false);
+    }
+    let start = chunk.len();
+    chunk.resize(start + GZIP_HEADER_SIZE, 0);
+    reader.read_exact(&mut chunk[start..])?;
+    let extra_len = usize::from(u16::from_le_bytes([chunk[start + 10], chunk[start + 11]]));
+    chunk.resize(start + GZIP_HEADER_SIZE + extra_len, 0);
+    reader.read_exact(&mut chunk[start + GZIP_HEADER_SIZE..])?;
+    let size = gzip_block_size(&chunk[start..])
+        .filter(|size| *size >= GZIP_HEADER_SIZE + extra_len)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The gzip member does not give its size like the previous ones",
+            )
+        })?;
+    let header_end = chunk.len();
+    chunk.resize(start + size, 0);
+    reader.read_exact(&mut chunk[header_end..])?;
+    Ok(true)
+}
+
+/// Returns the size of the gzip member starting with `header` if it is given in the BGZF `BC` extra field.
+#[cfg(not(target_arch = "wasm32"))]
+fn gzip_block_size(header: &[u8]) -> Option<usize> {
+    const FEXTRA: u8 = 0x04;
+    if !header.starts_with(&GZIP_MAGIC) || header.get(3)? & FEXTRA == 0 {
+        return None;
+    }
+    let extra_len = usize::from(u16::from_le_bytes([*header.get(10)?, *header.get(11)?]));
+    let mut extra = header.get(GZIP_HEADER_SIZE..GZIP_HEADER_SIZE + extra_len)?;
+    while extra.len() >= 4 {
+        let len = usize::from(u16::from_le_bytes([extra[2], extra[3]]));
+        if extra.starts_with(b"BC") && len == 2 {
+            return Some(usize::from(u16::from_le_bytes([*extra.get(4)?, *extra.get(5)?])) + 1);
+        }
+        extra = extra.get(4 + len..)?;
+    }
+    None
+}
+
+/// Derives from `seed` a key to map blank node labels to identifiers.
+///
+/// Parsing the same input with the same key always gives the same blank nodes.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn blank_node_key(seed: &str) -> (u64, u64) {
+    let mut hasher = SipHasher24::new();
+    hasher.write(seed.as_bytes());
+    let hash = hasher.finish128();
+    (hash.h1, hash.h2)
+}
+
 struct RioMapper {
     bnode_map: HashMap<String, BlankNode>,
+    /// If set, the blank node identifiers are derived from their labels using this key
+    /// so that the mappers used on different parts of the same file agree on them.
+    bnode_key: Option<(u64, u64)>,
 }
 
 impl<'a> RioMapper {
@@ -318,6 +715,11 @@ impl<'a> RioMapper {
     }
 
     fn blank_node(&mut self, node: rio::BlankNode<'a>) -> BlankNode {
+        if let Some((key0, key1)) = self.bnode_key {
+            let mut hasher = SipHasher24::new_with_keys(key0, key1);
+            hasher.write(node.id.as_bytes());
+            return BlankNode::new_from_unique_id(hasher.finish128().into());
+        }
         self.bnode_map
             .entry(node.id.to_owned())
             .or_insert_with(BlankNode::default)
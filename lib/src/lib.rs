@@ -131,6 +131,8 @@
     clippy::wrong_self_convention,
 )]
 
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod async_store;
 pub mod io;
 pub mod model;
 pub mod sparql;
@@ -3,15 +3,17 @@ use crate::sparql::algebra::QueryDataset;
 use crate::sparql::EvaluationError;
 use crate::storage::numeric_encoder::{insert_term, EncodedQuad, EncodedTerm, StrHash, StrLookup};
 use crate::storage::{StorageError, StorageReader};
-use std::cell::RefCell;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::iter::empty;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub struct DatasetView {
     reader: StorageReader,
-    extra: RefCell<HashMap<StrHash, String>>,
+    extra: Mutex<HashMap<StrHash, String>>,
     dataset: EncodedDatasetSpec,
+    cancellation_flag: Option<Arc<AtomicBool>>,
 }
 
 impl DatasetView {
@@ -26,21 +28,40 @@ impl DatasetView {
         };
         Self {
             reader,
-            extra: RefCell::new(HashMap::default()),
+            extra: Mutex::new(HashMap::default()),
             dataset,
+            cancellation_flag: None,
         }
     }
 
+    /// Makes the store scans fail with [`EvaluationError::Cancelled`] as soon as the flag is set.
+    ///
+    /// All the evaluation work is driven by these scans so it stops quickly.
+    #[must_use]
+    pub fn with_cancellation_flag(mut self, cancellation_flag: Option<Arc<AtomicBool>>) -> Self {
+        self.cancellation_flag = cancellation_flag;
+        self
+    }
+
     fn store_encoded_quads_for_pattern(
         &self,
         subject: Option<&EncodedTerm>,
         predicate: Option<&EncodedTerm>,
         object: Option<&EncodedTerm>,
         graph_name: Option<&EncodedTerm>,
-    ) -> impl Iterator<Item = Result<EncodedQuad, EvaluationError>> + 'static {
-        self.reader
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>> + Send> {
+        let iter = self
+            .reader
             .quads_for_pattern(subject, predicate, object, graph_name)
-            .map(|t| t.map_err(|e| e.into()))
+            .map(|t| t.map_err(|e| e.into()));
+        if let Some(cancellation_flag) = &self.cancellation_flag {
+            Box::new(CancellableIterator {
+                iter: Some(iter),
+                cancellation_flag: cancellation_flag.clone(),
+            })
+        } else {
+            Box::new(iter)
+        }
     }
 
     #[allow(clippy::needless_collect)]
@@ -50,7 +71,7 @@ impl DatasetView {
         predicate: Option<&EncodedTerm>,
         object: Option<&EncodedTerm>,
         graph_name: Option<&EncodedTerm>,
-    ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>>> {
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>> + Send> {
         if let Some(graph_name) = graph_name {
             if graph_name.is_default_graph() {
                 if let Some(default_graph_graphs) = &self.dataset.default {
@@ -148,6 +169,24 @@ impl DatasetView {
         }
     }
 
+    /// Same as [`encoded_quads_for_pattern`](Self::encoded_quads_for_pattern) but only returns the `index`-th of `count` disjoint partitions of the quads.
+    pub fn partitioned_encoded_quads_for_pattern(
+        &self,
+        subject: Option<&EncodedTerm>,
+        predicate: Option<&EncodedTerm>,
+        object: Option<&EncodedTerm>,
+        graph_name: Option<&EncodedTerm>,
+        index: usize,
+        count: usize,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>> + Send> {
+        Self {
+            reader: self.reader.partition(index, count),
+            extra: Mutex::default(),
+            dataset: self.dataset.clone(),
+        }
+        .encoded_quads_for_pattern(subject, predicate, object, graph_name)
+    }
+
     pub fn encode_term<'a>(&self, term: impl Into<TermRef<'a>>) -> EncodedTerm {
         let term = term.into();
         let encoded = term.into();
@@ -160,7 +199,7 @@ impl DatasetView {
     }
 
     pub fn insert_str(&self, key: &StrHash, value: &str) {
-        if let Entry::Vacant(e) = self.extra.borrow_mut().entry(*key) {
+        if let Entry::Vacant(e) = self.extra.lock().unwrap().entry(*key) {
             if !matches!(self.reader.contains_str(key), Ok(true)) {
                 e.insert(value.to_owned());
             }
@@ -170,15 +209,34 @@ impl DatasetView {
 
 impl StrLookup for DatasetView {
     fn get_str(&self, key: &StrHash) -> Result<Option<String>, StorageError> {
-        Ok(if let Some(value) = self.extra.borrow().get(key) {
-            Some(value.clone())
-        } else {
-            self.reader.get_str(key)?
-        })
+        if let Some(value) = self.extra.lock().unwrap().get(key) {
+            return Ok(Some(value.clone()));
+        }
+        self.reader.get_str(key)
     }
 
     fn contains_str(&self, key: &StrHash) -> Result<bool, StorageError> {
-        Ok(self.extra.borrow().contains_key(key) || self.reader.contains_str(key)?)
+        Ok(self.extra.lock().unwrap().contains_key(key) || self.reader.contains_str(key)?)
+    }
+}
+
+#[derive(Clone)]
+/// Returns a single [`EvaluationError::Cancelled`] error and stops as soon as the cancellation flag is set.
+struct CancellableIterator<I> {
+    iter: Option<I>,
+    cancellation_flag: Arc<AtomicBool>,
+}
+
+impl<I: Iterator<Item = Result<EncodedQuad, EvaluationError>>> Iterator for CancellableIterator<I> {
+    type Item = Result<EncodedQuad, EvaluationError>;
+
+    fn next(&mut self) -> Option<Result<EncodedQuad, EvaluationError>> {
+        let iter = self.iter.as_mut()?;
+        if self.cancellation_flag.load(Ordering::Relaxed) {
+            self.iter = None;
+            return Some(Err(EvaluationError::Cancelled));
+        }
+        iter.next()
     }
 }
 
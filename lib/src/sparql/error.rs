@@ -21,6 +21,8 @@ pub enum EvaluationError {
     Io(io::Error),
     /// An error returned during the query evaluation itself (not supported custom function...).
     Query(QueryError),
+    /// The evaluation has been cancelled using the flag given to [`QueryOptions::with_cancellation_flag`](super::QueryOptions::with_cancellation_flag).
+    Cancelled,
 }
 
 /// An error returned during the query evaluation itself (not supported custom function...).
@@ -45,6 +47,7 @@ impl fmt::Display for EvaluationError {
             Self::ResultsParsing(error) => error.fmt(f),
             Self::Io(error) => error.fmt(f),
             Self::Query(error) => error.fmt(f),
+            Self::Cancelled => write!(f, "The evaluation has been cancelled"),
         }
     }
 }
@@ -69,6 +72,7 @@ impl error::Error for EvaluationError {
             Self::ResultsParsing(e) => Some(e),
             Self::Io(e) => Some(e),
             Self::Query(e) => Some(e),
+            Self::Cancelled => None,
         }
     }
 }
@@ -153,6 +157,9 @@ impl From<EvaluationError> for io::Error {
             EvaluationError::Io(error) => error,
             EvaluationError::Storage(error) => error.into(),
             EvaluationError::Query(error) => Self::new(io::ErrorKind::Other, error),
+            error @ EvaluationError::Cancelled => {
+                Self::new(io::ErrorKind::Interrupted, error.to_string())
+            }
         }
     }
 }
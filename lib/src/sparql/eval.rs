@@ -5,6 +5,7 @@ use crate::sparql::algebra::{Query, QueryDataset};
 use crate::sparql::dataset::DatasetView;
 use crate::sparql::error::EvaluationError;
 use crate::sparql::model::*;
+use crate::sparql::parallel::{Worker, WorkerPool};
 use crate::sparql::plan::*;
 use crate::sparql::service::ServiceHandler;
 use crate::storage::numeric_encoder::*;
@@ -25,29 +26,44 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::iter::Iterator;
-use std::iter::{empty, once};
-use std::rc::Rc;
+use std::iter::{empty, once, once_with};
+use std::mem::take;
+use std::panic::resume_unwind;
 use std::str;
+use std::sync::mpsc::sync_channel;
+use std::sync::Arc;
 
 const REGEX_SIZE_LIMIT: usize = 1_000_000;
+/// Maximal number of workers used by a single scan or aggregation.
+///
+/// Only the operators evaluated from the empty tuple are parallelized, not the ones evaluated for each tuple of a loop join.
+const MAX_WORKERS_PER_OPERATOR: usize = 15;
+/// Number of tuples sent at once to the aggregation workers.
+const AGGREGATION_BATCH_SIZE: usize = 1024;
+/// Number of tuple batches buffered for each aggregation worker.
+const AGGREGATION_CHANNEL_SIZE: usize = 16;
 
-type EncodedTuplesIterator = Box<dyn Iterator<Item = Result<EncodedTuple, EvaluationError>>>;
+type EncodedTuplesIterator = Box<dyn Iterator<Item = Result<EncodedTuple, EvaluationError>> + Send>;
 
 #[derive(Clone)]
 pub struct SimpleEvaluator {
-    dataset: Rc<DatasetView>,
-    base_iri: Option<Rc<Iri<String>>>,
+    dataset: Arc<DatasetView>,
+    base_iri: Option<Arc<Iri<String>>>,
     now: DateTime,
-    service_handler: Rc<dyn ServiceHandler<Error = EvaluationError>>,
-    custom_functions: Rc<HashMap<NamedNode, Rc<dyn Fn(&[Term]) -> Option<Term>>>>,
+    service_handler: Arc<dyn ServiceHandler<Error = EvaluationError>>,
+    custom_functions: Arc<HashMap<NamedNode, Arc<dyn Fn(&[Term]) -> Option<Term> + Send + Sync>>>,
+    workers: Arc<WorkerPool>,
 }
 
 impl SimpleEvaluator {
     pub fn new(
-        dataset: Rc<DatasetView>,
-        base_iri: Option<Rc<Iri<String>>>,
-        service_handler: Rc<dyn ServiceHandler<Error = EvaluationError>>,
-        custom_functions: Rc<HashMap<NamedNode, Rc<dyn Fn(&[Term]) -> Option<Term>>>>,
+        dataset: Arc<DatasetView>,
+        base_iri: Option<Arc<Iri<String>>>,
+        service_handler: Arc<dyn ServiceHandler<Error = EvaluationError>>,
+        custom_functions: Arc<
+            HashMap<NamedNode, Arc<dyn Fn(&[Term]) -> Option<Term> + Send + Sync>>,
+        >,
+        parallelism: usize,
     ) -> Self {
         Self {
             dataset,
@@ -55,13 +71,14 @@ impl SimpleEvaluator {
             now: DateTime::now().unwrap(),
             service_handler,
             custom_functions,
+            workers: Arc::new(WorkerPool::new(parallelism)),
         }
     }
 
     pub fn evaluate_select_plan(
         &self,
         plan: &PlanNode,
-        variables: Rc<Vec<Variable>>,
+        variables: Arc<Vec<Variable>>,
     ) -> QueryResults {
         let iter = self.plan_evaluator(plan)(EncodedTuple::with_capacity(variables.len()));
         QueryResults::Solutions(decode_bindings(self.dataset.clone(), iter, variables))
@@ -93,13 +110,20 @@ impl SimpleEvaluator {
         })
     }
 
-    pub fn evaluate_describe_plan(&self, plan: &PlanNode) -> QueryResults {
+    pub fn evaluate_describe_plan(
+        &self,
+        plan: &PlanNode,
+        concise_bounded_description: bool,
+    ) -> QueryResults {
         let from = EncodedTuple::with_capacity(plan.used_variables().len());
         QueryResults::Graph(QueryTripleIter {
             iter: Box::new(DescribeIterator {
                 eval: self.clone(),
                 iter: self.plan_evaluator(plan)(from),
                 quads: Box::new(empty()),
+                concise_bounded_description,
+                to_describe: Vec::new(),
+                described: HashSet::new(),
             }),
         })
     }
@@ -107,11 +131,11 @@ impl SimpleEvaluator {
     pub fn plan_evaluator(
         &self,
         node: &PlanNode,
-    ) -> Rc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator> {
+    ) -> Arc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator + Send + Sync> {
         match node {
             PlanNode::StaticBindings { tuples } => {
                 let tuples = tuples.clone();
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     Box::new(
                         tuples
                             .iter()
@@ -133,7 +157,7 @@ impl SimpleEvaluator {
                 let service_name = service_name.clone();
                 let graph_pattern = graph_pattern.clone();
                 let eval = self.clone();
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     match eval.evaluate_service(
                         &service_name,
                         &graph_pattern,
@@ -166,12 +190,19 @@ impl SimpleEvaluator {
                 let object = object.clone();
                 let graph_name = graph_name.clone();
                 let dataset = self.dataset.clone();
-                Rc::new(move |from| {
-                    let iter = dataset.encoded_quads_for_pattern(
-                        get_pattern_value(&subject, &from).as_ref(),
-                        get_pattern_value(&predicate, &from).as_ref(),
-                        get_pattern_value(&object, &from).as_ref(),
-                        get_pattern_value(&graph_name, &from).as_ref(),
+                let workers = self.workers.clone();
+                Arc::new(move |from| {
+                    let iter = encoded_quads_for_pattern(
+                        &dataset,
+                        if from.is_empty() {
+                            Some(&workers)
+                        } else {
+                            None
+                        },
+                        get_pattern_value(&subject, &from),
+                        get_pattern_value(&predicate, &from),
+                        get_pattern_value(&object, &from),
+                        get_pattern_value(&graph_name, &from),
                     );
                     let subject = subject.clone();
                     let predicate = predicate.clone();
@@ -201,7 +232,7 @@ impl SimpleEvaluator {
                 let path = path.clone();
                 let object = object.clone();
                 let graph_name = graph_name.clone();
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     let input_subject = get_pattern_value(&subject, &from);
                     let input_object = get_pattern_value(&object, &from);
                     let input_graph_name =
@@ -282,9 +313,12 @@ impl SimpleEvaluator {
                     .collect();
                 let left = self.plan_evaluator(left);
                 let right = self.plan_evaluator(right);
+                let workers = self.workers.clone();
                 if join_keys.is_empty() {
                     // Cartesian product
-                    Rc::new(move |from| {
+                    Arc::new(move |from| {
+                        // The left side is evaluated in parallel of the right side materialization
+                        let left_iter = spawn_evaluation(&workers, &left, &from);
                         let mut errors = Vec::default();
                         let right_values = right(from.clone())
                             .filter_map(|result| match result {
@@ -296,14 +330,16 @@ impl SimpleEvaluator {
                             })
                             .collect::<Vec<_>>();
                         Box::new(CartesianProductJoinIterator {
-                            left_iter: left(from),
+                            left_iter: left_iter.unwrap_or_else(|| left(from)),
                             right: right_values,
                             buffered_results: errors,
                         })
                     })
                 } else {
                     // Real hash join
-                    Rc::new(move |from| {
+                    Arc::new(move |from| {
+                        // The left side is evaluated in parallel of the hash table build
+                        let left_iter = spawn_evaluation(&workers, &left, &from);
                         let mut errors = Vec::default();
                         let mut right_values = EncodedTupleSet::new(join_keys.clone());
                         right_values.extend(right(from.clone()).filter_map(
@@ -316,7 +352,7 @@ impl SimpleEvaluator {
                             },
                         ));
                         Box::new(HashJoinIterator {
-                            left_iter: left(from),
+                            left_iter: left_iter.unwrap_or_else(|| left(from)),
                             right: right_values,
                             buffered_results: errors,
                         })
@@ -326,7 +362,7 @@ impl SimpleEvaluator {
             PlanNode::ForLoopJoin { left, right } => {
                 let left = self.plan_evaluator(left);
                 let right = self.plan_evaluator(right);
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     let right = right.clone();
                     Box::new(left(from).flat_map(move |t| match t {
                         Ok(t) => right(t),
@@ -343,7 +379,7 @@ impl SimpleEvaluator {
                 let left = self.plan_evaluator(left);
                 let right = self.plan_evaluator(right);
                 if join_keys.is_empty() {
-                    Rc::new(move |from| {
+                    Arc::new(move |from| {
                         let right: Vec<_> = right(from.clone())
                             .filter_map(std::result::Result::ok)
                             .collect();
@@ -358,7 +394,7 @@ impl SimpleEvaluator {
                         }))
                     })
                 } else {
-                    Rc::new(move |from| {
+                    Arc::new(move |from| {
                         let mut right_values = EncodedTupleSet::new(join_keys.clone());
                         right_values
                             .extend(right(from.clone()).filter_map(std::result::Result::ok));
@@ -382,7 +418,7 @@ impl SimpleEvaluator {
                 let left = self.plan_evaluator(left);
                 let right = self.plan_evaluator(right);
                 let possible_problem_vars = possible_problem_vars.clone();
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     if possible_problem_vars.is_empty() {
                         Box::new(LeftJoinIterator {
                             right_evaluator: right.clone(),
@@ -403,7 +439,7 @@ impl SimpleEvaluator {
             PlanNode::Filter { child, expression } => {
                 let child = self.plan_evaluator(child);
                 let expression = self.expression_evaluator(expression);
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     let expression = expression.clone();
                     Box::new(child(from).filter(move |tuple| {
                         match tuple {
@@ -420,13 +456,35 @@ impl SimpleEvaluator {
                     .iter()
                     .map(|child| self.plan_evaluator(child))
                     .collect();
-                Rc::new(move |from| {
-                    Box::new(UnionIterator {
-                        plans: children.clone(),
-                        input: from,
-                        current_iterator: Box::new(empty()),
-                        current_plan: 0,
-                    })
+                let workers = self.workers.clone();
+                Arc::new(move |from| {
+                    let children_workers = workers.reserve(children.len().saturating_sub(1));
+                    if children_workers.is_empty() {
+                        return Box::new(UnionIterator {
+                            plans: children.clone(),
+                            input: from,
+                            current_iterator: Box::new(empty()),
+                            current_plan: 0,
+                        });
+                    }
+                    // The first child is evaluated in the current thread and the next ones by the workers if possible.
+                    // The results are returned in the same order as the sequential evaluation.
+                    let mut children_workers = children_workers.into_iter();
+                    let iters = children
+                        .iter()
+                        .enumerate()
+                        .map(|(i, child)| -> EncodedTuplesIterator {
+                            let child = child.clone();
+                            let from = from.clone();
+                            if i > 0 {
+                                if let Some(worker) = children_workers.next() {
+                                    return worker.spawn_iter(move || child(from));
+                                }
+                            }
+                            Box::new(once_with(move || child(from)).flatten())
+                        })
+                        .collect::<Vec<_>>();
+                    Box::new(iters.into_iter().flatten())
                 })
             }
             PlanNode::Extend {
@@ -437,7 +495,7 @@ impl SimpleEvaluator {
                 let child = self.plan_evaluator(child);
                 let position = *position;
                 let expression = self.expression_evaluator(expression);
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     let expression = expression.clone();
                     Box::new(child(from).map(move |tuple| {
                         let mut tuple = tuple?;
@@ -462,7 +520,7 @@ impl SimpleEvaluator {
                     })
                     .collect();
                 let dataset = self.dataset.clone();
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     let mut errors = Vec::default();
                     let mut values = child(from)
                         .filter_map(|result| match result {
@@ -507,11 +565,11 @@ impl SimpleEvaluator {
             }
             PlanNode::HashDeduplicate { child } => {
                 let child = self.plan_evaluator(child);
-                Rc::new(move |from| Box::new(hash_deduplicate(child(from))))
+                Arc::new(move |from| Box::new(hash_deduplicate(child(from))))
             }
             PlanNode::Reduced { child } => {
                 let child = self.plan_evaluator(child);
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     Box::new(ConsecutiveDeduplication {
                         inner: child(from),
                         current: None,
@@ -521,17 +579,17 @@ impl SimpleEvaluator {
             PlanNode::Skip { child, count } => {
                 let child = self.plan_evaluator(child);
                 let count = *count;
-                Rc::new(move |from| Box::new(child(from).skip(count)))
+                Arc::new(move |from| Box::new(child(from).skip(count)))
             }
             PlanNode::Limit { child, count } => {
                 let child = self.plan_evaluator(child);
                 let count = *count;
-                Rc::new(move |from| Box::new(child(from).take(count)))
+                Arc::new(move |from| Box::new(child(from).take(count)))
             }
             PlanNode::Project { child, mapping } => {
                 let child = self.plan_evaluator(child);
                 let mapping = mapping.clone();
-                Rc::new(move |from| {
+                Arc::new(move |from| {
                     let mapping = mapping.clone();
                     // We map forward the "from" values to make sure we join wit them
                     let mut inner_from = EncodedTuple::with_capacity(mapping.len());
@@ -559,90 +617,97 @@ impl SimpleEvaluator {
             } => {
                 let child = self.plan_evaluator(child);
                 let key_mapping = key_mapping.clone();
-                let aggregate_input_expressions: Vec<_> = aggregates
-                    .iter()
-                    .map(|(aggregate, _)| {
-                        aggregate
-                            .parameter
-                            .as_ref()
-                            .map(|p| self.expression_evaluator(p))
-                    })
-                    .collect();
-                let accumulator_builders: Vec<_> = aggregates
-                    .iter()
-                    .map(|(aggregate, _)| {
-                        Self::accumulator_builder(
-                            &self.dataset,
-                            &aggregate.function,
-                            aggregate.distinct,
-                        )
-                    })
-                    .collect();
+                let aggregate_input_expressions: Arc<Vec<_>> = Arc::new(
+                    aggregates
+                        .iter()
+                        .map(|(aggregate, _)| {
+                            aggregate
+                                .parameter
+                                .as_ref()
+                                .map(|p| self.expression_evaluator(p))
+                        })
+                        .collect(),
+                );
+                let accumulator_builders: Arc<Vec<_>> = Arc::new(
+                    aggregates
+                        .iter()
+                        .map(|(aggregate, _)| {
+                            Self::accumulator_builder(
+                                &self.dataset,
+                                &aggregate.function,
+                                aggregate.distinct,
+                            )
+                        })
+                        .collect(),
+                );
                 let accumulator_variables: Vec<_> =
                     aggregates.iter().map(|(_, var)| *var).collect();
-                Rc::new(move |from| {
+                let workers = self.workers.clone();
+                Arc::new(move |from| {
                     let tuple_size = from.capacity(); //TODO: not nice
+                    let is_parallel = from.is_empty();
                     let key_mapping = key_mapping.clone();
                     let mut errors = Vec::default();
-                    let mut accumulators_for_group =
-                        HashMap::<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator>>>::default();
-                    child(from)
-                        .filter_map(|result| match result {
-                            Ok(result) => Some(result),
-                            Err(error) => {
-                                errors.push(error);
-                                None
-                            }
-                        })
-                        .for_each(|tuple| {
-                            //TODO avoid copy for key?
-                            let key = key_mapping
-                                .iter()
-                                .map(|(v, _)| tuple.get(*v).cloned())
-                                .collect();
-
-                            let key_accumulators =
-                                accumulators_for_group.entry(key).or_insert_with(|| {
-                                    accumulator_builders.iter().map(|c| c()).collect::<Vec<_>>()
-                                });
-                            for (accumulator, input_expression) in key_accumulators
-                                .iter_mut()
-                                .zip(&aggregate_input_expressions)
-                            {
-                                accumulator.add(
-                                    input_expression
-                                        .as_ref()
-                                        .and_then(|parameter| parameter(&tuple)),
-                                );
-                            }
-                        });
-                    if accumulators_for_group.is_empty() && key_mapping.is_empty() {
+                    let tuples = child(from).filter_map(|result| match result {
+                        Ok(result) => Some(result),
+                        Err(error) => {
+                            errors.push(error);
+                            None
+                        }
+                    });
+                    let aggregation_workers = if is_parallel {
+                        workers.reserve(MAX_WORKERS_PER_OPERATOR)
+                    } else {
+                        Vec::new()
+                    };
+                    let mut groups = if aggregation_workers.is_empty() {
+                        vec![accumulate_groups(
+                            tuples.map(|tuple| (group_key(&tuple, &key_mapping), tuple)),
+                            &accumulator_builders,
+                            &aggregate_input_expressions,
+                        )]
+                    } else {
+                        match parallel_accumulate_groups(
+                            tuples,
+                            aggregation_workers,
+                            &key_mapping,
+                            &accumulator_builders,
+                            &aggregate_input_expressions,
+                        ) {
+                            Ok(groups) => groups,
+                            Err(error) => return Box::new(once(Err(error))),
+                        }
+                    };
+                    if groups.iter().all(HashMap::is_empty) && key_mapping.is_empty() {
                         // There is always a single group if there is no GROUP BY
-                        accumulators_for_group.insert(Vec::new(), Vec::new());
+                        groups.push(once((Vec::new(), Vec::new())).collect());
                     }
                     let accumulator_variables = accumulator_variables.clone();
                     Box::new(
                         errors
                             .into_iter()
                             .map(Err)
-                            .chain(accumulators_for_group.into_iter().map(
-                                move |(key, accumulators)| {
-                                    let mut result = EncodedTuple::with_capacity(tuple_size);
-                                    for (from_position, to_position) in key_mapping.iter() {
-                                        if let Some(value) = &key[*from_position] {
-                                            result.set(*to_position, value.clone());
+                            .chain(
+                                groups
+                                    .into_iter()
+                                    .flatten()
+                                    .map(move |(key, accumulators)| {
+                                        let mut result = EncodedTuple::with_capacity(tuple_size);
+                                        for (from_position, to_position) in key_mapping.iter() {
+                                            if let Some(value) = &key[*from_position] {
+                                                result.set(*to_position, value.clone());
+                                            }
                                         }
-                                    }
-                                    for (accumulator, variable) in
-                                        accumulators.into_iter().zip(&accumulator_variables)
-                                    {
-                                        if let Some(value) = accumulator.state() {
-                                            result.set(*variable, value);
+                                        for (accumulator, variable) in
+                                            accumulators.into_iter().zip(&accumulator_variables)
+                                        {
+                                            if let Some(value) = accumulator.state() {
+                                                result.set(*variable, value);
+                                            }
                                         }
-                                    }
-                                    Ok(result)
-                                },
-                            )),
+                                        Ok(result)
+                                    }),
+                            ),
                     )
                 })
             }
@@ -653,7 +718,7 @@ impl SimpleEvaluator {
         &self,
         service_name: &PatternValue,
         graph_pattern: &GraphPattern,
-        variables: Rc<Vec<Variable>>,
+        variables: Arc<Vec<Variable>>,
         from: &EncodedTuple,
     ) -> Result<EncodedTuplesIterator, EvaluationError> {
         let service_name = get_pattern_value(service_name, from)
@@ -678,10 +743,10 @@ impl SimpleEvaluator {
     }
 
     fn accumulator_builder(
-        dataset: &Rc<DatasetView>,
+        dataset: &Arc<DatasetView>,
         function: &PlanAggregationFunction,
         distinct: bool,
-    ) -> Box<dyn Fn() -> Box<dyn Accumulator>> {
+    ) -> Box<dyn Fn() -> Box<dyn Accumulator + Send> + Send + Sync> {
         match function {
             PlanAggregationFunction::Count => {
                 if distinct {
@@ -740,7 +805,7 @@ impl SimpleEvaluator {
         path: &PlanPropertyPath,
         start: &EncodedTerm,
         graph_name: &EncodedTerm,
-    ) -> Box<dyn Iterator<Item = Result<EncodedTerm, EvaluationError>>> {
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm, EvaluationError>> + Send> {
         match path {
             PlanPropertyPath::Path(p) => Box::new(
                 self.dataset
@@ -806,7 +871,7 @@ impl SimpleEvaluator {
         path: &PlanPropertyPath,
         end: &EncodedTerm,
         graph_name: &EncodedTerm,
-    ) -> Box<dyn Iterator<Item = Result<EncodedTerm, EvaluationError>>> {
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm, EvaluationError>> + Send> {
         match path {
             PlanPropertyPath::Path(p) => Box::new(
                 self.dataset
@@ -871,7 +936,7 @@ impl SimpleEvaluator {
         &self,
         path: &PlanPropertyPath,
         graph_name: &EncodedTerm,
-    ) -> Box<dyn Iterator<Item = Result<(EncodedTerm, EncodedTerm), EvaluationError>>> {
+    ) -> Box<dyn Iterator<Item = Result<(EncodedTerm, EncodedTerm), EvaluationError>> + Send> {
         match path {
             PlanPropertyPath::Path(p) => Box::new(
                 self.dataset
@@ -960,25 +1025,25 @@ impl SimpleEvaluator {
     fn expression_evaluator(
         &self,
         expression: &PlanExpression,
-    ) -> Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>> {
+    ) -> Arc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm> + Send + Sync> {
         match expression {
             PlanExpression::Constant(t) => {
                 let t = t.clone();
-                Rc::new(move |_| Some(t.clone()))
+                Arc::new(move |_| Some(t.clone()))
             }
             PlanExpression::Variable(v) => {
                 let v = *v;
-                Rc::new(move |tuple| tuple.get(v).cloned())
+                Arc::new(move |tuple| tuple.get(v).cloned())
             }
             PlanExpression::Exists(plan) => {
                 let plan = plan.clone();
                 let eval = self.plan_evaluator(&plan);
-                Rc::new(move |tuple| Some(eval(tuple.clone()).next().is_some().into()))
+                Arc::new(move |tuple| Some(eval(tuple.clone()).next().is_some().into()))
             }
             PlanExpression::Or(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(move |tuple| match a(tuple).and_then(|v| to_bool(&v)) {
+                Arc::new(move |tuple| match a(tuple).and_then(|v| to_bool(&v)) {
                     Some(true) => Some(true.into()),
                     Some(false) => b(tuple),
                     None => {
@@ -993,7 +1058,7 @@ impl SimpleEvaluator {
             PlanExpression::And(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(move |tuple| match a(tuple).and_then(|v| to_bool(&v)) {
+                Arc::new(move |tuple| match a(tuple).and_then(|v| to_bool(&v)) {
                     Some(true) => b(tuple),
                     Some(false) => Some(false.into()),
                     None => {
@@ -1008,13 +1073,13 @@ impl SimpleEvaluator {
             PlanExpression::Equal(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(move |tuple| equals(&a(tuple)?, &b(tuple)?).map(|v| v.into()))
+                Arc::new(move |tuple| equals(&a(tuple)?, &b(tuple)?).map(|v| v.into()))
             }
             PlanExpression::Greater(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(
                         (partial_cmp(&dataset, &a(tuple)?, &b(tuple)?)? == Ordering::Greater)
                             .into(),
@@ -1025,7 +1090,7 @@ impl SimpleEvaluator {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(
                         match partial_cmp(&dataset, &a(tuple)?, &b(tuple)?)? {
                             Ordering::Greater | Ordering::Equal => true,
@@ -1039,7 +1104,7 @@ impl SimpleEvaluator {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some((partial_cmp(&dataset, &a(tuple)?, &b(tuple)?)? == Ordering::Less).into())
                 })
             }
@@ -1047,7 +1112,7 @@ impl SimpleEvaluator {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(
                         match partial_cmp(&dataset, &a(tuple)?, &b(tuple)?)? {
                             Ordering::Less | Ordering::Equal => true,
@@ -1060,7 +1125,7 @@ impl SimpleEvaluator {
             PlanExpression::Add(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(
+                Arc::new(
                     move |tuple| match NumericBinaryOperands::new(a(tuple)?, b(tuple)?)? {
                         NumericBinaryOperands::Float(v1, v2) => Some((v1 + v2).into()),
                         NumericBinaryOperands::Double(v1, v2) => Some((v1 + v2).into()),
@@ -1104,7 +1169,7 @@ impl SimpleEvaluator {
             PlanExpression::Subtract(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(match NumericBinaryOperands::new(a(tuple)?, b(tuple)?)? {
                         NumericBinaryOperands::Float(v1, v2) => (v1 - v2).into(),
                         NumericBinaryOperands::Double(v1, v2) => (v1 - v2).into(),
@@ -1150,7 +1215,7 @@ impl SimpleEvaluator {
             PlanExpression::Multiply(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(
+                Arc::new(
                     move |tuple| match NumericBinaryOperands::new(a(tuple)?, b(tuple)?)? {
                         NumericBinaryOperands::Float(v1, v2) => Some((v1 * v2).into()),
                         NumericBinaryOperands::Double(v1, v2) => Some((v1 * v2).into()),
@@ -1163,7 +1228,7 @@ impl SimpleEvaluator {
             PlanExpression::Divide(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(
+                Arc::new(
                     move |tuple| match NumericBinaryOperands::new(a(tuple)?, b(tuple)?)? {
                         NumericBinaryOperands::Float(v1, v2) => Some((v1 / v2).into()),
                         NumericBinaryOperands::Double(v1, v2) => Some((v1 / v2).into()),
@@ -1177,7 +1242,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::UnaryPlus(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some(value.into()),
                     EncodedTerm::DoubleLiteral(value) => Some(value.into()),
                     EncodedTerm::IntegerLiteral(value) => Some(value.into()),
@@ -1190,7 +1255,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::UnaryMinus(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some((-value).into()),
                     EncodedTerm::DoubleLiteral(value) => Some((-value).into()),
                     EncodedTerm::IntegerLiteral(value) => Some((-value).into()),
@@ -1203,12 +1268,12 @@ impl SimpleEvaluator {
             }
             PlanExpression::Not(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| to_bool(&e(tuple)?).map(|v| (!v).into()))
+                Arc::new(move |tuple| to_bool(&e(tuple)?).map(|v| (!v).into()))
             }
             PlanExpression::Str(e) | PlanExpression::StringCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(build_string_literal_from_id(to_string_id(
                         &dataset,
                         &e(tuple)?,
@@ -1218,7 +1283,7 @@ impl SimpleEvaluator {
             PlanExpression::Lang(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::SmallSmallLangStringLiteral { language, .. }
                     | EncodedTerm::BigSmallLangStringLiteral { language, .. } => {
                         Some(build_string_literal_from_id(language.into()))
@@ -1235,7 +1300,7 @@ impl SimpleEvaluator {
                 let language_tag = self.expression_evaluator(language_tag);
                 let language_range = self.expression_evaluator(language_range);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let mut language_tag = to_simple_string(&dataset, &language_tag(tuple)?)?;
                     language_tag.make_ascii_lowercase();
                     let mut language_range = to_simple_string(&dataset, &language_range(tuple)?)?;
@@ -1260,17 +1325,17 @@ impl SimpleEvaluator {
             PlanExpression::Datatype(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| datatype(&dataset, &e(tuple)?))
+                Arc::new(move |tuple| datatype(&dataset, &e(tuple)?))
             }
             PlanExpression::Bound(v) => {
                 let v = *v;
-                Rc::new(move |tuple| Some(tuple.contains(v).into()))
+                Arc::new(move |tuple| Some(tuple.contains(v).into()))
             }
             PlanExpression::Iri(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
                 let base_iri = self.base_iri.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let e = e(tuple)?;
                     if e.is_named_node() {
                         Some(e)
@@ -1293,7 +1358,7 @@ impl SimpleEvaluator {
                 Some(id) => {
                     let id = self.expression_evaluator(id);
                     let dataset = self.dataset.clone();
-                    Rc::new(move |tuple| {
+                    Arc::new(move |tuple| {
                         Some(
                             dataset.encode_term(
                                 BlankNode::new(to_simple_string(&dataset, &id(tuple)?)?)
@@ -1303,16 +1368,16 @@ impl SimpleEvaluator {
                         )
                     })
                 }
-                None => Rc::new(|_| {
+                None => Arc::new(|_| {
                     Some(EncodedTerm::NumericalBlankNode {
                         id: random::<u128>(),
                     })
                 }),
             },
-            PlanExpression::Rand => Rc::new(|_| Some(random::<f64>().into())),
+            PlanExpression::Rand => Arc::new(|_| Some(random::<f64>().into())),
             PlanExpression::Abs(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::IntegerLiteral(value) => Some(value.checked_abs()?.into()),
                     EncodedTerm::DecimalLiteral(value) => Some(value.abs().into()),
                     EncodedTerm::FloatLiteral(value) => Some(value.abs().into()),
@@ -1322,7 +1387,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Ceil(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                     EncodedTerm::DecimalLiteral(value) => Some(value.ceil().into()),
                     EncodedTerm::FloatLiteral(value) => Some(value.ceil().into()),
@@ -1332,7 +1397,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Floor(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                     EncodedTerm::DecimalLiteral(value) => Some(value.floor().into()),
                     EncodedTerm::FloatLiteral(value) => Some(value.floor().into()),
@@ -1342,7 +1407,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Round(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                     EncodedTerm::DecimalLiteral(value) => Some(value.round().into()),
                     EncodedTerm::FloatLiteral(value) => Some(value.round().into()),
@@ -1353,7 +1418,7 @@ impl SimpleEvaluator {
             PlanExpression::Concat(l) => {
                 let l: Vec<_> = l.iter().map(|e| self.expression_evaluator(e)).collect();
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let mut result = String::default();
                     let mut language = None;
                     for e in &l {
@@ -1379,7 +1444,7 @@ impl SimpleEvaluator {
                 let starting_loc = self.expression_evaluator(starting_loc);
                 let length = length.as_ref().map(|l| self.expression_evaluator(l));
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (source, language) = to_string_and_language(&dataset, &source(tuple)?)?;
 
                     let starting_location: usize =
@@ -1423,7 +1488,7 @@ impl SimpleEvaluator {
             PlanExpression::StrLen(arg) => {
                 let arg = self.expression_evaluator(arg);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some((to_string(&dataset, &arg(tuple)?)?.chars().count() as i64).into())
                 })
             }
@@ -1433,7 +1498,7 @@ impl SimpleEvaluator {
                 let replacement = self.expression_evaluator(replacement);
                 let flags = flags.as_ref().map(|flags| self.expression_evaluator(flags));
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let regex = compile_pattern(
                         &dataset,
                         &pattern(tuple)?,
@@ -1455,7 +1520,7 @@ impl SimpleEvaluator {
             PlanExpression::UCase(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (value, language) = to_string_and_language(&dataset, &e(tuple)?)?;
                     Some(build_plain_literal(
                         &dataset,
@@ -1467,7 +1532,7 @@ impl SimpleEvaluator {
             PlanExpression::LCase(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (value, language) = to_string_and_language(&dataset, &e(tuple)?)?;
                     Some(build_plain_literal(
                         &dataset,
@@ -1480,7 +1545,7 @@ impl SimpleEvaluator {
                 let arg1 = self.expression_evaluator(arg1);
                 let arg2 = self.expression_evaluator(arg2);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (arg1, arg2, _) =
                         to_argument_compatible_strings(&dataset, &arg1(tuple)?, &arg2(tuple)?)?;
                     Some((&arg1).starts_with(arg2.as_str()).into())
@@ -1489,7 +1554,7 @@ impl SimpleEvaluator {
             PlanExpression::EncodeForUri(ltrl) => {
                 let ltrl = self.expression_evaluator(ltrl);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let ltlr = to_string(&dataset, &ltrl(tuple)?)?;
                     let mut result = Vec::with_capacity(ltlr.len());
                     for c in ltlr.bytes() {
@@ -1524,7 +1589,7 @@ impl SimpleEvaluator {
                 let arg1 = self.expression_evaluator(arg1);
                 let arg2 = self.expression_evaluator(arg2);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (arg1, arg2, _) =
                         to_argument_compatible_strings(&dataset, &arg1(tuple)?, &arg2(tuple)?)?;
                     Some((&arg1).ends_with(arg2.as_str()).into())
@@ -1534,7 +1599,7 @@ impl SimpleEvaluator {
                 let arg1 = self.expression_evaluator(arg1);
                 let arg2 = self.expression_evaluator(arg2);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (arg1, arg2, _) =
                         to_argument_compatible_strings(&dataset, &arg1(tuple)?, &arg2(tuple)?)?;
                     Some((&arg1).contains(arg2.as_str()).into())
@@ -1544,7 +1609,7 @@ impl SimpleEvaluator {
                 let arg1 = self.expression_evaluator(arg1);
                 let arg2 = self.expression_evaluator(arg2);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (arg1, arg2, language) =
                         to_argument_compatible_strings(&dataset, &arg1(tuple)?, &arg2(tuple)?)?;
                     Some(if let Some(position) = (&arg1).find(arg2.as_str()) {
@@ -1558,7 +1623,7 @@ impl SimpleEvaluator {
                 let arg1 = self.expression_evaluator(arg1);
                 let arg2 = self.expression_evaluator(arg2);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let (arg1, arg2, language) =
                         to_argument_compatible_strings(&dataset, &arg1(tuple)?, &arg2(tuple)?)?;
                     Some(if let Some(position) = (&arg1).find(arg2.as_str()) {
@@ -1570,7 +1635,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Year(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.year().into()),
                     EncodedTerm::DateLiteral(date) => Some(date.year().into()),
                     EncodedTerm::GYearMonthLiteral(year_month) => Some(year_month.year().into()),
@@ -1580,7 +1645,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Month(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.month().into()),
                     EncodedTerm::DateLiteral(date) => Some(date.month().into()),
                     EncodedTerm::GYearMonthLiteral(year_month) => Some(year_month.month().into()),
@@ -1591,7 +1656,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Day(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.day().into()),
                     EncodedTerm::DateLiteral(date) => Some(date.day().into()),
                     EncodedTerm::GMonthDayLiteral(month_day) => Some(month_day.day().into()),
@@ -1601,7 +1666,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Hours(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.hour().into()),
                     EncodedTerm::TimeLiteral(time) => Some(time.hour().into()),
                     _ => None,
@@ -1609,7 +1674,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Minutes(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.minute().into()),
                     EncodedTerm::TimeLiteral(time) => Some(time.minute().into()),
                     _ => None,
@@ -1617,7 +1682,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Seconds(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.second().into()),
                     EncodedTerm::TimeLiteral(time) => Some(time.second().into()),
                     _ => None,
@@ -1625,7 +1690,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Timezone(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(
                         match e(tuple)? {
                             EncodedTerm::DateTimeLiteral(date_time) => date_time.timezone(),
@@ -1645,7 +1710,7 @@ impl SimpleEvaluator {
             PlanExpression::Tz(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let timezone_offset = match e(tuple)? {
                         EncodedTerm::DateTimeLiteral(date_time) => date_time.timezone_offset(),
                         EncodedTerm::TimeLiteral(time) => time.timezone_offset(),
@@ -1667,11 +1732,11 @@ impl SimpleEvaluator {
             }
             PlanExpression::Now => {
                 let now = self.now;
-                Rc::new(move |_| Some(now.into()))
+                Arc::new(move |_| Some(now.into()))
             }
             PlanExpression::Uuid => {
                 let dataset = self.dataset.clone();
-                Rc::new(move |_| {
+                Arc::new(move |_| {
                     let mut buffer = String::with_capacity(44);
                     buffer.push_str("urn:uuid:");
                     generate_uuid(&mut buffer);
@@ -1680,7 +1745,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::StrUuid => {
                 let dataset = self.dataset.clone();
-                Rc::new(move |_| {
+                Arc::new(move |_| {
                     let mut buffer = String::with_capacity(36);
                     generate_uuid(&mut buffer);
                     Some(build_string_literal(&dataset, &buffer))
@@ -1693,7 +1758,7 @@ impl SimpleEvaluator {
             PlanExpression::Sha512(arg) => self.hash::<Sha512>(arg),
             PlanExpression::Coalesce(l) => {
                 let l: Vec<_> = l.iter().map(|e| self.expression_evaluator(e)).collect();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     for e in &l {
                         if let Some(result) = e(tuple) {
                             return Some(result);
@@ -1706,7 +1771,7 @@ impl SimpleEvaluator {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
                 let c = self.expression_evaluator(c);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     if to_bool(&a(tuple)?)? {
                         b(tuple)
                     } else {
@@ -1718,7 +1783,7 @@ impl SimpleEvaluator {
                 let lexical_form = self.expression_evaluator(lexical_form);
                 let lang_tag = self.expression_evaluator(lang_tag);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(build_lang_string_literal_from_id(
                         to_simple_string_id(&lexical_form(tuple)?)?,
                         build_language_id(&dataset, &lang_tag(tuple)?)?,
@@ -1729,7 +1794,7 @@ impl SimpleEvaluator {
                 let lexical_form = self.expression_evaluator(lexical_form);
                 let datatype = self.expression_evaluator(datatype);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let value = to_simple_string(&dataset, &lexical_form(tuple)?)?;
                     let datatype = if let EncodedTerm::NamedNode { iri_id } = datatype(tuple)? {
                         dataset.get_str(&iri_id).ok()?
@@ -1745,23 +1810,23 @@ impl SimpleEvaluator {
             PlanExpression::SameTerm(a, b) => {
                 let a = self.expression_evaluator(a);
                 let b = self.expression_evaluator(b);
-                Rc::new(move |tuple| Some((a(tuple)? == b(tuple)?).into()))
+                Arc::new(move |tuple| Some((a(tuple)? == b(tuple)?).into()))
             }
             PlanExpression::IsIri(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| Some(e(tuple)?.is_named_node().into()))
+                Arc::new(move |tuple| Some(e(tuple)?.is_named_node().into()))
             }
             PlanExpression::IsBlank(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| Some(e(tuple)?.is_blank_node().into()))
+                Arc::new(move |tuple| Some(e(tuple)?.is_blank_node().into()))
             }
             PlanExpression::IsLiteral(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| Some(e(tuple)?.is_literal().into()))
+                Arc::new(move |tuple| Some(e(tuple)?.is_literal().into()))
             }
             PlanExpression::IsNumeric(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     Some(
                         matches!(
                             e(tuple)?,
@@ -1779,7 +1844,7 @@ impl SimpleEvaluator {
                 let pattern = self.expression_evaluator(pattern);
                 let flags = flags.as_ref().map(|flags| self.expression_evaluator(flags));
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let regex = compile_pattern(
                         &dataset,
                         &pattern(tuple)?,
@@ -1797,7 +1862,7 @@ impl SimpleEvaluator {
                 let s = self.expression_evaluator(s);
                 let p = self.expression_evaluator(p);
                 let o = self.expression_evaluator(o);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     let s = s(tuple)?;
                     let p = p(tuple)?;
                     let o = o(tuple)?;
@@ -1814,7 +1879,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Subject(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     if let EncodedTerm::Triple(t) = e(tuple)? {
                         Some(t.subject.clone())
                     } else {
@@ -1824,7 +1889,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Predicate(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     if let EncodedTerm::Triple(t) = e(tuple)? {
                         Some(t.predicate.clone())
                     } else {
@@ -1834,7 +1899,7 @@ impl SimpleEvaluator {
             }
             PlanExpression::Object(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| {
+                Arc::new(move |tuple| {
                     if let EncodedTerm::Triple(t) = e(tuple)? {
                         Some(t.object.clone())
                     } else {
@@ -1844,12 +1909,12 @@ impl SimpleEvaluator {
             }
             PlanExpression::IsTriple(e) => {
                 let e = self.expression_evaluator(e);
-                Rc::new(move |tuple| Some(e(tuple)?.is_triple().into()))
+                Arc::new(move |tuple| Some(e(tuple)?.is_triple().into()))
             }
             PlanExpression::BooleanCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::BooleanLiteral(value) => Some(value.into()),
                     EncodedTerm::FloatLiteral(value) => Some(value.to_bool().into()),
                     EncodedTerm::DoubleLiteral(value) => Some(value.to_bool().into()),
@@ -1865,7 +1930,7 @@ impl SimpleEvaluator {
             PlanExpression::DoubleCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some(f64::from(value).into()),
                     EncodedTerm::DoubleLiteral(value) => Some(value.into()),
                     EncodedTerm::IntegerLiteral(value) => Some((value as f64).into()),
@@ -1883,7 +1948,7 @@ impl SimpleEvaluator {
             PlanExpression::FloatCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some(value.into()),
                     EncodedTerm::DoubleLiteral(value) => Some(value.to_f32().into()),
                     EncodedTerm::IntegerLiteral(value) => Some((value as f32).into()),
@@ -1901,7 +1966,7 @@ impl SimpleEvaluator {
             PlanExpression::IntegerCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some(value.to_i64().into()),
                     EncodedTerm::DoubleLiteral(value) => Some(value.to_i64().into()),
                     EncodedTerm::IntegerLiteral(value) => Some(value.into()),
@@ -1917,7 +1982,7 @@ impl SimpleEvaluator {
             PlanExpression::DecimalCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some(Decimal::from_float(value).into()),
                     EncodedTerm::DoubleLiteral(value) => Some(Decimal::from_double(value).into()),
                     EncodedTerm::IntegerLiteral(value) => Some(Decimal::from(value).into()),
@@ -1935,7 +2000,7 @@ impl SimpleEvaluator {
             PlanExpression::DateCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateLiteral(value) => Some(value.into()),
                     EncodedTerm::DateTimeLiteral(value) => Some(Date::try_from(value).ok()?.into()),
                     EncodedTerm::SmallStringLiteral(value) => parse_date_str(&value),
@@ -1948,7 +2013,7 @@ impl SimpleEvaluator {
             PlanExpression::TimeCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::TimeLiteral(value) => Some(value.into()),
                     EncodedTerm::DateTimeLiteral(value) => Some(Time::try_from(value).ok()?.into()),
                     EncodedTerm::SmallStringLiteral(value) => parse_time_str(&value),
@@ -1961,7 +2026,7 @@ impl SimpleEvaluator {
             PlanExpression::DateTimeCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DateTimeLiteral(value) => Some(value.into()),
                     EncodedTerm::DateLiteral(value) => Some(DateTime::try_from(value).ok()?.into()),
                     EncodedTerm::SmallStringLiteral(value) => parse_date_time_str(&value),
@@ -1974,7 +2039,7 @@ impl SimpleEvaluator {
             PlanExpression::DurationCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DurationLiteral(value) => Some(value.into()),
                     EncodedTerm::YearMonthDurationLiteral(value) => {
                         Some(Duration::from(value).into())
@@ -1992,7 +2057,7 @@ impl SimpleEvaluator {
             PlanExpression::YearMonthDurationCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DurationLiteral(value) => {
                         Some(YearMonthDuration::try_from(value).ok()?.into())
                     }
@@ -2007,7 +2072,7 @@ impl SimpleEvaluator {
             PlanExpression::DayTimeDurationCast(e) => {
                 let e = self.expression_evaluator(e);
                 let dataset = self.dataset.clone();
-                Rc::new(move |tuple| match e(tuple)? {
+                Arc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::DurationLiteral(value) => {
                         Some(DayTimeDuration::try_from(value).ok()?.into())
                     }
@@ -2026,7 +2091,7 @@ impl SimpleEvaluator {
                         .map(|e| self.expression_evaluator(e))
                         .collect::<Vec<_>>();
                     let dataset = self.dataset.clone();
-                    Rc::new(move |tuple| {
+                    Arc::new(move |tuple| {
                         let args = args
                             .iter()
                             .map(|f| dataset.decode_term(&f(tuple)?).ok())
@@ -2034,7 +2099,7 @@ impl SimpleEvaluator {
                         Some(dataset.encode_term(&function(&args)?))
                     })
                 } else {
-                    Rc::new(|_| None)
+                    Arc::new(|_| None)
                 }
             }
         }
@@ -2043,10 +2108,10 @@ impl SimpleEvaluator {
     fn hash<H: Digest>(
         &self,
         arg: &PlanExpression,
-    ) -> Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>> {
+    ) -> Arc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm> + Send + Sync> {
         let arg = self.expression_evaluator(arg);
         let dataset = self.dataset.clone();
-        Rc::new(move |tuple| {
+        Arc::new(move |tuple| {
             let input = to_simple_string(&dataset, &arg(tuple)?)?;
             let hash = hex::encode(H::new().chain_update(input.as_str()).finalize());
             Some(build_string_literal(&dataset, &hash))
@@ -2298,9 +2363,9 @@ fn compile_pattern(
 }
 
 fn decode_bindings(
-    dataset: Rc<DatasetView>,
+    dataset: Arc<DatasetView>,
     iter: EncodedTuplesIterator,
-    variables: Rc<Vec<Variable>>,
+    variables: Arc<Vec<Variable>>,
 ) -> QuerySolutionIter {
     let tuple_size = variables.len();
     QuerySolutionIter::new(
@@ -2319,8 +2384,8 @@ fn decode_bindings(
 
 // this is used to encode results from a BindingIterator into an EncodedTuplesIterator. This happens when SERVICE clauses are evaluated
 fn encode_bindings(
-    dataset: Rc<DatasetView>,
-    variables: Rc<Vec<Variable>>,
+    dataset: Arc<DatasetView>,
+    variables: Arc<Vec<Variable>>,
     iter: QuerySolutionIter,
 ) -> EncodedTuplesIterator {
     Box::new(iter.map(move |solution| {
@@ -3156,7 +3221,7 @@ impl Iterator for HashJoinIterator {
 }
 
 struct LeftJoinIterator {
-    right_evaluator: Rc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator>,
+    right_evaluator: Arc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator + Send + Sync>,
     left_iter: EncodedTuplesIterator,
     current_right: EncodedTuplesIterator,
 }
@@ -3183,11 +3248,11 @@ impl Iterator for LeftJoinIterator {
 }
 
 struct BadLeftJoinIterator {
-    right_evaluator: Rc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator>,
+    right_evaluator: Arc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator + Send + Sync>,
     left_iter: EncodedTuplesIterator,
     current_left: Option<EncodedTuple>,
     current_right: EncodedTuplesIterator,
-    problem_vars: Rc<Vec<usize>>,
+    problem_vars: Arc<Vec<usize>>,
 }
 
 impl Iterator for BadLeftJoinIterator {
@@ -3234,7 +3299,7 @@ impl Iterator for BadLeftJoinIterator {
 }
 
 struct UnionIterator {
-    plans: Vec<Rc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator>>,
+    plans: Vec<Arc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator + Send + Sync>>,
     input: EncodedTuple,
     current_iterator: EncodedTuplesIterator,
     current_plan: usize,
@@ -3386,10 +3451,20 @@ fn decode_triple<D: Decoder>(
     ))
 }
 
+/// Returns all the triples whose subject is a described resource.
+///
+/// If `concise_bounded_description` is set, it builds the [Concise Bounded Description](https://www.w3.org/Submission/CBD/)
+/// of the described resources: each resource is described once and, recursively,
+/// the triples whose subject is a blank node object of an already returned triple are also returned.
 struct DescribeIterator {
     eval: SimpleEvaluator,
     iter: EncodedTuplesIterator,
-    quads: Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>>>,
+    quads: Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>> + Send>,
+    concise_bounded_description: bool,
+    /// Stack of the resources to describe.
+    to_describe: Vec<EncodedTerm>,
+    /// The resources already described, only filled for the Concise Bounded Description.
+    described: HashSet<EncodedTerm>,
 }
 
 impl Iterator for DescribeIterator {
@@ -3398,34 +3473,55 @@ impl Iterator for DescribeIterator {
     fn next(&mut self) -> Option<Result<Triple, EvaluationError>> {
         loop {
             if let Some(quad) = self.quads.next() {
-                return Some(match quad {
-                    Ok(quad) => self
-                        .eval
+                let quad = match quad {
+                    Ok(quad) => quad,
+                    Err(error) => return Some(Err(error)),
+                };
+                if self.concise_bounded_description
+                    && quad.object.is_blank_node()
+                    && self.described.insert(quad.object.clone())
+                {
+                    self.to_describe.push(quad.object.clone());
+                }
+                return Some(
+                    self.eval
                         .dataset
                         .decode_quad(&quad)
                         .map(|q| q.into())
                         .map_err(|e| e.into()),
-                    Err(error) => Err(error),
-                });
+                );
             }
-            let tuple = match self.iter.next()? {
-                Ok(tuple) => tuple,
-                Err(error) => return Some(Err(error)),
+            let subject = if let Some(subject) = self.to_describe.pop() {
+                subject
+            } else {
+                let tuple = match self.iter.next()? {
+                    Ok(tuple) => tuple,
+                    Err(error) => return Some(Err(error)),
+                };
+                // Reversed to describe the resources in the tuple order
+                for subject in tuple.into_iter().flatten().rev() {
+                    if !self.concise_bounded_description || self.described.insert(subject.clone()) {
+                        self.to_describe.push(subject);
+                    }
+                }
+                continue;
             };
-            let eval = self.eval.clone();
-            self.quads = Box::new(tuple.into_iter().flatten().flat_map(move |subject| {
-                eval.dataset
+            self.quads = Box::new(
+                self.eval
+                    .dataset
                     .encoded_quads_for_pattern(
                         Some(&subject),
                         None,
                         None,
                         Some(&EncodedTerm::DefaultGraph),
                     )
-                    .chain(
-                        eval.dataset
-                            .encoded_quads_for_pattern(Some(&subject), None, None, None),
-                    )
-            }));
+                    .chain(self.eval.dataset.encoded_quads_for_pattern(
+                        Some(&subject),
+                        None,
+                        None,
+                        None,
+                    )),
+            );
         }
     }
 }
@@ -3498,6 +3594,162 @@ fn transitive_closure<T: Clone + Eq + Hash, NI: Iterator<Item = Result<T, Evalua
     errors.into_iter().map(Err).chain(all.into_iter().map(Ok))
 }
 
+/// Starts the evaluation of `plan` in a worker if one is available and `from` is the empty tuple.
+fn spawn_evaluation(
+    workers: &Arc<WorkerPool>,
+    plan: &Arc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator + Send + Sync>,
+    from: &EncodedTuple,
+) -> Option<EncodedTuplesIterator> {
+    if !from.is_empty() {
+        return None;
+    }
+    let worker = workers.reserve(1).pop()?;
+    let plan = plan.clone();
+    let from = from.clone();
+    Some(worker.spawn_iter(move || plan(from)))
+}
+
+/// Returns the quads matching a pattern.
+///
+/// If `workers` are given, the scans that might return a lot of quads are split into partitions scanned by the available workers.
+fn encoded_quads_for_pattern(
+    dataset: &Arc<DatasetView>,
+    workers: Option<&Arc<WorkerPool>>,
+    subject: Option<EncodedTerm>,
+    predicate: Option<EncodedTerm>,
+    object: Option<EncodedTerm>,
+    graph_name: Option<EncodedTerm>,
+) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>> + Send> {
+    let bound_count = [&subject, &predicate, &object]
+        .iter()
+        .filter(|t| t.is_some())
+        .count();
+    if bound_count <= 1 {
+        let partition_workers = workers.map_or_else(Vec::new, |workers| {
+            workers.reserve(MAX_WORKERS_PER_OPERATOR)
+        });
+        if !partition_workers.is_empty() {
+            let count = partition_workers.len() + 1;
+            let mut partitions = Vec::with_capacity(count);
+            partitions.push(dataset.partitioned_encoded_quads_for_pattern(
+                subject.as_ref(),
+                predicate.as_ref(),
+                object.as_ref(),
+                graph_name.as_ref(),
+                0,
+                count,
+            ));
+            for (i, worker) in partition_workers.into_iter().enumerate() {
+                let dataset = dataset.clone();
+                let subject = subject.clone();
+                let predicate = predicate.clone();
+                let object = object.clone();
+                let graph_name = graph_name.clone();
+                partitions.push(worker.spawn_iter(move || {
+                    dataset.partitioned_encoded_quads_for_pattern(
+                        subject.as_ref(),
+                        predicate.as_ref(),
+                        object.as_ref(),
+                        graph_name.as_ref(),
+                        i + 1,
+                        count,
+                    )
+                }));
+            }
+            return Box::new(partitions.into_iter().flatten());
+        }
+    }
+    dataset.encoded_quads_for_pattern(
+        subject.as_ref(),
+        predicate.as_ref(),
+        object.as_ref(),
+        graph_name.as_ref(),
+    )
+}
+
+type GroupAccumulators = HashMap<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator + Send>>>;
+
+fn group_key(tuple: &EncodedTuple, key_mapping: &[(usize, usize)]) -> Vec<Option<EncodedTerm>> {
+    //TODO avoid copy for key?
+    key_mapping
+        .iter()
+        .map(|(v, _)| tuple.get(*v).cloned())
+        .collect()
+}
+
+/// Adds the tuples to the accumulators of their group.
+fn accumulate_groups(
+    tuples: impl Iterator<Item = (Vec<Option<EncodedTerm>>, EncodedTuple)>,
+    accumulator_builders: &[Box<dyn Fn() -> Box<dyn Accumulator + Send> + Send + Sync>],
+    input_expressions: &[Option<Arc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm> + Send + Sync>>],
+) -> GroupAccumulators {
+    let mut accumulators_for_group = GroupAccumulators::default();
+    for (key, tuple) in tuples {
+        let key_accumulators = accumulators_for_group
+            .entry(key)
+            .or_insert_with(|| accumulator_builders.iter().map(|c| c()).collect::<Vec<_>>());
+        for (accumulator, input_expression) in key_accumulators.iter_mut().zip(input_expressions) {
+            accumulator.add(
+                input_expression
+                    .as_ref()
+                    .and_then(|parameter| parameter(&tuple)),
+            );
+        }
+    }
+    accumulators_for_group
+}
+
+/// Adds the tuples to the accumulators of their group using the workers.
+///
+/// Each group is handled by a single worker that gets its tuples in the same order
+/// so the accumulators end in the same state as with [`accumulate_groups`].
+fn parallel_accumulate_groups(
+    tuples: impl Iterator<Item = EncodedTuple>,
+    workers: Vec<Worker>,
+    key_mapping: &[(usize, usize)],
+    accumulator_builders: &Arc<Vec<Box<dyn Fn() -> Box<dyn Accumulator + Send> + Send + Sync>>>,
+    input_expressions: &Arc<
+        Vec<Option<Arc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm> + Send + Sync>>>,
+    >,
+) -> Result<Vec<GroupAccumulators>, EvaluationError> {
+    let mut batches = Vec::with_capacity(workers.len());
+    let mut handles = Vec::with_capacity(workers.len());
+    for worker in workers {
+        let (sender, receiver) =
+            sync_channel::<Vec<(Vec<Option<EncodedTerm>>, EncodedTuple)>>(AGGREGATION_CHANNEL_SIZE);
+        let accumulator_builders = accumulator_builders.clone();
+        let input_expressions = input_expressions.clone();
+        handles.push(worker.spawn(move || {
+            accumulate_groups(
+                receiver.into_iter().flatten(),
+                &accumulator_builders,
+                &input_expressions,
+            )
+        })?);
+        batches.push((sender, Vec::with_capacity(AGGREGATION_BATCH_SIZE)));
+    }
+    for tuple in tuples {
+        let key = group_key(&tuple, key_mapping);
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let worker_count = batches.len();
+        let (sender, batch) = &mut batches[(hasher.finish() as usize) % worker_count];
+        batch.push((key, tuple));
+        if batch.len() >= AGGREGATION_BATCH_SIZE && sender.send(take(batch)).is_err() {
+            break; // The worker has panicked, the panic is propagated below
+        }
+    }
+    for (sender, batch) in batches {
+        if !batch.is_empty() {
+            sender.send(batch).ok();
+        }
+    }
+    Ok(handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap_or_else(|panic| resume_unwind(panic)))
+        .collect())
+}
+
 fn hash_deduplicate<T: Eq + Hash + Clone>(
     iter: impl Iterator<Item = Result<T, EvaluationError>>,
 ) -> impl Iterator<Item = Result<T, EvaluationError>> {
@@ -3695,12 +3947,12 @@ impl Accumulator for AvgAccumulator {
 
 #[allow(clippy::option_option)]
 struct MinAccumulator {
-    dataset: Rc<DatasetView>,
+    dataset: Arc<DatasetView>,
     min: Option<Option<EncodedTerm>>,
 }
 
 impl MinAccumulator {
-    fn new(dataset: Rc<DatasetView>) -> Self {
+    fn new(dataset: Arc<DatasetView>) -> Self {
         Self { dataset, min: None }
     }
 }
@@ -3723,12 +3975,12 @@ impl Accumulator for MinAccumulator {
 
 #[allow(clippy::option_option)]
 struct MaxAccumulator {
-    dataset: Rc<DatasetView>,
+    dataset: Arc<DatasetView>,
     max: Option<Option<EncodedTerm>>,
 }
 
 impl MaxAccumulator {
-    fn new(dataset: Rc<DatasetView>) -> Self {
+    fn new(dataset: Arc<DatasetView>) -> Self {
         Self { dataset, max: None }
     }
 }
@@ -3768,14 +4020,14 @@ impl Accumulator for SampleAccumulator {
 
 #[allow(clippy::option_option)]
 struct GroupConcatAccumulator {
-    dataset: Rc<DatasetView>,
+    dataset: Arc<DatasetView>,
     concat: Option<String>,
     language: Option<Option<SmallStringOrId>>,
-    separator: Rc<String>,
+    separator: Arc<String>,
 }
 
 impl GroupConcatAccumulator {
-    fn new(dataset: Rc<DatasetView>, separator: Rc<String>) -> Self {
+    fn new(dataset: Arc<DatasetView>, separator: Arc<String>) -> Self {
         Self {
             dataset,
             concat: Some("".to_owned()),
@@ -3863,8 +4115,8 @@ impl From<StrHash> for SmallStringOrId {
 }
 
 pub enum ComparatorFunction {
-    Asc(Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>>),
-    Desc(Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>>),
+    Asc(Arc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm> + Send + Sync>),
+    Desc(Arc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm> + Send + Sync>),
 }
 
 struct EncodedTupleSet {
@@ -8,6 +8,7 @@ mod error;
 mod eval;
 mod http;
 mod model;
+pub(crate) mod parallel;
 mod plan;
 mod plan_builder;
 mod service;
@@ -28,7 +29,8 @@ pub use oxrdf::{Variable, VariableNameParseError};
 pub use sparesults::QueryResultsFormat;
 pub use spargebra::ParseError;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[allow(clippy::needless_pass_by_value)]
@@ -38,7 +40,8 @@ pub(crate) fn evaluate_query(
     options: QueryOptions,
 ) -> Result<QueryResults, EvaluationError> {
     let query = query.try_into().map_err(std::convert::Into::into)?;
-    let dataset = DatasetView::new(reader, &query.dataset);
+    let dataset = DatasetView::new(reader, &query.dataset)
+        .with_cancellation_flag(options.cancellation_flag.clone());
     match query.inner {
         spargebra::Query::Select {
             pattern, base_iri, ..
@@ -46,12 +49,13 @@ pub(crate) fn evaluate_query(
             let (plan, variables) =
                 PlanBuilder::build(&dataset, &pattern, true, &options.custom_functions)?;
             Ok(SimpleEvaluator::new(
-                Rc::new(dataset),
-                base_iri.map(Rc::new),
+                Arc::new(dataset),
+                base_iri.map(Arc::new),
                 options.service_handler(),
-                Rc::new(options.custom_functions),
+                Arc::new(options.custom_functions),
+                options.parallelism,
             )
-            .evaluate_select_plan(&plan, Rc::new(variables)))
+            .evaluate_select_plan(&plan, Arc::new(variables)))
         }
         spargebra::Query::Ask {
             pattern, base_iri, ..
@@ -59,10 +63,11 @@ pub(crate) fn evaluate_query(
             let (plan, _) =
                 PlanBuilder::build(&dataset, &pattern, false, &options.custom_functions)?;
             SimpleEvaluator::new(
-                Rc::new(dataset),
-                base_iri.map(Rc::new),
+                Arc::new(dataset),
+                base_iri.map(Arc::new),
                 options.service_handler(),
-                Rc::new(options.custom_functions),
+                Arc::new(options.custom_functions),
+                options.parallelism,
             )
             .evaluate_ask_plan(&plan)
         }
@@ -81,10 +86,11 @@ pub(crate) fn evaluate_query(
                 &options.custom_functions,
             );
             Ok(SimpleEvaluator::new(
-                Rc::new(dataset),
-                base_iri.map(Rc::new),
+                Arc::new(dataset),
+                base_iri.map(Arc::new),
                 options.service_handler(),
-                Rc::new(options.custom_functions),
+                Arc::new(options.custom_functions),
+                options.parallelism,
             )
             .evaluate_construct_plan(&plan, construct))
         }
@@ -94,12 +100,13 @@ pub(crate) fn evaluate_query(
             let (plan, _) =
                 PlanBuilder::build(&dataset, &pattern, false, &options.custom_functions)?;
             Ok(SimpleEvaluator::new(
-                Rc::new(dataset),
-                base_iri.map(Rc::new),
+                Arc::new(dataset),
+                base_iri.map(Arc::new),
                 options.service_handler(),
-                Rc::new(options.custom_functions),
+                Arc::new(options.custom_functions),
+                options.parallelism,
             )
-            .evaluate_describe_plan(&plan))
+            .evaluate_describe_plan(&plan, options.concise_bounded_description))
         }
     }
 }
@@ -124,9 +131,12 @@ pub(crate) fn evaluate_query(
 /// ```
 #[derive(Clone, Default)]
 pub struct QueryOptions {
-    service_handler: Option<Rc<dyn ServiceHandler<Error = EvaluationError>>>,
-    custom_functions: HashMap<NamedNode, Rc<dyn Fn(&[Term]) -> Option<Term>>>,
+    service_handler: Option<Arc<dyn ServiceHandler<Error = EvaluationError>>>,
+    custom_functions: HashMap<NamedNode, Arc<dyn Fn(&[Term]) -> Option<Term> + Send + Sync>>,
     http_timeout: Option<Duration>,
+    parallelism: usize,
+    concise_bounded_description: bool,
+    cancellation_flag: Option<Arc<AtomicBool>>,
 }
 
 impl QueryOptions {
@@ -134,7 +144,7 @@ impl QueryOptions {
     #[inline]
     #[must_use]
     pub fn with_service_handler(mut self, service_handler: impl ServiceHandler + 'static) -> Self {
-        self.service_handler = Some(Rc::new(ErrorConversionServiceHandler::wrap(
+        self.service_handler = Some(Arc::new(ErrorConversionServiceHandler::wrap(
             service_handler,
         )));
         self
@@ -144,7 +154,7 @@ impl QueryOptions {
     #[inline]
     #[must_use]
     pub fn without_service_handler(mut self) -> Self {
-        self.service_handler = Some(Rc::new(EmptyServiceHandler));
+        self.service_handler = Some(Arc::new(EmptyServiceHandler));
         self
     }
 
@@ -183,18 +193,100 @@ impl QueryOptions {
     pub fn with_custom_function(
         mut self,
         name: NamedNode,
-        evaluator: impl Fn(&[Term]) -> Option<Term> + 'static,
+        evaluator: impl Fn(&[Term]) -> Option<Term> + Send + Sync + 'static,
     ) -> Self {
-        self.custom_functions.insert(name, Rc::new(evaluator));
+        self.custom_functions.insert(name, Arc::new(evaluator));
         self
     }
 
-    fn service_handler(&self) -> Rc<dyn ServiceHandler<Error = EvaluationError>> {
+    /// Sets the maximal number of threads used to evaluate the query, including the calling thread.
+    ///
+    /// If more than one thread is allowed, the `UNION` operands, the hash joins, the large pattern scans and the aggregations
+    /// might be evaluated in parallel.
+    /// The solutions are the same as with the sequential evaluation but their order might change if the query has no `ORDER BY`.
+    ///
+    /// By default only the calling thread is used. The queries evaluated inside of transactions always use only the calling thread.
+    ///
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::sparql::QueryOptions;
+    ///
+    /// let store = Store::new()?;
+    /// store.query_opt(
+    ///     "SELECT (COUNT(*) AS ?c) WHERE { ?s ?p ?o }",
+    ///     QueryOptions::default().with_parallelism(4)
+    /// )?;
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Makes `DESCRIBE` queries return the [Concise Bounded Description](https://www.w3.org/Submission/CBD/) of the described resources.
+    ///
+    /// The triples whose subject is a blank node object of a returned triple are then also returned, recursively.
+    /// By default only the triples whose subject is a described resource are returned.
+    ///
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::sparql::{QueryOptions, QueryResults};
+    ///
+    /// let store = Store::new()?;
+    /// store.update("INSERT DATA { <http://example.com/s> <http://example.com/p> [ <http://example.com/p> <http://example.com/o> ] }")?;
+    /// if let QueryResults::Graph(triples) = store.query_opt(
+    ///     "DESCRIBE <http://example.com/s>",
+    ///     QueryOptions::default().with_concise_bounded_description()
+    /// )? {
+    ///     assert_eq!(triples.count(), 2);
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_concise_bounded_description(mut self) -> Self {
+        self.concise_bounded_description = true;
+        self
+    }
+
+    /// Cancels the evaluation as soon as the given flag is set to `true`.
+    ///
+    /// The evaluation, including the iteration on the results, then returns [`EvaluationError::Cancelled`] errors.
+    /// It allows to stop queries that take a long time before returning their first result like the ones with an `ORDER BY`.
+    ///
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::sparql::{EvaluationError, QueryOptions, QueryResults};
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let store = Store::new()?;
+    /// store.update("INSERT DATA { <http://example.com/s> <http://example.com/p> <http://example.com/o> }")?;
+    /// let cancellation_flag = Arc::new(AtomicBool::new(false));
+    /// cancellation_flag.store(true, Ordering::Relaxed); // Usually done by another thread
+    /// if let QueryResults::Solutions(mut solutions) = store.query_opt(
+    ///     "SELECT * WHERE { ?s ?p ?o } ORDER BY ?s",
+    ///     QueryOptions::default().with_cancellation_flag(cancellation_flag.clone())
+    /// )? {
+    ///     assert!(matches!(solutions.next(), Some(Err(EvaluationError::Cancelled))));
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_cancellation_flag(mut self, cancellation_flag: Arc<AtomicBool>) -> Self {
+        self.cancellation_flag = Some(cancellation_flag);
+        self
+    }
+
+    fn service_handler(&self) -> Arc<dyn ServiceHandler<Error = EvaluationError>> {
         self.service_handler.clone().unwrap_or_else(|| {
             if cfg!(feature = "http_client") {
-                Rc::new(service::SimpleServiceHandler::new(self.http_timeout))
+                Arc::new(service::SimpleServiceHandler::new(self.http_timeout))
             } else {
-                Rc::new(EmptyServiceHandler)
+                Arc::new(EmptyServiceHandler)
             }
         })
     }
@@ -9,7 +9,7 @@ use sparesults::{
     SolutionsReader,
 };
 use std::io::{BufRead, Write};
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// Results of a [SPARQL query](https://www.w3.org/TR/sparql11-query/).
 pub enum QueryResults {
@@ -24,7 +24,7 @@ pub enum QueryResults {
 impl QueryResults {
     /// Reads a SPARQL query results serialization.
     pub fn read(
-        reader: impl BufRead + 'static,
+        reader: impl BufRead + Send + 'static,
         format: QueryResultsFormat,
     ) -> Result<Self, ParseError> {
         Ok(QueryResultsParser::from_format(format)
@@ -137,7 +137,7 @@ impl From<QuerySolutionIter> for QueryResults {
     }
 }
 
-impl<R: BufRead + 'static> From<QueryResultsReader<R>> for QueryResults {
+impl<R: BufRead + Send + 'static> From<QueryResultsReader<R>> for QueryResults {
     fn from(reader: QueryResultsReader<R>) -> Self {
         match reader {
             QueryResultsReader::Solutions(s) => Self::Solutions(s.into()),
@@ -161,14 +161,14 @@ impl<R: BufRead + 'static> From<QueryResultsReader<R>> for QueryResults {
 /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
 /// ```
 pub struct QuerySolutionIter {
-    variables: Rc<Vec<Variable>>,
-    iter: Box<dyn Iterator<Item = Result<QuerySolution, EvaluationError>>>,
+    variables: Arc<Vec<Variable>>,
+    iter: Box<dyn Iterator<Item = Result<QuerySolution, EvaluationError>> + Send>,
 }
 
 impl QuerySolutionIter {
     pub fn new(
-        variables: Rc<Vec<Variable>>,
-        iter: impl Iterator<Item = Result<Vec<Option<Term>>, EvaluationError>> + 'static,
+        variables: Arc<Vec<Variable>>,
+        iter: impl Iterator<Item = Result<Vec<Option<Term>>, EvaluationError>> + Send + 'static,
     ) -> Self {
         Self {
             variables: variables.clone(),
@@ -194,10 +194,10 @@ impl QuerySolutionIter {
     }
 }
 
-impl<R: BufRead + 'static> From<SolutionsReader<R>> for QuerySolutionIter {
+impl<R: BufRead + Send + 'static> From<SolutionsReader<R>> for QuerySolutionIter {
     fn from(reader: SolutionsReader<R>) -> Self {
         Self {
-            variables: Rc::new(reader.variables().to_vec()),
+            variables: Arc::new(reader.variables().to_vec()),
             iter: Box::new(reader.map(|t| t.map_err(EvaluationError::from))),
         }
     }
@@ -232,7 +232,17 @@ impl Iterator for QuerySolutionIter {
 /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
 /// ```
 pub struct QueryTripleIter {
-    pub(crate) iter: Box<dyn Iterator<Item = Result<Triple, EvaluationError>>>,
+    pub(crate) iter: Box<dyn Iterator<Item = Result<Triple, EvaluationError>> + Send>,
+}
+
+impl QueryTripleIter {
+    pub fn new(
+        iter: impl Iterator<Item = Result<Triple, EvaluationError>> + Send + 'static,
+    ) -> Self {
+        Self {
+            iter: Box::new(iter),
+        }
+    }
 }
 
 impl Iterator for QueryTripleIter {
@@ -271,7 +281,7 @@ fn test_serialization_roundtrip() -> Result<(), EvaluationError> {
             QueryResults::Boolean(true),
             QueryResults::Boolean(false),
             QueryResults::Solutions(QuerySolutionIter::new(
-                Rc::new(vec![
+                Arc::new(vec![
                     Variable::new_unchecked("foo"),
                     Variable::new_unchecked("bar"),
                 ]),
@@ -0,0 +1,198 @@
+//! Threads used to evaluate parts of a query in parallel.
+
+use crate::sparql::error::EvaluationError;
+use lazy_static::lazy_static;
+use std::io;
+use std::iter::once;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Builder};
+use std::time::Duration;
+
+/// Number of results buffered between a worker and the consumer of its results.
+const WORKER_BUFFER_SIZE: usize = 1024;
+/// Duration after which an idle thread of the shared pool is stopped.
+const IDLE_THREAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+type Task = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    /// The threads waiting for a task, shared by all the query evaluations.
+    static ref IDLE_THREADS: Mutex<Vec<(usize, Sender<Task>)>> = Mutex::default();
+}
+
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The worker threads a query evaluation is allowed to use.
+///
+/// The thread evaluating the query is not counted.
+pub struct WorkerPool {
+    available: AtomicUsize,
+}
+
+impl WorkerPool {
+    /// A pool allowing to use at most `parallelism` threads including the current one.
+    pub fn new(parallelism: usize) -> Self {
+        Self {
+            available: AtomicUsize::new(if cfg!(target_arch = "wasm32") {
+                0 // No threads
+            } else {
+                parallelism.saturating_sub(1)
+            }),
+        }
+    }
+
+    /// Reserves at most `max` workers.
+    ///
+    /// Returns an empty vector if no worker is available: the evaluation should then be done in the current thread.
+    pub fn reserve(self: &Arc<Self>, max: usize) -> Vec<Worker> {
+        let mut available = self.available.load(Ordering::Relaxed);
+        loop {
+            let count = available.min(max);
+            if count == 0 {
+                return Vec::new();
+            }
+            match self.available.compare_exchange_weak(
+                available,
+                available - count,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return (0..count).map(|_| Worker { pool: self.clone() }).collect(),
+                Err(current) => available = current,
+            }
+        }
+    }
+}
+
+/// A reserved worker. It is given back to the pool when dropped.
+pub struct Worker {
+    pool: Arc<WorkerPool>,
+}
+
+impl Worker {
+    /// Runs `task` in a thread of the shared pool.
+    pub fn spawn<T: Send + 'static>(
+        self,
+        task: impl FnOnce() -> T + Send + 'static,
+    ) -> io::Result<TaskHandle<T>> {
+        let (sender, receiver) = sync_channel(1);
+        execute(Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(task));
+            drop(self); // The worker is given back before the result is sent
+            sender.send(result).ok();
+        }))?;
+        Ok(TaskHandle { receiver })
+    }
+
+    /// Consumes the iterator returned by `evaluate` in a new thread.
+    ///
+    /// The returned iterator yields the same results in the same order.
+    pub fn spawn_iter<T: Send + 'static, I: Iterator<Item = Result<T, EvaluationError>>>(
+        self,
+        evaluate: impl FnOnce() -> I + Send + 'static,
+    ) -> Box<dyn Iterator<Item = Result<T, EvaluationError>> + Send> {
+        let (sender, receiver) = sync_channel(WORKER_BUFFER_SIZE);
+        match self.spawn(move || {
+            for result in evaluate() {
+                if sender.send(result).is_err() {
+                    return; // The results are not consumed anymore
+                }
+            }
+        }) {
+            Ok(handle) => Box::new(WorkerIterator {
+                receiver,
+                handle: Some(handle),
+            }),
+            Err(e) => Box::new(once(Err(e.into()))),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.pool.available.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Allows to wait for the result of a task run by [`Worker::spawn`].
+pub struct TaskHandle<T> {
+    receiver: Receiver<thread::Result<T>>,
+}
+
+impl<T> TaskHandle<T> {
+    /// Waits for the task to be done. Returns an error if it has panicked.
+    pub fn join(self) -> thread::Result<T> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(Box::new("The task has been dropped without being run")))
+    }
+}
+
+/// Runs the task in an idle thread of the shared pool or, if there is none, in a new thread that joins the pool.
+fn execute(mut task: Task) -> io::Result<()> {
+    loop {
+        let idle = IDLE_THREADS.lock().unwrap().pop();
+        if let Some((_, thread)) = idle {
+            match thread.send(task) {
+                Ok(()) => return Ok(()),
+                Err(error) => task = error.0, // The thread is already stopped
+            }
+        } else {
+            break;
+        }
+    }
+    Builder::new()
+        .name("oxigraph-query".into())
+        .spawn(move || {
+            task();
+            let id = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+            loop {
+                let (sender, receiver) = channel::<Task>();
+                IDLE_THREADS.lock().unwrap().push((id, sender));
+                let task = match receiver.recv_timeout(IDLE_THREAD_TIMEOUT) {
+                    Ok(task) => task,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let mut idle = IDLE_THREADS.lock().unwrap();
+                        if let Some(position) = idle.iter().position(|(i, _)| *i == id) {
+                            idle.swap_remove(position);
+                            return;
+                        }
+                        drop(idle);
+                        // The thread has been picked just before the timeout, a task is coming
+                        match receiver.recv() {
+                            Ok(task) => task,
+                            Err(_) => return,
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+                task();
+            }
+        })
+        .map(|_| ())
+}
+
+struct WorkerIterator<T> {
+    receiver: Receiver<Result<T, EvaluationError>>,
+    handle: Option<TaskHandle<()>>,
+}
+
+impl<T> Iterator for WorkerIterator<T> {
+    type Item = Result<T, EvaluationError>;
+
+    fn next(&mut self) -> Option<Result<T, EvaluationError>> {
+        if let Ok(result) = self.receiver.recv() {
+            return Some(result);
+        }
+        // The worker is done, we make sure it has not panicked
+        if let Some(handle) = self.handle.take() {
+            if let Err(panic) = handle.join() {
+                resume_unwind(panic)
+            }
+        }
+        None
+    }
+}
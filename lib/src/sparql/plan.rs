@@ -5,7 +5,7 @@ use spargebra::algebra::GraphPattern;
 use std::cmp::max;
 use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet};
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum PlanNode {
@@ -14,9 +14,9 @@ pub enum PlanNode {
     },
     Service {
         service_name: PatternValue,
-        variables: Rc<Vec<Variable>>,
+        variables: Arc<Vec<Variable>>,
         child: Box<Self>,
-        graph_pattern: Rc<GraphPattern>,
+        graph_pattern: Arc<GraphPattern>,
         silent: bool,
     },
     QuadPattern {
@@ -27,7 +27,7 @@ pub enum PlanNode {
     },
     PathPattern {
         subject: PatternValue,
-        path: Rc<PlanPropertyPath>,
+        path: Arc<PlanPropertyPath>,
         object: PatternValue,
         graph_name: PatternValue,
     },
@@ -57,7 +57,7 @@ pub enum PlanNode {
     LeftJoin {
         left: Box<Self>,
         right: Box<Self>,
-        possible_problem_vars: Rc<Vec<usize>>, //Variables that should not be part of the entry of the left join
+        possible_problem_vars: Arc<Vec<usize>>, //Variables that should not be part of the entry of the left join
     },
     Extend {
         child: Box<Self>,
@@ -85,13 +85,13 @@ pub enum PlanNode {
     },
     Project {
         child: Box<Self>,
-        mapping: Rc<Vec<(usize, usize)>>, // pairs of (variable key in child, variable key in output)
+        mapping: Arc<Vec<(usize, usize)>>, // pairs of (variable key in child, variable key in output)
     },
     Aggregate {
         // By definition the group by key are the range 0..key_mapping.len()
         child: Box<Self>,
-        key_mapping: Rc<Vec<(usize, usize)>>, // aggregate key pairs of (variable key in child, variable key in output)
-        aggregates: Rc<Vec<(PlanAggregation, usize)>>,
+        key_mapping: Arc<Vec<(usize, usize)>>, // aggregate key pairs of (variable key in child, variable key in output)
+        aggregates: Arc<Vec<(PlanAggregation, usize)>>,
     },
 }
 
@@ -373,7 +373,7 @@ pub struct TriplePatternValue {
 pub enum PlanExpression {
     Constant(EncodedTerm),
     Variable(usize),
-    Exists(Rc<PlanNode>),
+    Exists(Arc<PlanNode>),
     Or(Box<Self>, Box<Self>),
     And(Box<Self>, Box<Self>),
     Equal(Box<Self>, Box<Self>),
@@ -588,19 +588,19 @@ pub enum PlanAggregationFunction {
     Max,
     Avg,
     Sample,
-    GroupConcat { separator: Rc<String> },
+    GroupConcat { separator: Arc<String> },
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum PlanPropertyPath {
     Path(EncodedTerm),
-    Reverse(Rc<Self>),
-    Sequence(Rc<Self>, Rc<Self>),
-    Alternative(Rc<Self>, Rc<Self>),
-    ZeroOrMore(Rc<Self>),
-    OneOrMore(Rc<Self>),
-    ZeroOrOne(Rc<Self>),
-    NegatedPropertySet(Rc<Vec<EncodedTerm>>),
+    Reverse(Arc<Self>),
+    Sequence(Arc<Self>, Arc<Self>),
+    Alternative(Arc<Self>, Arc<Self>),
+    ZeroOrMore(Arc<Self>),
+    OneOrMore(Arc<Self>),
+    ZeroOrOne(Arc<Self>),
+    NegatedPropertySet(Arc<Vec<EncodedTerm>>),
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
@@ -640,6 +640,11 @@ impl EncodedTuple {
         self.inner.capacity()
     }
 
+    /// Returns `true` if no variable is bound.
+    pub fn is_empty(&self) -> bool {
+        self.inner.iter().all(Option::is_none)
+    }
+
     pub fn contains(&self, index: usize) -> bool {
         self.inner.get(index).map_or(false, Option::is_some)
     }
@@ -10,11 +10,12 @@ use spargebra::algebra::*;
 use spargebra::term::*;
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::mem::swap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct PlanBuilder<'a> {
     dataset: &'a DatasetView,
-    custom_functions: &'a HashMap<NamedNode, Rc<dyn Fn(&[OxTerm]) -> Option<OxTerm>>>,
+    custom_functions:
+        &'a HashMap<NamedNode, Arc<dyn Fn(&[OxTerm]) -> Option<OxTerm> + Send + Sync>>,
 }
 
 impl<'a> PlanBuilder<'a> {
@@ -22,7 +23,10 @@ impl<'a> PlanBuilder<'a> {
         dataset: &'a DatasetView,
         pattern: &GraphPattern,
         is_cardinality_meaningful: bool,
-        custom_functions: &'a HashMap<NamedNode, Rc<dyn Fn(&[OxTerm]) -> Option<OxTerm>>>,
+        custom_functions: &'a HashMap<
+            NamedNode,
+            Arc<dyn Fn(&[OxTerm]) -> Option<OxTerm> + Send + Sync>,
+        >,
     ) -> Result<(PlanNode, Vec<Variable>), EvaluationError> {
         let mut variables = Vec::default();
         let plan = PlanBuilder {
@@ -50,7 +54,10 @@ impl<'a> PlanBuilder<'a> {
         dataset: &'a DatasetView,
         template: &[TriplePattern],
         mut variables: Vec<Variable>,
-        custom_functions: &'a HashMap<NamedNode, Rc<dyn Fn(&[OxTerm]) -> Option<OxTerm>>>,
+        custom_functions: &'a HashMap<
+            NamedNode,
+            Arc<dyn Fn(&[OxTerm]) -> Option<OxTerm> + Send + Sync>,
+        >,
     ) -> Vec<TripleTemplate> {
         PlanBuilder {
             dataset,
@@ -85,7 +92,7 @@ impl<'a> PlanBuilder<'a> {
                 object,
             } => PlanNode::PathPattern {
                 subject: self.pattern_value_from_term_or_variable(subject, variables),
-                path: Rc::new(self.build_for_path(path)),
+                path: Arc::new(self.build_for_path(path)),
                 object: self.pattern_value_from_term_or_variable(object, variables),
                 graph_name: graph_name.clone(),
             },
@@ -117,7 +124,7 @@ impl<'a> PlanBuilder<'a> {
                 PlanNode::LeftJoin {
                     left: Box::new(left),
                     right: Box::new(right),
-                    possible_problem_vars: Rc::new(possible_problem_vars.into_iter().collect()),
+                    possible_problem_vars: Arc::new(possible_problem_vars.into_iter().collect()),
                 }
             }
             GraphPattern::Filter { expr, inner } => Self::push_filter(
@@ -169,9 +176,9 @@ impl<'a> PlanBuilder<'a> {
                 let service_name = self.pattern_value_from_named_node_or_variable(name, variables);
                 PlanNode::Service {
                     service_name,
-                    variables: Rc::new(variables.clone()),
+                    variables: Arc::new(variables.clone()),
                     child: Box::new(child),
-                    graph_pattern: Rc::new(inner.as_ref().clone()),
+                    graph_pattern: Arc::new(inner.as_ref().clone()),
                     silent: *silent,
                 }
             }
@@ -190,7 +197,7 @@ impl<'a> PlanBuilder<'a> {
                         &mut inner_variables,
                         &inner_graph_name,
                     )?),
-                    key_mapping: Rc::new(
+                    key_mapping: Arc::new(
                         by.iter()
                             .map(|k| {
                                 (
@@ -200,7 +207,7 @@ impl<'a> PlanBuilder<'a> {
                             })
                             .collect(),
                     ),
-                    aggregates: Rc::new(
+                    aggregates: Arc::new(
                         aggregates
                             .iter()
                             .map(|(v, a)| {
@@ -249,7 +256,7 @@ impl<'a> PlanBuilder<'a> {
                         &mut inner_variables,
                         &inner_graph_name,
                     )?),
-                    mapping: Rc::new(
+                    mapping: Arc::new(
                         projection
                             .iter()
                             .enumerate()
@@ -293,27 +300,27 @@ impl<'a> PlanBuilder<'a> {
         match path {
             PropertyPathExpression::NamedNode(p) => PlanPropertyPath::Path(self.build_term(p)),
             PropertyPathExpression::Reverse(p) => {
-                PlanPropertyPath::Reverse(Rc::new(self.build_for_path(p)))
+                PlanPropertyPath::Reverse(Arc::new(self.build_for_path(p)))
             }
             PropertyPathExpression::Alternative(a, b) => PlanPropertyPath::Alternative(
-                Rc::new(self.build_for_path(a)),
-                Rc::new(self.build_for_path(b)),
+                Arc::new(self.build_for_path(a)),
+                Arc::new(self.build_for_path(b)),
             ),
             PropertyPathExpression::Sequence(a, b) => PlanPropertyPath::Sequence(
-                Rc::new(self.build_for_path(a)),
-                Rc::new(self.build_for_path(b)),
+                Arc::new(self.build_for_path(a)),
+                Arc::new(self.build_for_path(b)),
             ),
             PropertyPathExpression::ZeroOrMore(p) => {
-                PlanPropertyPath::ZeroOrMore(Rc::new(self.build_for_path(p)))
+                PlanPropertyPath::ZeroOrMore(Arc::new(self.build_for_path(p)))
             }
             PropertyPathExpression::OneOrMore(p) => {
-                PlanPropertyPath::OneOrMore(Rc::new(self.build_for_path(p)))
+                PlanPropertyPath::OneOrMore(Arc::new(self.build_for_path(p)))
             }
             PropertyPathExpression::ZeroOrOne(p) => {
-                PlanPropertyPath::ZeroOrOne(Rc::new(self.build_for_path(p)))
+                PlanPropertyPath::ZeroOrOne(Arc::new(self.build_for_path(p)))
             }
             PropertyPathExpression::NegatedPropertySet(p) => PlanPropertyPath::NegatedPropertySet(
-                Rc::new(p.iter().map(|p| self.build_term(p)).collect()),
+                Arc::new(p.iter().map(|p| self.build_term(p)).collect()),
             ),
         }
     }
@@ -753,7 +760,7 @@ impl<'a> PlanBuilder<'a> {
             ),
             Expression::Exists(n) => {
                 let mut variables = variables.clone(); // Do not expose the exists variables outside
-                PlanExpression::Exists(Rc::new(self.build_for_graph_pattern(
+                PlanExpression::Exists(Arc::new(self.build_for_graph_pattern(
                     n,
                     &mut variables,
                     graph_name,
@@ -937,7 +944,7 @@ impl<'a> PlanBuilder<'a> {
                 separator,
             } => Ok(PlanAggregation {
                 function: PlanAggregationFunction::GroupConcat {
-                    separator: Rc::new(separator.clone().unwrap_or_else(|| " ".to_owned())),
+                    separator: Arc::new(separator.clone().unwrap_or_else(|| " ".to_owned())),
                 },
                 parameter: Some(self.build_for_expression(expr, variables, graph_name)?),
                 distinct: *distinct,
@@ -5,7 +5,7 @@ use crate::sparql::http::Client;
 use crate::sparql::model::QueryResults;
 use crate::sparql::QueryResultsFormat;
 use std::error::Error;
-use std::io::BufReader;
+use std::io::{Cursor, Read};
 use std::time::Duration;
 
 /// Handler for [SPARQL 1.1 Federated Query](https://www.w3.org/TR/sparql11-federated-query/) SERVICE.
@@ -49,7 +49,7 @@ use std::time::Duration;
 /// }
 /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
 /// ```
-pub trait ServiceHandler {
+pub trait ServiceHandler: Send + Sync {
     type Error: Error + Send + Sync + 'static;
 
     /// Evaluates a [`Query`] against a given service identified by a [`NamedNode`](crate::model::NamedNode).
@@ -112,7 +112,7 @@ impl ServiceHandler for SimpleServiceHandler {
         service_name: NamedNode,
         query: Query,
     ) -> Result<QueryResults, EvaluationError> {
-        let (content_type, body) = self.client.post(
+        let (content_type, mut body) = self.client.post(
             service_name.as_str(),
             query.to_string().into_bytes(),
             "application/sparql-query",
@@ -124,6 +124,9 @@ impl ServiceHandler for SimpleServiceHandler {
                 service_name, content_type
             ))
         })?;
-        Ok(QueryResults::read(BufReader::new(body), format)?)
+        // The body is fully read in order for the returned results to be sendable between threads
+        let mut buffer = Vec::new();
+        body.read_to_end(&mut buffer)?;
+        Ok(QueryResults::read(Cursor::new(buffer), format)?)
     }
 }
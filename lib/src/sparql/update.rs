@@ -20,7 +20,7 @@ use spargebra::term::{
 use spargebra::GraphUpdateOperation;
 use std::collections::HashMap;
 use std::io::BufReader;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub fn evaluate_update<'a, 'b: 'a>(
     transaction: &'a mut StorageWriter<'b>,
@@ -29,7 +29,7 @@ pub fn evaluate_update<'a, 'b: 'a>(
 ) -> Result<(), EvaluationError> {
     SimpleUpdateEvaluator {
         transaction,
-        base_iri: update.inner.base_iri.clone().map(Rc::new),
+        base_iri: update.inner.base_iri.clone().map(Arc::new),
         options: options.clone(),
         client: Client::new(options.query_options.http_timeout),
     }
@@ -38,7 +38,7 @@ pub fn evaluate_update<'a, 'b: 'a>(
 
 struct SimpleUpdateEvaluator<'a, 'b> {
     transaction: &'a mut StorageWriter<'b>,
-    base_iri: Option<Rc<Iri<String>>>,
+    base_iri: Option<Arc<Iri<String>>>,
     options: UpdateOptions,
     client: Client,
 }
@@ -114,7 +114,10 @@ impl<'a, 'b: 'a> SimpleUpdateEvaluator<'a, 'b> {
         using: &QueryDataset,
         algebra: &GraphPattern,
     ) -> Result<(), EvaluationError> {
-        let dataset = Rc::new(DatasetView::new(self.transaction.reader(), using));
+        let dataset = Arc::new(
+            DatasetView::new(self.transaction.reader()?, using)
+                .with_cancellation_flag(self.options.query_options.cancellation_flag.clone()),
+        );
         let (plan, variables) = PlanBuilder::build(
             dataset.as_ref(),
             algebra,
@@ -125,7 +128,8 @@ impl<'a, 'b: 'a> SimpleUpdateEvaluator<'a, 'b> {
             dataset.clone(),
             self.base_iri.clone(),
             self.options.query_options.service_handler(),
-            Rc::new(self.options.query_options.custom_functions.clone()),
+            Arc::new(self.options.query_options.custom_functions.clone()),
+            1, // The transaction reader must not be used from multiple threads
         );
         let mut bnodes = HashMap::new();
         for tuple in evaluator.plan_evaluator(&plan)(EncodedTuple::with_capacity(variables.len())) {
@@ -193,7 +197,7 @@ impl<'a, 'b: 'a> SimpleUpdateEvaluator<'a, 'b> {
             GraphTarget::NamedNode(graph_name) => {
                 if self
                     .transaction
-                    .reader()
+                    .reader()?
                     .contains_named_graph(&graph_name.as_ref().into())?
                 {
                     Ok(self.transaction.clear_graph(graph_name.into())?)
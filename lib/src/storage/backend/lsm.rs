@@ -0,0 +1,2132 @@
+//! A pure Rust storage backend based on a [log-structured merge tree](https://en.wikipedia.org/wiki/Log-structured_merge-tree).
+//!
+//! The recent writes are kept in a multi-version in-memory table persisted using a write-ahead log.
+//! When this table becomes too large it is written to immutable sorted "run" files.
+//! The list of the run files and of the current write-ahead log is stored in the MANIFEST file.
+//! A read-write process holds an exclusive lock on the LOCK file while the database is open.
+
+use crate::storage::backend::{
+    Backend, BackendBulkInsertion, BackendIter, BackendReader, BackendTransaction, ColumnFamily,
+    ColumnFamilyDefinition, StoreOptions, Value,
+};
+use crate::storage::error::{CorruptionError, StorageError};
+use fs2::{lock_contended_error, FileExt};
+use siphasher::sip::SipHasher24;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::{
+    copy, create_dir, create_dir_all, hard_link, read, read_dir, read_to_string, remove_file,
+    rename, File, OpenOptions,
+};
+use std::hash::Hasher;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::mem::{replace, take};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Approximate size of the in-memory table before it is written to run files
+const MEMTABLE_MAX_SIZE: usize = 64 * 1024 * 1024;
+/// Approximate size of the run file blocks
+const BLOCK_SIZE: usize = 16 * 1024;
+/// Default size of the cache of the run blocks
+const DEFAULT_BLOCK_CACHE_SIZE: usize = 64 * 1024 * 1024;
+/// Number of keys read at once from the in-memory trees by the cursors
+const CURSOR_BATCH_SIZE: usize = 128;
+/// Number of runs per column family triggering a merge
+const MAX_RUNS_PER_COLUMN_FAMILY: usize = 8;
+const MANIFEST_HEADER: &str = "oxigraph-lsm 1";
+const RUN_MAGIC: &[u8; 8] = b"OXLSMRUN";
+const RUN_FOOTER_SIZE: usize = 32;
+/// Number of times a read-only open retries when a concurrent writer replaces the manifest
+const MAX_READ_ONLY_OPEN_ATTEMPTS: usize = 10;
+
+/// A key with its value or `None` if the key has been removed
+type Entry = (Vec<u8>, Option<Vec<u8>>);
+/// A write (column family index, key, new value or `None` for a removal)
+type Change = (usize, Vec<u8>, Option<Vec<u8>>);
+type Batch = Vec<Change>;
+/// For each key, the written values with their sequence numbers in increasing order
+type VersionedTree = BTreeMap<Vec<u8>, Vec<(u64, Option<Vec<u8>>)>>;
+type WriteSet = Vec<BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+
+#[derive(Clone)]
+pub struct Db(Arc<Inner>);
+
+struct Inner {
+    path: PathBuf,
+    column_families: Vec<ColumnFamilyDefinition>,
+    read_only: bool,
+    state: RwLock<State>,
+    writer: Mutex<Writer>,
+    block_cache: Arc<BlockCache>,
+    /// The locked LOCK file, only set in read-write mode
+    _lock: Option<File>,
+}
+
+/// A consistent version of the database content
+#[derive(Clone)]
+struct State {
+    /// Sequence number of the latest visible write
+    sequence: u64,
+    /// Number of in-memory tables written to runs so far
+    generation: u64,
+    /// Number of bulk insertions committed so far
+    bulk_insertions: u64,
+    memtable: Arc<Memtable>,
+    /// The runs of each column family, the newest first
+    runs: Arc<Vec<Vec<Arc<Run>>>>,
+}
+
+/// The write side of the database. Its lock is held during all writes to serialize them.
+struct Writer {
+    wal: Option<Wal>,
+    next_file: u64,
+    /// If the write-ahead log is synced to disk after each write
+    sync_writes: bool,
+}
+
+struct Wal {
+    name: String,
+    file: File,
+    len: u64,
+}
+
+impl Db {
+    pub fn open(
+        path: &Path,
+        column_families: &[ColumnFamilyDefinition],
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(Arc::new(Inner::open(
+            path.to_owned(),
+            column_families.to_vec(),
+            false,
+            options,
+        )?)))
+    }
+
+    pub fn open_read_only(
+        path: &Path,
+        column_families: &[ColumnFamilyDefinition],
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(Arc::new(Inner::open(
+            path.to_owned(),
+            column_families.to_vec(),
+            true,
+            options,
+        )?)))
+    }
+
+    pub fn purge_old_backups(
+        backup_directory: &Path,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        let mut backups = list_backups(backup_directory)?;
+        let num_backups_to_keep = usize::try_from(num_backups_to_keep).unwrap_or(usize::MAX);
+        let num_backups_to_remove = backups.len().saturating_sub(num_backups_to_keep);
+        for id in backups.drain(..num_backups_to_remove) {
+            remove_file(backup_directory.join("backups").join(id.to_string()))?;
+        }
+        // We remove the files not used anymore
+        let mut used_files = HashSet::new();
+        for id in backups {
+            let manifest = Manifest::read(&backup_directory.join("backups").join(id.to_string()))?;
+            used_files.extend(manifest.runs.into_iter().map(|(_, file)| file));
+        }
+        for file in read_dir(backup_directory.join("files"))? {
+            let file = file?;
+            if !used_files.contains(file.file_name().to_string_lossy().as_ref()) {
+                remove_file(file.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn restore_from_backup(
+        backup_directory: &Path,
+        target_directory: &Path,
+    ) -> Result<(), StorageError> {
+        if target_directory.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "The restoration target {} already exists",
+                    target_directory.display()
+                ),
+            )
+            .into());
+        }
+        if backup_directory.join("MANIFEST").is_file() {
+            // It is a copy created by `backup`: it is a regular database we copy
+            let manifest = Manifest::read(&backup_directory.join("MANIFEST"))?;
+            create_dir_all(target_directory)?;
+            for (_, file) in &manifest.runs {
+                copy(backup_directory.join(file), target_directory.join(file))?;
+            }
+            return manifest.write(target_directory);
+        }
+        let id = if let Some(id) = list_backups(backup_directory)?.pop() {
+            id
+        } else {
+            return Err(StorageError::Other(
+                format!("No backup found in {}", backup_directory.display()).into(),
+            ));
+        };
+        let manifest = Manifest::read(&backup_directory.join("backups").join(id.to_string()))?;
+        create_dir_all(target_directory)?;
+        for (_, file) in &manifest.runs {
+            copy(
+                backup_directory.join("files").join(file),
+                target_directory.join(file),
+            )?;
+        }
+        manifest.write(target_directory)
+    }
+
+    fn read_write(&self) -> Result<&Inner, StorageError> {
+        if self.0.read_only {
+            Err(StorageError::Other(
+                "This operation is not possible on a database opened in read-only mode".into(),
+            ))
+        } else {
+            Ok(&self.0)
+        }
+    }
+}
+
+impl Inner {
+    fn open(
+        path: PathBuf,
+        column_families: Vec<ColumnFamilyDefinition>,
+        read_only: bool,
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
+        let block_cache = Arc::new(BlockCache::new(
+            options.cache_size.unwrap_or(DEFAULT_BLOCK_CACHE_SIZE),
+        ));
+        let lock = if read_only {
+            None
+        } else {
+            create_dir_all(&path)?;
+            Some(lock_directory(&path)?)
+        };
+        let manifest_path = path.join("MANIFEST");
+        let mut manifest = if manifest_path.is_file() {
+            Manifest::read(&manifest_path)?
+        } else if read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No database found in {}", path.display()),
+            )
+            .into());
+        } else {
+            if read_dir(&path)?.any(|file| file.map_or(true, |file| file.file_name() != "LOCK")) {
+                return Err(StorageError::Other(
+                    format!(
+                        "The directory {} is not empty and does not contain an Oxigraph database",
+                        path.display()
+                    )
+                    .into(),
+                ));
+            }
+            Manifest::default()
+        };
+
+        let mut attempts = 0;
+        let (state, wal_len) = loop {
+            match load_files(&path, &column_families, &manifest, read_only, &block_cache) {
+                // A writer has replaced the files listed in the manifest since we read it
+                Err(StorageError::Io(e))
+                    if read_only
+                        && e.kind() == io::ErrorKind::NotFound
+                        && attempts < MAX_READ_ONLY_OPEN_ATTEMPTS =>
+                {
+                    attempts += 1;
+                    manifest = Manifest::read(&manifest_path)?;
+                }
+                result => break result?,
+            }
+        };
+
+        let mut writer = Writer {
+            wal: None,
+            next_file: manifest.next_file,
+            sync_writes: options.sync_writes,
+        };
+        if !read_only {
+            if let Some(name) = manifest.wal.filter(|wal| path.join(wal).is_file()) {
+                let mut file = OpenOptions::new().write(true).open(path.join(&name))?;
+                file.set_len(wal_len)?; // We remove the possibly broken end of the log
+                file.seek(SeekFrom::Start(wal_len))?;
+                writer.wal = Some(Wal {
+                    name,
+                    file,
+                    len: wal_len,
+                });
+            } else {
+                writer.wal = Some(writer.new_wal(&path)?);
+                Manifest {
+                    sequence: state.sequence,
+                    next_file: writer.next_file,
+                    wal: writer.wal.as_ref().map(|wal| wal.name.clone()),
+                    runs: manifest.runs.clone(),
+                }
+                .write(&path)?;
+            }
+            // We remove the files left by an interrupted operation
+            let used_files = manifest
+                .runs
+                .iter()
+                .map(|(_, file)| file.as_str())
+                .chain(writer.wal.as_ref().map(|wal| wal.name.as_str()))
+                .collect::<HashSet<_>>();
+            for file in read_dir(&path)? {
+                let file = file?;
+                let name = file.file_name().to_string_lossy().into_owned();
+                if (name.ends_with(".run") || name.ends_with(".wal") || name.ends_with(".tmp"))
+                    && !used_files.contains(name.as_str())
+                {
+                    remove_file(file.path())?;
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            column_families,
+            read_only,
+            state: RwLock::new(state),
+            writer: Mutex::new(writer),
+            block_cache,
+            _lock: lock,
+        })
+    }
+
+    fn current(&self) -> State {
+        self.state.read().unwrap().clone()
+    }
+
+    /// Writes a batch, failing if one of the `checked_keys` has been written since the `view` creation
+    fn write(
+        &self,
+        batch: Batch,
+        view: Option<&State>,
+        checked_keys: &[(usize, Vec<u8>)],
+    ) -> Result<(), StorageError> {
+        let mut writer = self.writer.lock().unwrap();
+        let state = self.current();
+        if let Some(view) = view {
+            if !checked_keys.is_empty() && has_conflict(view, &state, checked_keys) {
+                return Err(StorageError::Conflict);
+            }
+        }
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let sequence = state.sequence + 1;
+        writer.append(sequence, &batch)?;
+        state.memtable.apply(sequence, batch);
+        self.state.write().unwrap().sequence = sequence;
+        if state.memtable.size.load(Ordering::Relaxed) > MEMTABLE_MAX_SIZE {
+            self.flush_memtable(&mut writer)?;
+            self.merge_runs(&mut writer, false)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the in-memory table to runs and starts a new write-ahead log
+    fn flush_memtable(&self, writer: &mut Writer) -> Result<(), StorageError> {
+        let state = self.current();
+        if state.memtable.size.load(Ordering::Relaxed) == 0 {
+            return Ok(());
+        }
+        let mut runs = (*state.runs).clone();
+        let mut new_runs = Vec::new();
+        for (column_family, tree) in state.memtable.trees.read().unwrap().iter().enumerate() {
+            let mut entries = tree
+                .iter()
+                .filter_map(|(key, versions)| {
+                    let (_, value) = versions.last()?;
+                    // No need to keep the removals if there is no older run
+                    if value.is_none() && runs[column_family].is_empty() {
+                        None
+                    } else {
+                        Some((key.clone(), value.clone()))
+                    }
+                })
+                .peekable();
+            if entries.peek().is_some() {
+                let run = Arc::new(self.write_run(writer, entries)?);
+                // The run is removed if it is not added to the manifest
+                run.obsolete.store(true, Ordering::Relaxed);
+                runs[column_family].insert(0, Arc::clone(&run));
+                new_runs.push(run);
+            }
+        }
+        let new_wal = writer.new_wal(&self.path)?;
+        let old_wal = writer.wal.replace(new_wal);
+        if let Err(e) = self.write_manifest(writer, state.sequence, &runs) {
+            // We keep writing to the log listed in the manifest
+            if let Some(new_wal) = replace(&mut writer.wal, old_wal) {
+                drop(new_wal.file);
+                remove_file(self.path.join(new_wal.name)).ok();
+            }
+            return Err(e);
+        }
+        for run in new_runs {
+            run.obsolete.store(false, Ordering::Relaxed);
+        }
+        *self.state.write().unwrap() = State {
+            sequence: state.sequence,
+            generation: state.generation + 1,
+            bulk_insertions: state.bulk_insertions,
+            memtable: Arc::new(Memtable::new(self.column_families.len())),
+            runs: Arc::new(runs),
+        };
+        if let Some(old_wal) = old_wal {
+            drop(old_wal.file);
+            remove_file(self.path.join(old_wal.name))?;
+        }
+        Ok(())
+    }
+
+    /// Merges the runs of the column families with too many runs or, if `all` is set, all of them
+    fn merge_runs(&self, writer: &mut Writer, all: bool) -> Result<(), StorageError> {
+        let state = self.current();
+        let mut runs = (*state.runs).clone();
+        let mut merged_runs = Vec::new();
+        let mut new_runs = Vec::new();
+        for column_family_runs in &mut runs {
+            let count = if all {
+                column_family_runs.len()
+            } else if column_family_runs.len() > MAX_RUNS_PER_COLUMN_FAMILY {
+                // We merge the newest runs until the next one is much bigger than them
+                let mut count = 2;
+                let mut size = column_family_runs[0].size + column_family_runs[1].size;
+                while count < column_family_runs.len() && column_family_runs[count].size <= 2 * size
+                {
+                    size += column_family_runs[count].size;
+                    count += 1;
+                }
+                count
+            } else {
+                0
+            };
+            if count < 2 {
+                continue;
+            }
+            // The removals are only useful if there are older runs
+            let keep_removals = count < column_family_runs.len();
+            let mut cursors = column_family_runs[..count]
+                .iter()
+                .map(|run| Cursor::from_run(Arc::clone(run), &[]))
+                .collect::<Result<Vec<_>, _>>()?;
+            let name = writer.new_file_name("run");
+            let mut run_writer = RunWriter::new(self.path.join(&name))?;
+            let mut is_empty = true;
+            while let Some((key, value)) = next_entry(&mut cursors)? {
+                if keep_removals || value.is_some() {
+                    run_writer.add(&key, value.as_deref())?;
+                    is_empty = false;
+                }
+            }
+            let merged = if is_empty {
+                drop(run_writer);
+                remove_file(self.path.join(name))?;
+                None
+            } else {
+                let run = Arc::new(run_writer.finish(name, &self.block_cache)?);
+                // The run is removed if it is not added to the manifest
+                run.obsolete.store(true, Ordering::Relaxed);
+                new_runs.push(Arc::clone(&run));
+                Some(run)
+            };
+            merged_runs.extend(column_family_runs.drain(..count));
+            if let Some(merged) = merged {
+                column_family_runs.insert(0, merged);
+            }
+        }
+        if merged_runs.is_empty() {
+            return Ok(());
+        }
+        self.write_manifest(writer, state.sequence, &runs)?;
+        // The merged runs are only removed once the manifest does not list them anymore
+        for run in merged_runs {
+            run.obsolete.store(true, Ordering::Relaxed);
+        }
+        for run in new_runs {
+            run.obsolete.store(false, Ordering::Relaxed);
+        }
+        self.state.write().unwrap().runs = Arc::new(runs);
+        Ok(())
+    }
+
+    fn write_run(
+        &self,
+        writer: &mut Writer,
+        entries: impl Iterator<Item = Entry>,
+    ) -> Result<Run, StorageError> {
+        let name = writer.new_file_name("run");
+        let mut run_writer = RunWriter::new(self.path.join(&name))?;
+        for (key, value) in entries {
+            run_writer.add(&key, value.as_deref())?;
+        }
+        run_writer.finish(name, &self.block_cache)
+    }
+
+    fn write_manifest(
+        &self,
+        writer: &Writer,
+        sequence: u64,
+        runs: &[Vec<Arc<Run>>],
+    ) -> Result<(), StorageError> {
+        Manifest {
+            sequence,
+            next_file: writer.next_file,
+            wal: writer.wal.as_ref().map(|wal| wal.name.clone()),
+            runs: self.manifest_runs(runs),
+        }
+        .write(&self.path)
+    }
+
+    fn manifest_runs(&self, runs: &[Vec<Arc<Run>>]) -> Vec<(String, String)> {
+        runs.iter()
+            .zip(&self.column_families)
+            .flat_map(|(runs, cf)| {
+                runs.iter()
+                    .map(|run| (cf.name.to_owned(), run.name.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Opens the runs and replays the write-ahead log listed in the manifest.
+///
+/// Returns the database state and the length of the valid part of the log.
+fn load_files(
+    path: &Path,
+    column_families: &[ColumnFamilyDefinition],
+    manifest: &Manifest,
+    read_only: bool,
+    block_cache: &Arc<BlockCache>,
+) -> Result<(State, u64), StorageError> {
+    let mut runs = vec![Vec::new(); column_families.len()];
+    for (column_family, file) in &manifest.runs {
+        let index = column_families
+            .iter()
+            .position(|cf| cf.name == column_family)
+            .ok_or_else(|| {
+                CorruptionError::msg(format!("Unknown column family {}", column_family))
+            })?;
+        runs[index].push(Arc::new(Run::open(
+            path.join(file),
+            file.clone(),
+            Arc::clone(block_cache),
+        )?));
+    }
+
+    let memtable = Memtable::new(column_families.len());
+    let mut sequence = manifest.sequence;
+    let mut wal_len = 0;
+    if let Some(wal) = &manifest.wal {
+        let wal_path = path.join(wal);
+        // In read-only mode, a missing log has been written to runs by a concurrent writer
+        if read_only || wal_path.is_file() {
+            let (records, len) = decode_wal(&read(&wal_path)?, column_families.len());
+            for (record_sequence, batch) in records {
+                sequence = sequence.max(record_sequence);
+                memtable.apply(record_sequence, batch);
+            }
+            wal_len = len;
+        }
+    }
+    Ok((
+        State {
+            sequence,
+            generation: 0,
+            bulk_insertions: 0,
+            memtable: Arc::new(memtable),
+            runs: Arc::new(runs),
+        },
+        wal_len,
+    ))
+}
+
+/// Takes the exclusive lock of the database in `directory`, failing if another read-write instance holds it
+fn lock_directory(directory: &Path) -> Result<File, StorageError> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(directory.join("LOCK"))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(file),
+        Err(e) if e.raw_os_error() == lock_contended_error().raw_os_error() => {
+            Err(StorageError::Other(
+                format!(
+                    "The database {} is already opened in read-write mode by another instance",
+                    directory.display()
+                )
+                .into(),
+            ))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Checks if one of the keys has been written between `view` and `current`
+fn has_conflict(view: &State, current: &State, keys: &[(usize, Vec<u8>)]) -> bool {
+    if current.generation > view.generation + 1 {
+        return true; // We do not know anymore the sequence numbers of some writes
+    }
+    if current.bulk_insertions != view.bulk_insertions {
+        return true; // The bulk insertions do not keep track of the written keys
+    }
+    keys.iter().any(|(column_family, key)| {
+        view.memtable
+            .latest_sequence(*column_family, key)
+            .map_or(false, |sequence| sequence > view.sequence)
+            || (current.generation != view.generation
+                && current
+                    .memtable
+                    .latest_sequence(*column_family, key)
+                    .is_some())
+    })
+}
+
+impl Writer {
+    fn new_file_name(&mut self, extension: &str) -> String {
+        let name = format!("{:06}.{}", self.next_file, extension);
+        self.next_file += 1;
+        name
+    }
+
+    fn new_wal(&mut self, path: &Path) -> Result<Wal, StorageError> {
+        let name = self.new_file_name("wal");
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path.join(&name))?;
+        Ok(Wal { name, file, len: 0 })
+    }
+
+    fn append(&mut self, sequence: u64, batch: &[Change]) -> Result<(), StorageError> {
+        let wal = self.wal.as_mut().ok_or_else(|| {
+            StorageError::Other(
+                "This operation is not possible on a database opened in read-only mode".into(),
+            )
+        })?;
+        let record = encode_wal_record(sequence, batch)?;
+        if let Err(e) = wal.file.write_all(&record) {
+            // We try to remove the partially written record
+            wal.file.set_len(wal.len).ok();
+            wal.file.seek(SeekFrom::Start(wal.len)).ok();
+            return Err(e.into());
+        }
+        wal.len += record.len() as u64;
+        if self.sync_writes {
+            wal.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Db {
+    fn snapshot(&self) -> Box<dyn BackendReader> {
+        Box::new(Reader {
+            db: Arc::clone(&self.0),
+            view: self.0.current(),
+            writes: None,
+        })
+    }
+
+    fn transaction(&self) -> Result<Box<dyn BackendTransaction>, StorageError> {
+        let db = self.read_write()?;
+        Ok(Box::new(Transaction {
+            db: Arc::clone(&self.0),
+            view: db.current(),
+            writes: Arc::new(RwLock::new(vec![BTreeMap::new(); db.column_families.len()])),
+            read_for_update: Mutex::new(Vec::new()),
+        }))
+    }
+
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError> {
+        Ok(get_in_view(&self.0.current(), column_family.index(), key)?.map(Value::from))
+    }
+
+    fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.read_write()?.write(
+            vec![(column_family.index(), key.to_vec(), Some(value.to_vec()))],
+            None,
+            &[],
+        )
+    }
+
+    fn bulk_insertion(&self) -> Result<Box<dyn BackendBulkInsertion + '_>, StorageError> {
+        Ok(Box::new(RunBulkInsertion {
+            db: self.read_write()?,
+            runs: Vec::new(),
+        }))
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        let mut writer = db.writer.lock().unwrap();
+        db.flush_memtable(&mut writer)?;
+        db.merge_runs(&mut writer, false)
+    }
+
+    fn compact(&self) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        let mut writer = db.writer.lock().unwrap();
+        db.flush_memtable(&mut writer)?;
+        db.merge_runs(&mut writer, true)
+    }
+
+    fn disk_size(&self, column_family: &ColumnFamily) -> Result<Option<u64>, StorageError> {
+        Ok(Some(
+            self.0.current().runs[column_family.index()]
+                .iter()
+                .map(|run| run.size)
+                .sum(),
+        ))
+    }
+
+    fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        create_dir(target_directory)?;
+        let mut writer = db.writer.lock().unwrap();
+        db.flush_memtable(&mut writer)?;
+        let state = db.current();
+        for run in state.runs.iter().flatten() {
+            let target = target_directory.join(&run.name);
+            // The run files are immutable: we can use hard links if possible
+            if hard_link(&run.path, &target).is_err() {
+                copy(&run.path, &target)?;
+            }
+        }
+        Manifest {
+            sequence: state.sequence,
+            next_file: writer.next_file,
+            wal: None,
+            runs: db.manifest_runs(&state.runs),
+        }
+        .write(target_directory)
+    }
+
+    fn incremental_backup(&self, backup_directory: &Path) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        create_dir_all(backup_directory.join("files"))?;
+        create_dir_all(backup_directory.join("backups"))?;
+        let mut writer = db.writer.lock().unwrap();
+        db.flush_memtable(&mut writer)?;
+        let state = db.current();
+        for run in state.runs.iter().flatten() {
+            // The run files are immutable: we only copy the new ones
+            let target = backup_directory.join("files").join(&run.name);
+            if !target.exists() {
+                let temporary = backup_directory
+                    .join("files")
+                    .join(format!("{}.tmp", run.name));
+                copy(&run.path, &temporary)?;
+                rename(&temporary, &target)?;
+            }
+        }
+        let id = list_backups(backup_directory)?.pop().map_or(1, |id| id + 1);
+        let manifest = Manifest {
+            sequence: state.sequence,
+            next_file: writer.next_file,
+            wal: None,
+            runs: db.manifest_runs(&state.runs),
+        };
+        let temporary = backup_directory.join("backups").join(format!("{}.tmp", id));
+        write_synced(&temporary, manifest.to_string().as_bytes())?;
+        rename(
+            &temporary,
+            backup_directory.join("backups").join(id.to_string()),
+        )?;
+        Ok(())
+    }
+}
+
+/// The ids of the backups created by [`Backend::incremental_backup`] in increasing order
+fn list_backups(backup_directory: &Path) -> Result<Vec<u64>, StorageError> {
+    let mut ids = Vec::new();
+    for file in read_dir(backup_directory.join("backups"))? {
+        if let Ok(id) = file?.file_name().to_string_lossy().parse() {
+            ids.push(id);
+        }
+    }
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+fn get_in_view(
+    view: &State,
+    column_family: usize,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>, StorageError> {
+    if let Some(value) = view.memtable.get(column_family, key, view.sequence) {
+        return Ok(value);
+    }
+    for run in &view.runs[column_family] {
+        if let Some(value) = run.get(key)? {
+            return Ok(value);
+        }
+    }
+    Ok(None)
+}
+
+pub struct Reader {
+    // Keeps the database directory alive
+    db: Arc<Inner>,
+    view: State,
+    /// The writes of the transaction the reader belongs to
+    writes: Option<Arc<RwLock<WriteSet>>>,
+}
+
+impl BackendReader for Reader {
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError> {
+        if let Some(writes) = &self.writes {
+            if let Some(value) = writes.read().unwrap()[column_family.index()].get(key) {
+                return Ok(value.clone().map(Value::from));
+            }
+        }
+        Ok(get_in_view(&self.view, column_family.index(), key)?.map(Value::from))
+    }
+
+    fn scan_prefix(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<Box<dyn BackendIter>, StorageError> {
+        let column_family = column_family.index();
+        let mut cursors = Vec::with_capacity(self.view.runs[column_family].len() + 2);
+        if let Some(writes) = &self.writes {
+            cursors.push(Cursor::from_memory(
+                MemoryTree::Writes(Arc::clone(writes), column_family),
+                prefix,
+            )?);
+        }
+        cursors.push(Cursor::from_memory(
+            MemoryTree::Memtable(
+                Arc::clone(&self.view.memtable),
+                column_family,
+                self.view.sequence,
+            ),
+            prefix,
+        )?);
+        for run in &self.view.runs[column_family] {
+            cursors.push(Cursor::from_run(Arc::clone(run), prefix)?);
+        }
+        Ok(Box::new(Iter::new(
+            cursors,
+            prefix.to_vec(),
+            Arc::clone(&self.db),
+        )))
+    }
+}
+
+pub struct Transaction {
+    db: Arc<Inner>,
+    view: State,
+    writes: Arc<RwLock<WriteSet>>,
+    read_for_update: Mutex<Vec<(usize, Vec<u8>)>>,
+}
+
+impl BackendTransaction for Transaction {
+    fn reader(&self) -> Box<dyn BackendReader> {
+        Box::new(Reader {
+            db: Arc::clone(&self.db),
+            view: self.view.clone(),
+            writes: Some(Arc::clone(&self.writes)),
+        })
+    }
+
+    fn contains_key_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        self.read_for_update
+            .lock()
+            .unwrap()
+            .push((column_family.index(), key.to_vec()));
+        if let Some(value) = self.writes.read().unwrap()[column_family.index()].get(key) {
+            return Ok(value.is_some());
+        }
+        Ok(get_in_view(&self.view, column_family.index(), key)?.is_some())
+    }
+
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.writes.write().unwrap()[column_family.index()]
+            .insert(key.to_vec(), Some(value.to_vec()));
+        Ok(())
+    }
+
+    fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+        self.writes.write().unwrap()[column_family.index()].insert(key.to_vec(), None);
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        let writes = take(&mut *self.writes.write().unwrap());
+        let mut checked_keys = take(&mut *self.read_for_update.lock().unwrap());
+        let mut batch = Vec::new();
+        for (column_family, tree) in writes.into_iter().enumerate() {
+            for (key, value) in tree {
+                checked_keys.push((column_family, key.clone()));
+                batch.push((column_family, key, value));
+            }
+        }
+        self.db.write(batch, Some(&self.view), &checked_keys)
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// A bulk insertion writing run files that are all added to the database when committed.
+struct RunBulkInsertion<'a> {
+    db: &'a Inner,
+    runs: Vec<(usize, Run)>,
+}
+
+impl BackendBulkInsertion for RunBulkInsertion<'_> {
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        entries: &mut dyn Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), StorageError> {
+        let mut entries = entries.peekable();
+        if entries.peek().is_none() {
+            return Ok(());
+        }
+        let name = self.db.writer.lock().unwrap().new_file_name("run");
+        let mut run_writer = RunWriter::new(self.db.path.join(&name))?;
+        for (key, value) in entries {
+            run_writer.add(&key, Some(&value))?;
+        }
+        let run = run_writer.finish(name, &self.db.block_cache)?;
+        // The run is removed if the insertion is not committed
+        run.obsolete.store(true, Ordering::Relaxed);
+        self.runs.push((column_family.index(), run));
+        Ok(())
+    }
+
+    fn remove(
+        &mut self,
+        column_family: &ColumnFamily,
+        keys: &mut dyn Iterator<Item = Vec<u8>>,
+    ) -> Result<(), StorageError> {
+        let mut keys = keys.peekable();
+        if keys.peek().is_none() {
+            return Ok(());
+        }
+        let name = self.db.writer.lock().unwrap().new_file_name("run");
+        let mut run_writer = RunWriter::new(self.db.path.join(&name))?;
+        for key in keys {
+            run_writer.add(&key, None)?;
+        }
+        let run = run_writer.finish(name, &self.db.block_cache)?;
+        run.obsolete.store(true, Ordering::Relaxed);
+        self.runs.push((column_family.index(), run));
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        if self.runs.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self.db.writer.lock().unwrap();
+        // The new entries must override the previous writes
+        self.db.flush_memtable(&mut writer)?;
+        let state = self.db.current();
+        let mut runs = (*state.runs).clone();
+        let mut new_runs = Vec::with_capacity(self.runs.len());
+        for (column_family, run) in self.runs {
+            let run = Arc::new(run);
+            runs[column_family].insert(0, Arc::clone(&run));
+            new_runs.push(run);
+        }
+        self.db.write_manifest(&writer, state.sequence, &runs)?;
+        for run in new_runs {
+            run.obsolete.store(false, Ordering::Relaxed);
+        }
+        {
+            let mut state = self.db.state.write().unwrap();
+            state.runs = Arc::new(runs);
+            state.bulk_insertions += 1;
+        }
+        self.db.merge_runs(&mut writer, false)
+    }
+}
+
+/// The multi-version in-memory table
+struct Memtable {
+    trees: RwLock<Vec<VersionedTree>>,
+    size: AtomicUsize,
+}
+
+impl Memtable {
+    fn new(column_families: usize) -> Self {
+        Self {
+            trees: RwLock::new(vec![BTreeMap::new(); column_families]),
+            size: AtomicUsize::new(0),
+        }
+    }
+
+    fn apply(&self, sequence: u64, batch: Batch) {
+        let mut trees = self.trees.write().unwrap();
+        let mut size = 0;
+        for (column_family, key, value) in batch {
+            size += key.len() + value.as_ref().map_or(0, Vec::len) + 32;
+            trees[column_family]
+                .entry(key)
+                .or_default()
+                .push((sequence, value));
+        }
+        self.size.fetch_add(size, Ordering::Relaxed);
+    }
+
+    /// Returns `Some(value)` if the key has been written in the table before `sequence`
+    fn get(&self, column_family: usize, key: &[u8], sequence: u64) -> Option<Option<Vec<u8>>> {
+        let trees = self.trees.read().unwrap();
+        let (_, value) = trees[column_family]
+            .get(key)?
+            .iter()
+            .rev()
+            .find(|(s, _)| *s <= sequence)?;
+        Some(value.clone())
+    }
+
+    fn latest_sequence(&self, column_family: usize, key: &[u8]) -> Option<u64> {
+        let trees = self.trees.read().unwrap();
+        Some(trees[column_family].get(key)?.last()?.0)
+    }
+}
+
+/// Reads up to [`CURSOR_BATCH_SIZE`] keys of `tree` from `start` and adds to `entries` the ones for which `value` returns an entry.
+///
+/// Returns the start of the next batch or `None` if the end of the tree has been reached.
+fn read_batch<V>(
+    tree: &BTreeMap<Vec<u8>, V>,
+    start: &Bound<Vec<u8>>,
+    entries: &mut Vec<Entry>,
+    value: impl Fn(&V) -> Option<Option<Vec<u8>>>,
+) -> Option<Bound<Vec<u8>>> {
+    let mut count = 0;
+    let mut last = None;
+    for (key, versions) in tree
+        .range::<[u8], _>((start.as_ref().map(Vec::as_slice), Bound::Unbounded))
+        .take(CURSOR_BATCH_SIZE)
+    {
+        if let Some(value) = value(versions) {
+            entries.push((key.clone(), value));
+        }
+        count += 1;
+        last = Some(key);
+    }
+    if count < CURSOR_BATCH_SIZE {
+        None
+    } else {
+        last.map(|key| Bound::Excluded(key.clone()))
+    }
+}
+
+/// An immutable sorted file.
+///
+/// It is composed of blocks of entries followed by an index with the first key of each block and a footer.
+struct Run {
+    /// The identifier of the run in the block cache
+    id: u64,
+    name: String,
+    path: PathBuf,
+    /// Always set, only emptied to close the file before removing it
+    file: Option<File>,
+    blocks: Vec<BlockHandle>,
+    size: u64,
+    cache: Arc<BlockCache>,
+    /// If the file should be removed when not used anymore
+    obsolete: AtomicBool,
+}
+
+struct BlockHandle {
+    first_key: Vec<u8>,
+    offset: u64,
+    len: usize,
+    checksum: u64,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.cache.remove_run(self.id, self.blocks.len());
+        if self.obsolete.load(Ordering::Relaxed) {
+            self.file = None; // We close the file before removing it
+            remove_file(&self.path).ok();
+        }
+    }
+}
+
+impl Run {
+    fn open(path: PathBuf, name: String, cache: Arc<BlockCache>) -> Result<Self, StorageError> {
+        let file = File::open(&path)?;
+        let size = file.metadata()?.len();
+        let footer_offset = size
+            .checked_sub(RUN_FOOTER_SIZE as u64)
+            .ok_or_else(|| CorruptionError::msg(format!("The run {} is too short", name)))?;
+        let mut footer = [0; RUN_FOOTER_SIZE];
+        read_exact_at(&file, &mut footer, footer_offset)?;
+        let mut decoder = Decoder(&footer);
+        let index_offset = decoder.read_u64()?;
+        let index_len = decoder.read_u64()?;
+        let index_checksum = decoder.read_u64()?;
+        if decoder.0 != &RUN_MAGIC[..] {
+            return Err(CorruptionError::msg(format!("{} is not a run file", name)).into());
+        }
+        let mut index = vec![0; to_usize(index_len)?];
+        read_exact_at(&file, &mut index, index_offset)?;
+        if checksum(&index) != index_checksum {
+            return Err(
+                CorruptionError::msg(format!("The index of run {} is corrupted", name)).into(),
+            );
+        }
+        let mut decoder = Decoder(&index);
+        let mut blocks = Vec::new();
+        while !decoder.0.is_empty() {
+            blocks.push(BlockHandle {
+                first_key: decoder.read_bytes()?.to_vec(),
+                offset: decoder.read_u64()?,
+                len: to_usize(decoder.read_u64()?)?,
+                checksum: decoder.read_u64()?,
+            });
+        }
+        Ok(Self {
+            id: cache.new_run_id(),
+            name,
+            path,
+            file: Some(file),
+            blocks,
+            size,
+            cache,
+            obsolete: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns `Some(value)` if the key is in the run
+    fn get(&self, key: &[u8]) -> Result<Option<Option<Vec<u8>>>, StorageError> {
+        let count = self
+            .blocks
+            .partition_point(|block| block.first_key.as_slice() <= key);
+        if count == 0 {
+            return Ok(None);
+        }
+        let entries = self.read_block(count - 1)?;
+        Ok(entries
+            .binary_search_by(|(k, _)| k.as_slice().cmp(key))
+            .ok()
+            .map(|i| entries[i].1.clone()))
+    }
+
+    /// The index of the block that might contain the key
+    fn block_for(&self, key: &[u8]) -> usize {
+        self.blocks
+            .partition_point(|block| block.first_key.as_slice() <= key)
+            .saturating_sub(1)
+    }
+
+    fn read_block(&self, index: usize) -> Result<Arc<Vec<Entry>>, StorageError> {
+        if let Some(entries) = self.cache.get(self.id, index) {
+            return Ok(entries);
+        }
+        let handle = &self.blocks[index];
+        let mut data = vec![0; handle.len];
+        let file = self.file.as_ref().ok_or_else(|| {
+            StorageError::Other(format!("The run {} is closed", self.name).into())
+        })?;
+        read_exact_at(file, &mut data, handle.offset)?;
+        if checksum(&data) != handle.checksum {
+            return Err(CorruptionError::msg(format!(
+                "The block {} of run {} is corrupted",
+                index, self.name
+            ))
+            .into());
+        }
+        let mut decoder = Decoder(&data);
+        let mut entries = Vec::new();
+        while !decoder.0.is_empty() {
+            let key = decoder.read_bytes()?.to_vec();
+            let value = match decoder.read_u8()? {
+                0 => None,
+                1 => Some(decoder.read_bytes()?.to_vec()),
+                _ => {
+                    return Err(
+                        CorruptionError::msg(format!("Invalid entry in run {}", self.name)).into(),
+                    )
+                }
+            };
+            entries.push((key, value));
+        }
+        let entries = Arc::new(entries);
+        self.cache
+            .insert(self.id, index, Arc::clone(&entries), handle.len);
+        Ok(entries)
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+
+    file.read_exact_at(buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(len) => {
+                buffer = &mut buffer[len..];
+                offset += len as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// A cache of the decoded run blocks shared by all the runs of a database.
+///
+/// The least recently used blocks are evicted when the total size of the cached blocks exceeds the capacity.
+struct BlockCache {
+    capacity: usize,
+    next_run_id: AtomicU64,
+    state: Mutex<BlockCacheState>,
+}
+
+#[derive(Default)]
+struct BlockCacheState {
+    /// The blocks indexed by run id and block index
+    blocks: HashMap<(u64, usize), CachedBlock>,
+    /// The keys of the blocks indexed by last use time
+    uses: BTreeMap<u64, (u64, usize)>,
+    size: usize,
+    time: u64,
+}
+
+struct CachedBlock {
+    entries: Arc<Vec<Entry>>,
+    size: usize,
+    last_use: u64,
+}
+
+impl BlockCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_run_id: AtomicU64::new(0),
+            state: Mutex::default(),
+        }
+    }
+
+    fn new_run_id(&self) -> u64 {
+        self.next_run_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn get(&self, run: u64, index: usize) -> Option<Arc<Vec<Entry>>> {
+        let mut state = self.state.lock().unwrap();
+        let BlockCacheState {
+            blocks, uses, time, ..
+        } = &mut *state;
+        let block = blocks.get_mut(&(run, index))?;
+        uses.remove(&block.last_use);
+        *time += 1;
+        block.last_use = *time;
+        uses.insert(*time, (run, index));
+        Some(Arc::clone(&block.entries))
+    }
+
+    fn insert(&self, run: u64, index: usize, entries: Arc<Vec<Entry>>, size: usize) {
+        if size > self.capacity {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.time += 1;
+        let last_use = state.time;
+        let block = CachedBlock {
+            entries,
+            size,
+            last_use,
+        };
+        if let Some(old) = state.blocks.insert((run, index), block) {
+            state.uses.remove(&old.last_use);
+            state.size -= old.size;
+        }
+        state.uses.insert(last_use, (run, index));
+        state.size += size;
+        while state.size > self.capacity {
+            if let Some((_, key)) = state.uses.pop_first() {
+                if let Some(evicted) = state.blocks.remove(&key) {
+                    state.size -= evicted.size;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Removes the blocks of a run that is not used anymore
+    fn remove_run(&self, run: u64, block_count: usize) {
+        let mut state = self.state.lock().unwrap();
+        for index in 0..block_count {
+            if let Some(removed) = state.blocks.remove(&(run, index)) {
+                state.uses.remove(&removed.last_use);
+                state.size -= removed.size;
+            }
+        }
+    }
+}
+
+struct RunWriter {
+    file: File,
+    path: PathBuf,
+    blocks: Vec<BlockHandle>,
+    block: Vec<u8>,
+    block_first_key: Vec<u8>,
+    offset: u64,
+}
+
+impl RunWriter {
+    fn new(path: PathBuf) -> Result<Self, StorageError> {
+        Ok(Self {
+            file: OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?,
+            path,
+            blocks: Vec::new(),
+            block: Vec::with_capacity(BLOCK_SIZE),
+            block_first_key: Vec::new(),
+            offset: 0,
+        })
+    }
+
+    /// Adds an entry. The keys must be added in increasing order.
+    fn add(&mut self, key: &[u8], value: Option<&[u8]>) -> Result<(), StorageError> {
+        if self.block.is_empty() {
+            self.block_first_key = key.to_vec();
+        }
+        write_bytes(&mut self.block, key)?;
+        if let Some(value) = value {
+            self.block.push(1);
+            write_bytes(&mut self.block, value)?;
+        } else {
+            self.block.push(0);
+        }
+        if self.block.len() >= BLOCK_SIZE {
+            self.finish_block()?;
+        }
+        Ok(())
+    }
+
+    fn finish_block(&mut self) -> Result<(), StorageError> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        self.file.write_all(&self.block)?;
+        self.blocks.push(BlockHandle {
+            first_key: take(&mut self.block_first_key),
+            offset: self.offset,
+            len: self.block.len(),
+            checksum: checksum(&self.block),
+        });
+        self.offset += self.block.len() as u64;
+        self.block.clear();
+        Ok(())
+    }
+
+    fn finish(mut self, name: String, cache: &Arc<BlockCache>) -> Result<Run, StorageError> {
+        self.finish_block()?;
+        let mut index = Vec::new();
+        for block in &self.blocks {
+            write_bytes(&mut index, &block.first_key)?;
+            index.extend_from_slice(&block.offset.to_be_bytes());
+            index.extend_from_slice(&(block.len as u64).to_be_bytes());
+            index.extend_from_slice(&block.checksum.to_be_bytes());
+        }
+        let mut footer = Vec::with_capacity(RUN_FOOTER_SIZE);
+        footer.extend_from_slice(&self.offset.to_be_bytes());
+        footer.extend_from_slice(&(index.len() as u64).to_be_bytes());
+        footer.extend_from_slice(&checksum(&index).to_be_bytes());
+        footer.extend_from_slice(RUN_MAGIC);
+        self.file.write_all(&index)?;
+        self.file.write_all(&footer)?;
+        self.file.sync_all()?;
+        drop(self.file);
+        Run::open(self.path, name, Arc::clone(cache))
+    }
+}
+
+/// A sorted list of entries read by blocks from a run or by batches from an in-memory tree
+struct Cursor {
+    entries: Arc<Vec<Entry>>,
+    position: usize,
+    source: CursorSource,
+}
+
+enum CursorSource {
+    /// The run and the index of its next block to read
+    Run(Arc<Run>, usize),
+    /// The tree and the start of its next batch to read or `None` if it has been fully read
+    Memory(MemoryTree, Option<Bound<Vec<u8>>>),
+}
+
+/// A column family of an in-memory table read at a given sequence number or of the writes of a transaction
+enum MemoryTree {
+    Memtable(Arc<Memtable>, usize, u64),
+    Writes(Arc<RwLock<WriteSet>>, usize),
+}
+
+impl MemoryTree {
+    fn read_batch(
+        &self,
+        start: &Bound<Vec<u8>>,
+        entries: &mut Vec<Entry>,
+    ) -> Option<Bound<Vec<u8>>> {
+        match self {
+            Self::Memtable(memtable, column_family, sequence) => read_batch(
+                &memtable.trees.read().unwrap()[*column_family],
+                start,
+                entries,
+                |versions| {
+                    let (_, value) = versions.iter().rev().find(|(s, _)| s <= sequence)?;
+                    Some(value.clone())
+                },
+            ),
+            Self::Writes(writes, column_family) => read_batch(
+                &writes.read().unwrap()[*column_family],
+                start,
+                entries,
+                |value| Some(value.clone()),
+            ),
+        }
+    }
+}
+
+impl Cursor {
+    fn from_run(run: Arc<Run>, start: &[u8]) -> Result<Self, StorageError> {
+        Self::new(CursorSource::Run(run, 0), start)
+    }
+
+    fn from_memory(tree: MemoryTree, start: &[u8]) -> Result<Self, StorageError> {
+        Self::new(CursorSource::Memory(tree, Some(Bound::Unbounded)), start)
+    }
+
+    fn new(source: CursorSource, start: &[u8]) -> Result<Self, StorageError> {
+        let mut cursor = Self {
+            entries: Arc::new(Vec::new()),
+            position: 0,
+            source,
+        };
+        cursor.seek(start)?;
+        Ok(cursor)
+    }
+
+    fn current(&self) -> Option<&Entry> {
+        self.entries.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<(), StorageError> {
+        self.position += 1;
+        if self.position >= self.entries.len() {
+            self.read_next_block()?;
+        }
+        Ok(())
+    }
+
+    /// Moves to the first entry whose key is greater or equal to `key` if it is after the current one
+    fn seek(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        if self
+            .entries
+            .last()
+            .map_or(true, |(last, _)| last.as_slice() < key)
+        {
+            // We jump directly to the right block or batch
+            let jump = match &mut self.source {
+                CursorSource::Run(run, next_block) => {
+                    let block = run.block_for(key);
+                    if block >= *next_block {
+                        *next_block = block;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                CursorSource::Memory(_, next) => {
+                    if next.is_some() {
+                        *next = Some(Bound::Included(key.to_vec()));
+                        true
+                    } else {
+                        false
+                    }
+                }
+            };
+            if jump {
+                self.entries = Arc::new(Vec::new());
+                self.position = 0;
+            }
+        }
+        loop {
+            self.position += self.entries[self.position.min(self.entries.len())..]
+                .partition_point(|(k, _)| k.as_slice() < key);
+            if self.position < self.entries.len() || !self.read_next_block()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns `false` if there is no next block
+    fn read_next_block(&mut self) -> Result<bool, StorageError> {
+        self.entries = match &mut self.source {
+            CursorSource::Run(run, next_block) => {
+                if *next_block >= run.blocks.len() {
+                    return Ok(false);
+                }
+                let entries = run.read_block(*next_block)?;
+                *next_block += 1;
+                entries
+            }
+            CursorSource::Memory(tree, next) => {
+                let mut entries = Vec::new();
+                // The batches might only contain versions not visible to the cursor
+                while entries.is_empty() {
+                    if let Some(start) = next.take() {
+                        *next = tree.read_batch(&start, &mut entries);
+                    } else {
+                        return Ok(false);
+                    }
+                }
+                Arc::new(entries)
+            }
+        };
+        self.position = 0;
+        Ok(true)
+    }
+}
+
+/// Returns the next entry of the union of the cursors.
+///
+/// If the same key is in multiple cursors, the entry of the first cursor is used.
+fn next_entry(cursors: &mut [Cursor]) -> Result<Option<Entry>, StorageError> {
+    let mut best: Option<(usize, &Entry)> = None;
+    for (i, cursor) in cursors.iter().enumerate() {
+        if let Some(entry) = cursor.current() {
+            if best.map_or(true, |(_, (best_key, _))| entry.0 < *best_key) {
+                best = Some((i, entry));
+            }
+        }
+    }
+    let entry = if let Some((_, entry)) = best {
+        entry.clone()
+    } else {
+        return Ok(None);
+    };
+    for cursor in cursors {
+        if cursor.current().map_or(false, |(key, _)| *key == entry.0) {
+            cursor.next()?;
+        }
+    }
+    Ok(Some(entry))
+}
+
+pub struct Iter {
+    cursors: Vec<Cursor>,
+    prefix: Vec<u8>,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+    error: Option<StorageError>,
+    _db: Arc<Inner>, // needed to ensure that the database directory still exists while iter is used
+}
+
+impl Iter {
+    fn new(cursors: Vec<Cursor>, prefix: Vec<u8>, db: Arc<Inner>) -> Self {
+        let mut iter = Self {
+            cursors,
+            prefix,
+            current: None,
+            error: None,
+            _db: db,
+        };
+        iter.move_to_next_value();
+        iter
+    }
+
+    fn move_to_next_value(&mut self) {
+        self.current = None;
+        loop {
+            match next_entry(&mut self.cursors) {
+                Ok(Some((key, value))) => {
+                    if !key.starts_with(&self.prefix) {
+                        return;
+                    }
+                    if let Some(value) = value {
+                        self.current = Some((key, value));
+                        return;
+                    }
+                }
+                Ok(None) => return,
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl BackendIter for Iter {
+    fn key(&self) -> Option<&[u8]> {
+        Some(&self.current.as_ref()?.0)
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        Some(&self.current.as_ref()?.1)
+    }
+
+    fn next(&mut self) {
+        self.move_to_next_value();
+    }
+
+    fn seek(&mut self, key: &[u8]) {
+        if self
+            .current
+            .as_ref()
+            .map_or(true, |(current, _)| current.as_slice() >= key)
+        {
+            return;
+        }
+        for cursor in &mut self.cursors {
+            if let Err(e) = cursor.seek(key) {
+                self.current = None;
+                self.error = Some(e);
+                return;
+            }
+        }
+        self.move_to_next_value();
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        match &self.error {
+            None => Ok(()),
+            Some(StorageError::Io(e)) => Err(io::Error::new(e.kind(), e.to_string()).into()),
+            Some(e) => Err(CorruptionError::msg(e.to_string()).into()),
+        }
+    }
+}
+
+/// The list of the files of a database
+#[derive(Clone)]
+struct Manifest {
+    sequence: u64,
+    next_file: u64,
+    wal: Option<String>,
+    /// The column family name and file name of each run, the newest first
+    runs: Vec<(String, String)>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            sequence: 0,
+            next_file: 1,
+            wal: None,
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl Manifest {
+    fn read(path: &Path) -> Result<Self, StorageError> {
+        let content = read_to_string(path)?;
+        let mut lines = content.lines();
+        if lines.next() != Some(MANIFEST_HEADER) {
+            return Err(CorruptionError::msg(format!(
+                "{} is not a valid manifest file",
+                path.display()
+            ))
+            .into());
+        }
+        let mut manifest = Self::default();
+        for line in lines {
+            let (key, value) = line
+                .split_once(' ')
+                .ok_or_else(|| CorruptionError::msg(format!("Invalid manifest line: {}", line)))?;
+            match key {
+                "sequence" => manifest.sequence = parse_manifest_number(value)?,
+                "next-file" => manifest.next_file = parse_manifest_number(value)?,
+                "wal" => manifest.wal = Some(value.to_owned()),
+                "run" => {
+                    let (column_family, file) = value.split_once(' ').ok_or_else(|| {
+                        CorruptionError::msg(format!("Invalid manifest line: {}", line))
+                    })?;
+                    manifest
+                        .runs
+                        .push((column_family.to_owned(), file.to_owned()));
+                }
+                _ => {
+                    return Err(
+                        CorruptionError::msg(format!("Invalid manifest line: {}", line)).into(),
+                    )
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Atomically replaces the manifest of the database in `directory`
+    fn write(&self, directory: &Path) -> Result<(), StorageError> {
+        let temporary = directory.join("MANIFEST.tmp");
+        write_synced(&temporary, self.to_string().as_bytes())?;
+        rename(&temporary, directory.join("MANIFEST"))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", MANIFEST_HEADER)?;
+        writeln!(f, "sequence {}", self.sequence)?;
+        writeln!(f, "next-file {}", self.next_file)?;
+        if let Some(wal) = &self.wal {
+            writeln!(f, "wal {}", wal)?;
+        }
+        for (column_family, file) in &self.runs {
+            writeln!(f, "run {} {}", column_family, file)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_manifest_number(value: &str) -> Result<u64, StorageError> {
+    value
+        .parse()
+        .map_err(|_| CorruptionError::msg(format!("Invalid number in manifest: {}", value)).into())
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> Result<(), StorageError> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// A write-ahead log record is the payload length, the payload checksum and the payload
+/// composed of the sequence number and the list of writes.
+fn encode_wal_record(sequence: u64, batch: &[Change]) -> Result<Vec<u8>, StorageError> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&sequence.to_be_bytes());
+    for (column_family, key, value) in batch {
+        payload.extend_from_slice(&to_u32(*column_family)?.to_be_bytes());
+        write_bytes(&mut payload, key)?;
+        if let Some(value) = value {
+            payload.push(1);
+            write_bytes(&mut payload, value)?;
+        } else {
+            payload.push(0);
+        }
+    }
+    let mut record = Vec::with_capacity(payload.len() + 16);
+    record.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+    record.extend_from_slice(&checksum(&payload).to_be_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+/// Returns the valid records of the log and the length of the valid part of the log.
+///
+/// The decoding stops at the first invalid record: it is the result of an interrupted write.
+fn decode_wal(data: &[u8], column_families: usize) -> (Vec<(u64, Batch)>, u64) {
+    let mut records = Vec::new();
+    let mut decoder = Decoder(data);
+    let mut valid_len = 0;
+    while let Ok(record) = decode_wal_record(&mut decoder, column_families) {
+        records.push(record);
+        valid_len = (data.len() - decoder.0.len()) as u64;
+    }
+    (records, valid_len)
+}
+
+fn decode_wal_record(
+    decoder: &mut Decoder<'_>,
+    column_families: usize,
+) -> Result<(u64, Batch), StorageError> {
+    let len = to_usize(decoder.read_u64()?)?;
+    let expected_checksum = decoder.read_u64()?;
+    let payload = decoder.read_slice(len)?;
+    if checksum(payload) != expected_checksum {
+        return Err(CorruptionError::msg("Invalid write-ahead log record checksum").into());
+    }
+    let mut decoder = Decoder(payload);
+    let sequence = decoder.read_u64()?;
+    let mut batch = Vec::new();
+    while !decoder.0.is_empty() {
+        let column_family = to_usize(decoder.read_u32()?.into())?;
+        if column_family >= column_families {
+            return Err(CorruptionError::msg("Invalid column family in write-ahead log").into());
+        }
+        let key = decoder.read_bytes()?.to_vec();
+        let value = match decoder.read_u8()? {
+            0 => None,
+            1 => Some(decoder.read_bytes()?.to_vec()),
+            _ => return Err(CorruptionError::msg("Invalid write-ahead log entry").into()),
+        };
+        batch.push((column_family, key, value));
+    }
+    Ok((sequence, batch))
+}
+
+fn checksum(data: &[u8]) -> u64 {
+    let mut hasher = SipHasher24::new();
+    hasher.write(data);
+    hasher.finish()
+}
+
+fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) -> Result<(), StorageError> {
+    buffer.extend_from_slice(&to_u32(bytes.len())?.to_be_bytes());
+    buffer.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn to_u32(value: usize) -> Result<u32, StorageError> {
+    u32::try_from(value).map_err(|_| StorageError::Other("Too large key or value".into()))
+}
+
+fn to_usize(value: u64) -> Result<usize, StorageError> {
+    usize::try_from(value).map_err(|_| CorruptionError::msg("Too large length").into())
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], StorageError> {
+        if self.0.len() < len {
+            return Err(CorruptionError::msg("Unexpected end of data").into());
+        }
+        let (start, end) = self.0.split_at(len);
+        self.0 = end;
+        Ok(start)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, StorageError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, StorageError> {
+        let mut buffer = [0; 4];
+        buffer.copy_from_slice(self.read_slice(4)?);
+        Ok(u32::from_be_bytes(buffer))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, StorageError> {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(self.read_slice(8)?);
+        Ok(u64::from_be_bytes(buffer))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], StorageError> {
+        let len = to_usize(self.read_u32()?.into())?;
+        self.read_slice(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::column_families;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    struct TempDir(PathBuf);
+
+    impl Default for TempDir {
+        fn default() -> Self {
+            Self(temp_dir().join(format!("oxigraph-lsm-test-{}", random::<u128>())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = remove_dir_all(&self.0);
+        }
+    }
+
+    fn open(dir: &TempDir) -> Result<Db, StorageError> {
+        Db::open(&dir.0, column_families(), &StoreOptions::default())
+    }
+
+    fn default_cf() -> ColumnFamily {
+        ColumnFamily {
+            index: 0,
+            name: column_families()[0].name,
+        }
+    }
+
+    #[test]
+    fn test_lock() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        assert!(open(&dir).is_err());
+        let read_only = Db::open_read_only(&dir.0, column_families(), &StoreOptions::default())?;
+        drop(db);
+        drop(read_only);
+        open(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_memtable_flush() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+        db.insert(&cf, b"a", b"1")?;
+        db.insert(&cf, b"b", b"2")?;
+        let snapshot = db.snapshot();
+        db.flush()?;
+        let state = db.0.current();
+        assert_eq!(state.generation, 1);
+        assert_eq!(state.memtable.size.load(Ordering::Relaxed), 0);
+        assert_eq!(state.runs[cf.index()].len(), 1);
+        assert_eq!(keys(&*snapshot, &cf)?, [b"a".to_vec(), b"b".to_vec()]);
+        drop(snapshot);
+        drop(db);
+
+        let db = open(&dir)?;
+        assert_eq!(db.get(&cf, b"a")?.as_deref(), Some(&b"1"[..]));
+        assert_eq!(db.get(&cf, b"b")?.as_deref(), Some(&b"2"[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_merging() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+        for i in 0..=MAX_RUNS_PER_COLUMN_FAMILY {
+            db.insert(&cf, &[u8::try_from(i).unwrap()], b"")?;
+            db.flush()?;
+        }
+        assert!(db.0.current().runs[cf.index()].len() <= MAX_RUNS_PER_COLUMN_FAMILY);
+        let mut transaction = db.transaction()?;
+        transaction.remove(&cf, &[0])?;
+        transaction.commit()?;
+        db.compact()?;
+        let state = db.0.current();
+        assert_eq!(state.runs[cf.index()].len(), 1);
+        assert_eq!(
+            keys(&*db.snapshot(), &cf)?.len(),
+            MAX_RUNS_PER_COLUMN_FAMILY
+        );
+        let run_files = read_dir(&dir.0)?
+            .filter(|file| {
+                file.as_ref().map_or(false, |file| {
+                    file.file_name().to_string_lossy().ends_with(".run")
+                })
+            })
+            .count();
+        assert_eq!(run_files, 1); // The merged runs have been removed
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_keeps_runs_if_manifest_write_fails() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+        for i in 0..2 {
+            db.insert(&cf, &[i], b"")?;
+            db.flush()?;
+        }
+        create_dir(dir.0.join("MANIFEST.tmp"))?; // Makes the manifest write fail
+        assert!(db.compact().is_err());
+        drop(db);
+        remove_dir_all(dir.0.join("MANIFEST.tmp"))?;
+
+        let db = open(&dir)?;
+        assert_eq!(keys(&*db.snapshot(), &cf)?, [vec![0], vec![1]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wal_recovery() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+        db.insert(&cf, b"a", b"1")?;
+        let wal = dir.0.join(
+            db.0.writer
+                .lock()
+                .unwrap()
+                .wal
+                .as_ref()
+                .unwrap()
+                .name
+                .clone(),
+        );
+        drop(db);
+
+        // We simulate an interrupted write
+        let valid_len = wal.metadata()?.len();
+        OpenOptions::new()
+            .append(true)
+            .open(&wal)?
+            .write_all(&[0, 0, 0, 0, 0, 0, 0, 50, 1, 2])?;
+        let db = open(&dir)?;
+        assert_eq!(wal.metadata()?.len(), valid_len); // The broken record has been removed
+        db.insert(&cf, b"b", b"2")?;
+        drop(db);
+
+        let db = open(&dir)?;
+        assert_eq!(db.get(&cf, b"a")?.as_deref(), Some(&b"1"[..]));
+        assert_eq!(db.get(&cf, b"b")?.as_deref(), Some(&b"2"[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflicts_across_flushes() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+
+        // A write to the checked key before the flush
+        let mut transaction = db.transaction()?;
+        assert!(!transaction.contains_key_for_update(&cf, b"a")?);
+        transaction.insert(&cf, b"b", b"")?;
+        db.insert(&cf, b"a", b"")?;
+        db.flush()?;
+        assert!(matches!(transaction.commit(), Err(StorageError::Conflict)));
+
+        // A write to the checked key after the flush
+        let mut transaction = db.transaction()?;
+        assert!(!transaction.contains_key_for_update(&cf, b"c")?);
+        transaction.insert(&cf, b"d", b"")?;
+        db.flush()?;
+        db.insert(&cf, b"c", b"")?;
+        assert!(matches!(transaction.commit(), Err(StorageError::Conflict)));
+
+        // A write to an other key
+        let mut transaction = db.transaction()?;
+        assert!(!transaction.contains_key_for_update(&cf, b"e")?);
+        transaction.insert(&cf, b"e", b"")?;
+        db.insert(&cf, b"f", b"")?;
+        db.flush()?;
+        transaction.commit()?;
+
+        // Too many flushes to know
+        let mut transaction = db.transaction()?;
+        assert!(!transaction.contains_key_for_update(&cf, b"g")?);
+        transaction.insert(&cf, b"g", b"")?;
+        for key in [b"h", b"i"] {
+            db.insert(&cf, key, b"")?;
+            db.flush()?;
+        }
+        assert!(matches!(transaction.commit(), Err(StorageError::Conflict)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflict_with_bulk_insertion() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+        let mut transaction = db.transaction()?;
+        assert!(!transaction.contains_key_for_update(&cf, b"a")?);
+        transaction.insert(&cf, b"b", b"")?;
+        let mut bulk_insertion = db.bulk_insertion()?;
+        bulk_insertion.insert(&cf, &mut [(b"a".to_vec(), b"".to_vec())].into_iter())?;
+        bulk_insertion.commit()?;
+        assert!(matches!(transaction.commit(), Err(StorageError::Conflict)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_memtable_in_batches() -> Result<(), StorageError> {
+        let dir = TempDir::default();
+        let db = open(&dir)?;
+        let cf = default_cf();
+        let mut transaction = db.transaction()?;
+        for i in 0..1000_u32 {
+            transaction.insert(&cf, &i.to_be_bytes(), b"")?;
+        }
+        transaction.commit()?;
+        let snapshot = db.snapshot();
+        let mut transaction = db.transaction()?;
+        for i in (0..1000_u32).step_by(3) {
+            transaction.remove(&cf, &i.to_be_bytes())?;
+        }
+        transaction.insert(&cf, &2000_u32.to_be_bytes(), b"")?;
+        db.insert(&cf, &3000_u32.to_be_bytes(), b"")?;
+
+        let expected = (0..1000_u32)
+            .filter(|i| i % 3 != 0)
+            .chain([2000])
+            .map(|i| i.to_be_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(keys(&*transaction.reader(), &cf)?, expected);
+        let expected = (0..1000_u32)
+            .map(|i| i.to_be_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(keys(&*snapshot, &cf)?, expected);
+
+        let mut iter = snapshot.scan_prefix(&cf, &[])?;
+        iter.seek(&500_u32.to_be_bytes());
+        assert_eq!(iter.key(), Some(&500_u32.to_be_bytes()[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_cache() {
+        let cache = BlockCache::new(10);
+        let block = Arc::new(Vec::new());
+        cache.insert(0, 0, Arc::clone(&block), 4);
+        cache.insert(0, 1, Arc::clone(&block), 4);
+        assert!(cache.get(0, 0).is_some());
+        cache.insert(1, 0, Arc::clone(&block), 4);
+        assert!(cache.get(0, 1).is_none()); // The least recently used block is evicted
+        assert!(cache.get(0, 0).is_some());
+        assert!(cache.get(1, 0).is_some());
+        cache.insert(1, 1, block, 20);
+        assert!(cache.get(1, 1).is_none()); // Too large
+        cache.remove_run(0, 2);
+        assert!(cache.get(0, 0).is_none());
+        assert_eq!(cache.state.lock().unwrap().size, 4);
+    }
+
+    fn keys(reader: &dyn BackendReader, cf: &ColumnFamily) -> Result<Vec<Vec<u8>>, StorageError> {
+        let mut iter = reader.scan_prefix(cf, &[])?;
+        let mut keys = Vec::new();
+        while let Some(key) = iter.key() {
+            keys.push(key.to_vec());
+            iter.next();
+        }
+        iter.status()?;
+        Ok(keys)
+    }
+}
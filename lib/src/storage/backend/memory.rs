@@ -0,0 +1,512 @@
+//! An in-memory storage backend based on multi-version concurrency control.
+//!
+//! Each key is associated with the list of the values written to it, tagged with the sequence number of their commit.
+//! A snapshot is only a sequence number: it sees for each key the latest value written before it.
+//! Each commit records the keys whose older values or removal could be reclaimed.
+//! They are pruned as soon as the oldest live snapshot sees the commit, after the commits and when the snapshots are dropped.
+
+use crate::storage::backend::{
+    Backend, BackendIter, BackendReader, BackendTransaction, ColumnFamily, ColumnFamilyDefinition,
+    Value,
+};
+use crate::storage::StorageError;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem::take;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Number of keys read at once by the iterators while holding a read lock
+const ITER_BATCH_SIZE: usize = 256;
+
+/// For each key, the written values with their sequence numbers in increasing order. `None` is a removal.
+type VersionedTree = BTreeMap<Vec<u8>, Vec<(u64, Option<Vec<u8>>)>>;
+type WriteSet = Vec<BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+/// The keys with values to reclaim, grouped by the sequence number of the commit making them obsolete, in increasing order.
+type Garbage = VecDeque<(u64, Vec<(usize, Vec<u8>)>)>;
+
+#[derive(Clone)]
+pub struct Db(Arc<Inner>);
+
+struct Inner {
+    trees: Vec<RwLock<VersionedTree>>,
+    versions: Mutex<Versions>,
+    /// Held during commits to serialize them
+    writer: Mutex<()>,
+    garbage: Mutex<Garbage>,
+}
+
+struct Versions {
+    /// Sequence number of the latest visible commit
+    sequence: u64,
+    /// Number of live snapshots for each sequence number
+    snapshots: BTreeMap<u64, usize>,
+}
+
+impl Db {
+    pub fn new(column_families: &[ColumnFamilyDefinition]) -> Self {
+        Self(Arc::new(Inner {
+            trees: column_families
+                .iter()
+                .map(|_| RwLock::new(BTreeMap::new()))
+                .collect(),
+            versions: Mutex::new(Versions {
+                sequence: 0,
+                snapshots: BTreeMap::new(),
+            }),
+            writer: Mutex::new(()),
+            garbage: Mutex::new(VecDeque::new()),
+        }))
+    }
+}
+
+impl Inner {
+    fn snapshot(self: &Arc<Self>) -> Arc<Snapshot> {
+        let mut versions = self.versions.lock().unwrap();
+        let sequence = versions.sequence;
+        *versions.snapshots.entry(sequence).or_default() += 1;
+        Arc::new(Snapshot {
+            db: Arc::clone(self),
+            sequence,
+        })
+    }
+
+    /// The sequence number of the oldest state that might still be read
+    fn oldest_visible_sequence(&self) -> u64 {
+        let versions = self.versions.lock().unwrap();
+        versions
+            .snapshots
+            .keys()
+            .next()
+            .map_or(versions.sequence, |s| versions.sequence.min(*s))
+    }
+
+    /// Commits the writes, failing if one of the `checked_keys` has been written after `sequence`
+    fn commit(
+        &self,
+        writes: WriteSet,
+        sequence: u64,
+        checked_keys: &[(usize, Vec<u8>)],
+    ) -> Result<(), StorageError> {
+        let _writer = self.writer.lock().unwrap();
+        for (column_family, key) in checked_keys {
+            if self.trees[*column_family]
+                .read()
+                .unwrap()
+                .get(key)
+                .and_then(|versions| versions.last())
+                .map_or(false, |(s, _)| *s > sequence)
+            {
+                return Err(StorageError::Conflict);
+            }
+        }
+        let new_sequence = self.versions.lock().unwrap().sequence + 1;
+        let mut garbage = Vec::new();
+        for (column_family, (tree, writes)) in self.trees.iter().zip(writes).enumerate() {
+            if writes.is_empty() {
+                continue;
+            }
+            let mut tree = tree.write().unwrap();
+            for (key, value) in writes {
+                let versions = tree.entry(key.clone()).or_default();
+                // Removals and overridden values could be reclaimed later
+                if value.is_none() || !versions.is_empty() {
+                    garbage.push((column_family, key));
+                }
+                versions.push((new_sequence, value));
+            }
+        }
+        if !garbage.is_empty() {
+            self.garbage
+                .lock()
+                .unwrap()
+                .push_back((new_sequence, garbage));
+        }
+        // The new values become visible
+        self.versions.lock().unwrap().sequence = new_sequence;
+        Ok(())
+    }
+
+    /// Removes the values written by the commits seen by all snapshots that are not visible anymore
+    fn collect_garbage(&self) {
+        let oldest_sequence = self.oldest_visible_sequence();
+        let mut keys = Vec::new();
+        {
+            let mut garbage = self.garbage.lock().unwrap();
+            while garbage
+                .front()
+                .map_or(false, |(sequence, _)| *sequence <= oldest_sequence)
+            {
+                if let Some((_, commit_keys)) = garbage.pop_front() {
+                    keys.extend(commit_keys);
+                }
+            }
+        }
+        for (column_family, key) in keys {
+            let mut tree = self.trees[column_family].write().unwrap();
+            if let Some(versions) = tree.get_mut(&key) {
+                if prune(versions, oldest_sequence) {
+                    tree.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Removes the values hidden by a value older than `oldest_sequence`.
+///
+/// Returns `true` if the key is not visible anymore.
+fn prune(versions: &mut Vec<(u64, Option<Vec<u8>>)>, oldest_sequence: u64) -> bool {
+    let visible = versions
+        .iter()
+        .rposition(|(s, _)| *s <= oldest_sequence)
+        .unwrap_or(0);
+    versions.drain(..visible);
+    versions.len() == 1 && versions[0].1.is_none() && versions[0].0 <= oldest_sequence
+}
+
+fn visible_value(versions: &[(u64, Option<Vec<u8>>)], sequence: u64) -> Option<&Vec<u8>> {
+    versions
+        .iter()
+        .rev()
+        .find(|(s, _)| *s <= sequence)?
+        .1
+        .as_ref()
+}
+
+impl Backend for Db {
+    fn snapshot(&self) -> Box<dyn BackendReader> {
+        Box::new(Reader {
+            snapshot: self.0.snapshot(),
+            writes: None,
+        })
+    }
+
+    fn transaction(&self) -> Result<Box<dyn BackendTransaction>, StorageError> {
+        Ok(Box::new(Transaction {
+            snapshot: self.0.snapshot(),
+            writes: Arc::new(RwLock::new(vec![BTreeMap::new(); self.0.trees.len()])),
+            read_for_update: Mutex::new(Vec::new()),
+        }))
+    }
+
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError> {
+        let sequence = self.0.versions.lock().unwrap().sequence;
+        Ok(self.0.trees[column_family.index()]
+            .read()
+            .unwrap()
+            .get(key)
+            .and_then(|versions| visible_value(versions, sequence))
+            .cloned()
+            .map(Value::from))
+    }
+
+    fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        let mut writes = vec![BTreeMap::new(); self.0.trees.len()];
+        writes[column_family.index()].insert(key.to_vec(), Some(value.to_vec()));
+        self.0.commit(writes, 0, &[])?;
+        self.0.collect_garbage();
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<(), StorageError> {
+        let oldest_sequence = self.0.oldest_visible_sequence();
+        for tree in &self.0.trees {
+            tree.write()
+                .unwrap()
+                .retain(|_, versions| !prune(versions, oldest_sequence));
+        }
+        Ok(())
+    }
+}
+
+/// A registered snapshot: the values it might read are kept as long as it lives
+struct Snapshot {
+    db: Arc<Inner>,
+    sequence: u64,
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let mut versions = self.db.versions.lock().unwrap();
+        if let Some(count) = versions.snapshots.get_mut(&self.sequence) {
+            *count -= 1;
+            if *count == 0 {
+                versions.snapshots.remove(&self.sequence);
+            }
+        }
+        drop(versions);
+        // The oldest live snapshot might have advanced
+        self.db.collect_garbage();
+    }
+}
+
+pub struct Reader {
+    snapshot: Arc<Snapshot>,
+    /// The writes of the transaction the reader belongs to
+    writes: Option<Arc<RwLock<WriteSet>>>,
+}
+
+impl BackendReader for Reader {
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError> {
+        if let Some(writes) = &self.writes {
+            if let Some(value) = writes.read().unwrap()[column_family.index()].get(key) {
+                return Ok(value.clone().map(Value::from));
+            }
+        }
+        Ok(self.snapshot.db.trees[column_family.index()]
+            .read()
+            .unwrap()
+            .get(key)
+            .and_then(|versions| visible_value(versions, self.snapshot.sequence))
+            .cloned()
+            .map(Value::from))
+    }
+
+    fn scan_prefix(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<Box<dyn BackendIter>, StorageError> {
+        let overlay = if let Some(writes) = &self.writes {
+            writes.read().unwrap()[column_family.index()]
+                .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+                .take_while(|(k, _)| k.starts_with(prefix))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        } else {
+            VecDeque::new()
+        };
+        let mut iter = Iter {
+            snapshot: Arc::clone(&self.snapshot),
+            column_family: column_family.index(),
+            prefix: prefix.to_vec(),
+            buffer: VecDeque::new(),
+            next_batch_start: Some(Bound::Included(prefix.to_vec())),
+            overlay,
+            current: None,
+        };
+        iter.move_to_next();
+        Ok(Box::new(iter))
+    }
+}
+
+pub struct Transaction {
+    snapshot: Arc<Snapshot>,
+    writes: Arc<RwLock<WriteSet>>,
+    read_for_update: Mutex<Vec<(usize, Vec<u8>)>>,
+}
+
+impl BackendTransaction for Transaction {
+    fn reader(&self) -> Box<dyn BackendReader> {
+        Box::new(Reader {
+            snapshot: Arc::clone(&self.snapshot),
+            writes: Some(Arc::clone(&self.writes)),
+        })
+    }
+
+    fn contains_key_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        self.read_for_update
+            .lock()
+            .unwrap()
+            .push((column_family.index(), key.to_vec()));
+        self.reader().contains_key(column_family, key)
+    }
+
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.writes.write().unwrap()[column_family.index()]
+            .insert(key.to_vec(), Some(value.to_vec()));
+        Ok(())
+    }
+
+    fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+        self.writes.write().unwrap()[column_family.index()].insert(key.to_vec(), None);
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        let writes = take(&mut *self.writes.write().unwrap());
+        let mut checked_keys = take(&mut *self.read_for_update.lock().unwrap());
+        for (column_family, tree) in writes.iter().enumerate() {
+            checked_keys.extend(tree.keys().map(|key| (column_family, key.clone())));
+        }
+        let db = Arc::clone(&self.snapshot.db);
+        db.commit(writes, self.snapshot.sequence, &checked_keys)?;
+        drop(self); // We release the snapshot before cleaning up
+        db.collect_garbage();
+        Ok(())
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+pub struct Iter {
+    snapshot: Arc<Snapshot>,
+    column_family: usize,
+    prefix: Vec<u8>,
+    /// The next visible entries of the database
+    buffer: VecDeque<(Vec<u8>, Vec<u8>)>,
+    /// Where to start reading the next batch of the database entries or `None` if the end is reached
+    next_batch_start: Option<Bound<Vec<u8>>>,
+    /// The changes done by the transaction
+    overlay: VecDeque<(Vec<u8>, Option<Vec<u8>>)>,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Iter {
+    /// Reads the next batch of entries from the database
+    fn read_batch(&mut self) {
+        let start = if let Some(start) = self.next_batch_start.take() {
+            start
+        } else {
+            return;
+        };
+        let tree = self.snapshot.db.trees[self.column_family].read().unwrap();
+        let start = match &start {
+            Bound::Included(key) => Bound::Included(key.as_slice()),
+            Bound::Excluded(key) => Bound::Excluded(key.as_slice()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        for (i, (key, versions)) in tree.range::<[u8], _>((start, Bound::Unbounded)).enumerate() {
+            if !key.starts_with(&self.prefix) {
+                return;
+            }
+            if i == ITER_BATCH_SIZE {
+                self.next_batch_start = Some(Bound::Included(key.clone()));
+                return;
+            }
+            if let Some(value) = visible_value(versions, self.snapshot.sequence) {
+                self.buffer.push_back((key.clone(), value.clone()));
+            }
+        }
+    }
+
+    fn move_to_next(&mut self) {
+        loop {
+            while self.buffer.is_empty() && self.next_batch_start.is_some() {
+                self.read_batch();
+            }
+            let from_overlay = match (self.buffer.front(), self.overlay.front()) {
+                (Some((key, _)), Some((overlay_key, _))) => {
+                    if key == overlay_key {
+                        self.buffer.pop_front(); // The overlay value overrides it
+                        true
+                    } else {
+                        overlay_key < key
+                    }
+                }
+                (None, Some(_)) => true,
+                (_, None) => false,
+            };
+            if from_overlay {
+                if let Some((key, Some(value))) = self.overlay.pop_front() {
+                    self.current = Some((key, value));
+                    return;
+                }
+                // It is a removal: we continue
+            } else {
+                self.current = self.buffer.pop_front();
+                return;
+            }
+        }
+    }
+}
+
+impl BackendIter for Iter {
+    fn key(&self) -> Option<&[u8]> {
+        Some(&self.current.as_ref()?.0)
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        Some(&self.current.as_ref()?.1)
+    }
+
+    fn next(&mut self) {
+        self.move_to_next();
+    }
+
+    fn seek(&mut self, key: &[u8]) {
+        if self
+            .current
+            .as_ref()
+            .map_or(true, |(current, _)| current.as_slice() >= key)
+        {
+            return;
+        }
+        while self
+            .buffer
+            .front()
+            .map_or(false, |(k, _)| k.as_slice() < key)
+        {
+            self.buffer.pop_front();
+        }
+        if self.buffer.is_empty() && self.next_batch_start.is_some() {
+            self.next_batch_start = Some(Bound::Included(key.to_vec()));
+        }
+        while self
+            .overlay
+            .front()
+            .map_or(false, |(k, _)| k.as_slice() < key)
+        {
+            self.overlay.pop_front();
+        }
+        self.move_to_next();
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::column_families;
+
+    fn default_cf() -> ColumnFamily {
+        ColumnFamily {
+            index: 0,
+            name: column_families()[0].name,
+        }
+    }
+
+    #[test]
+    fn test_garbage_collection() -> Result<(), StorageError> {
+        let db = Db::new(column_families());
+        let cf = default_cf();
+        db.insert(&cf, b"a", b"1")?;
+        db.insert(&cf, b"b", b"1")?;
+        let snapshot = db.snapshot();
+        db.insert(&cf, b"a", b"2")?;
+        let mut transaction = db.transaction()?;
+        transaction.remove(&cf, b"b")?;
+        transaction.commit()?;
+
+        // The old values are kept for the snapshot
+        assert_eq!(db.0.trees[cf.index()].read().unwrap()[&b"a"[..]].len(), 2);
+        assert_eq!(snapshot.get(&cf, b"b")?.as_deref(), Some(&b"1"[..]));
+
+        // They are reclaimed as soon as it is dropped
+        drop(snapshot);
+        let tree = db.0.trees[cf.index()].read().unwrap();
+        assert_eq!(tree[&b"a"[..]].len(), 1);
+        assert!(!tree.contains_key(&b"b"[..]));
+        assert!(db.0.garbage.lock().unwrap().is_empty());
+        Ok(())
+    }
+}
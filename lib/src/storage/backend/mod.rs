@@ -1,14 +1,757 @@
 //! A storage backend
-//! RocksDB is available, if not in memory
+//! On disk, RocksDB is used by default and a pure Rust log-structured merge tree if the `rocksdb` feature is disabled.
+//! In-memory stores use a multi-version map allowing concurrent reads during writes.
+//!
+//! Other sorted key-value stores could be plugged by implementing the [`Backend`] trait.
 
-#[cfg(target_arch = "wasm32")]
-pub use fallback::{ColumnFamily, ColumnFamilyDefinition, Db, Iter, Reader, Transaction};
+use crate::storage::{StorageError, COLUMN_FAMILIES};
 #[cfg(not(target_arch = "wasm32"))]
-pub use rocksdb::{
-    ColumnFamily, ColumnFamilyDefinition, Db, Iter, Reader, SstFileWriter, Transaction,
-};
-
-#[cfg(target_arch = "wasm32")]
-mod fallback;
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::Deref;
+use std::path::Path;
 #[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::yield_now;
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "rocksdb")))]
+mod lsm;
+mod memory;
+#[cfg(all(not(target_arch = "wasm32"), feature = "rocksdb"))]
 mod rocksdb;
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "rocksdb")))]
+use self::lsm as on_disk;
+#[cfg(all(not(target_arch = "wasm32"), feature = "rocksdb"))]
+use self::rocksdb as on_disk;
+
+/// A sorted key-value store with column families, snapshots and transactions in which the store data is written.
+///
+/// The backend must provide all the column families returned by [`column_families`].
+pub trait Backend: Send + Sync + 'static {
+    /// Returns a reader on the current state of the database.
+    ///
+    /// It must not see the changes committed after its creation.
+    fn snapshot(&self) -> Box<dyn BackendReader>;
+
+    /// Starts a new transaction.
+    ///
+    /// The changes done inside of it must only become visible to the other readers when it is committed.
+    /// If it conflicts with a concurrent transaction, a [`StorageError::Conflict`] error should be returned
+    /// by the conflicting operation or by the commit: the transaction is then retried.
+    fn transaction(&self) -> Result<Box<dyn BackendTransaction>, StorageError>;
+
+    /// Returns the latest value written for a key, outside of any snapshot or transaction.
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError>;
+
+    /// Writes a value outside of any transaction.
+    ///
+    /// It is only used for content-addressed entries that are never removed.
+    /// It must be possible to call it while a transaction is running.
+    fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError>;
+
+    /// Starts an insertion or removal of large sets of sorted entries, made visible at once when committed.
+    ///
+    /// By default, a single transaction is used.
+    fn bulk_insertion(&self) -> Result<Box<dyn BackendBulkInsertion + '_>, StorageError> {
+        Ok(Box::new(TransactionBulkInsertion(self.transaction()?)))
+    }
+
+    /// Makes sure all the changes are persisted.
+    fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Optimizes the database content for reading.
+    fn compact(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Returns the size in bytes of the files storing the column family or `None` if it is not known.
+    fn disk_size(&self, _column_family: &ColumnFamily) -> Result<Option<u64>, StorageError> {
+        Ok(None)
+    }
+
+    /// Creates a copy of the database in `target_directory` that could be opened like the original one.
+    fn backup(&self, _target_directory: &Path) -> Result<(), StorageError> {
+        Err(StorageError::Other(
+            "Backups are not supported by this storage backend".into(),
+        ))
+    }
+
+    /// Adds an incremental backup of the database to `backup_directory`.
+    fn incremental_backup(&self, _backup_directory: &Path) -> Result<(), StorageError> {
+        Err(StorageError::Other(
+            "Incremental backups are not supported by this storage backend".into(),
+        ))
+    }
+}
+
+/// A value read from the database.
+///
+/// The backends could return values pinned in their own memory to avoid copying them.
+pub enum Value {
+    Owned(Vec<u8>),
+    #[cfg(all(not(target_arch = "wasm32"), feature = "rocksdb"))]
+    Pinned(rocksdb::PinnableSlice),
+}
+
+impl Deref for Value {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(value) => value,
+            #[cfg(all(not(target_arch = "wasm32"), feature = "rocksdb"))]
+            Self::Pinned(value) => value,
+        }
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Owned(value)
+    }
+}
+
+/// A read-only view of the database, either a snapshot or the content seen by a transaction.
+pub trait BackendReader: Send + Sync {
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError>;
+
+    fn contains_key(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<bool, StorageError> {
+        Ok(self.get(column_family, key)?.is_some())
+    }
+
+    /// Returns the entries whose keys start with `prefix` sorted by key in lexicographic order.
+    fn scan_prefix(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<Box<dyn BackendIter>, StorageError>;
+
+    /// Returns the number of entries in the column family.
+    fn len(&self, column_family: &ColumnFamily) -> Result<usize, StorageError> {
+        let mut count = 0;
+        let mut iter = self.scan_prefix(column_family, &[])?;
+        while iter.key().is_some() {
+            count += 1;
+            iter.next();
+        }
+        iter.status()?; // We makes sure there is no read problem
+        Ok(count)
+    }
+
+    fn is_empty(&self, column_family: &ColumnFamily) -> Result<bool, StorageError> {
+        let iter = self.scan_prefix(column_family, &[])?;
+        iter.status()?; // We makes sure there is no read problem
+        Ok(iter.key().is_none())
+    }
+}
+
+/// A cursor on sorted entries.
+pub trait BackendIter: Send {
+    /// The key of the current entry or `None` if the iteration is done.
+    fn key(&self) -> Option<&[u8]>;
+
+    /// The value of the current entry or `None` if the iteration is done.
+    fn value(&self) -> Option<&[u8]>;
+
+    /// Moves to the next entry.
+    fn next(&mut self);
+
+    /// Moves to the first entry whose key is greater or equal to `key`. The key is always after the current one.
+    fn seek(&mut self, key: &[u8]);
+
+    /// Returns the error that might have stopped the iteration.
+    fn status(&self) -> Result<(), StorageError>;
+}
+
+/// A transaction. It is rolled back if dropped without being committed.
+pub trait BackendTransaction: Send {
+    /// Returns a reader seeing the changes done by this transaction.
+    ///
+    /// The reader stops working when the transaction ends.
+    fn reader(&self) -> Box<dyn BackendReader>;
+
+    /// Checks if a key exists and makes sure the transaction conflicts with the concurrent ones writing it.
+    fn contains_key_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError>;
+
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError>;
+
+    fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError>;
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError>;
+
+    fn rollback(self: Box<Self>) -> Result<(), StorageError>;
+}
+
+/// An insertion or removal of large sets of sorted entries. Nothing is written if it is dropped without being committed.
+pub trait BackendBulkInsertion {
+    /// Adds entries to a column family.
+    ///
+    /// The entries are sorted by key and their keys are distinct.
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        entries: &mut dyn Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), StorageError>;
+
+    /// Removes keys from a column family.
+    ///
+    /// The keys are sorted and distinct. They are never inserted by the same bulk insertion.
+    fn remove(
+        &mut self,
+        column_family: &ColumnFamily,
+        keys: &mut dyn Iterator<Item = Vec<u8>>,
+    ) -> Result<(), StorageError>;
+
+    /// Makes all the entries visible at once.
+    fn commit(self: Box<Self>) -> Result<(), StorageError>;
+}
+
+/// A bulk insertion inside of a single transaction.
+struct TransactionBulkInsertion(Box<dyn BackendTransaction>);
+
+impl BackendBulkInsertion for TransactionBulkInsertion {
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        entries: &mut dyn Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), StorageError> {
+        for (key, value) in entries {
+            self.0.insert(column_family, &key, &value)?;
+        }
+        Ok(())
+    }
+
+    fn remove(
+        &mut self,
+        column_family: &ColumnFamily,
+        keys: &mut dyn Iterator<Item = Vec<u8>>,
+    ) -> Result<(), StorageError> {
+        for key in keys {
+            self.0.remove(column_family, &key)?;
+        }
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        self.0.commit()
+    }
+}
+
+/// A column family: an independent sorted key-value map inside of the backend.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ColumnFamily {
+    index: usize,
+    name: &'static str,
+}
+
+impl ColumnFamily {
+    /// The position of the column family definition in [`column_families`].
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// The description of a column family.
+///
+/// All the fields except the name are optimization hints.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ColumnFamilyDefinition {
+    pub name: &'static str,
+    /// If prefix scans are done on the column family.
+    pub use_iter: bool,
+    /// The minimal length of the scanned prefixes.
+    pub min_prefix_size: usize,
+    /// If the writes do not need to be ordered.
+    pub unordered_writes: bool,
+}
+
+/// The column families the backends must provide.
+#[inline]
+pub fn column_families() -> &'static [ColumnFamilyDefinition] {
+    COLUMN_FAMILIES
+}
+
+/// Options used to open an on-disk [`Store`](crate::store::Store) with [`Store::open_with_options`](crate::store::Store::open_with_options).
+///
+/// Only the cache size and the write synchronization are used by the pure Rust backend used if the `rocksdb` feature is disabled.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::store::{CompressionType, Store, StoreOptions};
+/// # use std::fs::remove_dir_all;
+///
+/// # {
+/// let options = StoreOptions::default()
+///     .with_cache_size_in_megabytes(256)
+///     .with_column_family_compression("id2str", CompressionType::Lz4hc)
+///     .with_sync_writes();
+/// let store = Store::open_with_options("example_with_options.db", &options)?;
+/// # };
+/// # remove_dir_all("example_with_options.db")?;
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(feature = "rocksdb"), allow(dead_code))] // Only RocksDB uses all the options
+#[derive(Clone, Debug, Default)]
+pub struct StoreOptions {
+    cache_size: Option<usize>,
+    compression: Option<CompressionType>,
+    column_family_compressions: HashMap<String, CompressionType>,
+    max_open_files: Option<u32>,
+    wal_directory: Option<PathBuf>,
+    sync_writes: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StoreOptions {
+    /// Sets the size of the cache of the blocks read from disk, shared by all the column families.
+    ///
+    /// By default the RocksDB default cache size is used, and 64MB with the pure Rust backend.
+    pub fn with_cache_size_in_megabytes(mut self, cache_size: usize) -> Self {
+        self.cache_size = Some(cache_size * 1024 * 1024);
+        self
+    }
+
+    /// Sets the compression algorithm of all the column families (LZ4 by default).
+    pub fn with_compression(mut self, compression: CompressionType) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Sets the compression algorithm of a column family, overriding the one set with [`StoreOptions::with_compression`].
+    ///
+    /// The column family names are the ones returned by [`column_families`](crate::store::backend::column_families).
+    pub fn with_column_family_compression(
+        mut self,
+        column_family: impl Into<String>,
+        compression: CompressionType,
+    ) -> Self {
+        self.column_family_compressions
+            .insert(column_family.into(), compression);
+        self
+    }
+
+    /// Sets the maximal number of files kept open by the database.
+    ///
+    /// By default there is no limit.
+    pub fn with_max_open_files(mut self, max_open_files: u32) -> Self {
+        self.max_open_files = Some(max_open_files);
+        self
+    }
+
+    /// Writes the write-ahead log in a different directory than the database one, for example on a faster disk.
+    ///
+    /// The same directory must be given each time the database is opened.
+    pub fn with_wal_directory(mut self, wal_directory: impl Into<PathBuf>) -> Self {
+        self.wal_directory = Some(wal_directory.into());
+        self
+    }
+
+    /// Waits for each write to be synced to disk before returning.
+    ///
+    /// By default the writes are only flushed to the operating system:
+    /// they survive a crash of the process but not a crash of the machine.
+    pub fn with_sync_writes(mut self) -> Self {
+        self.sync_writes = true;
+        self
+    }
+
+    /// The compression algorithm to use for a column family
+    #[cfg(feature = "rocksdb")]
+    fn compression(&self, column_family: &str) -> CompressionType {
+        self.column_family_compressions
+            .get(column_family)
+            .copied()
+            .or(self.compression)
+            .unwrap_or(CompressionType::Lz4)
+    }
+}
+
+/// A compression algorithm used to store the data on disk.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[non_exhaustive]
+pub enum CompressionType {
+    None,
+    Lz4,
+    /// A variant of LZ4 compressing more but slower.
+    Lz4hc,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CompressionType {
+    /// Looks for a compression algorithm from its name: "none", "lz4" or "lz4hc".
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::CompressionType;
+    ///
+    /// assert_eq!(CompressionType::from_name("lz4"), Some(CompressionType::Lz4));
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "lz4" => Some(Self::Lz4),
+            "lz4hc" => Some(Self::Lz4hc),
+            _ => None,
+        }
+    }
+}
+
+/// The backend used by the storage.
+#[derive(Clone)]
+pub struct Db(Arc<dyn Backend>);
+
+impl Db {
+    pub fn new() -> Self {
+        Self::from_backend(memory::Db::new(column_families()))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open(path: &Path, options: &StoreOptions) -> Result<Self, StorageError> {
+        check_options(options)?;
+        Ok(Self::from_backend(on_disk::Db::open(
+            path,
+            column_families(),
+            options,
+        )?))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_read_only(path: &Path, options: &StoreOptions) -> Result<Self, StorageError> {
+        check_options(options)?;
+        Ok(Self::from_backend(on_disk::Db::open_read_only(
+            path,
+            column_families(),
+            options,
+        )?))
+    }
+
+    pub fn from_backend(backend: impl Backend) -> Self {
+        Self(Arc::new(backend))
+    }
+
+    pub fn column_family(&self, name: &'static str) -> Option<ColumnFamily> {
+        column_families()
+            .iter()
+            .position(|cf| cf.name == name)
+            .map(|index| ColumnFamily { index, name })
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> Reader {
+        Reader(self.0.snapshot().into())
+    }
+
+    /// Runs `f` inside of a transaction that is retried as long as it conflicts with concurrent ones.
+    pub fn transaction<T, E: Error + 'static + From<StorageError>>(
+        &self,
+        f: impl Fn(Transaction) -> Result<T, E>,
+    ) -> Result<T, E> {
+        loop {
+            let transaction = self.start_transaction()?;
+            let result = f(transaction.clone());
+            let error = match result {
+                Ok(result) => match transaction.commit() {
+                    Ok(()) => return Ok(result),
+                    Err(e) => E::from(e),
+                },
+                Err(e) => {
+                    transaction.rollback()?;
+                    e
+                }
+            };
+            if is_conflict(&error) {
+                // We give a chance to the OS to do something else before retrying in order to help avoiding another conflict
+                yield_now();
+            } else {
+                // We raise the error
+                return Err(error);
+            }
+        }
+    }
+
+    /// Starts a transaction that is ended by [`Transaction::commit`] or [`Transaction::rollback`].
+    ///
+    /// It is rolled back if all its clones are dropped before.
+    pub fn start_transaction(&self) -> Result<Transaction, StorageError> {
+        Ok(Transaction(Arc::new(Mutex::new(Some(
+            self.0.transaction()?,
+        )))))
+    }
+
+    pub fn get(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Value>, StorageError> {
+        self.0.get(column_family, key)
+    }
+
+    pub fn contains_key(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        Ok(self.get(column_family, key)?.is_some()) //TODO: optimize
+    }
+
+    pub fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.0.insert(column_family, key, value)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn bulk_insertion(&self) -> Result<Box<dyn BackendBulkInsertion + '_>, StorageError> {
+        self.0.bulk_insertion()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn flush(&self) -> Result<(), StorageError> {
+        self.0.flush()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compact(&self) -> Result<(), StorageError> {
+        self.0.compact()
+    }
+
+    pub fn disk_size(&self, column_family: &ColumnFamily) -> Result<Option<u64>, StorageError> {
+        self.0.disk_size(column_family)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
+        self.0.backup(target_directory)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn incremental_backup(&self, backup_directory: &Path) -> Result<(), StorageError> {
+        self.0.incremental_backup(backup_directory)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn purge_old_backups(
+        backup_directory: &Path,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        on_disk::Db::purge_old_backups(backup_directory, num_backups_to_keep)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restore_from_backup(
+        backup_directory: &Path,
+        target_directory: &Path,
+    ) -> Result<(), StorageError> {
+        on_disk::Db::restore_from_backup(backup_directory, target_directory)
+    }
+}
+
+/// Looks for a [`StorageError::Conflict`] in the error chain.
+fn is_conflict(error: &(dyn Error + 'static)) -> bool {
+    let mut error = Some(error);
+    while let Some(e) = error {
+        if matches!(
+            e.downcast_ref::<StorageError>(),
+            Some(StorageError::Conflict)
+        ) {
+            return true;
+        }
+        error = e.source();
+    }
+    false
+}
+
+#[derive(Clone)]
+pub struct Reader(Arc<dyn BackendReader>);
+
+impl Reader {
+    pub fn get(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Value>, StorageError> {
+        self.0.get(column_family, key)
+    }
+
+    pub fn contains_key(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        self.0.contains_key(column_family, key)
+    }
+
+    pub fn iter(&self, column_family: &ColumnFamily) -> Result<Iter, StorageError> {
+        self.scan_prefix(column_family, &[])
+    }
+
+    pub fn scan_prefix(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<Iter, StorageError> {
+        Ok(Iter(self.0.scan_prefix(column_family, prefix)?))
+    }
+
+    pub fn len(&self, column_family: &ColumnFamily) -> Result<usize, StorageError> {
+        self.0.len(column_family)
+    }
+
+    pub fn is_empty(&self, column_family: &ColumnFamily) -> Result<bool, StorageError> {
+        self.0.is_empty(column_family)
+    }
+}
+
+/// A transaction created by [`Db::transaction`] or [`Db::start_transaction`].
+///
+/// Its clones share the same underlying transaction.
+#[derive(Clone)]
+pub struct Transaction(Arc<Mutex<Option<Box<dyn BackendTransaction>>>>);
+
+impl Transaction {
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut dyn BackendTransaction) -> Result<T, StorageError>,
+    ) -> Result<T, StorageError> {
+        f(self
+            .0
+            .lock()
+            .unwrap()
+            .as_deref_mut()
+            .ok_or_else(ended_transaction_error)?)
+    }
+
+    fn take(&self) -> Result<Box<dyn BackendTransaction>, StorageError> {
+        self.0
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(ended_transaction_error)
+    }
+
+    /// Fails if the transaction has been ended using one of its clones.
+    pub fn reader(&self) -> Result<Reader, StorageError> {
+        Ok(Reader(
+            self.0
+                .lock()
+                .unwrap()
+                .as_ref()
+                .ok_or_else(ended_transaction_error)?
+                .reader()
+                .into(),
+        ))
+    }
+
+    pub fn contains_key_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        self.with(|t| t.contains_key_for_update(column_family, key))
+    }
+
+    pub fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.with(|t| t.insert(column_family, key, value))
+    }
+
+    pub fn insert_empty(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<(), StorageError> {
+        self.insert(column_family, key, &[])
+    }
+
+    pub fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+        self.with(|t| t.remove(column_family, key))
+    }
+
+    /// Commits the transaction. Its clones are not usable anymore.
+    pub fn commit(&self) -> Result<(), StorageError> {
+        self.take()?.commit()
+    }
+
+    /// Rolls back the transaction. Its clones are not usable anymore.
+    pub fn rollback(&self) -> Result<(), StorageError> {
+        self.take()?.rollback()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn check_options(options: &StoreOptions) -> Result<(), StorageError> {
+    for column_family in options.column_family_compressions.keys() {
+        if !column_families()
+            .iter()
+            .any(|cf| cf.name == column_family.as_str())
+        {
+            return Err(StorageError::Other(
+                format!("Unknown column family {}", column_family).into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn ended_transaction_error() -> StorageError {
+    StorageError::Other("The transaction is already ended".into())
+}
+
+pub struct Iter(Box<dyn BackendIter>);
+
+impl Iter {
+    pub fn key(&self) -> Option<&[u8]> {
+        self.0.key()
+    }
+
+    pub fn value(&self) -> Option<&[u8]> {
+        self.0.value()
+    }
+
+    pub fn next(&mut self) {
+        self.0.next();
+    }
+
+    /// Moves to the first key greater or equal to `key`. The key should be after the current one.
+    pub fn seek(&mut self, key: &[u8]) {
+        self.0.seek(key);
+    }
+
+    pub fn status(&self) -> Result<(), StorageError> {
+        self.0.status()
+    }
+}
@@ -2,26 +2,26 @@
 
 #![allow(unsafe_code, trivial_casts)]
 
+use crate::storage::backend::{
+    ended_transaction_error, Backend, BackendBulkInsertion, BackendIter, BackendReader,
+    BackendTransaction, ColumnFamily, ColumnFamilyDefinition, CompressionType, StoreOptions, Value,
+};
 use crate::storage::error::StorageError;
 use crate::store::CorruptionError;
 use lazy_static::lazy_static;
-use libc::{self, c_char, c_void, free};
+use libc::{self, c_char, c_int, c_void, free};
 use oxrocksdb_sys::*;
 use rand::random;
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::env::temp_dir;
 use std::error::Error;
 use std::ffi::{CStr, CString};
 use std::fmt;
-use std::fs::remove_dir_all;
+use std::fs::{copy, create_dir_all, read_dir};
 use std::io;
-use std::marker::PhantomData;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::rc::{Rc, Weak};
-use std::sync::Arc;
-use std::thread::yield_now;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::{ptr, slice};
 use sysinfo::{System, SystemExt};
 
@@ -60,32 +60,24 @@ lazy_static! {
             UnsafeEnv(env)
         }
     };
-    static ref ROCKSDB_MEM_ENV: UnsafeEnv = {
-        unsafe {
-            let env = rocksdb_create_mem_env();
-            assert!(!env.is_null(), "rocksdb_create_mem_env returned null");
-            UnsafeEnv(env)
-        }
-    };
     static ref CPU_COUNT: Option<usize> = System::new().physical_core_count();
 }
 
-pub struct ColumnFamilyDefinition {
-    pub name: &'static str,
-    pub use_iter: bool,
-    pub min_prefix_size: usize,
-    pub unordered_writes: bool,
-}
+#[derive(Clone)]
+pub struct Db(DbKind);
 
 #[derive(Clone)]
-pub struct Db(Arc<DbHandler>);
+enum DbKind {
+    ReadOnly(Arc<RoDbHandler>),
+    ReadWrite(Arc<RwDbHandler>),
+}
 
 #[allow(clippy::non_send_fields_in_send_ty)]
 unsafe impl Send for Db {}
 
 unsafe impl Sync for Db {}
 
-struct DbHandler {
+struct RwDbHandler {
     db: *mut rocksdb_transactiondb_t,
     options: *mut rocksdb_options_t,
     transaction_options: *mut rocksdb_transaction_options_t,
@@ -96,15 +88,12 @@ struct DbHandler {
     env_options: *mut rocksdb_envoptions_t,
     ingest_external_file_options: *mut rocksdb_ingestexternalfileoptions_t,
     compaction_options: *mut rocksdb_compactoptions_t,
-    block_based_table_options: *mut rocksdb_block_based_table_options_t,
-    column_family_names: Vec<&'static str>,
     cf_handles: Vec<*mut rocksdb_column_family_handle_t>,
     cf_options: Vec<*mut rocksdb_options_t>,
     path: PathBuf,
-    in_memory: bool,
 }
 
-impl Drop for DbHandler {
+impl Drop for RwDbHandler {
     fn drop(&mut self) {
         unsafe {
             for cf_handle in &self.cf_handles {
@@ -123,84 +112,70 @@ impl Drop for DbHandler {
             rocksdb_transaction_options_destroy(self.transaction_options);
             rocksdb_transactiondb_options_destroy(self.transactiondb_options);
             rocksdb_options_destroy(self.options);
-            rocksdb_block_based_options_destroy(self.block_based_table_options);
-        }
-        if self.in_memory && self.path.exists() {
-            remove_dir_all(&self.path).unwrap();
         }
     }
 }
 
-impl Db {
-    pub fn new(column_families: Vec<ColumnFamilyDefinition>) -> Result<Self, StorageError> {
-        let path = if cfg!(target_os = "linux") {
-            "/dev/shm/".into()
-        } else {
-            temp_dir()
+struct RoDbHandler {
+    db: *mut rocksdb_t,
+    options: *mut rocksdb_options_t,
+    read_options: *mut rocksdb_readoptions_t,
+    cf_handles: Vec<*mut rocksdb_column_family_handle_t>,
+    cf_options: Vec<*mut rocksdb_options_t>,
+}
+
+impl Drop for RoDbHandler {
+    fn drop(&mut self) {
+        unsafe {
+            for cf_handle in &self.cf_handles {
+                rocksdb_column_family_handle_destroy(*cf_handle);
+            }
+            rocksdb_close(self.db);
+            for cf_option in &self.cf_options {
+                rocksdb_options_destroy(*cf_option);
+            }
+            rocksdb_readoptions_destroy(self.read_options);
+            rocksdb_options_destroy(self.options);
         }
-        .join(format!("oxigraph-rocksdb-{}", random::<u128>()));
-        Ok(Self(Arc::new(Self::do_open(path, column_families, true)?)))
     }
+}
 
+impl Db {
     pub fn open(
         path: &Path,
-        column_families: Vec<ColumnFamilyDefinition>,
+        column_families: &[ColumnFamilyDefinition],
+        options: &StoreOptions,
     ) -> Result<Self, StorageError> {
-        Ok(Self(Arc::new(Self::do_open(
+        Ok(Self(DbKind::ReadWrite(Arc::new(Self::do_open_read_write(
             path.to_owned(),
-            column_families,
-            false,
-        )?)))
+            column_families.to_vec(),
+            options,
+        )?))))
     }
 
-    fn do_open(
+    pub fn open_read_only(
+        path: &Path,
+        column_families: &[ColumnFamilyDefinition],
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(DbKind::ReadOnly(Arc::new(Self::do_open_read_only(
+            path,
+            column_families.to_vec(),
+            options,
+        )?))))
+    }
+
+    fn do_open_read_write(
         path: PathBuf,
-        mut column_families: Vec<ColumnFamilyDefinition>,
-        in_memory: bool,
-    ) -> Result<DbHandler, StorageError> {
+        column_families: Vec<ColumnFamilyDefinition>,
+        store_options: &StoreOptions,
+    ) -> Result<RwDbHandler, StorageError> {
         let c_path = path_to_cstring(&path)?;
 
         unsafe {
-            let options = rocksdb_options_create();
-            assert!(!options.is_null(), "rocksdb_options_create returned null");
+            let options = Self::db_options(store_options)?;
             rocksdb_options_set_create_if_missing(options, 1);
             rocksdb_options_set_create_missing_column_families(options, 1);
-            rocksdb_options_optimize_level_style_compaction(options, 512 * 1024 * 1024);
-            if let Some(cpu_count) = *CPU_COUNT {
-                rocksdb_options_increase_parallelism(options, cpu_count.try_into().unwrap());
-            }
-            rocksdb_options_set_info_log_level(options, 2); // We only log warnings
-            rocksdb_options_set_max_log_file_size(options, 1024 * 1024); // Only 1MB log size
-            rocksdb_options_set_recycle_log_file_num(options, 10); // We do not keep more than 10 log files
-            rocksdb_options_set_compression(
-                options,
-                if in_memory {
-                    rocksdb_no_compression
-                } else {
-                    rocksdb_lz4_compression
-                }
-                .try_into()
-                .unwrap(),
-            );
-            rocksdb_options_set_env(
-                options,
-                if in_memory {
-                    ROCKSDB_MEM_ENV.0
-                } else {
-                    ROCKSDB_ENV.0
-                },
-            );
-            let block_based_table_options = rocksdb_block_based_options_create();
-            assert!(
-                !block_based_table_options.is_null(),
-                "rocksdb_block_based_options_create returned null"
-            );
-            rocksdb_block_based_options_set_format_version(block_based_table_options, 5);
-            rocksdb_block_based_options_set_index_block_restart_interval(
-                block_based_table_options,
-                16,
-            );
-            rocksdb_options_set_block_based_table_factory(options, block_based_table_options);
 
             let transactiondb_options = rocksdb_transactiondb_options_create();
             assert!(
@@ -208,40 +183,8 @@ impl Db {
                 "rocksdb_transactiondb_options_create returned null"
             );
 
-            if !column_families.iter().any(|c| c.name == "default") {
-                column_families.push(ColumnFamilyDefinition {
-                    name: "default",
-                    use_iter: true,
-                    min_prefix_size: 0,
-                    unordered_writes: false,
-                })
-            }
-            let column_family_names = column_families.iter().map(|c| c.name).collect::<Vec<_>>();
-            let c_column_families = column_family_names
-                .iter()
-                .map(|name| CString::new(*name))
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| StorageError::Other(Box::new(e)))?;
-            let cf_options = column_families
-                .into_iter()
-                .map(|cf| {
-                    let options = rocksdb_options_create_copy(options);
-                    if !cf.use_iter {
-                        rocksdb_options_optimize_for_point_lookup(options, 128);
-                    }
-                    if cf.min_prefix_size > 0 {
-                        rocksdb_options_set_prefix_extractor(
-                            options,
-                            rocksdb_slicetransform_create_fixed_prefix(cf.min_prefix_size),
-                        );
-                    }
-                    if cf.unordered_writes {
-                        rocksdb_options_set_unordered_write(options, 1);
-                    }
-                    options
-                })
-                .collect::<Vec<_>>();
-
+            let (column_family_names, c_column_families, cf_options) =
+                Self::column_families_names_and_options(column_families, options, store_options)?;
             let mut cf_handles: Vec<*mut rocksdb_column_family_handle_t> =
                 vec![ptr::null_mut(); column_family_names.len()];
             let db = ffi_result!(rocksdb_transactiondb_open_column_families_with_status(
@@ -263,7 +206,6 @@ impl Db {
                 }
                 rocksdb_transactiondb_options_destroy(transactiondb_options);
                 rocksdb_options_destroy(options);
-                rocksdb_block_based_options_destroy(block_based_table_options);
                 e
             })?;
             assert!(!db.is_null(), "rocksdb_create returned null");
@@ -285,8 +227,8 @@ impl Db {
                 !write_options.is_null(),
                 "rocksdb_writeoptions_create returned null"
             );
-            if in_memory {
-                rocksdb_writeoptions_disable_WAL(write_options, 1); // No need for WAL
+            if store_options.sync_writes {
+                rocksdb_writeoptions_set_sync(write_options, 1);
             }
 
             let flush_options = rocksdb_flushoptions_create();
@@ -320,7 +262,7 @@ impl Db {
             );
             rocksdb_transaction_options_set_set_snapshot(transaction_options, 1);
 
-            Ok(DbHandler {
+            Ok(RwDbHandler {
                 db,
                 options,
                 transaction_options,
@@ -331,196 +273,193 @@ impl Db {
                 env_options,
                 ingest_external_file_options,
                 compaction_options,
-                block_based_table_options,
-                column_family_names,
                 cf_handles,
                 cf_options,
                 path,
-                in_memory,
             })
         }
     }
 
-    pub fn column_family(&self, name: &'static str) -> Option<ColumnFamily> {
-        for (cf, cf_handle) in self.0.column_family_names.iter().zip(&self.0.cf_handles) {
-            if *cf == name {
-                return Some(ColumnFamily(*cf_handle));
-            }
-        }
-        None
-    }
+    fn do_open_read_only(
+        path: &Path,
+        column_families: Vec<ColumnFamilyDefinition>,
+        store_options: &StoreOptions,
+    ) -> Result<RoDbHandler, StorageError> {
+        let c_path = path_to_cstring(path)?;
 
-    #[must_use]
-    pub fn snapshot(&self) -> Reader {
         unsafe {
-            let snapshot = rocksdb_transactiondb_create_snapshot(self.0.db);
-            assert!(
-                !snapshot.is_null(),
-                "rocksdb_transactiondb_create_snapshot returned null"
-            );
-            let options = rocksdb_readoptions_create_copy(self.0.read_options);
-            rocksdb_readoptions_set_snapshot(options, snapshot);
-            Reader {
-                inner: InnerReader::Snapshot(Rc::new(InnerSnapshot {
-                    db: self.0.clone(),
-                    snapshot,
-                })),
+            let options = Self::db_options(store_options)?;
+            let (column_family_names, c_column_families, cf_options) =
+                Self::column_families_names_and_options(column_families, options, store_options)?;
+            let mut cf_handles: Vec<*mut rocksdb_column_family_handle_t> =
+                vec![ptr::null_mut(); column_family_names.len()];
+            let db = ffi_result!(rocksdb_open_for_read_only_column_families_with_status(
                 options,
-            }
-        }
-    }
-
-    pub fn transaction<'a, 'b: 'a, T, E: Error + 'static + From<StorageError>>(
-        &'b self,
-        f: impl Fn(Transaction<'a>) -> Result<T, E>,
-    ) -> Result<T, E> {
-        loop {
-            let transaction = unsafe {
-                let transaction = rocksdb_transaction_begin(
-                    self.0.db,
-                    self.0.write_options,
-                    self.0.transaction_options,
-                    ptr::null_mut(),
-                );
+                c_path.as_ptr(),
+                c_column_families.len().try_into().unwrap(),
+                c_column_families
+                    .iter()
+                    .map(|cf| cf.as_ptr())
+                    .collect::<Vec<_>>()
+                    .as_ptr(),
+                cf_options.as_ptr() as *const *const rocksdb_options_t,
+                cf_handles.as_mut_ptr(),
+                0, // false
+            ))
+            .map_err(|e| {
+                for cf_option in &cf_options {
+                    rocksdb_options_destroy(*cf_option);
+                }
+                rocksdb_options_destroy(options);
+                e
+            })?;
+            assert!(!db.is_null(), "rocksdb_open_for_read_only returned null");
+            for handle in &cf_handles {
                 assert!(
-                    !transaction.is_null(),
-                    "rocksdb_transaction_begin returned null"
+                    !handle.is_null(),
+                    "rocksdb_open_for_read_only returned a null column family"
                 );
-                transaction
-            };
-            let (read_options, snapshot) = unsafe {
-                let options = rocksdb_readoptions_create_copy(self.0.read_options);
-                let snapshot = rocksdb_transaction_get_snapshot(transaction);
-                rocksdb_readoptions_set_snapshot(options, snapshot);
-                (options, snapshot)
-            };
-            let result = f(Transaction {
-                transaction: Rc::new(transaction),
-                read_options,
-                _lifetime: PhantomData::default(),
-            });
-            match result {
-                Ok(result) => {
-                    unsafe {
-                        let r = ffi_result!(rocksdb_transaction_commit_with_status(transaction));
-                        rocksdb_transaction_destroy(transaction);
-                        rocksdb_readoptions_destroy(read_options);
-                        free(snapshot as *mut c_void);
-                        r.map_err(StorageError::from)?; // We make sure to also run destructors if the commit fails
-                    }
-                    return Ok(result);
-                }
-                Err(e) => {
-                    unsafe {
-                        let r = ffi_result!(rocksdb_transaction_rollback_with_status(transaction));
-                        rocksdb_transaction_destroy(transaction);
-                        rocksdb_readoptions_destroy(read_options);
-                        free(snapshot as *mut c_void);
-                        r.map_err(StorageError::from)?; // We make sure to also run destructors if the commit fails
-                    }
-                    // We look for the root error
-                    let mut error: &(dyn Error + 'static) = &e;
-                    while let Some(e) = error.source() {
-                        error = e;
-                    }
-                    let is_conflict_error =
-                        error.downcast_ref::<ErrorStatus>().map_or(false, |e| {
-                            e.0.code == rocksdb_status_code_t_rocksdb_status_code_busy
-                                || e.0.code == rocksdb_status_code_t_rocksdb_status_code_timed_out
-                                || e.0.code == rocksdb_status_code_t_rocksdb_status_code_try_again
-                        });
-                    if is_conflict_error {
-                        // We give a chance to the OS to do something else before retrying in order to help avoiding another conflict
-                        yield_now();
-                    } else {
-                        // We raise the error
-                        return Err(e);
-                    }
-                }
             }
-        }
-    }
 
-    pub fn get(
-        &self,
-        column_family: &ColumnFamily,
-        key: &[u8],
-    ) -> Result<Option<PinnableSlice>, StorageError> {
-        unsafe {
-            let slice = ffi_result!(rocksdb_transactiondb_get_pinned_cf_with_status(
-                self.0.db,
-                self.0.read_options,
-                column_family.0,
-                key.as_ptr() as *const c_char,
-                key.len()
-            ))?;
-            Ok(if slice.is_null() {
-                None
-            } else {
-                Some(PinnableSlice(slice))
+            let read_options = rocksdb_readoptions_create();
+            assert!(
+                !read_options.is_null(),
+                "rocksdb_readoptions_create returned null"
+            );
+
+            Ok(RoDbHandler {
+                db,
+                options,
+                read_options,
+                cf_handles,
+                cf_options,
             })
         }
     }
 
-    pub fn contains_key(
-        &self,
-        column_family: &ColumnFamily,
-        key: &[u8],
-    ) -> Result<bool, StorageError> {
-        Ok(self.get(column_family, key)?.is_some()) //TODO: optimize
+    unsafe fn db_options(
+        store_options: &StoreOptions,
+    ) -> Result<*mut rocksdb_options_t, StorageError> {
+        let wal_directory = store_options
+            .wal_directory
+            .as_deref()
+            .map(path_to_cstring)
+            .transpose()?;
+        let options = rocksdb_options_create();
+        assert!(!options.is_null(), "rocksdb_options_create returned null");
+        rocksdb_options_optimize_level_style_compaction(options, 512 * 1024 * 1024);
+        if let Some(cpu_count) = *CPU_COUNT {
+            rocksdb_options_increase_parallelism(options, cpu_count.try_into().unwrap());
+        }
+        rocksdb_options_set_info_log_level(options, 2); // We only log warnings
+        rocksdb_options_set_max_log_file_size(options, 1024 * 1024); // Only 1MB log size
+        rocksdb_options_set_recycle_log_file_num(options, 10); // We do not keep more than 10 log files
+        if let Some(max_open_files) = store_options.max_open_files {
+            rocksdb_options_set_max_open_files(
+                options,
+                max_open_files.try_into().unwrap_or(c_int::MAX),
+            );
+        }
+        if let Some(wal_directory) = &wal_directory {
+            rocksdb_options_set_wal_dir(options, wal_directory.as_ptr());
+        }
+        rocksdb_options_set_env(options, ROCKSDB_ENV.0);
+        let block_based_table_options = rocksdb_block_based_options_create();
+        assert!(
+            !block_based_table_options.is_null(),
+            "rocksdb_block_based_options_create returned null"
+        );
+        rocksdb_block_based_options_set_format_version(block_based_table_options, 5);
+        rocksdb_block_based_options_set_index_block_restart_interval(block_based_table_options, 16);
+        if let Some(cache_size) = store_options.cache_size {
+            let cache = rocksdb_cache_create_lru(cache_size);
+            assert!(!cache.is_null(), "rocksdb_cache_create_lru returned null");
+            rocksdb_block_based_options_set_block_cache(block_based_table_options, cache);
+            rocksdb_cache_destroy(cache); // The table options keep a reference
+        }
+        rocksdb_options_set_block_based_table_factory(options, block_based_table_options);
+        rocksdb_block_based_options_destroy(block_based_table_options); // The factory keeps a copy
+        Ok(options)
     }
 
-    pub fn insert(
-        &self,
-        column_family: &ColumnFamily,
-        key: &[u8],
-        value: &[u8],
-    ) -> Result<(), StorageError> {
-        unsafe {
-            ffi_result!(rocksdb_transactiondb_put_cf_with_status(
-                self.0.db,
-                self.0.write_options,
-                column_family.0,
-                key.as_ptr() as *const c_char,
-                key.len(),
-                value.as_ptr() as *const c_char,
-                value.len(),
-            ))?;
+    #[allow(clippy::type_complexity)]
+    unsafe fn column_families_names_and_options(
+        mut column_families: Vec<ColumnFamilyDefinition>,
+        base_options: *mut rocksdb_options_t,
+        store_options: &StoreOptions,
+    ) -> Result<(Vec<&'static str>, Vec<CString>, Vec<*mut rocksdb_options_t>), StorageError> {
+        if !column_families.iter().any(|c| c.name == "default") {
+            column_families.push(ColumnFamilyDefinition {
+                name: "default",
+                use_iter: true,
+                min_prefix_size: 0,
+                unordered_writes: false,
+            })
         }
-        Ok(())
+        let column_family_names = column_families.iter().map(|c| c.name).collect::<Vec<_>>();
+        let c_column_families = column_family_names
+            .iter()
+            .map(|name| CString::new(*name))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| StorageError::Other(Box::new(e)))?;
+        let cf_options = column_families
+            .into_iter()
+            .map(|cf| {
+                let options = rocksdb_options_create_copy(base_options);
+                let compression = compression_type(store_options.compression(cf.name));
+                rocksdb_options_set_compression(options, compression);
+                // Like with the level style compaction optimization, the first two levels are not compressed.
+                // The last value is used for the next levels.
+                let mut compression_per_level = [
+                    compression_type(CompressionType::None),
+                    compression_type(CompressionType::None),
+                    compression,
+                ];
+                rocksdb_options_set_compression_per_level(
+                    options,
+                    compression_per_level.as_mut_ptr(),
+                    compression_per_level.len(),
+                );
+                if !cf.use_iter {
+                    rocksdb_options_optimize_for_point_lookup(options, 128);
+                }
+                if cf.min_prefix_size > 0 {
+                    rocksdb_options_set_prefix_extractor(
+                        options,
+                        rocksdb_slicetransform_create_fixed_prefix(cf.min_prefix_size),
+                    );
+                }
+                if cf.unordered_writes {
+                    rocksdb_options_set_unordered_write(options, 1);
+                }
+                options
+            })
+            .collect::<Vec<_>>();
+        Ok((column_family_names, c_column_families, cf_options))
     }
 
-    pub fn flush(&self, column_family: &ColumnFamily) -> Result<(), StorageError> {
-        unsafe {
-            ffi_result!(rocksdb_transactiondb_flush_cf_with_status(
-                self.0.db,
-                self.0.flush_options,
-                column_family.0,
-            ))?;
+    fn cf_handles(&self) -> &[*mut rocksdb_column_family_handle_t] {
+        match &self.0 {
+            DbKind::ReadOnly(db) => &db.cf_handles,
+            DbKind::ReadWrite(db) => &db.cf_handles,
         }
-        Ok(())
     }
 
-    #[allow(clippy::unnecessary_wraps)]
-    pub fn compact(&self, column_family: &ColumnFamily) -> Result<(), StorageError> {
-        unsafe {
-            ffi_result!(rocksdb_transactiondb_compact_range_cf_opt_with_status(
-                self.0.db,
-                column_family.0,
-                self.0.compaction_options,
-                ptr::null(),
-                0,
-                ptr::null(),
-                0,
-            ))?;
+    fn read_write(&self) -> Result<&Arc<RwDbHandler>, StorageError> {
+        match &self.0 {
+            DbKind::ReadOnly(_) => Err(StorageError::Other(
+                "This operation is not possible on a database opened in read-only mode".into(),
+            )),
+            DbKind::ReadWrite(db) => Ok(db),
         }
-        Ok(())
     }
 
-    pub fn new_sst_file(&self) -> Result<SstFileWriter, StorageError> {
+    fn new_sst_file(&self, column_family: &ColumnFamily) -> Result<SstFileWriter, StorageError> {
+        let db = self.read_write()?;
         unsafe {
-            let path = self.0.path.join(random::<u128>().to_string());
-            let writer = rocksdb_sstfilewriter_create(self.0.env_options, self.0.options);
+            let path = db.path.join(random::<u128>().to_string());
+            let writer =
+                rocksdb_sstfilewriter_create(db.env_options, db.cf_options[column_family.index()]);
             ffi_result!(rocksdb_sstfilewriter_open_with_status(
                 writer,
                 path_to_cstring(&path)?.as_ptr()
@@ -533,10 +472,11 @@ impl Db {
         }
     }
 
-    pub fn insert_stt_files(
+    fn insert_stt_files(
         &self,
-        ssts_for_cf: &[(&ColumnFamily, PathBuf)],
+        ssts_for_cf: &[(*mut rocksdb_column_family_handle_t, PathBuf)],
     ) -> Result<(), StorageError> {
+        let db = self.read_write()?;
         let mut paths_by_cf = HashMap::<_, Vec<_>>::new();
         for (cf, path) in ssts_for_cf {
             paths_by_cf
@@ -551,15 +491,15 @@ impl Db {
         let args = cpaths_by_cf
             .iter()
             .map(|(cf, p)| rocksdb_ingestexternalfilearg_t {
-                column_family: cf.0,
+                column_family: *cf,
                 external_files: p.as_ptr(),
                 external_files_len: p.len(),
-                options: self.0.ingest_external_file_options,
+                options: db.ingest_external_file_options,
             })
             .collect::<Vec<_>>();
         unsafe {
             ffi_result!(rocksdb_transactiondb_ingest_external_files_with_status(
-                self.0.db,
+                db.db,
                 args.as_ptr(),
                 args.len()
             ))?;
@@ -567,31 +507,330 @@ impl Db {
         Ok(())
     }
 
-    pub fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
-        if self.0.in_memory {
-            return Err(StorageError::Other(
-                "It is not possible to backup an in-memory database created with `Store::open`"
-                    .into(),
-            ));
+    pub fn purge_old_backups(
+        backup_directory: &Path,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        let engine = BackupEngine::open(backup_directory)?;
+        unsafe {
+            ffi_result!(rocksdb_backup_engine_purge_old_backups_with_status(
+                engine.0,
+                num_backups_to_keep
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn restore_from_backup(
+        backup_directory: &Path,
+        target_directory: &Path,
+    ) -> Result<(), StorageError> {
+        if target_directory.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "The restoration target {} already exists",
+                    target_directory.display()
+                ),
+            )
+            .into());
+        }
+        if !backup_directory.join("meta").is_dir() {
+            // It is a checkpoint created by `backup`: it is a regular database we copy
+            return Ok(copy_directory(backup_directory, target_directory)?);
+        }
+        let engine = BackupEngine::open(backup_directory)?;
+        let path = path_to_cstring(target_directory)?;
+        unsafe {
+            let restore_options = rocksdb_restore_options_create();
+            assert!(
+                !restore_options.is_null(),
+                "rocksdb_restore_options_create returned null"
+            );
+            let result = ffi_result!(
+                rocksdb_backup_engine_restore_db_from_latest_backup_with_status(
+                    engine.0,
+                    path.as_ptr(),
+                    path.as_ptr(),
+                    restore_options
+                )
+            );
+            rocksdb_restore_options_destroy(restore_options);
+            result?;
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Db {
+    fn snapshot(&self) -> Box<dyn BackendReader> {
+        Box::new(unsafe {
+            match &self.0 {
+                DbKind::ReadOnly(db) => {
+                    // The database content never changes: no need for an explicit snapshot
+                    Reader {
+                        inner: InnerReader::PlainDb(db.clone()),
+                        options: rocksdb_readoptions_create_copy(db.read_options),
+                    }
+                }
+                DbKind::ReadWrite(db) => {
+                    let snapshot = rocksdb_transactiondb_create_snapshot(db.db);
+                    assert!(
+                        !snapshot.is_null(),
+                        "rocksdb_transactiondb_create_snapshot returned null"
+                    );
+                    let options = rocksdb_readoptions_create_copy(db.read_options);
+                    rocksdb_readoptions_set_snapshot(options, snapshot);
+                    Reader {
+                        inner: InnerReader::Snapshot(Arc::new(InnerSnapshot {
+                            db: db.clone(),
+                            snapshot,
+                        })),
+                        options,
+                    }
+                }
+            }
+        })
+    }
+
+    fn transaction(&self) -> Result<Box<dyn BackendTransaction>, StorageError> {
+        let db = self.read_write()?;
+        unsafe {
+            let transaction = rocksdb_transaction_begin(
+                db.db,
+                db.write_options,
+                db.transaction_options,
+                ptr::null_mut(),
+            );
+            assert!(
+                !transaction.is_null(),
+                "rocksdb_transaction_begin returned null"
+            );
+            let read_options = rocksdb_readoptions_create_copy(db.read_options);
+            let snapshot = rocksdb_transaction_get_snapshot(transaction);
+            rocksdb_readoptions_set_snapshot(read_options, snapshot);
+            Ok(Box::new(Transaction {
+                db: db.clone(),
+                transaction: Arc::new(TransactionHandle {
+                    transaction,
+                    ended: Mutex::new(false),
+                }),
+                read_options,
+                snapshot,
+            }))
+        }
+    }
+
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError> {
+        unsafe {
+            let slice = match &self.0 {
+                DbKind::ReadOnly(db) => ffi_result!(rocksdb_get_pinned_cf_with_status(
+                    db.db,
+                    db.read_options,
+                    self.cf_handles()[column_family.index()],
+                    key.as_ptr() as *const c_char,
+                    key.len()
+                )),
+                DbKind::ReadWrite(db) => {
+                    ffi_result!(rocksdb_transactiondb_get_pinned_cf_with_status(
+                        db.db,
+                        db.read_options,
+                        self.cf_handles()[column_family.index()],
+                        key.as_ptr() as *const c_char,
+                        key.len()
+                    ))
+                }
+            }?;
+            Ok(if slice.is_null() {
+                None
+            } else {
+                Some(Value::Pinned(PinnableSlice(slice)))
+            })
+        }
+    }
+
+    fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        unsafe {
+            ffi_result!(rocksdb_transactiondb_put_cf_with_status(
+                db.db,
+                db.write_options,
+                self.cf_handles()[column_family.index()],
+                key.as_ptr() as *const c_char,
+                key.len(),
+                value.as_ptr() as *const c_char,
+                value.len(),
+            ))?;
         }
+        Ok(())
+    }
+
+    fn bulk_insertion(&self) -> Result<Box<dyn BackendBulkInsertion + '_>, StorageError> {
+        self.read_write()?;
+        Ok(Box::new(SstBulkInsertion {
+            db: self,
+            files: Vec::new(),
+        }))
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        for cf_handle in &db.cf_handles {
+            unsafe {
+                ffi_result!(rocksdb_transactiondb_flush_cf_with_status(
+                    db.db,
+                    db.flush_options,
+                    *cf_handle,
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        for cf_handle in &db.cf_handles {
+            unsafe {
+                ffi_result!(rocksdb_transactiondb_compact_range_cf_opt_with_status(
+                    db.db,
+                    *cf_handle,
+                    db.compaction_options,
+                    ptr::null(),
+                    0,
+                    ptr::null(),
+                    0,
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn disk_size(&self, column_family: &ColumnFamily) -> Result<Option<u64>, StorageError> {
+        let cf_handle = self.cf_handles()[column_family.index()];
+        let property = b"rocksdb.total-sst-files-size\0".as_ptr() as *const c_char;
+        let mut size = 0;
+        let status = unsafe {
+            match &self.0 {
+                DbKind::ReadOnly(db) => {
+                    rocksdb_property_int_cf(db.db, cf_handle, property, &mut size)
+                }
+                DbKind::ReadWrite(db) => {
+                    rocksdb_transactiondb_get_int_property_cf(db.db, cf_handle, property, &mut size)
+                }
+            }
+        };
+        Ok(if status == 0 { Some(size) } else { None })
+    }
+
+    fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
+        let db = self.read_write()?;
         let path = path_to_cstring(target_directory)?;
         unsafe {
             ffi_result!(rocksdb_transactiondb_create_checkpoint_with_status(
-                self.0.db,
+                db.db,
                 path.as_ptr()
             ))?;
         }
         Ok(())
     }
+
+    fn incremental_backup(&self, backup_directory: &Path) -> Result<(), StorageError> {
+        let db = self.read_write()?;
+        let engine = BackupEngine::open(backup_directory)?;
+        unsafe {
+            ffi_result!(
+                rocksdb_backup_engine_create_new_backup_from_transactiondb_with_status(
+                    engine.0, db.db, 1
+                )
+            )?;
+        }
+        Ok(())
+    }
 }
 
-// It is fine to not keep a lifetime: there is no way to use this type without the database being still in scope.
-// So, no use after free possible.
-#[derive(Clone, Eq, PartialEq, Hash)]
-pub struct ColumnFamily(*mut rocksdb_column_family_handle_t);
+/// A bulk insertion writing SST files that are all ingested when committed.
+struct SstBulkInsertion<'a> {
+    db: &'a Db,
+    files: Vec<(*mut rocksdb_column_family_handle_t, PathBuf)>,
+}
 
-unsafe impl Send for ColumnFamily {}
-unsafe impl Sync for ColumnFamily {}
+impl BackendBulkInsertion for SstBulkInsertion<'_> {
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        entries: &mut dyn Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), StorageError> {
+        let mut entries = entries.peekable();
+        if entries.peek().is_none() {
+            return Ok(()); // RocksDB does not allow empty SST files
+        }
+        let mut sst = self.db.new_sst_file(column_family)?;
+        for (key, value) in entries {
+            sst.insert(&key, &value)?;
+        }
+        self.files
+            .push((self.db.cf_handles()[column_family.index()], sst.finish()?));
+        Ok(())
+    }
+
+    fn remove(
+        &mut self,
+        column_family: &ColumnFamily,
+        keys: &mut dyn Iterator<Item = Vec<u8>>,
+    ) -> Result<(), StorageError> {
+        let mut keys = keys.peekable();
+        if keys.peek().is_none() {
+            return Ok(()); // RocksDB does not allow empty SST files
+        }
+        let mut sst = self.db.new_sst_file(column_family)?;
+        for key in keys {
+            sst.remove(&key)?;
+        }
+        self.files
+            .push((self.db.cf_handles()[column_family.index()], sst.finish()?));
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        if self.files.is_empty() {
+            return Ok(());
+        }
+        self.db.insert_stt_files(&self.files)
+    }
+}
+
+/// A RocksDB [backup engine](https://github.com/facebook/rocksdb/wiki/How-to-backup-RocksDB) used for incremental backups.
+struct BackupEngine(*mut rocksdb_backup_engine_t);
+
+impl BackupEngine {
+    fn open(backup_directory: &Path) -> Result<Self, StorageError> {
+        let path = path_to_cstring(backup_directory)?;
+        unsafe {
+            let options = rocksdb_options_create();
+            assert!(!options.is_null(), "rocksdb_options_create returned null");
+            rocksdb_options_set_env(options, ROCKSDB_ENV.0);
+            let engine = ffi_result!(rocksdb_backup_engine_open_with_status(
+                options,
+                path.as_ptr()
+            ));
+            rocksdb_options_destroy(options);
+            Ok(Self(engine?))
+        }
+    }
+}
+
+impl Drop for BackupEngine {
+    fn drop(&mut self) {
+        unsafe {
+            rocksdb_backup_engine_close(self.0);
+        }
+    }
+}
 
 pub struct Reader {
     inner: InnerReader,
@@ -600,12 +839,16 @@ pub struct Reader {
 
 #[derive(Clone)]
 enum InnerReader {
-    Snapshot(Rc<InnerSnapshot>),
-    Transaction(Weak<*mut rocksdb_transaction_t>),
+    Snapshot(Arc<InnerSnapshot>),
+    Transaction {
+        transaction: Arc<TransactionHandle>,
+        db: Arc<RwDbHandler>,
+    },
+    PlainDb(Arc<RoDbHandler>),
 }
 
 struct InnerSnapshot {
-    db: Arc<DbHandler>,
+    db: Arc<RwDbHandler>,
     snapshot: *const rocksdb_snapshot_t,
 }
 
@@ -630,64 +873,71 @@ impl Drop for Reader {
     }
 }
 
+// Reads from snapshots and plain databases are thread safe in RocksDB.
+// Transaction readers only use the transaction while holding its lock.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for Reader {}
+
+unsafe impl Sync for Reader {}
+
 impl Reader {
-    pub fn get(
-        &self,
-        column_family: &ColumnFamily,
-        key: &[u8],
-    ) -> Result<Option<PinnableSlice>, StorageError> {
+    fn cf_handle(&self, column_family: &ColumnFamily) -> *mut rocksdb_column_family_handle_t {
+        match &self.inner {
+            InnerReader::Snapshot(inner) => inner.db.cf_handles[column_family.index()],
+            InnerReader::Transaction { db, .. } => db.cf_handles[column_family.index()],
+            InnerReader::PlainDb(inner) => inner.cf_handles[column_family.index()],
+        }
+    }
+}
+
+impl BackendReader for Reader {
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Result<Option<Value>, StorageError> {
+        let column_family = self.cf_handle(column_family);
         unsafe {
             let slice = match &self.inner {
                 InnerReader::Snapshot(inner) => {
                     ffi_result!(rocksdb_transactiondb_get_pinned_cf_with_status(
                         inner.db.db,
                         self.options,
-                        column_family.0,
+                        column_family,
                         key.as_ptr() as *const c_char,
                         key.len()
                     ))
                 }
-                InnerReader::Transaction(inner) => {
-                    if let Some(inner) = inner.upgrade() {
-                        ffi_result!(rocksdb_transaction_get_pinned_cf_with_status(
-                            *inner,
-                            self.options,
-                            column_family.0,
-                            key.as_ptr() as *const c_char,
-                            key.len()
-                        ))
-                    } else {
-                        return Err(StorageError::Other(
-                            "The transaction is already ended".into(),
-                        ));
-                    }
+                InnerReader::Transaction { transaction, .. } => {
+                    let _lock = transaction.lock()?;
+                    ffi_result!(rocksdb_transaction_get_pinned_cf_with_status(
+                        transaction.transaction,
+                        self.options,
+                        column_family,
+                        key.as_ptr() as *const c_char,
+                        key.len()
+                    ))
+                }
+                InnerReader::PlainDb(inner) => {
+                    ffi_result!(rocksdb_get_pinned_cf_with_status(
+                        inner.db,
+                        self.options,
+                        column_family,
+                        key.as_ptr() as *const c_char,
+                        key.len()
+                    ))
                 }
             }?;
             Ok(if slice.is_null() {
                 None
             } else {
-                Some(PinnableSlice(slice))
+                Some(Value::Pinned(PinnableSlice(slice)))
             })
         }
     }
 
-    pub fn contains_key(
-        &self,
-        column_family: &ColumnFamily,
-        key: &[u8],
-    ) -> Result<bool, StorageError> {
-        Ok(self.get(column_family, key)?.is_some()) //TODO: optimize
-    }
-
-    pub fn iter(&self, column_family: &ColumnFamily) -> Result<Iter, StorageError> {
-        self.scan_prefix(column_family, &[])
-    }
-
-    pub fn scan_prefix(
+    fn scan_prefix(
         &self,
         column_family: &ColumnFamily,
         prefix: &[u8],
-    ) -> Result<Iter, StorageError> {
+    ) -> Result<Box<dyn BackendIter>, StorageError> {
+        let column_family = self.cf_handle(column_family);
         //We generate the upper bound
         let upper_bound = {
             let mut bound = prefix.to_vec();
@@ -719,19 +969,35 @@ impl Reader {
                     upper_bound.len(),
                 );
             }
-            let iter = match &self.inner {
-                InnerReader::Snapshot(inner) => {
-                    rocksdb_transactiondb_create_iterator_cf(inner.db.db, options, column_family.0)
-                }
-                InnerReader::Transaction(inner) => {
-                    if let Some(inner) = inner.upgrade() {
-                        rocksdb_transaction_create_iterator_cf(*inner, options, column_family.0)
-                    } else {
-                        return Err(StorageError::Other(
-                            "The transaction is already ended".into(),
-                        ));
-                    }
+            let (iter, transaction, lock) = match &self.inner {
+                InnerReader::Snapshot(inner) => (
+                    rocksdb_transactiondb_create_iterator_cf(inner.db.db, options, column_family),
+                    None,
+                    None,
+                ),
+                InnerReader::Transaction { transaction, .. } => {
+                    let lock = match transaction.lock() {
+                        Ok(lock) => lock,
+                        Err(e) => {
+                            rocksdb_readoptions_destroy(options);
+                            return Err(e);
+                        }
+                    };
+                    (
+                        rocksdb_transaction_create_iterator_cf(
+                            transaction.transaction,
+                            options,
+                            column_family,
+                        ),
+                        Some(transaction.clone()),
+                        Some(lock),
+                    )
                 }
+                InnerReader::PlainDb(inner) => (
+                    rocksdb_create_iterator_cf(inner.db, options, column_family),
+                    None,
+                    None,
+                ),
             };
             assert!(!iter.is_null(), "rocksdb_create_iterator returned null");
             if prefix.is_empty() {
@@ -740,58 +1006,101 @@ impl Reader {
                 rocksdb_iter_seek(iter, prefix.as_ptr() as *const c_char, prefix.len());
             }
             let is_currently_valid = rocksdb_iter_valid(iter) != 0;
-            Ok(Iter {
+            let current = if lock.is_some() && is_currently_valid {
+                Some(Iter::copy_current(iter))
+            } else {
+                None
+            };
+            drop(lock);
+            Ok(Box::new(Iter {
                 iter,
                 options,
                 _upper_bound: upper_bound,
                 _reader: self.clone(),
                 is_currently_valid,
-            })
+                transaction,
+                current,
+                is_transaction_ended: false,
+            }))
         }
     }
+}
 
-    pub fn len(&self, column_family: &ColumnFamily) -> Result<usize, StorageError> {
-        let mut count = 0;
-        let mut iter = self.iter(column_family)?;
-        while iter.is_valid() {
-            count += 1;
-            iter.next();
-        }
-        iter.status()?; // We makes sure there is no read problem
-        Ok(count)
-    }
+pub struct Transaction {
+    db: Arc<RwDbHandler>,
+    transaction: Arc<TransactionHandle>,
+    read_options: *mut rocksdb_readoptions_t,
+    snapshot: *const rocksdb_snapshot_t,
+}
 
-    pub fn is_empty(&self, column_family: &ColumnFamily) -> Result<bool, StorageError> {
-        let iter = self.iter(column_family)?;
-        iter.status()?; // We makes sure there is no read problem
-        Ok(!iter.is_valid())
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // A transaction that is not committed is rolled back
+        if let Ok(mut ended) = self.transaction.lock() {
+            // We can't report the error, the transaction is destroyed with the last reader anyway
+            let _ = unsafe {
+                ffi_result!(rocksdb_transaction_rollback_with_status(
+                    self.transaction.transaction
+                ))
+            };
+            *ended = true;
+        }
+        unsafe {
+            rocksdb_readoptions_destroy(self.read_options);
+            free(self.snapshot as *mut c_void);
+        }
     }
 }
 
-pub struct Transaction<'a> {
-    transaction: Rc<*mut rocksdb_transaction_t>,
-    read_options: *mut rocksdb_readoptions_t,
-    _lifetime: PhantomData<&'a ()>,
+/// A RocksDB transaction shared by a [`Transaction`] and its readers.
+///
+/// RocksDB transactions are not thread safe so they are only used while holding the lock.
+/// The transaction is destroyed when the last reader is dropped.
+struct TransactionHandle {
+    transaction: *mut rocksdb_transaction_t,
+    /// If the transaction has been committed or rolled back.
+    ended: Mutex<bool>,
 }
 
-impl Transaction<'_> {
-    pub fn reader(&self) -> Reader {
-        Reader {
-            inner: InnerReader::Transaction(Rc::downgrade(&self.transaction)),
-            options: unsafe { rocksdb_readoptions_create_copy(self.read_options) },
+impl TransactionHandle {
+    /// Locks the transaction. Fails if it is already ended.
+    fn lock(&self) -> Result<MutexGuard<'_, bool>, StorageError> {
+        let ended = self.ended.lock().unwrap();
+        if *ended {
+            Err(ended_transaction_error())
+        } else {
+            Ok(ended)
         }
     }
+}
 
-    pub fn get_for_update(
+impl Drop for TransactionHandle {
+    fn drop(&mut self) {
+        unsafe { rocksdb_transaction_destroy(self.transaction) }
+    }
+}
+
+// The transaction is only used while holding the lock.
+unsafe impl Send for TransactionHandle {}
+
+unsafe impl Sync for TransactionHandle {}
+
+// RocksDB transactions could be used from any thread as long as they are not used concurrently.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for Transaction {}
+
+impl Transaction {
+    fn get_for_update(
         &self,
         column_family: &ColumnFamily,
         key: &[u8],
     ) -> Result<Option<PinnableSlice>, StorageError> {
+        let _lock = self.transaction.lock()?;
         unsafe {
             let slice = ffi_result!(rocksdb_transaction_get_for_update_pinned_cf_with_status(
-                *self.transaction,
+                self.transaction.transaction,
                 self.read_options,
-                column_family.0,
+                self.db.cf_handles[column_family.index()],
                 key.as_ptr() as *const c_char,
                 key.len()
             ))?;
@@ -802,8 +1111,20 @@ impl Transaction<'_> {
             })
         }
     }
+}
+
+impl BackendTransaction for Transaction {
+    fn reader(&self) -> Box<dyn BackendReader> {
+        Box::new(Reader {
+            inner: InnerReader::Transaction {
+                transaction: self.transaction.clone(),
+                db: self.db.clone(),
+            },
+            options: unsafe { rocksdb_readoptions_create_copy(self.read_options) },
+        })
+    }
 
-    pub fn contains_key_for_update(
+    fn contains_key_for_update(
         &self,
         column_family: &ColumnFamily,
         key: &[u8],
@@ -811,16 +1132,17 @@ impl Transaction<'_> {
         Ok(self.get_for_update(column_family, key)?.is_some()) //TODO: optimize
     }
 
-    pub fn insert(
+    fn insert(
         &mut self,
         column_family: &ColumnFamily,
         key: &[u8],
         value: &[u8],
     ) -> Result<(), StorageError> {
+        let _lock = self.transaction.lock()?;
         unsafe {
             ffi_result!(rocksdb_transaction_put_cf_with_status(
-                *self.transaction,
-                column_family.0,
+                self.transaction.transaction,
+                self.db.cf_handles[column_family.index()],
                 key.as_ptr() as *const c_char,
                 key.len(),
                 value.as_ptr() as *const c_char,
@@ -830,25 +1152,40 @@ impl Transaction<'_> {
         Ok(())
     }
 
-    pub fn insert_empty(
-        &mut self,
-        column_family: &ColumnFamily,
-        key: &[u8],
-    ) -> Result<(), StorageError> {
-        self.insert(column_family, key, &[])
-    }
-
-    pub fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+    fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+        let _lock = self.transaction.lock()?;
         unsafe {
             ffi_result!(rocksdb_transaction_delete_cf_with_status(
-                *self.transaction,
-                column_family.0,
+                self.transaction.transaction,
+                self.db.cf_handles[column_family.index()],
                 key.as_ptr() as *const c_char,
                 key.len(),
             ))?;
         }
         Ok(())
     }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        let mut ended = self.transaction.lock()?;
+        unsafe {
+            ffi_result!(rocksdb_transaction_commit_with_status(
+                self.transaction.transaction
+            ))?;
+        }
+        *ended = true;
+        Ok(())
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), StorageError> {
+        let mut ended = self.transaction.lock()?;
+        unsafe {
+            ffi_result!(rocksdb_transaction_rollback_with_status(
+                self.transaction.transaction
+            ))?;
+        }
+        *ended = true;
+        Ok(())
+    }
 }
 
 pub struct PinnableSlice(*mut rocksdb_pinnableslice_t);
@@ -936,10 +1273,22 @@ pub struct Iter {
     _upper_bound: Option<Vec<u8>>,
     _reader: Reader, // needed to ensure that DB still lives while iter is used
     options: *mut rocksdb_readoptions_t, // needed to ensure that options still lives while iter is used
+    /// The transaction the iterator reads from, if any.
+    transaction: Option<Arc<TransactionHandle>>,
+    /// The current entry of transaction iterators.
+    ///
+    /// It is copied because the transaction data could change as soon as its lock is released.
+    current: Option<(Vec<u8>, Vec<u8>)>,
+    is_transaction_ended: bool,
 }
 
 impl Drop for Iter {
     fn drop(&mut self) {
+        // The transaction must not be used concurrently, even if it is already ended
+        let _lock = self
+            .transaction
+            .as_ref()
+            .map(|transaction| transaction.ended.lock().unwrap());
         unsafe {
             rocksdb_iter_destroy(self.iter);
             rocksdb_readoptions_destroy(self.options);
@@ -953,35 +1302,94 @@ unsafe impl Send for Iter {}
 unsafe impl Sync for Iter {}
 
 impl Iter {
-    pub fn is_valid(&self) -> bool {
-        self.is_currently_valid
+    /// Moves the iterator using `action` while holding the transaction lock if there is one.
+    fn move_with(&mut self, action: impl FnOnce(*mut rocksdb_iterator_t)) {
+        let lock = if let Some(transaction) = &self.transaction {
+            if let Ok(lock) = transaction.lock() {
+                Some(lock)
+            } else {
+                self.is_currently_valid = false;
+                self.is_transaction_ended = true;
+                self.current = None;
+                return;
+            }
+        } else {
+            None
+        };
+        action(self.iter);
+        self.is_currently_valid = unsafe { rocksdb_iter_valid(self.iter) } != 0;
+        if lock.is_some() {
+            self.current = if self.is_currently_valid {
+                Some(Self::copy_current(self.iter))
+            } else {
+                None
+            };
+        }
     }
 
-    pub fn status(&self) -> Result<(), StorageError> {
+    /// Copies the current entry of a valid transaction iterator. The transaction lock must be held.
+    fn copy_current(iter: *mut rocksdb_iterator_t) -> (Vec<u8>, Vec<u8>) {
         unsafe {
-            ffi_result!(rocksdb_iter_get_status(self.iter))?;
+            let mut key_len = 0;
+            let key = rocksdb_iter_key(iter, &mut key_len);
+            let mut value_len = 0;
+            let value = rocksdb_iter_value(iter, &mut value_len);
+            (
+                slice::from_raw_parts(key as *const u8, key_len).to_vec(),
+                slice::from_raw_parts(value as *const u8, value_len).to_vec(),
+            )
         }
-        Ok(())
     }
+}
 
-    pub fn next(&mut self) {
-        unsafe {
-            rocksdb_iter_next(self.iter);
-            self.is_currently_valid = rocksdb_iter_valid(self.iter) != 0;
+impl BackendIter for Iter {
+    fn key(&self) -> Option<&[u8]> {
+        if self.transaction.is_some() {
+            self.current.as_ref().map(|(key, _)| key.as_slice())
+        } else if self.is_currently_valid {
+            unsafe {
+                let mut len = 0;
+                let val = rocksdb_iter_key(self.iter, &mut len);
+                Some(slice::from_raw_parts(val as *const u8, len))
+            }
+        } else {
+            None
         }
     }
 
-    pub fn key(&self) -> Option<&[u8]> {
-        if self.is_valid() {
+    fn value(&self) -> Option<&[u8]> {
+        if self.transaction.is_some() {
+            self.current.as_ref().map(|(_, value)| value.as_slice())
+        } else if self.is_currently_valid {
             unsafe {
                 let mut len = 0;
-                let val = rocksdb_iter_key(self.iter, &mut len);
+                let val = rocksdb_iter_value(self.iter, &mut len);
                 Some(slice::from_raw_parts(val as *const u8, len))
             }
         } else {
             None
         }
     }
+
+    fn next(&mut self) {
+        self.move_with(|iter| unsafe { rocksdb_iter_next(iter) })
+    }
+
+    fn seek(&mut self, key: &[u8]) {
+        self.move_with(|iter| unsafe {
+            rocksdb_iter_seek(iter, key.as_ptr() as *const c_char, key.len())
+        })
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        if self.is_transaction_ended {
+            return Err(ended_transaction_error());
+        }
+        unsafe {
+            ffi_result!(rocksdb_iter_get_status(self.iter))?;
+        }
+        Ok(())
+    }
 }
 
 pub struct SstFileWriter {
@@ -1015,6 +1423,17 @@ impl SstFileWriter {
         self.insert(key, &[])
     }
 
+    pub fn remove(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        unsafe {
+            ffi_result!(rocksdb_sstfilewriter_delete_with_status(
+                self.writer,
+                key.as_ptr() as *const c_char,
+                key.len(),
+            ))?;
+        }
+        Ok(())
+    }
+
     pub fn finish(self) -> Result<PathBuf, StorageError> {
         unsafe {
             ffi_result!(rocksdb_sstfilewriter_finish_with_status(self.writer))?;
@@ -1085,6 +1504,11 @@ impl From<ErrorStatus> for StorageError {
             Self::Io(io::Error::new(kind, status))
         } else if status.0.code == rocksdb_status_code_t_rocksdb_status_code_corruption {
             Self::Corruption(CorruptionError::new(status))
+        } else if status.0.code == rocksdb_status_code_t_rocksdb_status_code_busy
+            || status.0.code == rocksdb_status_code_t_rocksdb_status_code_timed_out
+            || status.0.code == rocksdb_status_code_t_rocksdb_status_code_try_again
+        {
+            Self::Conflict
         } else {
             Self::Other(Box::new(status))
         }
@@ -1096,6 +1520,29 @@ struct UnsafeEnv(*mut rocksdb_env_t);
 // Hack for lazy_static. OK because only written in lazy static and used in a thread-safe way by RocksDB
 unsafe impl Sync for UnsafeEnv {}
 
+fn copy_directory(source: &Path, target: &Path) -> io::Result<()> {
+    create_dir_all(target)?;
+    for entry in read_dir(source)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_directory(&entry.path(), &target.join(entry.file_name()))?;
+        } else {
+            copy(entry.path(), target.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+fn compression_type(compression: CompressionType) -> c_int {
+    match compression {
+        CompressionType::None => rocksdb_no_compression,
+        CompressionType::Lz4 => rocksdb_lz4_compression,
+        CompressionType::Lz4hc => rocksdb_lz4hc_compression,
+    }
+    .try_into()
+    .unwrap()
+}
+
 fn path_to_cstring(path: &Path) -> Result<CString, StorageError> {
     Ok(CString::new(path.to_str().ok_or_else(|| {
         io::Error::new(
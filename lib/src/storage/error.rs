@@ -11,6 +11,11 @@ pub enum StorageError {
     Io(io::Error),
     /// Error related to data corruption.
     Corruption(CorruptionError),
+    /// The transaction conflicts with a concurrent transaction.
+    ///
+    /// The transactions run by [`Store::transaction`](crate::store::Store::transaction) are retried automatically.
+    /// The transactions started by [`Store::start_transaction`](crate::store::Store::start_transaction) have to be started again.
+    Conflict,
     #[doc(hidden)]
     Other(Box<dyn Error + Send + Sync + 'static>),
 }
@@ -21,6 +26,7 @@ impl fmt::Display for StorageError {
         match self {
             Self::Io(e) => e.fmt(f),
             Self::Corruption(e) => e.fmt(f),
+            Self::Conflict => write!(f, "The transaction conflicts with a concurrent transaction"),
             Self::Other(e) => e.fmt(f),
         }
     }
@@ -32,6 +38,7 @@ impl Error for StorageError {
         match self {
             Self::Io(e) => Some(e),
             Self::Corruption(e) => Some(e),
+            Self::Conflict => None,
             Self::Other(e) => Some(e.as_ref()),
         }
     }
@@ -50,6 +57,7 @@ impl From<StorageError> for io::Error {
         match error {
             StorageError::Io(error) => error,
             StorageError::Corruption(error) => error.into(),
+            StorageError::Conflict => Self::new(io::ErrorKind::Other, error),
             StorageError::Other(error) => Self::new(io::ErrorKind::Other, error),
         }
     }
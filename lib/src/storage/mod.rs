@@ -1,5 +1,7 @@
 use crate::model::{GraphNameRef, NamedOrBlankNodeRef, Quad, QuadRef, TermRef};
-use crate::storage::backend::{Reader, Transaction};
+use crate::storage::backend::{Backend, Reader, Transaction};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::storage::backend::{CompressionType, StoreOptions};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::storage::binary_encoder::LATEST_STORAGE_VERSION;
 use crate::storage::binary_encoder::{
@@ -12,24 +14,30 @@ pub use crate::storage::error::{CorruptionError, LoaderError, SerializerError, S
 use crate::storage::numeric_encoder::{
     insert_term, Decoder, EncodedQuad, EncodedTerm, StrHash, StrLookup,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use backend::BackendBulkInsertion;
 use backend::{ColumnFamily, ColumnFamilyDefinition, Db, Iter};
+use rand::random;
 use std::cmp::{max, min};
-use std::collections::VecDeque;
 #[cfg(not(target_arch = "wasm32"))]
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 #[cfg(not(target_arch = "wasm32"))]
 use std::mem::take;
 #[cfg(not(target_arch = "wasm32"))]
-use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::ops::Range;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::str;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 #[cfg(not(target_arch = "wasm32"))]
 use std::thread::spawn;
 use std::thread::JoinHandle;
 use sysinfo::{System, SystemExt};
 
-mod backend;
+pub mod backend;
 mod binary_encoder;
 mod error;
 pub mod numeric_encoder;
@@ -49,7 +57,95 @@ const GRAPHS_CF: &str = "graphs";
 const DEFAULT_CF: &str = "default";
 #[cfg(not(target_arch = "wasm32"))]
 const DEFAULT_BULK_LOAD_BATCH_SIZE: usize = 1_000_000;
+/// Prefix of the keys of the bulk load checkpoints in the default column family
+#[cfg(not(target_arch = "wasm32"))]
+const CHECKPOINT_KEY_PREFIX: &[u8] = b"bulkload\0";
 const MAX_BULK_LOAD_BATCH_SIZE: usize = 100_000_000;
+/// Prefix of the keys storing the number of quads in the default column family, followed by the writer id
+const LEN_KEY_PREFIX: &[u8] = b"len\0";
+/// Prefix of the keys storing the number of quads of each graph in the default column family, followed by the writer id and the graph name
+const GRAPH_LEN_KEY_PREFIX: &[u8] = b"graphlen\0";
+/// Prefix of the keys written in the default column family by the bulk loads until they count the quads they have written, followed by a random id
+#[cfg(not(target_arch = "wasm32"))]
+const UNCOUNTED_KEY_PREFIX: &[u8] = b"uncounted\0";
+/// Number of commits after which the changes to the number of quads are merged
+const LEN_MERGE_THRESHOLD: usize = 128;
+
+/// The column families of the storage, the order defines the column family indexes.
+const COLUMN_FAMILIES: &[ColumnFamilyDefinition] = &[
+    ColumnFamilyDefinition {
+        name: DEFAULT_CF,
+        use_iter: true,
+        min_prefix_size: 0,
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: ID2STR_CF,
+        use_iter: false,
+        min_prefix_size: 0,
+        unordered_writes: true,
+    },
+    ColumnFamilyDefinition {
+        name: SPOG_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: POSG_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: OSPG_CF,
+        use_iter: true,
+        min_prefix_size: 0, // There are small literals...
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: GSPO_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: GPOS_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: GOSP_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: DSPO_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: DPOS_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: DOSP_CF,
+        use_iter: true,
+        min_prefix_size: 0, // There are small literals...
+        unordered_writes: false,
+    },
+    ColumnFamilyDefinition {
+        name: GRAPHS_CF,
+        use_iter: true,
+        min_prefix_size: 17, // named or blank node start
+        unordered_writes: false,
+    },
+];
 
 /// Low level storage primitives
 #[derive(Clone)]
@@ -67,91 +163,55 @@ pub struct Storage {
     dpos_cf: ColumnFamily,
     dosp_cf: ColumnFamily,
     graphs_cf: ColumnFamily,
+    /// Number of commits since the changes to the number of quads have been merged
+    unmerged_len_commits: Arc<AtomicUsize>,
 }
 
 impl Storage {
     pub fn new() -> Result<Self, StorageError> {
-        Self::setup(Db::new(Self::column_families())?)
+        Self::setup(Db::new())
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn open(path: &Path) -> Result<Self, StorageError> {
-        Self::setup(Db::open(path, Self::column_families())?)
+    pub fn open(path: &Path, options: &StoreOptions) -> Result<Self, StorageError> {
+        Self::setup(Db::open(path, options)?)
     }
 
-    fn column_families() -> Vec<ColumnFamilyDefinition> {
-        vec![
-            ColumnFamilyDefinition {
-                name: ID2STR_CF,
-                use_iter: false,
-                min_prefix_size: 0,
-                unordered_writes: true,
-            },
-            ColumnFamilyDefinition {
-                name: SPOG_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: POSG_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: OSPG_CF,
-                use_iter: true,
-                min_prefix_size: 0, // There are small literals...
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: GSPO_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: GPOS_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: GOSP_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: DSPO_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: DPOS_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: DOSP_CF,
-                use_iter: true,
-                min_prefix_size: 0, // There are small literals...
-                unordered_writes: false,
-            },
-            ColumnFamilyDefinition {
-                name: GRAPHS_CF,
-                use_iter: true,
-                min_prefix_size: 17, // named or blank node start
-                unordered_writes: false,
-            },
-        ]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_read_only(path: &Path, options: &StoreOptions) -> Result<Self, StorageError> {
+        let this = Self::from_db(Db::open_read_only(path, options)?);
+        match this.read_version()? {
+            Some(LATEST_STORAGE_VERSION) => Ok(this),
+            Some(version) => Err(CorruptionError::msg(format!(
+                "The RocksDB database is using the encoding version {} and not the version {} expected by this Oxigraph version. Open it once in read-write mode to migrate it or upgrade Oxigraph",
+                version, LATEST_STORAGE_VERSION
+            )).into()),
+            None => Err(CorruptionError::msg(
+                "The RocksDB database has not been initialized yet. Open it once in read-write mode before opening it in read-only mode"
+            ).into())
+        }
+    }
+
+    pub fn from_backend(backend: impl Backend) -> Result<Self, StorageError> {
+        Self::setup(Db::from_backend(backend))
     }
 
     fn setup(db: Db) -> Result<Self, StorageError> {
-        let this = Self {
+        let this = Self::from_db(db);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            this.migrate()?;
+            if this.snapshot().has_uncounted_quads()? {
+                // A bulk load has been interrupted before counting the quads it wrote
+                this.recount_lens()?;
+            }
+        }
+        this.merge_lens()?;
+        Ok(this)
+    }
+
+    fn from_db(db: Db) -> Self {
+        Self {
             default_cf: db.column_family(DEFAULT_CF).unwrap(),
             id2str_cf: db.column_family(ID2STR_CF).unwrap(),
             spog_cf: db.column_family(SPOG_CF).unwrap(),
@@ -165,10 +225,8 @@ impl Storage {
             dosp_cf: db.column_family(DOSP_CF).unwrap(),
             graphs_cf: db.column_family(GRAPHS_CF).unwrap(),
             db,
-        };
-        #[cfg(not(target_arch = "wasm32"))]
-        this.migrate()?;
-        Ok(this)
+            unmerged_len_commits: Arc::new(AtomicUsize::new(0)),
+        }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -188,15 +246,24 @@ impl Storage {
                 .map(|g| encode_term(&g))
                 .collect::<Vec<_>>();
             graph_names.sort_unstable();
-            let mut stt_file = self.db.new_sst_file()?;
-            for k in graph_names {
-                stt_file.insert_empty(&k)?;
-            }
-            self.db
-                .insert_stt_files(&[(&self.graphs_cf, stt_file.finish()?)])?;
+            let mut insertion = self.db.bulk_insertion()?;
+            insertion.insert(
+                &self.graphs_cf,
+                &mut graph_names.into_iter().map(|k| (k, Vec::new())),
+            )?;
+            insertion.commit()?;
             version = 1;
             self.update_version(version)?;
         }
+        if version == 1 {
+            // We migrate to v2: the number of quads is stored
+            let len = self.snapshot().count_lens()?;
+            let mut insertion = self.db.bulk_insertion()?;
+            insertion.insert(&self.default_cf, &mut len.entries().into_iter())?;
+            insertion.commit()?;
+            version = 2;
+            self.update_version(version)?;
+        }
 
         match version {
             _ if version < LATEST_STORAGE_VERSION => Err(CorruptionError::msg(format!(
@@ -213,29 +280,35 @@ impl Storage {
 
     #[cfg(not(target_arch = "wasm32"))]
     fn ensure_version(&self) -> Result<u64, StorageError> {
-        Ok(
-            if let Some(version) = self.db.get(&self.default_cf, b"oxversion")? {
-                let mut buffer = [0; 8];
-                buffer.copy_from_slice(&version);
-                u64::from_be_bytes(buffer)
-            } else {
-                self.update_version(LATEST_STORAGE_VERSION)?;
-                LATEST_STORAGE_VERSION
-            },
-        )
+        Ok(if let Some(version) = self.read_version()? {
+            version
+        } else {
+            self.update_version(LATEST_STORAGE_VERSION)?;
+            LATEST_STORAGE_VERSION
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_version(&self) -> Result<Option<u64>, StorageError> {
+        Ok(self.db.get(&self.default_cf, b"oxversion")?.map(|version| {
+            let mut buffer = [0; 8];
+            buffer.copy_from_slice(&version);
+            u64::from_be_bytes(buffer)
+        }))
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     fn update_version(&self, version: u64) -> Result<(), StorageError> {
         self.db
             .insert(&self.default_cf, b"oxversion", &version.to_be_bytes())?;
-        self.db.flush(&self.default_cf)
+        self.db.flush()
     }
 
     pub fn snapshot(&self) -> StorageReader {
         StorageReader {
             reader: self.db.snapshot(),
             storage: self.clone(),
+            partition: None,
         }
     }
 
@@ -243,59 +316,340 @@ impl Storage {
         &'b self,
         f: impl Fn(StorageWriter<'a>) -> Result<T, E>,
     ) -> Result<T, E> {
-        self.db.transaction(|transaction| {
-            f(StorageWriter {
-                buffer: Vec::new(),
-                transaction,
-                storage: self,
-            })
+        let result = self
+            .db
+            .transaction(|transaction| f(StorageWriter::new(transaction, self)))?;
+        self.on_commit();
+        Ok(result)
+    }
+
+    pub fn start_transaction(&self) -> Result<StorageTransaction, StorageError> {
+        Ok(StorageTransaction {
+            transaction: self.db.start_transaction()?,
+            storage: self.clone(),
+        })
+    }
+
+    /// Merges from time to time the changes to the number of quads written by the committed writers.
+    fn on_commit(&self) {
+        if self.unmerged_len_commits.fetch_add(1, Ordering::Relaxed) + 1 >= LEN_MERGE_THRESHOLD
+            && self.merge_lens().is_ok()
+        {
+            // If the merge fails, it is tried again after the next commit
+            self.unmerged_len_commits.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Replaces the changes to the number of quads written by each writer by their sums.
+    fn merge_lens(&self) -> Result<(), StorageError> {
+        let merged_len_key = len_key(0);
+        self.db.transaction(|mut transaction| {
+            // Concurrent merges conflict on this key
+            transaction.contains_key_for_update(&self.default_cf, &merged_len_key)?;
+            let reader = transaction.reader()?;
+            let mut len = 0;
+            let mut removed = Vec::new();
+            let mut iter = reader.scan_prefix(&self.default_cf, LEN_KEY_PREFIX)?;
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                len += decode_len(value)?;
+                if key != merged_len_key {
+                    removed.push(key.to_vec());
+                }
+                iter.next();
+            }
+            iter.status()?;
+            if removed.is_empty() {
+                return Ok(());
+            }
+            transaction.insert(&self.default_cf, &merged_len_key, &len.to_be_bytes())?;
+
+            // We only read the graph changes and not the merged values
+            let mut graph_lens = HashMap::<Vec<u8>, i64>::new();
+            let mut iter = reader.scan_prefix(&self.default_cf, GRAPH_LEN_KEY_PREFIX)?;
+            iter.seek(&graph_len_key(1, &[]));
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                *graph_lens
+                    .entry(key[GRAPH_LEN_KEY_PREFIX.len() + 16..].to_vec())
+                    .or_default() += decode_len(value)?;
+                removed.push(key.to_vec());
+                iter.next();
+            }
+            iter.status()?;
+            for (graph_name, delta) in graph_lens {
+                let key = graph_len_key(0, &graph_name);
+                let len = if let Some(value) = reader.get(&self.default_cf, &key)? {
+                    decode_len(&value)?
+                } else {
+                    0
+                } + delta;
+                if len == 0 {
+                    transaction.remove(&self.default_cf, &key)?;
+                } else {
+                    transaction.insert(&self.default_cf, &key, &len.to_be_bytes())?;
+                }
+            }
+            for key in removed {
+                transaction.remove(&self.default_cf, &key)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Counts the stored quads and replaces the stored numbers of quads with the result.
+    ///
+    /// It is used after bulk loads that do not count the quads they write.
+    /// The changes committed after the counted snapshot are kept.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn recount_lens(&self) -> Result<(), StorageError> {
+        let merged_len_key = len_key(0);
+        self.db.transaction(|mut transaction| {
+            // Concurrent merges conflict on this key
+            transaction.contains_key_for_update(&self.default_cf, &merged_len_key)?;
+            let reader = transaction.reader()?;
+            let lens = StorageReader {
+                reader: reader.clone(),
+                storage: self.clone(),
+                partition: None,
+            }
+            .count_lens()?
+            .entries();
+            let written = lens
+                .iter()
+                .map(|(k, _)| k.as_slice())
+                .collect::<HashSet<_>>();
+            for key in scan_keys(&reader, &self.default_cf, LEN_KEY_PREFIX)?
+                .into_iter()
+                .chain(scan_keys(&reader, &self.default_cf, GRAPH_LEN_KEY_PREFIX)?)
+                .chain(scan_keys(&reader, &self.default_cf, UNCOUNTED_KEY_PREFIX)?)
+            {
+                if !written.contains(key.as_slice()) {
+                    transaction.remove(&self.default_cf, &key)?;
+                }
+            }
+            for (key, value) in &lens {
+                transaction.insert(&self.default_cf, key, value)?;
+            }
+            Ok(())
         })
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn flush(&self) -> Result<(), StorageError> {
-        self.db.flush(&self.default_cf)?;
-        self.db.flush(&self.gpos_cf)?;
-        self.db.flush(&self.gpos_cf)?;
-        self.db.flush(&self.gosp_cf)?;
-        self.db.flush(&self.spog_cf)?;
-        self.db.flush(&self.posg_cf)?;
-        self.db.flush(&self.ospg_cf)?;
-        self.db.flush(&self.dspo_cf)?;
-        self.db.flush(&self.dpos_cf)?;
-        self.db.flush(&self.dosp_cf)?;
-        self.db.flush(&self.id2str_cf)
+        self.db.flush()
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn compact(&self) -> Result<(), StorageError> {
-        self.db.compact(&self.default_cf)?;
-        self.db.compact(&self.gpos_cf)?;
-        self.db.compact(&self.gpos_cf)?;
-        self.db.compact(&self.gosp_cf)?;
-        self.db.compact(&self.spog_cf)?;
-        self.db.compact(&self.posg_cf)?;
-        self.db.compact(&self.ospg_cf)?;
-        self.db.compact(&self.dspo_cf)?;
-        self.db.compact(&self.dpos_cf)?;
-        self.db.compact(&self.dosp_cf)?;
-        self.db.compact(&self.id2str_cf)
+        self.db.compact()
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
         self.db.backup(target_directory)
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn incremental_backup(&self, backup_directory: &Path) -> Result<(), StorageError> {
+        self.db.incremental_backup(backup_directory)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn purge_old_backups(
+        backup_directory: &Path,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        Db::purge_old_backups(backup_directory, num_backups_to_keep)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restore_from_backup(
+        backup_directory: &Path,
+        target_directory: &Path,
+    ) -> Result<(), StorageError> {
+        Db::restore_from_backup(backup_directory, target_directory)
+    }
+}
+
+/// Statistics about the stored data computed by [`StorageReader::statistics`].
+pub struct StorageStatistics {
+    /// The number of quads of the default graph and of each named graph
+    pub graph_lens: Vec<(EncodedTerm, usize)>,
+    pub distinct_subjects: usize,
+    pub distinct_predicates: usize,
+    pub distinct_objects: usize,
+    /// The number of stored strings
+    pub dictionary_len: usize,
+    /// The size on disk of each column family if it is known
+    pub disk_sizes: Vec<(&'static str, Option<u64>)>,
 }
 
 pub struct StorageReader {
     reader: Reader,
     storage: Storage,
+    partition: Option<KeyPartition>,
 }
 
 impl StorageReader {
+    /// Returns a reader whose quad scans only return the `index`-th of `count` disjoint partitions of the quads.
+    ///
+    /// The union of the `count` partitions is the full set of quads.
+    /// It allows to scan large sets of quads from multiple threads.
+    pub fn partition(&self, index: usize, count: usize) -> Self {
+        debug_assert!(index < count && count <= 256);
+        Self {
+            reader: self.reader.clone(),
+            storage: self.storage.clone(),
+            partition: Some(KeyPartition {
+                start: (index * 256 / count) as u8,
+                end: if index + 1 < count {
+                    Some(((index + 1) * 256 / count) as u8)
+                } else {
+                    None
+                },
+            }),
+        }
+    }
+
     pub fn len(&self) -> Result<usize, StorageError> {
-        Ok(self.reader.len(&self.storage.gspo_cf)? + self.reader.len(&self.storage.dspo_cf)?)
+        let mut len = 0;
+        let mut iter = self
+            .reader
+            .scan_prefix(&self.storage.default_cf, LEN_KEY_PREFIX)?;
+        while let Some(value) = iter.value() {
+            len += decode_len(value)?;
+            iter.next();
+        }
+        iter.status()?;
+        to_len(len)
+    }
+
+    /// Returns the number of quads of the default graph and of each named graph.
+    pub fn graph_lens(&self) -> Result<Vec<(EncodedTerm, usize)>, StorageError> {
+        let mut deltas = HashMap::<EncodedTerm, i64>::new();
+        let mut iter = self
+            .reader
+            .scan_prefix(&self.storage.default_cf, GRAPH_LEN_KEY_PREFIX)?;
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            let graph_name = &key[GRAPH_LEN_KEY_PREFIX.len() + 16..];
+            let graph_name = if graph_name.is_empty() {
+                EncodedTerm::DefaultGraph
+            } else {
+                decode_term(graph_name)?
+            };
+            *deltas.entry(graph_name).or_default() += decode_len(value)?;
+            iter.next();
+        }
+        iter.status()?;
+        let mut graph_lens = vec![(
+            EncodedTerm::DefaultGraph,
+            to_len(deltas.remove(&EncodedTerm::DefaultGraph).unwrap_or(0))?,
+        )];
+        for graph_name in self.named_graphs() {
+            let graph_name = graph_name?;
+            let len = to_len(deltas.remove(&graph_name).unwrap_or(0))?;
+            graph_lens.push((graph_name, len));
+        }
+        for (graph_name, len) in deltas {
+            // Graphs that have been removed while their changes are not merged yet
+            if len != 0 {
+                graph_lens.push((graph_name, to_len(len)?));
+            }
+        }
+        Ok(graph_lens)
+    }
+
+    /// Returns if bulk loads have written quads without counting them since the last [`Storage::recount_lens`].
+    #[cfg(not(target_arch = "wasm32"))]
+    fn has_uncounted_quads(&self) -> Result<bool, StorageError> {
+        let iter = self
+            .reader
+            .scan_prefix(&self.storage.default_cf, UNCOUNTED_KEY_PREFIX)?;
+        let found = iter.key().is_some();
+        iter.status()?;
+        Ok(found)
+    }
+
+    /// Counts the quads of the default graph and of each named graph by scanning the indexes.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn count_lens(&self) -> Result<LenDelta, StorageError> {
+        let mut len = LenDelta::merged();
+        len.add(
+            &EncodedTerm::DefaultGraph,
+            self.reader.len(&self.storage.dspo_cf)?.try_into().unwrap(),
+        );
+        for quad in self.gspo_quads(&[]) {
+            len.add(&quad?.graph_name, 1);
+        }
+        Ok(len)
+    }
+
+    /// Computes statistics about the stored quads.
+    ///
+    /// Only the number of quads is stored, the other numbers are computed by scanning the indexes.
+    pub fn statistics(&self) -> Result<StorageStatistics, StorageError> {
+        Ok(StorageStatistics {
+            graph_lens: self.graph_lens()?,
+            distinct_subjects: self
+                .count_distinct_key_starts([&self.storage.dspo_cf, &self.storage.spog_cf])?,
+            distinct_predicates: self
+                .count_distinct_key_starts([&self.storage.dpos_cf, &self.storage.posg_cf])?,
+            distinct_objects: self
+                .count_distinct_key_starts([&self.storage.dosp_cf, &self.storage.ospg_cf])?,
+            dictionary_len: self.reader.len(&self.storage.id2str_cf)?,
+            disk_sizes: COLUMN_FAMILIES
+                .iter()
+                .map(|cf| {
+                    let size = self
+                        .storage
+                        .db
+                        .disk_size(&self.storage.db.column_family(cf.name).unwrap())?;
+                    Ok((cf.name, size))
+                })
+                .collect::<Result<_, StorageError>>()?,
+        })
+    }
+
+    /// Counts the distinct terms the keys of the column families start with.
+    ///
+    /// The keys starting with the same term are skipped using seeks.
+    fn count_distinct_key_starts(
+        &self,
+        column_families: [&ColumnFamily; 2],
+    ) -> Result<usize, StorageError> {
+        let mut iters = column_families
+            .iter()
+            .map(|cf| self.reader.iter(cf))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut count = 0;
+        loop {
+            let mut smallest: Option<Vec<u8>> = None;
+            for iter in &iters {
+                iter.status()?;
+                if let Some(key) = iter.key() {
+                    let start = encode_term(&decode_term(key)?);
+                    if smallest.as_ref().map_or(true, |s| start < *s) {
+                        smallest = Some(start);
+                    }
+                }
+            }
+            let smallest = if let Some(smallest) = smallest {
+                smallest
+            } else {
+                return Ok(count);
+            };
+            count += 1;
+            let next = if let Some(next) = prefix_successor(&smallest) {
+                next
+            } else {
+                // All the remaining keys start with the same term
+                return Ok(count);
+            };
+            for iter in &mut iters {
+                if iter.key().map_or(false, |key| key.starts_with(&smallest)) {
+                    iter.seek(&next);
+                }
+            }
+        }
     }
 
     pub fn is_empty(&self) -> Result<bool, StorageError> {
@@ -604,43 +958,26 @@ impl StorageReader {
         DecodingQuadIterator {
             iter: self.reader.scan_prefix(column_family, prefix).unwrap(), // TODO: propagate error?
             encoding,
+            partition: self.partition.map(|partition| (prefix.len(), partition)),
         }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
     pub fn get_str(&self, key: &StrHash) -> Result<Option<String>, StorageError> {
         Ok(self
             .storage
             .db
             .get(&self.storage.id2str_cf, &key.to_be_bytes())?
-            .map(|v| String::from_utf8(v.into()))
+            .map(|value| str::from_utf8(&value).map(str::to_owned))
             .transpose()
             .map_err(CorruptionError::new)?)
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn get_str(&self, key: &StrHash) -> Result<Option<String>, StorageError> {
-        Ok(self
-            .reader
-            .get(&self.storage.id2str_cf, &key.to_be_bytes())?
-            .map(|v| String::from_utf8(v.into()))
-            .transpose()
-            .map_err(CorruptionError::new)?)
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
     pub fn contains_str(&self, key: &StrHash) -> Result<bool, StorageError> {
         self.storage
             .db
             .contains_key(&self.storage.id2str_cf, &key.to_be_bytes())
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn contains_str(&self, key: &StrHash) -> Result<bool, StorageError> {
-        self.reader
-            .contains_key(&self.storage.id2str_cf, &key.to_be_bytes())
-    }
-
     /// Validates that all the storage invariants held in the data
     #[cfg(not(target_arch = "wasm32"))]
     pub fn validate(&self) -> Result<(), StorageError> {
@@ -750,6 +1087,21 @@ impl StorageReader {
                 );
             }
         }
+
+        // number of quads
+        if self.len()? != dspo_size + self.gspo_quads(&[]).count() {
+            return Err(
+                CorruptionError::new("The stored number of quads is not the right one").into(),
+            );
+        }
+        for (graph_name, len) in self.graph_lens()? {
+            if self.quads_for_graph(&graph_name).count() != len {
+                return Err(CorruptionError::new(
+                    "The stored number of quads of a graph is not the right one",
+                )
+                .into());
+            }
+        }
         Ok(())
     }
 }
@@ -792,6 +1144,8 @@ impl Iterator for ChainedDecodingQuadIterator {
 pub struct DecodingQuadIterator {
     iter: Iter,
     encoding: QuadEncoding,
+    /// The scan prefix length and the partition of the keys to return
+    partition: Option<(usize, KeyPartition)>,
 }
 
 impl Iterator for DecodingQuadIterator {
@@ -801,12 +1155,85 @@ impl Iterator for DecodingQuadIterator {
         if let Err(e) = self.iter.status() {
             return Some(Err(e));
         }
+        if let Some((prefix_len, partition)) = self.partition {
+            loop {
+                match partition.position(self.iter.key()?, prefix_len) {
+                    PartitionPosition::Inside => break,
+                    PartitionPosition::SeekTo(key) => self.iter.seek(&key),
+                    PartitionPosition::End => return None,
+                }
+            }
+        }
         let term = self.encoding.decode(self.iter.key()?);
         self.iter.next();
         Some(term)
     }
 }
 
+/// A partition of the keys of a scan based on the first byte of the encoding of the first term after the scan prefix.
+///
+/// Most of the terms are encoded using hashes so this byte is evenly distributed.
+/// Keys too short to contain this byte belong to the partition starting at `0`.
+#[derive(Clone, Copy)]
+struct KeyPartition {
+    start: u8,
+    /// `None` if the partition goes up to the last byte value
+    end: Option<u8>,
+}
+
+enum PartitionPosition {
+    Inside,
+    /// The next key of the partition is greater or equal to this key
+    SeekTo(Vec<u8>),
+    /// There is no key of the partition left
+    End,
+}
+
+impl KeyPartition {
+    fn position(self, key: &[u8], prefix_len: usize) -> PartitionPosition {
+        let term_type = if let Some(term_type) = key.get(prefix_len) {
+            *term_type
+        } else {
+            return if self.start == 0 {
+                PartitionPosition::Inside
+            } else {
+                let mut next = key.to_vec();
+                next.push(0);
+                PartitionPosition::SeekTo(next)
+            };
+        };
+        let byte = if let Some(byte) = key.get(prefix_len + 1) {
+            *byte
+        } else {
+            return if self.start == 0 {
+                PartitionPosition::Inside
+            } else {
+                let mut next = key[..=prefix_len].to_vec();
+                next.push(self.start);
+                PartitionPosition::SeekTo(next)
+            };
+        };
+        if byte < self.start {
+            let mut next = key[..=prefix_len].to_vec();
+            next.push(self.start);
+            PartitionPosition::SeekTo(next)
+        } else if self.end.map_or(false, |end| byte >= end) {
+            // We jump to the next term type
+            if term_type == u8::MAX {
+                return PartitionPosition::End;
+            }
+            let mut next = key[..prefix_len].to_vec();
+            next.push(term_type + 1);
+            if self.start > 0 {
+                next.push(self.start);
+            }
+            PartitionPosition::SeekTo(next)
+        } else {
+            PartitionPosition::Inside
+        }
+    }
+}
+
 pub struct DecodingGraphIterator {
     iter: Iter,
 }
@@ -834,20 +1261,180 @@ impl StrLookup for StorageReader {
     }
 }
 
+/// Changes to the number of quads done by a writer.
+///
+/// They are stored in the default column family under keys containing the writer id
+/// and merged from time to time into the entries of the writer `0` by [`Storage::merge_lens`].
+struct LenDelta {
+    writer_id: u128,
+    len: i64,
+    graph_lens: HashMap<EncodedTerm, i64>,
+}
+
+impl LenDelta {
+    fn new() -> Self {
+        Self {
+            writer_id: random::<u128>() | 1,
+            len: 0,
+            graph_lens: HashMap::new(),
+        }
+    }
+
+    /// Builds the merged values directly, the stored ones are overridden
+    #[cfg(not(target_arch = "wasm32"))]
+    fn merged() -> Self {
+        Self {
+            writer_id: 0,
+            len: 0,
+            graph_lens: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, graph_name: &EncodedTerm, delta: i64) {
+        self.len += delta;
+        *self.graph_lens.entry(graph_name.clone()).or_default() += delta;
+    }
+
+    /// The entries to write for a change in the given graph
+    fn entries_for(&self, graph_name: &EncodedTerm) -> [(Vec<u8>, Vec<u8>); 2] {
+        [
+            (len_key(self.writer_id), self.len.to_be_bytes().to_vec()),
+            (
+                graph_len_key(self.writer_id, &encode_term(graph_name)),
+                self.graph_lens
+                    .get(graph_name)
+                    .copied()
+                    .unwrap_or(0)
+                    .to_be_bytes()
+                    .to_vec(),
+            ),
+        ]
+    }
+
+    /// All the entries to write, sorted by key
+    #[cfg(not(target_arch = "wasm32"))]
+    fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        if self.graph_lens.is_empty() {
+            return Vec::new();
+        }
+        let mut entries = self
+            .graph_lens
+            .iter()
+            .map(|(graph_name, len)| {
+                (
+                    graph_len_key(self.writer_id, &encode_term(graph_name)),
+                    len.to_be_bytes().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        entries.push((len_key(self.writer_id), self.len.to_be_bytes().to_vec()));
+        entries.sort_unstable();
+        entries
+    }
+}
+
+fn len_key(writer_id: u128) -> Vec<u8> {
+    [LEN_KEY_PREFIX, &writer_id.to_be_bytes()].concat()
+}
+
+/// The key for the number of quads of a graph, `encoded_graph_name` is empty for the default graph
+fn graph_len_key(writer_id: u128, encoded_graph_name: &[u8]) -> Vec<u8> {
+    [
+        GRAPH_LEN_KEY_PREFIX,
+        &writer_id.to_be_bytes(),
+        encoded_graph_name,
+    ]
+    .concat()
+}
+
+/// Returns the keys of the `column_family` entries starting with `prefix`
+#[cfg(not(target_arch = "wasm32"))]
+fn scan_keys(
+    reader: &Reader,
+    column_family: &ColumnFamily,
+    prefix: &[u8],
+) -> Result<Vec<Vec<u8>>, StorageError> {
+    let mut keys = Vec::new();
+    let mut iter = reader.scan_prefix(column_family, prefix)?;
+    while let Some(key) = iter.key() {
+        keys.push(key.to_vec());
+        iter.next();
+    }
+    iter.status()?;
+    Ok(keys)
+}
+
+fn decode_len(bytes: &[u8]) -> Result<i64, StorageError> {
+    Ok(i64::from_be_bytes(bytes.try_into().map_err(|_| {
+        CorruptionError::msg("Invalid stored number of quads")
+    })?))
+}
+
+fn to_len(len: i64) -> Result<usize, StorageError> {
+    len.try_into()
+        .map_err(|_| CorruptionError::msg(format!("Invalid number of quads: {}", len)).into())
+}
+
+/// The smallest key greater than all the keys starting with `prefix` or `None` if there is none
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+    while let Some(last) = successor.pop() {
+        if last < u8::MAX {
+            successor.push(last + 1);
+            return Some(successor);
+        }
+    }
+    None
+}
+
+/// A transaction that is not bound to a closure. It is rolled back if dropped without being committed.
+pub struct StorageTransaction {
+    transaction: Transaction,
+    storage: Storage,
+}
+
+impl StorageTransaction {
+    pub fn writer(&self) -> StorageWriter<'_> {
+        StorageWriter::new(self.transaction.clone(), &self.storage)
+    }
+
+    pub fn commit(self) -> Result<(), StorageError> {
+        self.transaction.commit()?;
+        self.storage.on_commit();
+        Ok(())
+    }
+
+    pub fn rollback(self) -> Result<(), StorageError> {
+        self.transaction.rollback()
+    }
+}
+
 pub struct StorageWriter<'a> {
     buffer: Vec<u8>,
-    transaction: Transaction<'a>,
+    transaction: Transaction,
     storage: &'a Storage,
+    /// Changes to the number of quads done by this writer
+    len_delta: LenDelta,
 }
 
 impl<'a> StorageWriter<'a> {
-    pub fn reader(&self) -> StorageReader {
-        StorageReader {
-            reader: self.transaction.reader(),
-            storage: self.storage.clone(),
+    fn new(transaction: Transaction, storage: &'a Storage) -> Self {
+        Self {
+            buffer: Vec::new(),
+            transaction,
+            storage,
+            len_delta: LenDelta::new(),
         }
     }
 
+    pub fn reader(&self) -> Result<StorageReader, StorageError> {
+        Ok(StorageReader {
+            reader: self.transaction.reader()?,
+            storage: self.storage.clone(),
+            partition: None,
+        })
+    }
+
     pub fn insert(&mut self, quad: QuadRef<'_>) -> Result<bool, StorageError> {
         let encoded = quad.into();
         self.buffer.clear();
@@ -875,6 +1462,7 @@ impl<'a> StorageWriter<'a> {
                 self.insert_term(quad.subject.into(), &encoded.subject)?;
                 self.insert_term(quad.predicate.into(), &encoded.predicate)?;
                 self.insert_term(quad.object, &encoded.object)?;
+                self.add_len(&encoded.graph_name, 1)?;
                 true
             }
         } else {
@@ -927,6 +1515,7 @@ impl<'a> StorageWriter<'a> {
                         .insert_empty(&self.storage.graphs_cf, &self.buffer)?;
                     self.insert_graph_name(quad.graph_name, &encoded.graph_name)?;
                 }
+                self.add_len(&encoded.graph_name, 1)?;
                 true
             }
         };
@@ -975,7 +1564,6 @@ impl<'a> StorageWriter<'a> {
         }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
     fn insert_str(&mut self, key: &StrHash, value: &str) -> Result<(), StorageError> {
         if self
             .storage
@@ -991,15 +1579,6 @@ impl<'a> StorageWriter<'a> {
         )
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn insert_str(&mut self, key: &StrHash, value: &str) -> Result<(), StorageError> {
-        self.transaction.insert(
-            &self.storage.id2str_cf,
-            &key.to_be_bytes(),
-            value.as_bytes(),
-        )
-    }
-
     pub fn remove(&mut self, quad: QuadRef<'_>) -> Result<bool, StorageError> {
         self.remove_encoded(&quad.into())
     }
@@ -1025,6 +1604,7 @@ impl<'a> StorageWriter<'a> {
                 write_osp_quad(&mut self.buffer, quad);
                 self.transaction
                     .remove(&self.storage.dosp_cf, &self.buffer)?;
+                self.add_len(&quad.graph_name, -1)?;
                 true
             } else {
                 false
@@ -1063,6 +1643,7 @@ impl<'a> StorageWriter<'a> {
                 write_gosp_quad(&mut self.buffer, quad);
                 self.transaction
                     .remove(&self.storage.gosp_cf, &self.buffer)?;
+                self.add_len(&quad.graph_name, -1)?;
                 true
             } else {
                 false
@@ -1071,9 +1652,21 @@ impl<'a> StorageWriter<'a> {
         Ok(result)
     }
 
+    /// Writes the changes of the number of quads with the ones of this writer.
+    ///
+    /// Each writer uses its own keys so that concurrent writers do not conflict.
+    fn add_len(&mut self, graph_name: &EncodedTerm, delta: i64) -> Result<(), StorageError> {
+        self.len_delta.add(graph_name, delta);
+        for (key, value) in self.len_delta.entries_for(graph_name) {
+            self.transaction
+                .insert(&self.storage.default_cf, &key, &value)?;
+        }
+        Ok(())
+    }
+
     pub fn clear_graph(&mut self, graph_name: GraphNameRef<'_>) -> Result<(), StorageError> {
         if graph_name.is_default_graph() {
-            for quad in self.reader().quads_for_graph(&EncodedTerm::DefaultGraph) {
+            for quad in self.reader()?.quads_for_graph(&EncodedTerm::DefaultGraph) {
                 self.remove_encoded(&quad?)?;
             }
         } else {
@@ -1084,7 +1677,7 @@ impl<'a> StorageWriter<'a> {
                 .contains_key_for_update(&self.storage.graphs_cf, &self.buffer)?
             {
                 // The condition is useful to lock the graph itself and ensure no quad is inserted at the same time
-                for quad in self.reader().quads_for_graph(&graph_name.into()) {
+                for quad in self.reader()?.quads_for_graph(&graph_name.into()) {
                     self.remove_encoded(&quad?)?;
                 }
             }
@@ -1093,14 +1686,14 @@ impl<'a> StorageWriter<'a> {
     }
 
     pub fn clear_all_named_graphs(&mut self) -> Result<(), StorageError> {
-        for quad in self.reader().quads_in_named_graph() {
+        for quad in self.reader()?.quads_in_named_graph() {
             self.remove_encoded(&quad?)?;
         }
         Ok(())
     }
 
     pub fn clear_all_graphs(&mut self) -> Result<(), StorageError> {
-        for quad in self.reader().quads() {
+        for quad in self.reader()?.quads() {
             self.remove_encoded(&quad?)?;
         }
         Ok(())
@@ -1124,7 +1717,7 @@ impl<'a> StorageWriter<'a> {
             .contains_key_for_update(&self.storage.graphs_cf, &self.buffer)?
         {
             // The condition is done ASAP to lock the graph itself
-            for quad in self.reader().quads_for_graph(graph_name) {
+            for quad in self.reader()?.quads_for_graph(graph_name) {
                 self.remove_encoded(&quad?)?;
             }
             self.buffer.clear();
@@ -1139,17 +1732,17 @@ impl<'a> StorageWriter<'a> {
     }
 
     pub fn remove_all_named_graphs(&mut self) -> Result<(), StorageError> {
-        for graph_name in self.reader().named_graphs() {
+        for graph_name in self.reader()?.named_graphs() {
             self.remove_encoded_named_graph(&graph_name?)?;
         }
         Ok(())
     }
 
     pub fn clear(&mut self) -> Result<(), StorageError> {
-        for graph_name in self.reader().named_graphs() {
+        for graph_name in self.reader()?.named_graphs() {
             self.remove_encoded_named_graph(&graph_name?)?;
         }
-        for quad in self.reader().quads() {
+        for quad in self.reader()?.quads() {
             self.remove_encoded(&quad?)?;
         }
         Ok(())
@@ -1162,6 +1755,8 @@ pub struct StorageBulkLoader {
     hooks: Vec<Box<dyn Fn(u64)>>,
     num_threads: Option<usize>,
     max_memory_size: Option<usize>,
+    checkpoint_id: Option<String>,
+    resume: bool,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -1172,6 +1767,8 @@ impl StorageBulkLoader {
             hooks: Vec::new(),
             num_threads: None,
             max_memory_size: None,
+            checkpoint_id: None,
+            resume: false,
         }
     }
 
@@ -1190,25 +1787,153 @@ impl StorageBulkLoader {
         self
     }
 
+    /// Records the committed parts of the loaded input under `id` and, if `resume` is set, skips the ones recorded by a previous load.
+    pub fn set_checkpoint(mut self, id: String, resume: bool) -> Self {
+        self.checkpoint_id = Some(id);
+        self.resume = resume;
+        self
+    }
+
+    pub fn checkpoint_id(&self) -> Option<&str> {
+        self.checkpoint_id.as_deref()
+    }
+
     pub fn load<EI, EO: From<StorageError> + From<EI>, I: IntoIterator<Item = Result<Quad, EI>>>(
         &self,
         quads: I,
     ) -> Result<(), EO> {
-        let system = System::new_all();
-        let cpu_count = min(8, system.physical_core_count().unwrap_or(2));
-        let num_threads = max(
-            if let Some(num_threads) = self.num_threads {
-                num_threads
-            } else if let Some(max_memory_size) = self.max_memory_size {
-                min(
-                    cpu_count,
-                    max_memory_size * 1000 / DEFAULT_BULK_LOAD_BATCH_SIZE,
-                )
+        let checkpoint_id = if let Some(checkpoint_id) = &self.checkpoint_id {
+            checkpoint_id
+        } else {
+            return self.run(quads, FileBulkLoader::load, None);
+        };
+        let checkpoint = Checkpoint::read(&self.storage, checkpoint_id)?;
+        if !self.resume {
+            checkpoint.clear(&self.storage)?;
+        }
+        let committed = if self.resume {
+            checkpoint.committed()?
+        } else {
+            Vec::new()
+        };
+        self.run(
+            quads,
+            FileBulkLoader::load,
+            Some((&checkpoint, committed.as_slice())),
+        )?;
+        // The load is done, we do not need the checkpoint anymore
+        Checkpoint::read(&self.storage, checkpoint_id)?.clear(&self.storage)?;
+        Ok(())
+    }
+
+    pub fn remove<
+        EI,
+        EO: From<StorageError> + From<EI>,
+        I: IntoIterator<Item = Result<EncodedQuad, EI>>,
+    >(
+        &self,
+        quads: I,
+    ) -> Result<(), EO> {
+        self.run(quads, FileBulkLoader::remove, None)
+    }
+
+    pub fn remove_quads_for_pattern(
+        &self,
+        subject: Option<&EncodedTerm>,
+        predicate: Option<&EncodedTerm>,
+        object: Option<&EncodedTerm>,
+        graph_name: Option<&EncodedTerm>,
+    ) -> Result<(), StorageError> {
+        self.run(
+            self.storage
+                .snapshot()
+                .quads_for_pattern(subject, predicate, object, graph_name),
+            FileBulkLoader::remove,
+            None,
+        )
+    }
+
+    /// Replaces the content of a graph by the given quads in a single write.
+    ///
+    /// All the quads must be in the graph `graph_name`.
+    /// They are all kept in memory with the removed ones: it fails if they do not fit in the memory limit.
+    pub fn replace_graph<EI, EO: From<StorageError> + From<EI>>(
+        &self,
+        graph_name: GraphNameRef<'_>,
+        quads: impl IntoIterator<Item = Result<Quad, EI>>,
+    ) -> Result<(), EO> {
+        // The same number of quads per megabyte as the batches of the other operations
+        let max_size = self
+            .max_memory_size
+            .map(|max_memory_size| max(1000, max_memory_size * 1000));
+        let check_size = |size: usize| {
+            if max_size.map_or(false, |max_size| size > max_size) {
+                Err(StorageError::Other(
+                    format!(
+                        "The graph replacement does not fit in the memory limit of {}MB",
+                        self.max_memory_size.unwrap_or_default()
+                    )
+                    .into(),
+                ))
             } else {
-                cpu_count
-            },
-            2,
-        );
+                Ok(())
+            }
+        };
+        let mut loader = FileBulkLoader::new(self.storage.clone());
+        for quad in quads {
+            loader.encode([quad?])?;
+            check_size(loader.triples.len() + loader.quads.len())?;
+        }
+        let encoded_graph_name = EncodedTerm::from(graph_name);
+        let (new_quads, removed_quads) = if let GraphNameRef::DefaultGraph = graph_name {
+            (&loader.triples, &mut loader.removed_triples)
+        } else {
+            loader.encode_graph_name(graph_name, &encoded_graph_name)?;
+            (&loader.quads, &mut loader.removed_quads)
+        };
+        for quad in self.storage.snapshot().quads_for_graph(&encoded_graph_name) {
+            let quad = quad?;
+            if !new_quads.contains(&quad) {
+                removed_quads.insert(quad);
+                check_size(new_quads.len() + removed_quads.len())?;
+            }
+        }
+        let size = loader.triples.len() + loader.quads.len();
+        loader.save()?;
+        self.storage.recount_lens()?;
+        for hook in &self.hooks {
+            hook(size.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    /// Splits the items in batches processed in parallel using `operation`.
+    ///
+    /// If a checkpoint is given, the items in the already committed ranges are skipped
+    /// and the range of each batch is recorded with it.
+    ///
+    /// The batches do not count the quads they write so the quads are counted once all batches are committed,
+    /// even if one of them has failed.
+    fn run<T: Send + 'static, EI, EO: From<StorageError> + From<EI>>(
+        &self,
+        items: impl IntoIterator<Item = Result<T, EI>>,
+        operation: fn(&mut FileBulkLoader, Vec<T>, &AtomicU64) -> Result<(), StorageError>,
+        checkpoint: Option<(&Checkpoint, &[Range<u64>])>,
+    ) -> Result<(), EO> {
+        let result = self.run_batches(items, operation, checkpoint);
+        let recount = self.storage.recount_lens();
+        result?;
+        Ok(recount?)
+    }
+
+    fn run_batches<T: Send + 'static, EI, EO: From<StorageError> + From<EI>>(
+        &self,
+        items: impl IntoIterator<Item = Result<T, EI>>,
+        operation: fn(&mut FileBulkLoader, Vec<T>, &AtomicU64) -> Result<(), StorageError>,
+        checkpoint: Option<(&Checkpoint, &[Range<u64>])>,
+    ) -> Result<(), EO> {
+        let system = System::new_all();
+        let num_threads = self.compute_num_threads(&system);
         let batch_size = min(
             if let Some(max_memory_size) = self.max_memory_size {
                 max(1000, max_memory_size * 1000 / num_threads)
@@ -1224,21 +1949,53 @@ impl StorageBulkLoader {
         let mut buffer = Vec::with_capacity(batch_size);
         let done_counter = Arc::new(AtomicU64::new(0));
         let mut done_and_displayed_counter = 0;
-        for quad in quads {
-            let quad = quad?;
-            buffer.push(quad);
+        let mut committed = checkpoint
+            .map_or(&[][..], |(_, committed)| committed)
+            .iter()
+            .peekable();
+        let checkpoint = checkpoint.map(|(checkpoint, _)| checkpoint);
+        let mut position = 0;
+        let mut batch_start = 0;
+        for item in items {
+            let item = match item {
+                Ok(item) => item,
+                Err(e) => {
+                    // We wait for the running batches to be committed before returning
+                    for thread in threads {
+                        thread.join().unwrap()?;
+                    }
+                    return Err(e.into());
+                }
+            };
+            let index = position;
+            position += 1;
+            while committed.peek().map_or(false, |range| range.end <= index) {
+                committed.next();
+            }
+            if committed
+                .peek()
+                .map_or(false, |range| range.contains(&index))
+            {
+                continue; // Already loaded
+            }
+            buffer.push(item);
             if buffer.len() >= batch_size {
                 self.spawn_load_thread(
                     &mut buffer,
+                    operation,
+                    checkpoint.map(|c| c.entry(batch_start..position)),
                     &mut threads,
                     &done_counter,
                     &mut done_and_displayed_counter,
                     num_threads,
                 )?;
+                batch_start = position;
             }
         }
         self.spawn_load_thread(
             &mut buffer,
+            operation,
+            checkpoint.map(|c| c.entry(batch_start..position)),
             &mut threads,
             &done_counter,
             &mut done_and_displayed_counter,
@@ -1251,9 +2008,33 @@ impl StorageBulkLoader {
         Ok(())
     }
 
-    fn spawn_load_thread(
+    /// Number of threads to be used to parse the input.
+    pub fn num_parsing_threads(&self) -> usize {
+        self.compute_num_threads(&System::new())
+    }
+
+    fn compute_num_threads(&self, system: &System) -> usize {
+        let cpu_count = min(8, system.physical_core_count().unwrap_or(2));
+        max(
+            if let Some(num_threads) = self.num_threads {
+                num_threads
+            } else if let Some(max_memory_size) = self.max_memory_size {
+                min(
+                    cpu_count,
+                    max_memory_size * 1000 / DEFAULT_BULK_LOAD_BATCH_SIZE,
+                )
+            } else {
+                cpu_count
+            },
+            2,
+        )
+    }
+
+    fn spawn_load_thread<T: Send + 'static>(
         &self,
-        buffer: &mut Vec<Quad>,
+        buffer: &mut Vec<T>,
+        operation: fn(&mut FileBulkLoader, Vec<T>, &AtomicU64) -> Result<(), StorageError>,
+        checkpoint_entry: Option<(Vec<u8>, Vec<u8>)>,
         threads: &mut VecDeque<JoinHandle<Result<(), StorageError>>>,
         done_counter: &Arc<AtomicU64>,
         done_and_displayed_counter: &mut u64,
@@ -1271,7 +2052,9 @@ impl StorageBulkLoader {
         let storage = self.storage.clone();
         let done_counter_clone = done_counter.clone();
         threads.push_back(spawn(move || {
-            FileBulkLoader::new(storage).load(buffer, &done_counter_clone)
+            let mut loader = FileBulkLoader::new(storage);
+            loader.checkpoint_entry = checkpoint_entry;
+            operation(&mut loader, buffer, &done_counter_clone)
         }));
         self.on_possible_progress(done_counter, done_and_displayed_counter);
         Ok(())
@@ -1296,6 +2079,10 @@ struct FileBulkLoader {
     quads: HashSet<EncodedQuad>,
     triples: HashSet<EncodedQuad>,
     graphs: HashSet<EncodedTerm>,
+    removed_quads: HashSet<EncodedQuad>,
+    removed_triples: HashSet<EncodedQuad>,
+    /// Entry to write with the data in order to record that it has been committed.
+    checkpoint_entry: Option<(Vec<u8>, Vec<u8>)>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -1307,6 +2094,9 @@ impl FileBulkLoader {
             quads: HashSet::default(),
             triples: HashSet::default(),
             graphs: HashSet::default(),
+            removed_quads: HashSet::default(),
+            removed_triples: HashSet::default(),
+            checkpoint_entry: None,
         }
     }
 
@@ -1322,165 +2112,128 @@ impl FileBulkLoader {
         Ok(())
     }
 
+    fn remove(
+        &mut self,
+        quads: impl IntoIterator<Item = EncodedQuad>,
+        counter: &AtomicU64,
+    ) -> Result<(), StorageError> {
+        for quad in quads {
+            if quad.graph_name.is_default_graph() {
+                self.removed_triples.insert(quad);
+            } else {
+                self.removed_quads.insert(quad);
+            }
+        }
+        let size = self.removed_triples.len() + self.removed_quads.len();
+        self.save()?;
+        counter.fetch_add(size.try_into().unwrap(), Ordering::Relaxed);
+        Ok(())
+    }
+
     fn encode(&mut self, quads: impl IntoIterator<Item = Quad>) -> Result<(), StorageError> {
-        let mut buffer = Vec::new();
         for quad in quads {
             let encoded = EncodedQuad::from(quad.as_ref());
-            buffer.clear();
             if quad.graph_name.is_default_graph() {
-                write_spo_quad(&mut buffer, &encoded);
                 if self.triples.insert(encoded.clone()) {
                     self.insert_term(quad.subject.as_ref().into(), &encoded.subject)?;
                     self.insert_term(quad.predicate.as_ref().into(), &encoded.predicate)?;
                     self.insert_term(quad.object.as_ref(), &encoded.object)?;
                 }
-            } else {
-                write_spog_quad(&mut buffer, &encoded);
-                if self.quads.insert(encoded.clone()) {
-                    self.insert_term(quad.subject.as_ref().into(), &encoded.subject)?;
-                    self.insert_term(quad.predicate.as_ref().into(), &encoded.predicate)?;
-                    self.insert_term(quad.object.as_ref(), &encoded.object)?;
-
-                    buffer.clear();
-                    write_term(&mut buffer, &encoded.graph_name);
-                    if self.graphs.insert(encoded.graph_name.clone()) {
-                        self.insert_term(
-                            match quad.graph_name.as_ref() {
-                                GraphNameRef::NamedNode(n) => n.into(),
-                                GraphNameRef::BlankNode(n) => n.into(),
-                                GraphNameRef::DefaultGraph => unreachable!(),
-                            },
-                            &encoded.graph_name,
-                        )?;
-                    }
-                }
+            } else if self.quads.insert(encoded.clone()) {
+                self.insert_term(quad.subject.as_ref().into(), &encoded.subject)?;
+                self.insert_term(quad.predicate.as_ref().into(), &encoded.predicate)?;
+                self.insert_term(quad.object.as_ref(), &encoded.object)?;
+                self.encode_graph_name(quad.graph_name.as_ref(), &encoded.graph_name)?;
             }
         }
         Ok(())
     }
 
+    fn encode_graph_name(
+        &mut self,
+        graph_name: GraphNameRef<'_>,
+        encoded: &EncodedTerm,
+    ) -> Result<(), StorageError> {
+        if self.graphs.insert(encoded.clone()) {
+            self.insert_term(
+                match graph_name {
+                    GraphNameRef::NamedNode(n) => n.into(),
+                    GraphNameRef::BlankNode(n) => n.into(),
+                    GraphNameRef::DefaultGraph => unreachable!(),
+                },
+                encoded,
+            )?;
+        }
+        Ok(())
+    }
+
     fn save(&mut self) -> Result<(), StorageError> {
-        let mut to_load = Vec::new();
+        let mut insertion = self.storage.db.bulk_insertion()?;
 
         // id2str
         if !self.id2str.is_empty() {
             let mut id2str = take(&mut self.id2str)
                 .into_iter()
-                .map(|(k, v)| (k.to_be_bytes(), v))
+                .map(|(k, v)| (k.to_be_bytes().to_vec(), v.into_boxed_bytes().into_vec()))
                 .collect::<Vec<_>>();
             id2str.sort_unstable();
-            let mut id2str_sst = self.storage.db.new_sst_file()?;
-            for (k, v) in id2str {
-                id2str_sst.insert(&k, v.as_bytes())?;
-            }
-            to_load.push((&self.storage.id2str_cf, id2str_sst.finish()?));
-        }
-
-        if !self.triples.is_empty() {
-            to_load.push((
-                &self.storage.dspo_cf,
-                self.build_sst_for_keys(
-                    self.triples.iter().map(|quad| {
-                        encode_term_triple(&quad.subject, &quad.predicate, &quad.object)
-                    }),
-                )?,
-            ));
-            to_load.push((
-                &self.storage.dpos_cf,
-                self.build_sst_for_keys(
-                    self.triples.iter().map(|quad| {
-                        encode_term_triple(&quad.predicate, &quad.object, &quad.subject)
-                    }),
-                )?,
-            ));
-            to_load.push((
-                &self.storage.dosp_cf,
-                self.build_sst_for_keys(
-                    self.triples.iter().map(|quad| {
-                        encode_term_triple(&quad.object, &quad.subject, &quad.predicate)
-                    }),
-                )?,
-            ));
-            self.triples.clear();
-        }
-
-        if !self.quads.is_empty() {
-            let quads = take(&mut self.graphs);
-            to_load.push((
+            insertion.insert(&self.storage.id2str_cf, &mut id2str.into_iter())?;
+        }
+
+        if !self.graphs.is_empty() {
+            insert_keys(
+                insertion.as_mut(),
                 &self.storage.graphs_cf,
-                self.build_sst_for_keys(quads.into_iter().map(|g| encode_term(&g)))?,
-            ));
-
-            to_load.push((
-                &self.storage.gspo_cf,
-                self.build_sst_for_keys(self.quads.iter().map(|quad| {
-                    encode_term_quad(
-                        &quad.graph_name,
-                        &quad.subject,
-                        &quad.predicate,
-                        &quad.object,
-                    )
-                }))?,
-            ));
-            to_load.push((
-                &self.storage.gpos_cf,
-                self.build_sst_for_keys(self.quads.iter().map(|quad| {
-                    encode_term_quad(
-                        &quad.graph_name,
-                        &quad.predicate,
-                        &quad.object,
-                        &quad.subject,
-                    )
-                }))?,
-            ));
-            to_load.push((
-                &self.storage.gosp_cf,
-                self.build_sst_for_keys(self.quads.iter().map(|quad| {
-                    encode_term_quad(
-                        &quad.graph_name,
-                        &quad.object,
-                        &quad.subject,
-                        &quad.predicate,
-                    )
-                }))?,
-            ));
-            to_load.push((
-                &self.storage.spog_cf,
-                self.build_sst_for_keys(self.quads.iter().map(|quad| {
-                    encode_term_quad(
-                        &quad.subject,
-                        &quad.predicate,
-                        &quad.object,
-                        &quad.graph_name,
-                    )
-                }))?,
-            ));
-            to_load.push((
-                &self.storage.posg_cf,
-                self.build_sst_for_keys(self.quads.iter().map(|quad| {
-                    encode_term_quad(
-                        &quad.predicate,
-                        &quad.object,
-                        &quad.subject,
-                        &quad.graph_name,
-                    )
-                }))?,
-            ));
-            to_load.push((
-                &self.storage.ospg_cf,
-                self.build_sst_for_keys(self.quads.iter().map(|quad| {
-                    encode_term_quad(
-                        &quad.object,
-                        &quad.subject,
-                        &quad.predicate,
-                        &quad.graph_name,
-                    )
-                }))?,
-            ));
-            self.quads.clear();
+                take(&mut self.graphs).into_iter().map(|g| encode_term(&g)),
+            )?;
         }
 
-        self.storage.db.insert_stt_files(&to_load)
+        if !self.triples.is_empty() || !self.removed_triples.is_empty() {
+            for (column_family, encode) in default_graph_indexes(&self.storage) {
+                insert_keys(
+                    insertion.as_mut(),
+                    column_family,
+                    self.triples.iter().map(encode),
+                )?;
+                remove_keys(
+                    insertion.as_mut(),
+                    column_family,
+                    self.removed_triples.iter().map(encode),
+                )?;
+            }
+        }
+
+        if !self.quads.is_empty() || !self.removed_quads.is_empty() {
+            for (column_family, encode) in named_graph_indexes(&self.storage) {
+                insert_keys(
+                    insertion.as_mut(),
+                    column_family,
+                    self.quads.iter().map(encode),
+                )?;
+                remove_keys(
+                    insertion.as_mut(),
+                    column_family,
+                    self.removed_quads.iter().map(encode),
+                )?;
+            }
+        }
+
+        // Counting the added and removed quads would require to look up each of them
+        // so we only record that the quads are counted again at the end of the load.
+        let mut default_entries = vec![(
+            [UNCOUNTED_KEY_PREFIX, &random::<u128>().to_be_bytes()].concat(),
+            Vec::new(),
+        )];
+        default_entries.extend(self.checkpoint_entry.take());
+        default_entries.sort_unstable();
+        insertion.insert(&self.storage.default_cf, &mut default_entries.into_iter())?;
+        insertion.commit()?;
+
+        self.triples.clear();
+        self.quads.clear();
+        self.removed_triples.clear();
+        self.removed_quads.clear();
+        Ok(())
     }
 
     fn insert_term(
@@ -1493,17 +2246,180 @@ impl FileBulkLoader {
             Ok(())
         })
     }
+}
 
-    fn build_sst_for_keys(
-        &self,
-        values: impl Iterator<Item = Vec<u8>>,
-    ) -> Result<PathBuf, StorageError> {
-        let mut values = values.collect::<Vec<_>>();
-        values.sort_unstable();
-        let mut sst = self.storage.db.new_sst_file()?;
-        for t in values {
-            sst.insert_empty(&t)?;
+/// Sorts the keys and adds them with an empty value to the bulk insertion.
+#[cfg(not(target_arch = "wasm32"))]
+fn insert_keys(
+    insertion: &mut dyn BackendBulkInsertion,
+    column_family: &ColumnFamily,
+    keys: impl Iterator<Item = Vec<u8>>,
+) -> Result<(), StorageError> {
+    let mut keys = keys.collect::<Vec<_>>();
+    keys.sort_unstable();
+    insertion.insert(
+        column_family,
+        &mut keys.into_iter().map(|k| (k, Vec::new())),
+    )
+}
+
+/// The ranges of the input of a bulk load that have been committed.
+///
+/// They are stored in the default column family, each batch writes its range with its data.
+#[cfg(not(target_arch = "wasm32"))]
+struct Checkpoint {
+    /// The prefix of the keys, followed by the range start
+    prefix: Vec<u8>,
+    /// The recorded entries: the key and the range end
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Checkpoint {
+    fn read(storage: &Storage, id: &str) -> Result<Self, StorageError> {
+        let mut prefix = CHECKPOINT_KEY_PREFIX.to_vec();
+        prefix.extend_from_slice(id.as_bytes());
+        prefix.push(0);
+        let mut entries = Vec::new();
+        let mut iter = storage
+            .db
+            .snapshot()
+            .scan_prefix(&storage.default_cf, &prefix)?;
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            entries.push((key.to_vec(), value.to_vec()));
+            iter.next();
+        }
+        iter.status()?;
+        Ok(Self { prefix, entries })
+    }
+
+    /// Returns the committed ranges sorted and merged.
+    fn committed(&self) -> Result<Vec<Range<u64>>, StorageError> {
+        let mut ranges = self
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                Ok(decode_checkpoint_position(&key[self.prefix.len()..])?
+                    ..decode_checkpoint_position(value)?)
+            })
+            .collect::<Result<Vec<_>, StorageError>>()?;
+        ranges.sort_unstable_by_key(|range| range.start);
+        let mut merged = Vec::<Range<u64>>::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = max(last.end, range.end),
+                _ => merged.push(range),
+            }
+        }
+        Ok(merged)
+    }
+
+    /// The entry recording that a range has been committed.
+    fn entry(&self, range: Range<u64>) -> (Vec<u8>, Vec<u8>) {
+        let mut key = self.prefix.clone();
+        key.extend_from_slice(&range.start.to_be_bytes());
+        (key, range.end.to_be_bytes().to_vec())
+    }
+
+    fn clear(&self, storage: &Storage) -> Result<(), StorageError> {
+        if self.entries.is_empty() {
+            return Ok(());
         }
-        sst.finish()
+        storage.db.transaction(|mut transaction| {
+            for (key, _) in &self.entries {
+                transaction.remove(&storage.default_cf, key)?;
+            }
+            Ok(())
+        })
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decode_checkpoint_position(bytes: &[u8]) -> Result<u64, StorageError> {
+    Ok(u64::from_be_bytes(bytes.try_into().map_err(|_| {
+        CorruptionError::msg("Invalid bulk load checkpoint position")
+    })?))
+}
+
+/// Sorts the keys and removes them in the bulk insertion.
+#[cfg(not(target_arch = "wasm32"))]
+fn remove_keys(
+    insertion: &mut dyn BackendBulkInsertion,
+    column_family: &ColumnFamily,
+    keys: impl Iterator<Item = Vec<u8>>,
+) -> Result<(), StorageError> {
+    let mut keys = keys.collect::<Vec<_>>();
+    keys.sort_unstable();
+    insertion.remove(column_family, &mut keys.into_iter())
+}
+
+/// The column families storing the default graph and how the quads are encoded in it.
+#[cfg(not(target_arch = "wasm32"))]
+fn default_graph_indexes(storage: &Storage) -> [(&ColumnFamily, fn(&EncodedQuad) -> Vec<u8>); 3] {
+    [
+        (&storage.dspo_cf, |quad| {
+            encode_term_triple(&quad.subject, &quad.predicate, &quad.object)
+        }),
+        (&storage.dpos_cf, |quad| {
+            encode_term_triple(&quad.predicate, &quad.object, &quad.subject)
+        }),
+        (&storage.dosp_cf, |quad| {
+            encode_term_triple(&quad.object, &quad.subject, &quad.predicate)
+        }),
+    ]
+}
+
+/// The column families storing the named graphs and how the quads are encoded in them.
+#[cfg(not(target_arch = "wasm32"))]
+fn named_graph_indexes(storage: &Storage) -> [(&ColumnFamily, fn(&EncodedQuad) -> Vec<u8>); 6] {
+    [
+        (&storage.gspo_cf, |quad| {
+            encode_term_quad(
+                &quad.graph_name,
+                &quad.subject,
+                &quad.predicate,
+                &quad.object,
+            )
+        }),
+        (&storage.gpos_cf, |quad| {
+            encode_term_quad(
+                &quad.graph_name,
+                &quad.predicate,
+                &quad.object,
+                &quad.subject,
+            )
+        }),
+        (&storage.gosp_cf, |quad| {
+            encode_term_quad(
+                &quad.graph_name,
+                &quad.object,
+                &quad.subject,
+                &quad.predicate,
+            )
+        }),
+        (&storage.spog_cf, |quad| {
+            encode_term_quad(
+                &quad.subject,
+                &quad.predicate,
+                &quad.object,
+                &quad.graph_name,
+            )
+        }),
+        (&storage.posg_cf, |quad| {
+            encode_term_quad(
+                &quad.predicate,
+                &quad.object,
+                &quad.subject,
+                &quad.graph_name,
+            )
+        }),
+        (&storage.ospg_cf, |quad| {
+            encode_term_quad(
+                &quad.object,
+                &quad.subject,
+                &quad.predicate,
+                &quad.graph_name,
+            )
+        }),
+    ]
+}
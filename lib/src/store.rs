@@ -23,7 +23,13 @@
 //! };
 //! # Result::<_, Box<dyn std::error::Error>>::Ok(())
 //! ```
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub use crate::async_store::{
+    AsyncQueryResults, AsyncStore, QuerySolutionStream, QueryTripleStream,
+};
 use crate::io::read::ParseError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::io::read::{blank_node_key, LineFormat, ParallelQuadReader};
 use crate::io::{
     DatasetFormat, DatasetParser, DatasetSerializer, GraphFormat, GraphParser, GraphSerializer,
 };
@@ -36,8 +42,11 @@ use crate::storage::numeric_encoder::{Decoder, EncodedQuad, EncodedTerm};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::storage::StorageBulkLoader;
 use crate::storage::{
-    ChainedDecodingQuadIterator, DecodingGraphIterator, Storage, StorageReader, StorageWriter,
+    ChainedDecodingQuadIterator, DecodingGraphIterator, Storage, StorageReader, StorageTransaction,
+    StorageWriter,
 };
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::storage::{CompressionType, StoreOptions};
 pub use crate::storage::{CorruptionError, LoaderError, SerializerError, StorageError};
 use std::error::Error;
 use std::io::{BufRead, Write};
@@ -45,9 +54,22 @@ use std::io::{BufRead, Write};
 use std::path::Path;
 use std::{fmt, str};
 
-/// An on-disk [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset).
+/// Traits to plug a custom sorted key-value store below a [`Store`] using [`Store::from_backend`].
+///
+/// The store data is written in the column families listed by [`column_families`](backend::column_families).
+/// A backend must provide snapshots, transactions that are rolled back if they are not committed
+/// and raise [`StorageError::Conflict`] on conflicts with concurrent transactions.
+pub mod backend {
+    pub use crate::storage::backend::{
+        column_families, Backend, BackendBulkInsertion, BackendIter, BackendReader,
+        BackendTransaction, ColumnFamily, ColumnFamilyDefinition,
+    };
+}
+
+/// An on-disk or in-memory [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset).
 /// Allows to query and update it using SPARQL.
-/// It is based on the [RocksDB](https://rocksdb.org/) key-value store.
+/// On disk, it is based on the [RocksDB](https://rocksdb.org/) key-value store or,
+/// if the `rocksdb` feature is disabled, on a pure Rust log-structured merge tree.
 ///
 /// This store ensures the "repeatable read" isolation level: the store only exposes changes that have
 /// been "committed" (i.e. no partial writes) and the exposed state does not change for the complete duration
@@ -87,18 +109,68 @@ pub struct Store {
 }
 
 impl Store {
-    /// Creates a temporary [`Store`] that will be deleted after drop.
+    /// Creates a [`Store`] keeping its data in memory. They are lost after drop.
+    ///
+    /// Concurrent reads are not blocked by writes: each read operation works on a cheap snapshot of the store.
     pub fn new() -> Result<Self, StorageError> {
         Ok(Self {
             storage: Storage::new()?,
         })
     }
 
+    /// Creates a [`Store`] writing its data in a custom [`Backend`](backend::Backend).
+    ///
+    /// The backend must provide all the column families returned by [`backend::column_families`].
+    pub fn from_backend(backend: impl backend::Backend) -> Result<Self, StorageError> {
+        Ok(Self {
+            storage: Storage::from_backend(backend)?,
+        })
+    }
+
     /// Opens a [`Store`] and creates it if it does not exist yet.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::open_with_options(path, &StoreOptions::default())
+    }
+
+    /// Opens a [`Store`] with custom storage options like the cache size and creates it if it does not exist yet.
+    ///
+    /// See [`StoreOptions`] for an example.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_with_options(
+        path: impl AsRef<Path>,
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
         Ok(Self {
-            storage: Storage::open(path.as_ref())?,
+            storage: Storage::open(path.as_ref(), options)?,
+        })
+    }
+
+    /// Opens an existing [`Store`] in read-only mode.
+    ///
+    /// All the write operations (insertions, SPARQL updates, bulk loading...) are going to fail.
+    ///
+    /// Several processes might open the same database in read-only mode at the same time.
+    /// Opening as read-only while having another process writing the database is not supported:
+    /// the read-only view is the state of the database when this function has been called.
+    ///
+    /// The database must have been created before using [`Store::open`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::open_read_only_with_options(path, &StoreOptions::default())
+    }
+
+    /// Opens an existing [`Store`] in read-only mode with custom storage options.
+    ///
+    /// The write-ahead log directory must be the one used when the database has been written.
+    /// See [`Store::open_read_only`] for the read-only mode limitations.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_read_only_with_options(
+        path: impl AsRef<Path>,
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
+        Ok(Self {
+            storage: Storage::open_read_only(path.as_ref(), options)?,
         })
     }
 
@@ -185,13 +257,15 @@ impl Store {
     ) -> QuadIter {
         let reader = self.storage.snapshot();
         QuadIter {
-            iter: reader.quads_for_pattern(
-                subject.map(EncodedTerm::from).as_ref(),
-                predicate.map(EncodedTerm::from).as_ref(),
-                object.map(EncodedTerm::from).as_ref(),
-                graph_name.map(EncodedTerm::from).as_ref(),
-            ),
-            reader,
+            inner: QuadIterInner::Quads {
+                iter: reader.quads_for_pattern(
+                    subject.map(EncodedTerm::from).as_ref(),
+                    predicate.map(EncodedTerm::from).as_ref(),
+                    object.map(EncodedTerm::from).as_ref(),
+                    graph_name.map(EncodedTerm::from).as_ref(),
+                ),
+                reader,
+            },
         }
     }
 
@@ -242,7 +316,8 @@ impl Store {
 
     /// Returns the number of quads in the store.
     ///
-    /// Warning: this function executes a full scan.
+    /// The number of quads is maintained on writes so this function does not scan the store.
+    /// The quads written by a [`BulkLoader`] operation are only counted once the operation is done.
     ///
     /// Usage example:
     /// ```
@@ -260,6 +335,55 @@ impl Store {
         self.storage.snapshot().len()
     }
 
+    /// Computes statistics about the store content.
+    ///
+    /// Only the numbers of quads are maintained on writes.
+    /// The other statistics are computed by scanning the store indexes so this function is slow on big stores.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::model::*;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    /// let statistics = store.statistics()?;
+    /// assert_eq!(
+    ///     vec![(GraphName::DefaultGraph, 1), (ex.into_owned().into(), 1)],
+    ///     statistics.graph_sizes
+    /// );
+    /// assert_eq!(1, statistics.distinct_subjects);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn statistics(&self) -> Result<StoreStatistics, StorageError> {
+        let reader = self.storage.snapshot();
+        let statistics = reader.statistics()?;
+        Ok(StoreStatistics {
+            graph_sizes: statistics
+                .graph_lens
+                .into_iter()
+                .map(|(graph_name, len)| {
+                    let graph_name = if graph_name.is_default_graph() {
+                        GraphName::DefaultGraph
+                    } else {
+                        match reader.decode_named_or_blank_node(&graph_name)? {
+                            NamedOrBlankNode::NamedNode(node) => node.into(),
+                            NamedOrBlankNode::BlankNode(node) => node.into(),
+                        }
+                    };
+                    Ok((graph_name, len))
+                })
+                .collect::<Result<_, StorageError>>()?,
+            distinct_subjects: statistics.distinct_subjects,
+            distinct_predicates: statistics.distinct_predicates,
+            distinct_objects: statistics.distinct_objects,
+            dictionary_size: statistics.dictionary_len,
+            disk_sizes: statistics.disk_sizes,
+        })
+    }
+
     /// Returns if the store is empty.
     ///
     /// Usage example:
@@ -311,6 +435,41 @@ impl Store {
         self.storage.transaction(|writer| f(Transaction { writer }))
     }
 
+    /// Starts a transaction that is ended by an explicit call to [`InteractiveTransaction::commit`] or [`InteractiveTransaction::rollback`].
+    ///
+    /// Contrary to [`Store::transaction`], the transaction is not bound to a closure: it can be kept alive
+    /// between calls, for example during several requests of a client, and moved to another thread.
+    /// It provides the same "repeatable read" isolation level and sees its own writes.
+    ///
+    /// Conflicts with concurrent transactions are not retried automatically:
+    /// the operations and the commit might fail with [`StorageError::Conflict`].
+    /// The transaction should then be rolled back and started again.
+    ///
+    /// The transaction is rolled back if dropped without being committed.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::model::*;
+    ///
+    /// let store = Store::new()?;
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let quad = QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph);
+    ///
+    /// let transaction = store.start_transaction()?;
+    /// transaction.transaction().insert(quad)?;
+    /// assert!(transaction.transaction().contains(quad)?); // The transaction sees its own writes
+    /// assert!(!store.contains(quad)?); // But they are not visible outside of it before commit
+    /// transaction.commit()?;
+    /// assert!(store.contains(quad)?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn start_transaction(&self) -> Result<InteractiveTransaction, StorageError> {
+        Ok(InteractiveTransaction {
+            inner: self.storage.start_transaction()?,
+        })
+    }
+
     /// Executes a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/).
     ///
     /// Usage example:
@@ -589,8 +748,10 @@ impl Store {
     pub fn named_graphs(&self) -> GraphNameIter {
         let reader = self.storage.snapshot();
         GraphNameIter {
-            iter: reader.named_graphs(),
-            reader,
+            inner: GraphNameIterInner::GraphNames {
+                iter: reader.named_graphs(),
+                reader,
+            },
         }
     }
 
@@ -738,7 +899,7 @@ impl Store {
     /// like a regular Oxigraph database and operates independently from the original database.
     ///
     /// Warning: Backups are only possible for on-disk databases created using [`Store::open`].
-    /// Temporary in-memory databases created using [`Store::new`] are not compatible with RocksDB backup system.
+    /// In-memory databases created using [`Store::new`] are not compatible with the backup system.
     ///
     /// Warning: An error is raised if the `target_directory` already exists.
     ///
@@ -753,6 +914,57 @@ impl Store {
         self.storage.backup(target_directory.as_ref())
     }
 
+    /// Adds a new incremental backup of the database to the `backup_directory`.
+    ///
+    /// Contrary to [`Store::backup`], the backup directory is managed by RocksDB backup engine:
+    /// it could contain many backups and only the files that changed since the previous backup are copied.
+    /// The directory is created if it does not exist yet.
+    ///
+    /// Use [`Store::purge_incremental_backups`] to remove old backups
+    /// and [`Store::restore_from_backup`] to restore the latest one.
+    ///
+    /// Warning: Backups are only possible for on-disk databases created using [`Store::open`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn incremental_backup(
+        &self,
+        backup_directory: impl AsRef<Path>,
+    ) -> Result<(), StorageError> {
+        self.storage.incremental_backup(backup_directory.as_ref())
+    }
+
+    /// Removes the oldest incremental backups from the `backup_directory` in order to only keep the `num_backups_to_keep` most recent ones.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn purge_incremental_backups(
+        backup_directory: impl AsRef<Path>,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        Storage::purge_old_backups(backup_directory.as_ref(), num_backups_to_keep)
+    }
+
+    /// Restores a backup into `target_directory` that could then be opened using [`Store::open`].
+    ///
+    /// The backup could either be a directory created by [`Store::backup`]
+    /// or a directory of incremental backups created by [`Store::incremental_backup`].
+    /// In the later case, the most recent backup is restored.
+    ///
+    /// Warning: An error is raised if the `target_directory` already exists.
+    ///
+    /// Usage example:
+    /// ```no_run
+    /// use oxigraph::store::Store;
+    ///
+    /// Store::restore_from_backup("backups", "restored")?;
+    /// let store = Store::open("restored")?;
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restore_from_backup(
+        backup_directory: impl AsRef<Path>,
+        target_directory: impl AsRef<Path>,
+    ) -> Result<(), StorageError> {
+        Storage::restore_from_backup(backup_directory.as_ref(), target_directory.as_ref())
+    }
+
     /// Creates a bulk loader allowing to load at lot of data quickly into the store.
     ///
     /// Usage example:
@@ -866,7 +1078,11 @@ impl<'a> Transaction<'a> {
         query: impl TryInto<Query, Error = impl Into<EvaluationError>>,
         options: QueryOptions,
     ) -> Result<QueryResults, EvaluationError> {
-        evaluate_query(self.writer.reader(), query, options)
+        evaluate_query(
+            self.writer.reader()?,
+            query,
+            options.with_parallelism(1), // The transaction reader must not be used from multiple threads
+        )
     }
 
     /// Retrieves quads with a filter on each quad component.
@@ -898,15 +1114,24 @@ impl<'a> Transaction<'a> {
         object: Option<TermRef<'_>>,
         graph_name: Option<GraphNameRef<'_>>,
     ) -> QuadIter {
-        let reader = self.writer.reader();
+        let reader = match self.writer.reader() {
+            Ok(reader) => reader,
+            Err(error) => {
+                return QuadIter {
+                    inner: QuadIterInner::Error(Some(error)),
+                }
+            }
+        };
         QuadIter {
-            iter: reader.quads_for_pattern(
-                subject.map(EncodedTerm::from).as_ref(),
-                predicate.map(EncodedTerm::from).as_ref(),
-                object.map(EncodedTerm::from).as_ref(),
-                graph_name.map(EncodedTerm::from).as_ref(),
-            ),
-            reader,
+            inner: QuadIterInner::Quads {
+                iter: reader.quads_for_pattern(
+                    subject.map(EncodedTerm::from).as_ref(),
+                    predicate.map(EncodedTerm::from).as_ref(),
+                    object.map(EncodedTerm::from).as_ref(),
+                    graph_name.map(EncodedTerm::from).as_ref(),
+                ),
+                reader,
+            },
         }
     }
 
@@ -918,19 +1143,20 @@ impl<'a> Transaction<'a> {
     /// Checks if this store contains a given quad.
     pub fn contains<'b>(&self, quad: impl Into<QuadRef<'b>>) -> Result<bool, StorageError> {
         let quad = EncodedQuad::from(quad.into());
-        self.writer.reader().contains(&quad)
+        self.writer.reader()?.contains(&quad)
     }
 
     /// Returns the number of quads in the store.
     ///
-    /// Warning: this function executes a full scan.
+    /// The number of quads is maintained on writes so this function does not scan the store.
+    /// The quads written by a [`BulkLoader`] operation are only counted once the operation is done.
     pub fn len(&self) -> Result<usize, StorageError> {
-        self.writer.reader().len()
+        self.writer.reader()?.len()
     }
 
     /// Returns if the store is empty.
     pub fn is_empty(&self) -> Result<bool, StorageError> {
-        self.writer.reader().is_empty()
+        self.writer.reader()?.is_empty()
     }
 
     /// Executes a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/).
@@ -1113,10 +1339,19 @@ impl<'a> Transaction<'a> {
 
     /// Returns all the store named graphs.
     pub fn named_graphs(&self) -> GraphNameIter {
-        let reader = self.writer.reader();
+        let reader = match self.writer.reader() {
+            Ok(reader) => reader,
+            Err(error) => {
+                return GraphNameIter {
+                    inner: GraphNameIterInner::Error(Some(error)),
+                }
+            }
+        };
         GraphNameIter {
-            iter: reader.named_graphs(),
-            reader,
+            inner: GraphNameIterInner::GraphNames {
+                iter: reader.named_graphs(),
+                reader,
+            },
         }
     }
 
@@ -1126,7 +1361,7 @@ impl<'a> Transaction<'a> {
         graph_name: impl Into<NamedOrBlankNodeRef<'b>>,
     ) -> Result<bool, StorageError> {
         self.writer
-            .reader()
+            .reader()?
             .contains_named_graph(&EncodedTerm::from(graph_name.into()))
     }
 
@@ -1227,48 +1462,125 @@ impl<'a> Transaction<'a> {
     }
 }
 
+/// A transaction that is ended by an explicit call to [`commit`](Self::commit) or [`rollback`](Self::rollback).
+///
+/// It is rolled back if dropped without being committed.
+/// The store is read and written inside of it using [`transaction`](Self::transaction).
+///
+/// See [`Store::start_transaction`] for a more detailed description.
+pub struct InteractiveTransaction {
+    inner: StorageTransaction,
+}
+
+impl InteractiveTransaction {
+    /// Returns a [`Transaction`] to read and write the store inside of this transaction.
+    ///
+    /// The reads see the writes done by the transaction.
+    /// A usage example is given in [`Store::start_transaction`].
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction {
+            writer: self.inner.writer(),
+        }
+    }
+
+    /// Commits the transaction: its writes become visible at once.
+    ///
+    /// If it fails with [`StorageError::Conflict`], nothing is written and the transaction should be started again.
+    pub fn commit(self) -> Result<(), StorageError> {
+        self.inner.commit()
+    }
+
+    /// Rolls back the transaction: none of its writes is kept.
+    pub fn rollback(self) -> Result<(), StorageError> {
+        self.inner.rollback()
+    }
+}
+
 /// An iterator returning the quads contained in a [`Store`].
 pub struct QuadIter {
-    iter: ChainedDecodingQuadIterator,
-    reader: StorageReader,
+    inner: QuadIterInner,
+}
+
+enum QuadIterInner {
+    Quads {
+        iter: ChainedDecodingQuadIterator,
+        reader: StorageReader,
+    },
+    Error(Option<StorageError>),
 }
 
 impl Iterator for QuadIter {
     type Item = Result<Quad, StorageError>;
 
     fn next(&mut self) -> Option<Result<Quad, StorageError>> {
-        Some(match self.iter.next()? {
-            Ok(quad) => self.reader.decode_quad(&quad),
-            Err(error) => Err(error),
-        })
+        match &mut self.inner {
+            QuadIterInner::Quads { iter, reader } => Some(match iter.next()? {
+                Ok(quad) => reader.decode_quad(&quad),
+                Err(error) => Err(error),
+            }),
+            QuadIterInner::Error(error) => error.take().map(Err),
+        }
     }
 }
 
 /// An iterator returning the graph names contained in a [`Store`].
 pub struct GraphNameIter {
-    iter: DecodingGraphIterator,
-    reader: StorageReader,
+    inner: GraphNameIterInner,
+}
+
+enum GraphNameIterInner {
+    GraphNames {
+        iter: DecodingGraphIterator,
+        reader: StorageReader,
+    },
+    Error(Option<StorageError>),
 }
 
 impl Iterator for GraphNameIter {
     type Item = Result<NamedOrBlankNode, StorageError>;
 
     fn next(&mut self) -> Option<Result<NamedOrBlankNode, StorageError>> {
-        Some(
-            self.iter
-                .next()?
-                .and_then(|graph_name| self.reader.decode_named_or_blank_node(&graph_name)),
-        )
+        match &mut self.inner {
+            GraphNameIterInner::GraphNames { iter, reader } => Some(
+                iter.next()?
+                    .and_then(|graph_name| reader.decode_named_or_blank_node(&graph_name)),
+            ),
+            GraphNameIterInner::Error(error) => error.take().map(Err),
+        }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+        match &self.inner {
+            GraphNameIterInner::GraphNames { iter, .. } => iter.size_hint(),
+            GraphNameIterInner::Error(error) => {
+                let len = usize::from(error.is_some());
+                (len, Some(len))
+            }
+        }
     }
 }
 
-/// A bulk loader allowing to load at lot of data quickly into the store.
+/// Statistics about the content of a [`Store`] returned by [`Store::statistics`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct StoreStatistics {
+    /// The number of quads of the default graph and of each named graph.
+    pub graph_sizes: Vec<(GraphName, usize)>,
+    /// The number of distinct subjects of the quads.
+    pub distinct_subjects: usize,
+    /// The number of distinct predicates of the quads.
+    pub distinct_predicates: usize,
+    /// The number of distinct objects of the quads.
+    pub distinct_objects: usize,
+    /// The number of strings stored in the dictionary of the store (IRIs, literal values...).
+    pub dictionary_size: usize,
+    /// The size in bytes of the files of each column family if the storage backend provides it.
+    pub disk_sizes: Vec<(&'static str, Option<u64>)>,
+}
+
+/// A bulk loader allowing to load at lot of data quickly into the store or to remove it.
 ///
-/// Warning: The operations provided here are not atomic.
+/// Warning: The operations provided here are not atomic except [`BulkLoader::replace_graph`].
 /// If the operation fails in the middle, only a part of the data may be written to the store.
 /// Results might get weird if you delete data during the loading process.
 ///
@@ -1278,6 +1590,11 @@ impl Iterator for GraphNameIter {
 /// By default the memory consumption target (excluding the system and RocksDB internal consumption)
 /// is 1GB per thread and the number of threads is set to the number of logical CPU cores provided by the system.
 /// These targets are considered per loaded file.
+/// N-Triples and N-Quads files are split into chunks at line boundaries that are parsed using the same number of threads.
+///
+/// The quads written by an operation are not counted while they are written:
+/// the whole store is scanned to count them again at the end of each operation
+/// so [`Store::len`] does not include the batches of the running operations.
 ///
 /// Usage example with loading a dataset:
 /// ```
@@ -1307,6 +1624,7 @@ impl BulkLoader {
     /// Sets the maximal number of threads to be used by the bulk loader per operation.
     ///
     /// This number must be at last 2 (one for parsing and one for loading).
+    /// N-Triples and N-Quads files are also parsed using this number of threads.
     ///
     /// By default this is the number of logical CPU cores provided by the system except if
     /// [`BulkLoader::set_max_memory_size_in_megabytes`] is set. In this case at least one 1GB is reserved
@@ -1338,6 +1656,52 @@ impl BulkLoader {
         self
     }
 
+    /// Records in the store the parts of the input that have been written under the given `id`.
+    ///
+    /// If the load is interrupted, for example by a crash, it could be restarted from where it stopped
+    /// using [`BulkLoader::resume_from_checkpoint`] with the same `id` and the same input.
+    /// The parts recorded by a previous load with the same `id` are discarded.
+    /// The record is removed when the load succeeds.
+    ///
+    /// The blank node identifiers are derived from the file labels and from `id`:
+    /// loading twice the same input with the same `id` gives the same blank nodes.
+    ///
+    /// It only applies to [`BulkLoader::load_dataset`], [`BulkLoader::load_graph`] and [`BulkLoader::load_quads`].
+    pub fn with_checkpoint(mut self, id: impl Into<String>) -> Self {
+        self.storage = self.storage.set_checkpoint(id.into(), false);
+        self
+    }
+
+    /// Resumes a load interrupted while using [`BulkLoader::with_checkpoint`] with the same `id`.
+    ///
+    /// The input must be the same as the one of the interrupted load: the quads at the positions
+    /// that have already been written are skipped. The progress keeps being recorded under `id`.
+    ///
+    /// The blank node identifiers are derived from the file labels and from `id`
+    /// so that the blank nodes written before and after the interruption are the same.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::io::DatasetFormat;
+    /// use oxigraph::model::*;
+    ///
+    /// let store = Store::new()?;
+    /// let file = b"<http://example.com> <http://example.com> <http://example.com> <http://example.com> .";
+    /// store
+    ///     .bulk_loader()
+    ///     .resume_from_checkpoint("my-file.nq")
+    ///     .load_dataset(file.as_ref(), DatasetFormat::NQuads, None)?;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// assert!(store.contains(QuadRef::new(ex, ex, ex, ex))?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn resume_from_checkpoint(mut self, id: impl Into<String>) -> Self {
+        self.storage = self.storage.set_checkpoint(id.into(), true);
+        self
+    }
+
     /// Adds a `callback` catching all parse errors and choosing if the parsing should continue
     /// by returning `Ok` or fail by returning `Err`.
     ///
@@ -1354,6 +1718,12 @@ impl BulkLoader {
     ///
     /// This function is optimized for large dataset loading speed. For small files, [`Store::load_dataset`] might be more convenient.
     ///
+    /// N-Quads files are parsed in parallel.
+    /// In this case the blank node identifiers are derived from the file labels
+    /// and the positions given in the syntax errors are relative to the beginning of the parsed chunk.
+    /// Gzip compressed N-Quads files are also supported. Their members are decompressed in parallel
+    /// if they give their compressed size, like the ones written by `bgzip`.
+    ///
     /// Warning: This method is not atomic.
     /// If the parsing fails in the middle of the file, only a part of it may be written to the store.
     /// Results might get weird if you delete data during the loading process.
@@ -1389,27 +1759,29 @@ impl BulkLoader {
                 .with_base_iri(base_iri)
                 .map_err(|e| ParseError::invalid_base_iri(base_iri, e))?;
         }
-        self.storage
-            .load(parser.read_quads(reader)?.filter_map(|r| match r {
-                Ok(q) => Some(Ok(q)),
-                Err(e) => {
-                    if let Some(callback) = &self.on_parse_error {
-                        if let Err(e) = callback(e) {
-                            Some(Err(e))
-                        } else {
-                            None
-                        }
-                    } else {
-                        Some(Err(e))
-                    }
-                }
-            }))
+        if let Some(key) = self.blank_node_key() {
+            parser = parser.with_blank_node_key(key);
+        }
+        if format == DatasetFormat::NQuads {
+            return self.load_lines(reader, LineFormat::NQuads);
+        }
+        self.storage.load(
+            parser
+                .read_quads(reader)?
+                .filter_map(|r| self.filter_parse_error(r)),
+        )
     }
 
     /// Loads a graph file using the bulk loader.
     ///
     /// This function is optimized for large graph loading speed. For small files, [`Store::load_graph`] might be more convenient.   
     ///
+    /// N-Triples files are parsed in parallel.
+    /// In this case the blank node identifiers are derived from the file labels
+    /// and the positions given in the syntax errors are relative to the beginning of the parsed chunk.
+    /// Gzip compressed N-Triples files are also supported. Their members are decompressed in parallel
+    /// if they give their compressed size, like the ones written by `bgzip`.
+    ///
     /// Warning: This method is not atomic.
     /// If the parsing fails in the middle of the file, only a part of it may be written to the store.
     /// Results might get weird if you delete data during the loading process.
@@ -1446,24 +1818,39 @@ impl BulkLoader {
                 .with_base_iri(base_iri)
                 .map_err(|e| ParseError::invalid_base_iri(base_iri, e))?;
         }
+        if let Some(key) = self.blank_node_key() {
+            parser = parser.with_blank_node_key(key);
+        }
         let to_graph_name = to_graph_name.into();
+        if format == GraphFormat::NTriples {
+            return self.load_lines(reader, LineFormat::NTriples(to_graph_name.into_owned()));
+        }
         self.storage
-            .load(parser.read_triples(reader)?.filter_map(|r| match r {
-                Ok(q) => Some(Ok(q.in_graph(to_graph_name.into_owned()))),
-                Err(e) => {
-                    if let Some(callback) = &self.on_parse_error {
-                        if let Err(e) = callback(e) {
-                            Some(Err(e))
-                        } else {
-                            None
-                        }
-                    } else {
-                        Some(Err(e))
-                    }
-                }
+            .load(parser.read_triples(reader)?.filter_map(|r| {
+                self.filter_parse_error(r.map(|t| t.in_graph(to_graph_name.into_owned())))
             }))
     }
 
+    /// Loads a line based file, parsing it using several threads.
+    fn load_lines(&self, reader: impl BufRead, format: LineFormat) -> Result<(), LoaderError> {
+        self.storage.load(
+            ParallelQuadReader::new(
+                reader,
+                format,
+                self.storage.num_parsing_threads(),
+                self.blank_node_key(),
+            )
+            .filter_map(|r| self.filter_parse_error(r)),
+        )
+    }
+
+    /// The key used to derive the blank node identifiers from the file labels.
+    ///
+    /// It only depends on the checkpoint so that a resumed load gives the same blank nodes as the interrupted one.
+    fn blank_node_key(&self) -> Option<(u64, u64)> {
+        self.storage.checkpoint_id().map(blank_node_key)
+    }
+
     /// Adds a set of quads using the bulk loader.
     ///
     /// Warning: This method is not atomic.
@@ -1475,6 +1862,184 @@ impl BulkLoader {
         self.storage
             .load::<StorageError, _, _>(quads.into_iter().map(Ok))
     }
+
+    /// Removes a set of quads using the bulk loader.
+    ///
+    /// This function is optimized for the removal of a lot of quads. For a few quads, [`Store::remove`] might be more convenient.
+    ///
+    /// Warning: This method is not atomic.
+    /// If the process fails in the middle, only a part of the quads may be removed from the store.
+    ///
+    /// Warning: This method is optimized for speed. See [the struct](BulkLoader) documentation for more details.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::model::*;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let quad = QuadRef::new(ex, ex, ex, ex);
+    /// let store = Store::new()?;
+    /// store.insert(quad)?;
+    ///
+    /// store.bulk_loader().remove_quads([quad.into_owned()])?;
+    /// assert!(!store.contains(quad)?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn remove_quads(&self, quads: impl IntoIterator<Item = Quad>) -> Result<(), StorageError> {
+        self.storage.remove::<StorageError, _, _>(
+            quads
+                .into_iter()
+                .map(|quad| Ok(EncodedQuad::from(quad.as_ref()))),
+        )
+    }
+
+    /// Removes all the quads matching a pattern using the bulk loader.
+    ///
+    /// This is the equivalent of a SPARQL `DELETE WHERE { GRAPH ?g { ?s ?p ?o } }` update where some of the variables are bound.
+    /// The removed quads are the ones returned by [`Store::quads_for_pattern`] when the removal starts.
+    ///
+    /// Only a single quad pattern is supported. To remove the quads matching a more complex SPARQL pattern,
+    /// give the results of a `CONSTRUCT` query to [`BulkLoader::remove_quads`]:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::QueryResults;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    ///
+    /// if let QueryResults::Graph(triples) = store.query("CONSTRUCT WHERE { ?s ?p ?s }")? {
+    ///     let quads = triples
+    ///         .map(|t| t.map(|t| t.in_graph(GraphName::DefaultGraph)))
+    ///         .collect::<Result<Vec<_>, _>>()?;
+    ///     store.bulk_loader().remove_quads(quads)?;
+    /// }
+    /// assert!(store.is_empty()?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    ///
+    /// Warning: This method is not atomic.
+    /// If the process fails in the middle, only a part of the quads may be removed from the store.
+    ///
+    /// Warning: This method is optimized for speed. See [the struct](BulkLoader) documentation for more details.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::model::*;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    ///
+    /// store.bulk_loader().remove_quads_for_pattern(None, Some(ex), None, Some(ex.into()))?;
+    /// assert_eq!(store.len()?, 1);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn remove_quads_for_pattern(
+        &self,
+        subject: Option<SubjectRef<'_>>,
+        predicate: Option<NamedNodeRef<'_>>,
+        object: Option<TermRef<'_>>,
+        graph_name: Option<GraphNameRef<'_>>,
+    ) -> Result<(), StorageError> {
+        self.storage.remove_quads_for_pattern(
+            subject.map(EncodedTerm::from).as_ref(),
+            predicate.map(EncodedTerm::from).as_ref(),
+            object.map(EncodedTerm::from).as_ref(),
+            graph_name.map(EncodedTerm::from).as_ref(),
+        )
+    }
+
+    /// Removes all the quads of a graph using the bulk loader.
+    ///
+    /// Like [`Store::clear_graph`], the graph itself is not removed from the named graphs.
+    ///
+    /// Warning: This method is not atomic.
+    /// If the process fails in the middle, only a part of the quads may be removed from the store.
+    ///
+    /// Warning: This method is optimized for speed. See [the struct](BulkLoader) documentation for more details.
+    pub fn clear_graph<'a>(
+        &self,
+        graph_name: impl Into<GraphNameRef<'a>>,
+    ) -> Result<(), StorageError> {
+        self.remove_quads_for_pattern(None, None, None, Some(graph_name.into()))
+    }
+
+    /// Replaces the content of a graph by the content of a graph file using the bulk loader.
+    ///
+    /// The replacement is atomic: the readers either see the previous content of the graph or the new one.
+    /// If the parsing fails, the graph is not modified.
+    ///
+    /// Warning: The parsed triples and the removed ones are kept in memory until they are written to the store.
+    /// If a memory limit is set with [`BulkLoader::set_max_memory_size_in_megabytes`],
+    /// the replacement fails without modifying the graph if they need more memory than this limit.
+    /// There is no limit by default.
+    /// Results might get weird if you modify the graph during the replacement process.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::Store;
+    /// use oxigraph::io::GraphFormat;
+    /// use oxigraph::model::*;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let old = NamedNodeRef::new("http://example.com/old")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, old, ex))?;
+    ///
+    /// let file = b"<http://example.com> <http://example.com> <http://example.com> .";
+    /// store.bulk_loader().replace_graph(file.as_ref(), GraphFormat::NTriples, ex, None)?;
+    ///
+    /// assert!(store.contains(QuadRef::new(ex, ex, ex, ex))?);
+    /// assert!(!store.contains(QuadRef::new(ex, ex, old, ex))?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn replace_graph<'a>(
+        &self,
+        reader: impl BufRead,
+        format: GraphFormat,
+        graph_name: impl Into<GraphNameRef<'a>>,
+        base_iri: Option<&str>,
+    ) -> Result<(), LoaderError> {
+        let mut parser = GraphParser::from_format(format);
+        if let Some(base_iri) = base_iri {
+            parser = parser
+                .with_base_iri(base_iri)
+                .map_err(|e| ParseError::invalid_base_iri(base_iri, e))?;
+        }
+        let graph_name = graph_name.into();
+        self.storage.replace_graph(
+            graph_name,
+            parser.read_triples(reader)?.filter_map(|r| {
+                self.filter_parse_error(r.map(|t| t.in_graph(graph_name.into_owned())))
+            }),
+        )
+    }
+
+    /// Applies the [`BulkLoader::on_parse_error`] callback.
+    fn filter_parse_error<T>(
+        &self,
+        result: Result<T, ParseError>,
+    ) -> Option<Result<T, ParseError>> {
+        match result {
+            Ok(value) => Some(Ok(value)),
+            Err(e) => {
+                if let Some(callback) = &self.on_parse_error {
+                    if let Err(e) = callback(e) {
+                        Some(Err(e))
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(Err(e))
+                }
+            }
+        }
+    }
 }
 
 #[test]
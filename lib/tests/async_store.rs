@@ -0,0 +1,106 @@
+#![cfg(all(feature = "async", not(target_arch = "wasm32")))]
+
+use futures::StreamExt;
+use oxigraph::io::{DatasetFormat, GraphFormat};
+use oxigraph::model::*;
+use oxigraph::store::{AsyncQueryResults, AsyncStore, Store};
+use std::error::Error;
+
+const DATA: &str = r#"
+<http://example.com/s> <http://example.com/p> "1" .
+<http://example.com/s> <http://example.com/p> "2" .
+<http://example.com/s> <http://example.com/p> "3" .
+"#;
+
+#[tokio::test]
+async fn test_async_load_and_dump() -> Result<(), Box<dyn Error>> {
+    let store = AsyncStore::from(Store::new()?);
+    let graph = NamedNode::new("http://example.com/g")?;
+    store
+        .load_graph(DATA.as_bytes(), GraphFormat::NTriples, graph.clone(), None)
+        .await?;
+    assert_eq!(store.store().len()?, 3);
+
+    let mut buffer = Vec::new();
+    store
+        .dump_graph(&mut buffer, GraphFormat::NTriples, graph.clone())
+        .await?;
+    let copy = AsyncStore::from(Store::new()?);
+    copy.load_graph(buffer.as_slice(), GraphFormat::NTriples, graph, None)
+        .await?;
+    assert_eq!(copy.store().len()?, 3);
+
+    let mut buffer = Vec::new();
+    store
+        .dump_dataset(&mut buffer, DatasetFormat::NQuads)
+        .await?;
+    let copy = AsyncStore::from(Store::new()?);
+    copy.load_dataset(buffer.as_slice(), DatasetFormat::NQuads, None)
+        .await?;
+    assert_eq!(copy.store().len()?, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_load_error() -> Result<(), Box<dyn Error>> {
+    let store = AsyncStore::from(Store::new()?);
+    assert!(store
+        .load_graph(
+            b"<http://example.com/s> foo .".as_ref(),
+            GraphFormat::NTriples,
+            GraphName::DefaultGraph,
+            None
+        )
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_query_and_update() -> Result<(), Box<dyn Error>> {
+    let store = AsyncStore::from(Store::new()?);
+    store
+        .load_graph(
+            DATA.as_bytes(),
+            GraphFormat::NTriples,
+            GraphName::DefaultGraph,
+            None,
+        )
+        .await?;
+
+    if let AsyncQueryResults::Solutions(mut solutions) = store
+        .query("SELECT ?o WHERE { ?s ?p ?o } ORDER BY ?o")
+        .await?
+    {
+        assert_eq!(solutions.variables(), [Variable::new("o")?]);
+        let mut values = Vec::new();
+        while let Some(solution) = solutions.next().await {
+            values.push(solution?.get("o").cloned());
+        }
+        assert_eq!(
+            values,
+            vec![
+                Some(Literal::from("1").into()),
+                Some(Literal::from("2").into()),
+                Some(Literal::from("3").into())
+            ]
+        );
+    } else {
+        panic!("SELECT queries should return solutions")
+    }
+
+    store.update("DELETE WHERE { ?s ?p \"1\" }").await?;
+    assert!(matches!(
+        store.query("ASK { ?s ?p \"1\" }").await?,
+        AsyncQueryResults::Boolean(false)
+    ));
+
+    if let AsyncQueryResults::Graph(triples) = store.query("CONSTRUCT WHERE { ?s ?p ?o }").await? {
+        assert_eq!(triples.collect::<Vec<_>>().await.len(), 2);
+    } else {
+        panic!("CONSTRUCT queries should return triples")
+    }
+
+    assert!(store.query("SELECT").await.is_err());
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use oxigraph::model::*;
+use oxigraph::sparql::QueryResults;
+use oxigraph::store::backend::{
+    column_families, Backend, BackendIter, BackendReader, BackendTransaction, ColumnFamily,
+};
+use oxigraph::store::{StorageError, Store};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+type Trees = Vec<BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// A naive backend keeping a copy of the data for each snapshot.
+struct MemoryBackend(Arc<Mutex<Trees>>);
+
+impl Backend for MemoryBackend {
+    fn snapshot(&self) -> Box<dyn BackendReader> {
+        Box::new(MemoryReader(Arc::new(Mutex::new(
+            self.0.lock().unwrap().clone(),
+        ))))
+    }
+
+    fn transaction(&self) -> Result<Box<dyn BackendTransaction>, StorageError> {
+        Ok(Box::new(MemoryTransaction {
+            db: self.0.clone(),
+            view: Arc::new(Mutex::new(self.0.lock().unwrap().clone())),
+            writes: Vec::new(),
+        }))
+    }
+
+    fn get(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.0.lock().unwrap()[column_family.index()]
+            .get(key)
+            .cloned())
+    }
+
+    fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.0.lock().unwrap()[column_family.index()].insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+}
+
+impl MemoryBackend {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(
+            column_families().iter().map(|_| BTreeMap::new()).collect(),
+        )))
+    }
+}
+
+struct MemoryReader(Arc<Mutex<Trees>>);
+
+impl BackendReader for MemoryReader {
+    fn get(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.0.lock().unwrap()[column_family.index()]
+            .get(key)
+            .cloned())
+    }
+
+    fn scan_prefix(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<Box<dyn BackendIter>, StorageError> {
+        let entries = self.0.lock().unwrap()[column_family.index()]
+            .range(prefix.to_vec()..)
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<_>>();
+        Ok(Box::new(MemoryIter {
+            entries,
+            position: 0,
+        }))
+    }
+}
+
+struct MemoryTransaction {
+    db: Arc<Mutex<Trees>>,
+    view: Arc<Mutex<Trees>>,
+    writes: Vec<(usize, Vec<u8>, Option<Vec<u8>>)>,
+}
+
+impl BackendTransaction for MemoryTransaction {
+    fn reader(&self) -> Box<dyn BackendReader> {
+        Box::new(MemoryReader(self.view.clone()))
+    }
+
+    fn contains_key_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        Ok(self.view.lock().unwrap()[column_family.index()].contains_key(key))
+    }
+
+    fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.view.lock().unwrap()[column_family.index()].insert(key.to_vec(), value.to_vec());
+        self.writes
+            .push((column_family.index(), key.to_vec(), Some(value.to_vec())));
+        Ok(())
+    }
+
+    fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+        self.view.lock().unwrap()[column_family.index()].remove(key);
+        self.writes
+            .push((column_family.index(), key.to_vec(), None));
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        let mut db = self.db.lock().unwrap();
+        for (column_family, key, value) in self.writes {
+            if let Some(value) = value {
+                db[column_family].insert(key, value);
+            } else {
+                db[column_family].remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+struct MemoryIter {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    position: usize,
+}
+
+impl BackendIter for MemoryIter {
+    fn key(&self) -> Option<&[u8]> {
+        Some(&self.entries.get(self.position)?.0)
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        Some(&self.entries.get(self.position)?.1)
+    }
+
+    fn next(&mut self) {
+        self.position += 1;
+    }
+
+    fn seek(&mut self, key: &[u8]) {
+        while self.key().map_or(false, |k| k < key) {
+            self.next();
+        }
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_backend() -> Result<(), Box<dyn Error>> {
+    let store = Store::from_backend(MemoryBackend::new())?;
+    let ex = NamedNodeRef::new_unchecked("http://example.com");
+    let graph = NamedNodeRef::new_unchecked("http://example.com/g");
+    let quad = QuadRef::new(ex, ex, ex, graph);
+
+    assert!(store.insert(quad)?);
+    assert!(!store.insert(quad)?);
+    assert!(store.contains(quad)?);
+    assert!(store.contains_named_graph(graph)?);
+    assert_eq!(store.len()?, 1);
+
+    // The transaction is rolled back on error
+    let result: Result<(), StorageError> = store.transaction(|mut t| {
+        t.remove(quad)?;
+        assert!(!t.contains(quad)?);
+        Err(StorageError::Other("rollback".into()))
+    });
+    assert!(result.is_err());
+    assert!(store.contains(quad)?);
+
+    if let QueryResults::Boolean(result) =
+        store.query("ASK { GRAPH <http://example.com/g> { ?s ?s ?s } }")?
+    {
+        assert!(result);
+    } else {
+        unreachable!()
+    }
+
+    assert!(store.remove(quad)?);
+    assert!(store.is_empty()?);
+    store.validate()?;
+    Ok(())
+}
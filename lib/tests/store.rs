@@ -1,15 +1,22 @@
+use flate2::{Compression, GzBuilder};
 use oxigraph::io::{DatasetFormat, GraphFormat};
 use oxigraph::model::vocab::{rdf, xsd};
 use oxigraph::model::*;
-use oxigraph::store::Store;
+use oxigraph::sparql::{EvaluationError, QueryOptions, QueryResults};
+use oxigraph::store::{CompressionType, StorageError, Store, StoreOptions};
 use rand::random;
+use std::collections::HashSet;
 use std::env::temp_dir;
 use std::error::Error;
 use std::fs::{create_dir, remove_dir_all, File};
 use std::io::{Cursor, Write};
 use std::iter::once;
 use std::path::PathBuf;
+#[cfg(all(target_os = "linux", feature = "rocksdb"))]
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::spawn;
 
 const DATA: &str = r#"
 @prefix schema: <http://schema.org/> .
@@ -174,6 +181,37 @@ fn test_bulk_load_dataset() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn test_bulk_load_dataset_in_parallel() -> Result<(), Box<dyn Error>> {
+    // The file is large enough to be split into several chunks
+    let mut file = Vec::new();
+    for i in 0..100_000 {
+        writeln!(
+            file,
+            "_:b{} <http://example.com/p> \"{}\" <http://example.com/g> .",
+            i % 10,
+            i
+        )?;
+    }
+    let store = Store::new()?;
+    for _ in 0..2 {
+        store.bulk_loader().set_num_threads(4).load_dataset(
+            Cursor::new(&file),
+            DatasetFormat::NQuads,
+            None,
+        )?;
+    }
+    assert_eq!(store.len()?, 200_000);
+    // The blank nodes are shared between the chunks but not between the loads
+    let subjects = store
+        .quads_for_pattern(None, None, None, None)
+        .map(|q| Ok(q?.subject))
+        .collect::<Result<HashSet<_>, StorageError>>()?;
+    assert_eq!(subjects.len(), 20);
+    store.validate()?;
+    Ok(())
+}
+
 #[test]
 fn test_load_graph_generates_new_blank_nodes() -> Result<(), Box<dyn Error>> {
     let store = Store::new()?;
@@ -225,6 +263,136 @@ fn test_dump_dataset() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn test_query_cancellation() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    store.bulk_loader().load_quads((0..1000).map(|i| {
+        Quad::new(
+            NamedNode::new_unchecked(format!("http://example.com/s{}", i)),
+            NamedNode::new_unchecked("http://example.com/p"),
+            Literal::from(i),
+            GraphName::DefaultGraph,
+        )
+    }))?;
+    let cancellation_flag = Arc::new(AtomicBool::new(false));
+    let options = QueryOptions::default().with_cancellation_flag(cancellation_flag.clone());
+    if let QueryResults::Solutions(mut solutions) =
+        store.query_opt("SELECT * WHERE { ?s ?p ?o }", options.clone())?
+    {
+        assert!(solutions.next().unwrap().is_ok());
+        cancellation_flag.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            solutions.next(),
+            Some(Err(EvaluationError::Cancelled))
+        ));
+        assert!(solutions.next().is_none());
+    } else {
+        panic!("SELECT should return solutions");
+    }
+    // The sort is stopped too
+    if let QueryResults::Solutions(mut solutions) =
+        store.query_opt("SELECT * WHERE { ?s ?p ?o } ORDER BY ?o", options)?
+    {
+        assert!(matches!(
+            solutions.next(),
+            Some(Err(EvaluationError::Cancelled))
+        ));
+    } else {
+        panic!("SELECT should return solutions");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_describe_concise_bounded_description() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    store.load_graph(
+        Cursor::new(
+            "<http://example.com/s> <http://example.com/p> _:b1 .
+             _:b1 <http://example.com/p> _:b2 .
+             _:b2 <http://example.com/p> <http://example.com/o> .
+             <http://example.com/o> <http://example.com/p> <http://example.com/o2> .",
+        ),
+        GraphFormat::NTriples,
+        GraphNameRef::DefaultGraph,
+        None,
+    )?;
+    if let QueryResults::Graph(triples) = store.query_opt(
+        "DESCRIBE <http://example.com/s>",
+        QueryOptions::default().with_concise_bounded_description(),
+    )? {
+        assert_eq!(triples.collect::<Result<Vec<_>, _>>()?.len(), 3);
+    } else {
+        panic!("DESCRIBE should return a graph");
+    }
+    // By default only the triples with the described resource as subject are returned
+    if let QueryResults::Graph(triples) = store.query("DESCRIBE <http://example.com/s>")? {
+        assert_eq!(triples.collect::<Result<Vec<_>, _>>()?.len(), 1);
+    } else {
+        panic!("DESCRIBE should return a graph");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_query_results_in_another_thread() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    for quad in quads(GraphNameRef::DefaultGraph) {
+        store.insert(quad)?;
+    }
+    let results = store.query("SELECT ?s WHERE { ?s ?p ?o }")?;
+    let count = spawn(move || {
+        if let QueryResults::Solutions(solutions) = results {
+            solutions.collect::<Result<Vec<_>, _>>().map(|s| s.len())
+        } else {
+            panic!("SELECT should return solutions")
+        }
+    })
+    .join()
+    .unwrap()?;
+    assert_eq!(count, quads(GraphNameRef::DefaultGraph).len());
+    Ok(())
+}
+
+#[test]
+fn test_parallel_query_evaluation() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    store.load_dataset(Cursor::new(GRAPH_DATA), DatasetFormat::TriG, None)?;
+    store.load_graph(
+        Cursor::new(DATA),
+        GraphFormat::Turtle,
+        GraphNameRef::DefaultGraph,
+        None,
+    )?;
+    for query in [
+        "SELECT * WHERE { ?s ?p ?o }",
+        "SELECT * WHERE { GRAPH ?g { ?s ?p ?o } }",
+        "SELECT * WHERE { { ?s ?p ?o } UNION { GRAPH ?g { ?s ?p ?o } } UNION { ?s a ?o } }",
+        "SELECT * WHERE { ?s ?p ?o . OPTIONAL { GRAPH ?g { ?s ?p2 ?o2 } } }",
+        "SELECT ?p (COUNT(*) AS ?c) (GROUP_CONCAT(STR(?o)) AS ?os) WHERE { ?s ?p ?o } GROUP BY ?p",
+        "SELECT (COUNT(*) AS ?c) WHERE { ?s ?p ?o }",
+    ] {
+        let solutions = |options: QueryOptions| -> Result<Vec<String>, Box<dyn Error>> {
+            if let QueryResults::Solutions(solutions) = store.query_opt(query, options)? {
+                let mut solutions = solutions
+                    .map(|s| Ok(format!("{:?}", s?.values())))
+                    .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+                solutions.sort();
+                Ok(solutions)
+            } else {
+                panic!("SELECT should return solutions")
+            }
+        };
+        assert_eq!(
+            solutions(QueryOptions::default())?,
+            solutions(QueryOptions::default().with_parallelism(4))?,
+            "{}",
+            query
+        );
+    }
+    Ok(())
+}
+
 #[test]
 fn test_snapshot_isolation_iterator() -> Result<(), Box<dyn Error>> {
     let quad = QuadRef::new(
@@ -245,6 +413,188 @@ fn test_snapshot_isolation_iterator() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn test_concurrent_reads_during_writes() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    let writer = {
+        let store = store.clone();
+        spawn(move || {
+            for i in 0..100 {
+                store.transaction(|mut t| {
+                    for j in 0..10 {
+                        t.insert(QuadRef::new(
+                            NamedNode::new_unchecked(format!("http://example.com/s{}", i)).as_ref(),
+                            NamedNode::new_unchecked(format!("http://example.com/p{}", j)).as_ref(),
+                            NamedNodeRef::new_unchecked("http://example.com/o"),
+                            GraphNameRef::DefaultGraph,
+                        ))?;
+                    }
+                    Ok::<_, StorageError>(())
+                })?;
+            }
+            Ok::<_, StorageError>(())
+        })
+    };
+    // Readers always see the quads of complete transactions
+    for _ in 0..100 {
+        assert_eq!(store.iter().count() % 10, 0);
+    }
+    writer.join().unwrap()?;
+    assert_eq!(store.len()?, 1000);
+    store.validate()?;
+    Ok(())
+}
+
+#[test]
+fn test_interactive_transaction() -> Result<(), Box<dyn Error>> {
+    let ex = NamedNodeRef::new_unchecked("http://example.com");
+    let quad = QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph);
+    let store = Store::new()?;
+
+    // Read your writes
+    let transaction = store.start_transaction()?;
+    transaction.transaction().insert(quad)?;
+    assert!(transaction.transaction().contains(quad)?);
+    if let QueryResults::Boolean(result) = transaction.transaction().query("ASK { ?s ?p ?o }")? {
+        assert!(result);
+    } else {
+        unreachable!()
+    }
+    assert!(!store.contains(quad)?);
+
+    // Commit from another thread
+    spawn(move || transaction.commit()).join().unwrap()?;
+    assert!(store.contains(quad)?);
+
+    // Rollback
+    let transaction = store.start_transaction()?;
+    transaction
+        .transaction()
+        .update("DELETE WHERE { ?s ?p ?o }")?;
+    assert!(transaction.transaction().is_empty()?);
+    transaction.rollback()?;
+    assert!(store.contains(quad)?);
+
+    // Drop
+    let transaction = store.start_transaction()?;
+    transaction.transaction().remove(quad)?;
+    drop(transaction);
+    assert!(store.contains(quad)?);
+    store.validate()?;
+    Ok(())
+}
+
+#[test]
+fn test_transaction_query_results_after_the_transaction_end() -> Result<(), Box<dyn Error>> {
+    let dir = TempDir::default();
+    let store = Store::open(&dir.0)?;
+    for quad in quads(GraphNameRef::DefaultGraph) {
+        store.insert(quad)?;
+    }
+    let results = store
+        .transaction(|transaction| transaction.query("SELECT ?s WHERE { ?s ?p ?o } LIMIT 1"))?;
+    let transaction = store.start_transaction()?;
+    let transaction_results = transaction
+        .transaction()
+        .query("SELECT ?s WHERE { ?s ?p ?o }")?;
+    transaction
+        .transaction()
+        .insert(quads(GraphNameRef::DefaultGraph)[0])?;
+    transaction.commit()?;
+    for results in [results, transaction_results] {
+        spawn(move || {
+            if let QueryResults::Solutions(mut solutions) = results {
+                // Depending on the backend, the ended transaction is either not readable anymore
+                // or its snapshot is still read
+                assert!(solutions
+                    .all(|solution| solution.map_or(true, |solution| solution.get("s").is_some())));
+            } else {
+                panic!("SELECT should return solutions")
+            }
+        })
+        .join()
+        .unwrap();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_interactive_transaction_conflict() -> Result<(), Box<dyn Error>> {
+    let ex = NamedNodeRef::new_unchecked("http://example.com");
+    let quad = QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph);
+    let store = Store::new()?;
+    let transaction1 = store.start_transaction()?;
+    transaction1.transaction().insert(quad)?;
+    let transaction2 = store.start_transaction()?;
+    let result2 = transaction2.transaction().insert(quad).map(|_| ());
+    transaction1.commit()?;
+    assert!(matches!(
+        result2.and_then(|()| transaction2.commit()),
+        Err(StorageError::Conflict)
+    ));
+    assert_eq!(store.len()?, 1);
+    store.validate()?;
+    Ok(())
+}
+
+#[test]
+fn test_len_and_statistics() -> Result<(), Box<dyn Error>> {
+    let store_dir = TempDir::default();
+    for store in [Store::new()?, Store::open(&store_dir.0)?] {
+        let graph = NamedNodeRef::new_unchecked("http://example.com/g");
+        let p = NamedNodeRef::new_unchecked("http://example.com/p");
+        // Enough transactions to merge the stored numbers of quads
+        for i in 0..300 {
+            let s = NamedNode::new(format!("http://example.com/s{}", i))?;
+            store.insert(QuadRef::new(&s, p, graph, GraphNameRef::DefaultGraph))?;
+            store.insert(QuadRef::new(&s, p, graph, graph))?;
+        }
+        assert_eq!(store.len()?, 600);
+        for i in 0..100 {
+            let s = NamedNode::new(format!("http://example.com/s{}", i))?;
+            store.remove(QuadRef::new(&s, p, graph, graph))?;
+        }
+        let transaction = store.start_transaction()?;
+        let mut writer = transaction.transaction();
+        writer.insert(QuadRef::new(p, p, p, graph))?;
+        writer.remove(QuadRef::new(p, p, p, graph))?;
+        drop(writer);
+        transaction.commit()?;
+        store.bulk_loader().load_quads((300..400).map(|i| {
+            Quad::new(
+                NamedNode::new_unchecked(format!("http://example.com/s{}", i)),
+                p,
+                graph,
+                GraphName::DefaultGraph,
+            )
+        }))?;
+        assert_eq!(store.len()?, 600);
+
+        let statistics = store.statistics()?;
+        assert_eq!(
+            statistics.graph_sizes,
+            vec![
+                (GraphName::DefaultGraph, 400),
+                (graph.into_owned().into(), 200)
+            ]
+        );
+        assert_eq!(statistics.distinct_subjects, 400);
+        assert_eq!(statistics.distinct_predicates, 1);
+        assert_eq!(statistics.distinct_objects, 1);
+        assert!(statistics.dictionary_size > 0);
+        store.validate()?;
+
+        // The bulk loaded quads are counted again after enough transactions
+        for i in 400..600 {
+            let s = NamedNode::new(format!("http://example.com/s{}", i))?;
+            store.insert(QuadRef::new(&s, p, graph, graph))?;
+        }
+        assert_eq!(store.len()?, 800);
+        store.validate()?;
+    }
+    Ok(())
+}
+
 #[test]
 fn test_bulk_load_on_existing_delete_overrides_the_delete() -> Result<(), Box<dyn Error>> {
     let quad = QuadRef::new(
@@ -260,6 +610,317 @@ fn test_bulk_load_on_existing_delete_overrides_the_delete() -> Result<(), Box<dy
     Ok(())
 }
 
+#[test]
+fn test_bulk_remove() -> Result<(), Box<dyn Error>> {
+    let store_dir = TempDir::default();
+    for store in [Store::new()?, Store::open(&store_dir.0)?] {
+        let graph =
+            NamedNodeRef::new_unchecked("http://www.wikidata.org/wiki/Special:EntityData/Q90");
+        store
+            .bulk_loader()
+            .load_graph(Cursor::new(DATA), GraphFormat::Turtle, graph, None)?;
+        store.bulk_loader().load_graph(
+            Cursor::new(DATA),
+            GraphFormat::Turtle,
+            GraphNameRef::DefaultGraph,
+            None,
+        )?;
+        let default_quads = quads(GraphNameRef::DefaultGraph);
+        let named_quads = quads(graph);
+
+        store
+            .bulk_loader()
+            .remove_quads([default_quads[0].into_owned(), named_quads[0].into_owned()])?;
+        assert!(!store.contains(default_quads[0])?);
+        assert!(!store.contains(named_quads[0])?);
+        assert!(store.contains(default_quads[1])?);
+        store.validate()?;
+
+        store.bulk_loader().remove_quads_for_pattern(
+            None,
+            Some(named_quads[1].predicate),
+            None,
+            None,
+        )?;
+        assert!(!store.contains(default_quads[1])?);
+        assert!(!store.contains(named_quads[1])?);
+        store.validate()?;
+
+        store.bulk_loader().clear_graph(graph)?;
+        assert!(store.contains_named_graph(graph)?);
+        for q in &named_quads {
+            assert!(!store.contains(*q)?);
+        }
+        for q in &default_quads[2..] {
+            if q.predicate != named_quads[1].predicate {
+                assert!(store.contains(*q)?);
+            }
+        }
+        store.validate()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bulk_replace_graph() -> Result<(), Box<dyn Error>> {
+    let store_dir = TempDir::default();
+    for store in [Store::new()?, Store::open(&store_dir.0)?] {
+        let graph =
+            NamedNodeRef::new_unchecked("http://www.wikidata.org/wiki/Special:EntityData/Q90");
+        let old = QuadRef::new(
+            NamedNodeRef::new_unchecked("http://example.com/s"),
+            NamedNodeRef::new_unchecked("http://example.com/p"),
+            NamedNodeRef::new_unchecked("http://example.com/o"),
+            graph,
+        );
+        let kept = quads(graph)[0];
+        let other = QuadRef::new(
+            old.subject,
+            old.predicate,
+            old.object,
+            GraphNameRef::DefaultGraph,
+        );
+        store.insert(old)?;
+        store.insert(kept)?;
+        store.insert(other)?;
+
+        store
+            .bulk_loader()
+            .replace_graph(Cursor::new(DATA), GraphFormat::Turtle, graph, None)?;
+        assert!(!store.contains(old)?);
+        for q in quads(graph) {
+            assert!(store.contains(q)?);
+        }
+        assert!(store.contains(other)?);
+        assert_eq!(store.len()?, quads(graph).len() + 1);
+        store.validate()?;
+
+        // A parsing error does not modify the graph
+        assert!(store
+            .bulk_loader()
+            .replace_graph(
+                Cursor::new("<http://example.com/s> <http://example.com/p> ."),
+                GraphFormat::NTriples,
+                graph,
+                None
+            )
+            .is_err());
+        assert_eq!(store.len()?, quads(graph).len() + 1);
+
+        // Neither does a replacement that does not fit in the memory limit
+        let big = (0..1001)
+            .map(|i| format!("<http://example.com/s{}> <http://example.com/p> 1 .\n", i))
+            .collect::<String>();
+        assert!(store
+            .bulk_loader()
+            .set_max_memory_size_in_megabytes(1)
+            .replace_graph(Cursor::new(big), GraphFormat::NTriples, graph, None)
+            .is_err());
+        assert_eq!(store.len()?, quads(graph).len() + 1);
+
+        store.bulk_loader().replace_graph(
+            Cursor::new(""),
+            GraphFormat::NTriples,
+            GraphNameRef::DefaultGraph,
+            None,
+        )?;
+        assert!(!store.contains(other)?);
+        assert_eq!(store.len()?, quads(graph).len());
+        store.validate()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bulk_load_resume_from_checkpoint() -> Result<(), Box<dyn Error>> {
+    fn file(size: usize, prefix: &str, error_at: Option<usize>) -> Vec<u8> {
+        let mut file = Vec::new();
+        for i in 0..size {
+            if error_at == Some(i) {
+                writeln!(file, "<http://example.com/s> <http://example.com/p> .").unwrap();
+            } else {
+                writeln!(
+                    file,
+                    "<http://example.com/{}{}> <http://example.com/p> <http://example.com/o> .",
+                    prefix, i
+                )
+                .unwrap();
+            }
+        }
+        file
+    }
+    let s = |prefix: &str, i: usize| {
+        QuadRef::new(
+            NamedNodeRef::new_unchecked(&format!("http://example.com/{}{}", prefix, i)),
+            NamedNodeRef::new_unchecked("http://example.com/p"),
+            NamedNodeRef::new_unchecked("http://example.com/o"),
+            GraphNameRef::DefaultGraph,
+        )
+        .into_owned()
+    };
+
+    let store = Store::new()?;
+    // The load fails after the first batches
+    assert!(store
+        .bulk_loader()
+        .set_max_memory_size_in_megabytes(1)
+        .with_checkpoint("test")
+        .load_graph(
+            Cursor::new(file(5000, "a", Some(2500))),
+            GraphFormat::NTriples,
+            GraphNameRef::DefaultGraph,
+            None
+        )
+        .is_err());
+    assert!(store.contains(&s("a", 0))?);
+    assert!(!store.contains(&s("a", 4999))?);
+
+    // The already loaded quads are skipped
+    store
+        .bulk_loader()
+        .set_max_memory_size_in_megabytes(1)
+        .resume_from_checkpoint("test")
+        .load_graph(
+            Cursor::new(file(5000, "b", None)),
+            GraphFormat::NTriples,
+            GraphNameRef::DefaultGraph,
+            None,
+        )?;
+    assert!(!store.contains(&s("b", 0))?);
+    assert!(store.contains(&s("b", 4999))?);
+    assert_eq!(store.len()?, 5000);
+    store.validate()?;
+
+    // The checkpoint is removed when the load succeeds
+    store
+        .bulk_loader()
+        .resume_from_checkpoint("test")
+        .load_graph(
+            Cursor::new(file(1, "c", None)),
+            GraphFormat::NTriples,
+            GraphNameRef::DefaultGraph,
+            None,
+        )?;
+    assert!(store.contains(&s("c", 0))?);
+    Ok(())
+}
+
+#[test]
+fn test_bulk_load_resume_keeps_blank_nodes() -> Result<(), Box<dyn Error>> {
+    let file = |error_at: Option<usize>| {
+        let mut file = Vec::new();
+        for i in 0..5000 {
+            if error_at == Some(i) {
+                writeln!(file, "<http://example.com/s> <http://example.com/p> .").unwrap();
+            } else if i == 0 || i == 4999 {
+                writeln!(file, "_:b <http://example.com/p> \"{}\" .", i).unwrap();
+            } else {
+                writeln!(
+                    file,
+                    "<http://example.com/s{}> <http://example.com/p> <http://example.com/o> .",
+                    i
+                )
+                .unwrap();
+            }
+        }
+        file
+    };
+
+    let store = Store::new()?;
+    assert!(store
+        .bulk_loader()
+        .set_max_memory_size_in_megabytes(1)
+        .with_checkpoint("test")
+        .load_graph(
+            Cursor::new(file(Some(2500))),
+            GraphFormat::NTriples,
+            GraphNameRef::DefaultGraph,
+            None
+        )
+        .is_err());
+    store
+        .bulk_loader()
+        .set_max_memory_size_in_megabytes(1)
+        .resume_from_checkpoint("test")
+        .load_graph(
+            Cursor::new(file(None)),
+            GraphFormat::NTriples,
+            GraphNameRef::DefaultGraph,
+            None,
+        )?;
+    let subjects = store
+        .quads_for_pattern(
+            None,
+            None,
+            Some(LiteralRef::new_simple_literal("0").into()),
+            None,
+        )
+        .chain(store.quads_for_pattern(
+            None,
+            None,
+            Some(LiteralRef::new_simple_literal("4999").into()),
+            None,
+        ))
+        .map(|q| Ok(q?.subject))
+        .collect::<Result<Vec<_>, StorageError>>()?;
+    assert_eq!(subjects.len(), 2);
+    assert!(subjects[0].is_blank_node());
+    assert_eq!(subjects[0], subjects[1]);
+    Ok(())
+}
+
+#[test]
+fn test_bulk_load_gzip() -> Result<(), Box<dyn Error>> {
+    fn gzip(content: &[u8], with_block_size: bool) -> Vec<u8> {
+        let builder = if with_block_size {
+            // The BGZF extra field with a block size to fill
+            GzBuilder::new().extra(vec![b'B', b'C', 2, 0, 0, 0])
+        } else {
+            GzBuilder::new()
+        };
+        let mut encoder = builder.write(Vec::new(), Compression::default());
+        encoder.write_all(content).unwrap();
+        let mut member = encoder.finish().unwrap();
+        if with_block_size {
+            let size = u16::try_from(member.len() - 1).unwrap().to_le_bytes();
+            member[16..18].copy_from_slice(&size);
+        }
+        member
+    }
+
+    // The file is large enough to be split into several chunks
+    let mut file = Vec::new();
+    for i in 0..100_000 {
+        writeln!(
+            file,
+            "_:b{} <http://example.com/p> \"{}\" <http://example.com/g> .",
+            i % 10,
+            i
+        )?;
+    }
+    for with_block_size in [true, false] {
+        // The members are split in the middle of the lines
+        let compressed = file
+            .chunks(1000)
+            .flat_map(|member| gzip(member, with_block_size))
+            .collect::<Vec<_>>();
+        let store = Store::new()?;
+        store.bulk_loader().set_num_threads(4).load_dataset(
+            Cursor::new(compressed),
+            DatasetFormat::NQuads,
+            None,
+        )?;
+        assert_eq!(store.len()?, 100_000);
+        let subjects = store
+            .iter()
+            .map(|q| Ok(q?.subject))
+            .collect::<Result<HashSet<_>, StorageError>>()?;
+        assert_eq!(subjects.len(), 10);
+        store.validate()?;
+    }
+    Ok(())
+}
+
 #[test]
 fn test_open_bad_dir() -> Result<(), Box<dyn Error>> {
     let dir = TempDir::default();
@@ -330,7 +991,149 @@ fn test_backup_on_in_memory() -> Result<(), Box<dyn Error>> {
 }
 
 #[test]
-#[cfg(target_os = "linux")]
+fn test_restore_from_backup() -> Result<(), Box<dyn Error>> {
+    let quad = QuadRef {
+        subject: NamedNodeRef::new_unchecked("http://example.com/s").into(),
+        predicate: NamedNodeRef::new_unchecked("http://example.com/p"),
+        object: NamedNodeRef::new_unchecked("http://example.com/o").into(),
+        graph_name: GraphNameRef::DefaultGraph,
+    };
+    let store_dir = TempDir::default();
+    let backup_dir = TempDir::default();
+    let restore_dir = TempDir::default();
+
+    let store = Store::open(&store_dir.0)?;
+    store.insert(quad)?;
+    store.backup(&backup_dir.0)?;
+    store.remove(quad)?;
+
+    Store::restore_from_backup(&backup_dir.0, &restore_dir.0)?;
+    let restored = Store::open(&restore_dir.0)?;
+    restored.validate()?;
+    assert!(restored.contains(quad)?);
+    assert!(Store::restore_from_backup(&backup_dir.0, &restore_dir.0).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_incremental_backup() -> Result<(), Box<dyn Error>> {
+    let first = QuadRef {
+        subject: NamedNodeRef::new_unchecked("http://example.com/s").into(),
+        predicate: NamedNodeRef::new_unchecked("http://example.com/p"),
+        object: NamedNodeRef::new_unchecked("http://example.com/o1").into(),
+        graph_name: GraphNameRef::DefaultGraph,
+    };
+    let second = QuadRef {
+        subject: NamedNodeRef::new_unchecked("http://example.com/s").into(),
+        predicate: NamedNodeRef::new_unchecked("http://example.com/p"),
+        object: NamedNodeRef::new_unchecked("http://example.com/o2").into(),
+        graph_name: GraphNameRef::DefaultGraph,
+    };
+    let store_dir = TempDir::default();
+    let backup_dir = TempDir::default();
+    let restore_dir = TempDir::default();
+
+    let store = Store::open(&store_dir.0)?;
+    store.insert(first)?;
+    store.incremental_backup(&backup_dir.0)?;
+    store.insert(second)?;
+    store.incremental_backup(&backup_dir.0)?;
+    Store::purge_incremental_backups(&backup_dir.0, 1)?;
+    store.remove(first)?;
+
+    Store::restore_from_backup(&backup_dir.0, &restore_dir.0)?;
+    let restored = Store::open(&restore_dir.0)?;
+    restored.validate()?;
+    assert!(restored.contains(first)?);
+    assert!(restored.contains(second)?);
+    Ok(())
+}
+
+#[test]
+fn test_incremental_backup_on_in_memory() -> Result<(), Box<dyn Error>> {
+    let backup_dir = TempDir::default();
+    assert!(Store::new()?.incremental_backup(&backup_dir.0).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_read_only() -> Result<(), Box<dyn Error>> {
+    let quad = QuadRef {
+        subject: NamedNodeRef::new_unchecked("http://example.com/s").into(),
+        predicate: NamedNodeRef::new_unchecked("http://example.com/p"),
+        object: NamedNodeRef::new_unchecked("http://example.com/o").into(),
+        graph_name: GraphNameRef::DefaultGraph,
+    };
+    let store_dir = TempDir::default();
+
+    {
+        let store = Store::open(&store_dir.0)?;
+        store.insert(quad)?;
+        store.flush()?;
+    }
+
+    // Two read-only stores on the same directory
+    let first = Store::open_read_only(&store_dir.0)?;
+    let second = Store::open_read_only(&store_dir.0)?;
+    assert!(first.contains(quad)?);
+    assert!(second.contains(quad)?);
+    assert!(first.remove(quad).is_err());
+    assert!(first.update("CLEAR ALL").is_err());
+    assert!(first
+        .bulk_loader()
+        .load_quads(once(quad.into_owned()))
+        .is_err());
+    assert!(second.contains(quad)?);
+    Ok(())
+}
+
+#[test]
+fn test_read_only_on_missing_dir() -> Result<(), Box<dyn Error>> {
+    let store_dir = TempDir::default();
+    assert!(Store::open_read_only(&store_dir.0).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_open_with_options() -> Result<(), Box<dyn Error>> {
+    let quad = QuadRef::new(
+        NamedNodeRef::new_unchecked("http://example.com/s"),
+        NamedNodeRef::new_unchecked("http://example.com/p"),
+        NamedNodeRef::new_unchecked("http://example.com/o"),
+        GraphNameRef::DefaultGraph,
+    );
+    let store_dir = TempDir::default();
+    let wal_dir = TempDir::default();
+    let options = StoreOptions::default()
+        .with_cache_size_in_megabytes(16)
+        .with_compression(CompressionType::None)
+        .with_column_family_compression("id2str", CompressionType::Lz4hc)
+        .with_max_open_files(64)
+        .with_wal_directory(&wal_dir.0)
+        .with_sync_writes();
+    {
+        let store = Store::open_with_options(&store_dir.0, &options)?;
+        store.insert(quad)?;
+        store.bulk_loader().load_quads(once(Quad::new(
+            NamedNode::new_unchecked("http://example.com/s2"),
+            quad.predicate,
+            quad.object,
+            GraphName::DefaultGraph,
+        )))?;
+        store.validate()?;
+    }
+    let store = Store::open_read_only_with_options(&store_dir.0, &options)?;
+    assert!(store.contains(quad)?);
+    assert_eq!(store.len()?, 2);
+
+    let unknown_column_family =
+        StoreOptions::default().with_column_family_compression("foo", CompressionType::None);
+    assert!(Store::open_with_options(&TempDir::default().0, &unknown_column_family).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "rocksdb"))]
 fn test_backward_compatibility() -> Result<(), Box<dyn Error>> {
     // We run twice to check if data is properly saved and closed
     for _ in 0..2 {
@@ -348,11 +1151,14 @@ fn test_backward_compatibility() -> Result<(), Box<dyn Error>> {
             vec![NamedOrBlankNode::from(graph_name)],
             store.named_graphs().collect::<Result<Vec<_>, _>>()?
         );
+        assert_eq!(store.len()?, 2 * NUMBER_OF_TRIPLES);
+        store.validate()?;
     }
     reset_dir("tests/rocksdb_bc_data")?;
     Ok(())
 }
 
+#[cfg(all(target_os = "linux", feature = "rocksdb"))]
 fn reset_dir(dir: &str) -> Result<(), Box<dyn Error>> {
     assert!(Command::new("git")
         .args(&["clean", "-fX", dir])
@@ -51,6 +51,55 @@ rocksdb_transactiondb_t* rocksdb_transactiondb_open_column_families_with_status(
     return result;
 }
 
+rocksdb_t* rocksdb_open_for_read_only_column_families_with_status(
+        const rocksdb_options_t* db_options, const char* name,
+        int num_column_families, const char* const* column_family_names,
+        const rocksdb_options_t* const* column_family_options,
+        rocksdb_column_family_handle_t** column_family_handles,
+        unsigned char error_if_wal_file_exists, rocksdb_status_t* statusptr) {
+    std::vector<ColumnFamilyDescriptor> column_families;
+    for (int i = 0; i < num_column_families; i++) {
+        column_families.push_back(ColumnFamilyDescriptor(
+                std::string(column_family_names[i]),
+                ColumnFamilyOptions(column_family_options[i]->rep)));
+    }
+
+    DB* db;
+    std::vector<ColumnFamilyHandle*> handles;
+    if (SaveStatus(statusptr, DB::OpenForReadOnly(DBOptions(db_options->rep),
+                                              std::string(name), column_families,
+                                              &handles, &db, error_if_wal_file_exists))) {
+        return nullptr;
+    }
+
+    for (size_t i = 0; i < handles.size(); i++) {
+        rocksdb_column_family_handle_t* c_handle =
+                new rocksdb_column_family_handle_t;
+        c_handle->rep = handles[i];
+        column_family_handles[i] = c_handle;
+    }
+    rocksdb_t* result = new rocksdb_t;
+    result->rep = db;
+    return result;
+}
+
+rocksdb_pinnableslice_t* rocksdb_get_pinned_cf_with_status(
+        rocksdb_t* db, const rocksdb_readoptions_t* options,
+        rocksdb_column_family_handle_t* column_family, const char* key,
+        size_t keylen, rocksdb_status_t* statusptr) {
+    rocksdb_pinnableslice_t* v = new rocksdb_pinnableslice_t;
+    Status s = db->rep->Get(options->rep, column_family->rep, Slice(key, keylen),
+                            &v->rep);
+    if (!s.ok()) {
+        delete v;
+        if (!s.IsNotFound()) {
+            SaveStatus(statusptr, s);
+        }
+        return nullptr;
+    }
+    return v;
+}
+
 rocksdb_pinnableslice_t* rocksdb_transactiondb_get_pinned_cf_with_status(
         rocksdb_transactiondb_t* db, const rocksdb_readoptions_t* options,
         rocksdb_column_family_handle_t* column_family, const char* key,
@@ -100,6 +149,12 @@ void rocksdb_transactiondb_compact_range_cf_opt_with_status(rocksdb_transactiond
             (limit_key ? (b = Slice(limit_key, limit_key_len), &b) : nullptr)));
 }
 
+int rocksdb_transactiondb_get_int_property_cf(rocksdb_transactiondb_t* db,
+                                  rocksdb_column_family_handle_t* column_family,
+                                  const char* propname, uint64_t* out_val) {
+    return db->rep->GetIntProperty(column_family->rep, Slice(propname), out_val) ? 0 : -1;
+}
+
 void rocksdb_transactiondb_ingest_external_files_with_status(
         rocksdb_transactiondb_t* db, const rocksdb_ingestexternalfilearg_t* list,
         const size_t list_len, rocksdb_status_t* statusptr) {
@@ -129,6 +184,39 @@ void rocksdb_transactiondb_create_checkpoint_with_status(
     delete checkpoint;
 }
 
+rocksdb_backup_engine_t* rocksdb_backup_engine_open_with_status(
+        const rocksdb_options_t* options, const char* path,
+        rocksdb_status_t* statusptr) {
+    BackupEngine* be;
+    if (SaveStatus(statusptr, BackupEngine::Open(options->rep.env,
+                                             BackupEngineOptions(path), &be))) {
+        return nullptr;
+    }
+    rocksdb_backup_engine_t* result = new rocksdb_backup_engine_t;
+    result->rep = be;
+    return result;
+}
+
+void rocksdb_backup_engine_create_new_backup_from_transactiondb_with_status(
+        rocksdb_backup_engine_t* be, rocksdb_transactiondb_t* db,
+        unsigned char flush_before_backup, rocksdb_status_t* statusptr) {
+    SaveStatus(statusptr, be->rep->CreateNewBackup(db->rep, flush_before_backup));
+}
+
+void rocksdb_backup_engine_purge_old_backups_with_status(
+        rocksdb_backup_engine_t* be, uint32_t num_backups_to_keep,
+        rocksdb_status_t* statusptr) {
+    SaveStatus(statusptr, be->rep->PurgeOldBackups(num_backups_to_keep));
+}
+
+void rocksdb_backup_engine_restore_db_from_latest_backup_with_status(
+        rocksdb_backup_engine_t* be, const char* db_dir, const char* wal_dir,
+        const rocksdb_restore_options_t* restore_options, rocksdb_status_t* statusptr) {
+    SaveStatus(statusptr, be->rep->RestoreDBFromLatestBackup(std::string(db_dir),
+                                                             std::string(wal_dir),
+                                                             restore_options->rep));
+}
+
 
 void rocksdb_transaction_commit_with_status(rocksdb_transaction_t* txn, rocksdb_status_t* statusptr) {
     SaveStatus(statusptr, txn->rep->Commit());
@@ -199,6 +287,11 @@ void rocksdb_sstfilewriter_put_with_status(rocksdb_sstfilewriter_t* writer, cons
     SaveStatus(statusptr, writer->rep->Put(Slice(key, keylen), Slice(val, vallen)));
 }
 
+void rocksdb_sstfilewriter_delete_with_status(rocksdb_sstfilewriter_t* writer, const char* key,
+                               size_t keylen, rocksdb_status_t* statusptr) {
+    SaveStatus(statusptr, writer->rep->Delete(Slice(key, keylen)));
+}
+
 void rocksdb_sstfilewriter_finish_with_status(rocksdb_sstfilewriter_t* writer,
                                               rocksdb_status_t* statusptr) {
     SaveStatus(statusptr, writer->rep->Finish(nullptr));
@@ -72,6 +72,18 @@ extern ROCKSDB_LIBRARY_API rocksdb_transactiondb_t* rocksdb_transactiondb_open_c
         const rocksdb_options_t* const* column_family_options,
         rocksdb_column_family_handle_t** column_family_handles, rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API rocksdb_t* rocksdb_open_for_read_only_column_families_with_status(
+        const rocksdb_options_t* options, const char* name, int num_column_families,
+        const char* const* column_family_names,
+        const rocksdb_options_t* const* column_family_options,
+        rocksdb_column_family_handle_t** column_family_handles,
+        unsigned char error_if_wal_file_exists, rocksdb_status_t* statusptr);
+
+
+extern ROCKSDB_LIBRARY_API rocksdb_pinnableslice_t* rocksdb_get_pinned_cf_with_status(
+        rocksdb_t* db, const rocksdb_readoptions_t* options,
+        rocksdb_column_family_handle_t* column_family, const char* key,
+        size_t keylen, rocksdb_status_t* statusptr);
 
 extern ROCKSDB_LIBRARY_API rocksdb_pinnableslice_t* rocksdb_transactiondb_get_pinned_cf_with_status(
         rocksdb_transactiondb_t* db, const rocksdb_readoptions_t* options,
@@ -92,6 +104,10 @@ extern ROCKSDB_LIBRARY_API void rocksdb_transactiondb_compact_range_cf_opt_with_
         rocksdb_compactoptions_t* opt, const char* start_key, size_t start_key_len,
         const char* limit_key, size_t limit_key_len, rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API int rocksdb_transactiondb_get_int_property_cf(
+        rocksdb_transactiondb_t* db, rocksdb_column_family_handle_t* column_family,
+        const char* propname, uint64_t* out_val);
+
 extern ROCKSDB_LIBRARY_API void rocksdb_transactiondb_ingest_external_files_with_status(
         rocksdb_transactiondb_t* db, const rocksdb_ingestexternalfilearg_t* list,
         const size_t list_len, rocksdb_status_t* statusptr);
@@ -99,6 +115,20 @@ extern ROCKSDB_LIBRARY_API void rocksdb_transactiondb_ingest_external_files_with
 extern ROCKSDB_LIBRARY_API void rocksdb_transactiondb_create_checkpoint_with_status(
         rocksdb_transactiondb_t* db, const char* checkpoint_dir, rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API rocksdb_backup_engine_t* rocksdb_backup_engine_open_with_status(
+        const rocksdb_options_t* options, const char* path, rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API void rocksdb_backup_engine_create_new_backup_from_transactiondb_with_status(
+        rocksdb_backup_engine_t* be, rocksdb_transactiondb_t* db,
+        unsigned char flush_before_backup, rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API void rocksdb_backup_engine_purge_old_backups_with_status(
+        rocksdb_backup_engine_t* be, uint32_t num_backups_to_keep, rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API void rocksdb_backup_engine_restore_db_from_latest_backup_with_status(
+        rocksdb_backup_engine_t* be, const char* db_dir, const char* wal_dir,
+        const rocksdb_restore_options_t* restore_options, rocksdb_status_t* statusptr);
+
 
 extern ROCKSDB_LIBRARY_API void rocksdb_transaction_commit_with_status(
         rocksdb_transaction_t* txn, rocksdb_status_t* statusptr);
@@ -132,6 +162,10 @@ extern ROCKSDB_LIBRARY_API void rocksdb_sstfilewriter_put_with_status(
         rocksdb_sstfilewriter_t* writer, const char* key, size_t keylen,
         const char* val, size_t vallen, rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API void rocksdb_sstfilewriter_delete_with_status(
+        rocksdb_sstfilewriter_t* writer, const char* key, size_t keylen,
+        rocksdb_status_t* statusptr);
+
 extern ROCKSDB_LIBRARY_API void rocksdb_sstfilewriter_finish_with_status(
         rocksdb_sstfilewriter_t* writer, rocksdb_status_t* statusptr);
 
@@ -10,7 +10,7 @@ use pyo3::prelude::*;
 
 /// Oxigraph Python bindings
 #[pymodule]
-fn pyoxigraph(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+fn pyoxigraph(py: Python<'_>, module: &PyModule) -> PyResult<()> {
     module.add("__package__", "pyoxigraph")?;
     module.add("__version__", env!("CARGO_PKG_VERSION"))?;
     module.add("__author__", env!("CARGO_PKG_AUTHORS").replace(':', "\n"))?;
@@ -22,6 +22,8 @@ fn pyoxigraph(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
     module.add_class::<PyTriple>()?;
     module.add_class::<PyQuad>()?;
     module.add_class::<PyStore>()?;
+    module.add_class::<PyTransaction>()?;
+    module.add("ConflictError", py.get_type::<ConflictError>())?;
     module.add_class::<PyVariable>()?;
     module.add_class::<PyQuerySolutions>()?;
     module.add_class::<PyQuerySolution>()?;
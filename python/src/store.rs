@@ -6,11 +6,16 @@ use crate::sparql::*;
 use oxigraph::io::{DatasetFormat, GraphFormat};
 use oxigraph::model::{GraphName, GraphNameRef};
 use oxigraph::sparql::Update;
-use oxigraph::store::{self, LoaderError, SerializerError, StorageError, Store};
+use oxigraph::store::{
+    self, InteractiveTransaction, LoaderError, SerializerError, StorageError, Store, Transaction,
+};
+use pyo3::create_exception;
 use pyo3::exceptions::{PyIOError, PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::{Py, PyRef};
 
+create_exception!(pyoxigraph, ConflictError, PyRuntimeError);
+
 /// RDF store.
 ///
 /// It encodes a `RDF dataset <https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset>`_ and allows to query it using SPARQL.
@@ -21,8 +26,7 @@ use pyo3::{Py, PyRef};
 /// of a read operation (e.g. a SPARQL query) or a read/write operation (e.g. a SPARQL update).
 ///
 /// :param path: the path of the directory in which the store should read and write its data. If the directory does not exist, it is created.
-///              If no directory is provided a temporary one is created and removed when the Python garbage collector removes the store.
-///              In this case, the store data are kept in memory and never written on disk.
+///              If no directory is provided the store data are kept in memory and never written on disk.
 /// :type path: str or None, optional.
 /// :raises IOError: if the target directory contains invalid data or could not be accessed.
 ///
@@ -200,6 +204,34 @@ impl PyStore {
         query_results_to_python(py, results)
     }
 
+    /// Starts a transaction.
+    ///
+    /// The transaction sees its own writes and its writes become visible at once when it is committed.
+    /// It is usable as a context manager: it is committed if the ``with`` block ends normally and rolled back if an exception is raised.
+    ///
+    /// If the transaction conflicts with a concurrent one, its operations or its commit raise a :py:class:`ConflictError`.
+    /// The transaction should then be started again.
+    ///
+    /// :return: the new transaction.
+    /// :rtype: Transaction
+    /// :raises IOError: if an I/O error happens while starting the transaction.
+    ///
+    /// >>> store = Store()
+    /// >>> with store.transaction() as transaction:
+    /// ...     transaction.add(Quad(NamedNode('http://example.com'), NamedNode('http://example.com/p'), Literal('1')))
+    /// ...     len(store), len(transaction)
+    /// (0, 1)
+    /// >>> len(store)
+    /// 1
+    #[pyo3(text_signature = "($self)")]
+    fn transaction(&self, py: Python<'_>) -> PyResult<PyTransaction> {
+        py.allow_threads(|| {
+            Ok(PyTransaction {
+                inner: Some(self.inner.start_transaction().map_err(map_storage_error)?),
+            })
+        })
+    }
+
     /// Executes a `SPARQL 1.1 update <https://www.w3.org/TR/sparql11-update/>`_.
     ///
     /// Updates are applied in a transactional manner: either the full operation succeeds or nothing is written to the database.
@@ -611,7 +643,7 @@ impl PyStore {
     /// like a regular pyxigraph database and operates independently from the original database.
     ///
     /// Warning: Backups are only possible for on-disk databases created by providing a path to :py:class:`Store` constructor.
-    /// Temporary in-memory databases created without path are not compatible with the backup system.
+    /// In-memory databases created without path are not compatible with the backup system.
     ///
     /// Warning: An error is raised if the ``target_directory`` already exists.
     ///
@@ -657,7 +689,207 @@ impl PyStore {
     }
 }
 
-#[pyclass(unsendable, module = "oxigraph")]
+/// A transaction on a :py:class:`Store` created by :py:func:`Store.transaction`.
+///
+/// It is rolled back if it is garbage collected without being committed.
+///
+/// >>> store = Store()
+/// >>> transaction = store.transaction()
+/// >>> transaction.update('INSERT DATA { <http://example.com> <http://example.com/p> "1" }')
+/// >>> transaction.query('ASK { ?s ?p ?o }'), store.query('ASK { ?s ?p ?o }')
+/// (True, False)
+/// >>> transaction.commit()
+/// >>> store.query('ASK { ?s ?p ?o }')
+/// True
+#[pyclass(name = "Transaction", module = "oxigraph")]
+pub struct PyTransaction {
+    inner: Option<InteractiveTransaction>,
+}
+
+impl PyTransaction {
+    fn inner(&self) -> PyResult<Transaction<'_>> {
+        Ok(self
+            .inner
+            .as_ref()
+            .ok_or_else(ended_transaction_error)?
+            .transaction())
+    }
+
+    fn take(&mut self) -> PyResult<InteractiveTransaction> {
+        self.inner.take().ok_or_else(ended_transaction_error)
+    }
+}
+
+#[pymethods]
+impl PyTransaction {
+    /// Adds a quad to the store in the transaction.
+    ///
+    /// :param quad: the quad to add.
+    /// :type quad: Quad
+    /// :raises IOError: if an I/O error happens during the quad insertion.
+    /// :raises ConflictError: if the transaction conflicts with a concurrent one.
+    #[pyo3(text_signature = "($self, quad)")]
+    fn add(&mut self, quad: &PyQuad, py: Python<'_>) -> PyResult<()> {
+        let mut inner = self.inner()?;
+        py.allow_threads(|| {
+            inner.insert(quad).map_err(map_storage_error)?;
+            Ok(())
+        })
+    }
+
+    /// Removes a quad from the store in the transaction.
+    ///
+    /// :param quad: the quad to remove.
+    /// :type quad: Quad
+    /// :raises IOError: if an I/O error happens during the quad removal.
+    /// :raises ConflictError: if the transaction conflicts with a concurrent one.
+    #[pyo3(text_signature = "($self, quad)")]
+    fn remove(&mut self, quad: &PyQuad, py: Python<'_>) -> PyResult<()> {
+        let mut inner = self.inner()?;
+        py.allow_threads(|| {
+            inner.remove(quad).map_err(map_storage_error)?;
+            Ok(())
+        })
+    }
+
+    /// Looks for the quads matching a given pattern, including the ones written by the transaction.
+    ///
+    /// See :py:func:`Store.quads_for_pattern` for the parameters.
+    #[pyo3(text_signature = "($self, subject, predicate, object, graph_name = None)")]
+    fn quads_for_pattern(
+        &self,
+        subject: &PyAny,
+        predicate: &PyAny,
+        object: &PyAny,
+        graph_name: Option<&PyAny>,
+    ) -> PyResult<QuadIter> {
+        let (subject, predicate, object, graph_name) =
+            extract_quads_pattern(subject, predicate, object, graph_name)?;
+        Ok(QuadIter {
+            inner: self.inner()?.quads_for_pattern(
+                subject.as_ref().map(|p| p.into()),
+                predicate.as_ref().map(|p| p.into()),
+                object.as_ref().map(|p| p.into()),
+                graph_name.as_ref().map(|p| p.into()),
+            ),
+        })
+    }
+
+    /// Executes a `SPARQL 1.1 query <https://www.w3.org/TR/sparql11-query/>`_ that sees the writes done by the transaction.
+    ///
+    /// See :py:func:`Store.query` for the parameters.
+    #[pyo3(
+        text_signature = "($self, query, *, base_iri, use_default_graph_as_union, default_graph, named_graphs)"
+    )]
+    #[args(
+        query,
+        "*",
+        base_iri = "None",
+        use_default_graph_as_union = "false",
+        default_graph = "None",
+        named_graphs = "None"
+    )]
+    fn query(
+        &self,
+        query: &str,
+        base_iri: Option<&str>,
+        use_default_graph_as_union: bool,
+        default_graph: Option<&PyAny>,
+        named_graphs: Option<&PyAny>,
+        py: Python<'_>,
+    ) -> PyResult<PyObject> {
+        let query = parse_query(
+            query,
+            base_iri,
+            use_default_graph_as_union,
+            default_graph,
+            named_graphs,
+        )?;
+        let inner = self.inner()?;
+        let results = allow_threads_unsafe(|| inner.query(query)).map_err(map_evaluation_error)?;
+        query_results_to_python(py, results)
+    }
+
+    /// Executes a `SPARQL 1.1 update <https://www.w3.org/TR/sparql11-update/>`_ in the transaction.
+    ///
+    /// :param update: the update to execute.
+    /// :type update: str
+    /// :param base_iri: the base IRI used to resolve the relative IRIs in the SPARQL update or :py:const:`None` if relative IRI resolution should not be done.
+    /// :type base_iri: str or None, optional
+    /// :raises SyntaxError: if the provided update is invalid.
+    /// :raises IOError: if an I/O error happens while reading the store.
+    /// :raises ConflictError: if the transaction conflicts with a concurrent one.
+    #[pyo3(text_signature = "($self, update, *, base_iri)")]
+    #[args(update, "*", base_iri = "None")]
+    fn update(&mut self, update: &str, base_iri: Option<&str>, py: Python<'_>) -> PyResult<()> {
+        let mut inner = self.inner()?;
+        py.allow_threads(|| {
+            let update =
+                Update::parse(update, base_iri).map_err(|e| map_evaluation_error(e.into()))?;
+            inner.update(update).map_err(map_evaluation_error)
+        })
+    }
+
+    /// Commits the transaction: all its writes become visible at once.
+    ///
+    /// :raises IOError: if an I/O error happens during the commit.
+    /// :raises ConflictError: if the transaction conflicts with a concurrent one. Nothing is written in this case.
+    #[pyo3(text_signature = "($self)")]
+    fn commit(&mut self, py: Python<'_>) -> PyResult<()> {
+        let inner = self.take()?;
+        py.allow_threads(|| inner.commit().map_err(map_storage_error))
+    }
+
+    /// Rolls back the transaction: none of its writes is kept.
+    ///
+    /// :raises IOError: if an I/O error happens during the rollback.
+    #[pyo3(text_signature = "($self)")]
+    fn rollback(&mut self, py: Python<'_>) -> PyResult<()> {
+        let inner = self.take()?;
+        py.allow_threads(|| inner.rollback().map_err(map_storage_error))
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> Py<Self> {
+        slf.into()
+    }
+
+    fn __exit__(
+        &mut self,
+        exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+        py: Python<'_>,
+    ) -> PyResult<bool> {
+        if self.inner.is_some() {
+            if exc_type.map_or(true, |t| t.is_none()) {
+                self.commit(py)?;
+            } else {
+                self.rollback(py)?;
+            }
+        }
+        Ok(false) // We do not suppress the exception
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        self.inner()?.len().map_err(map_storage_error)
+    }
+
+    fn __contains__(&self, quad: PyQuad) -> PyResult<bool> {
+        self.inner()?.contains(&quad).map_err(map_storage_error)
+    }
+
+    fn __iter__(&self) -> PyResult<QuadIter> {
+        Ok(QuadIter {
+            inner: self.inner()?.iter(),
+        })
+    }
+}
+
+fn ended_transaction_error() -> PyErr {
+    PyRuntimeError::new_err("The transaction is already ended")
+}
+
+#[pyclass(module = "oxigraph")]
 pub struct QuadIter {
     inner: store::QuadIter,
 }
@@ -676,7 +908,7 @@ impl QuadIter {
     }
 }
 
-#[pyclass(unsendable, module = "oxigraph")]
+#[pyclass(module = "oxigraph")]
 pub struct GraphNameIter {
     inner: store::GraphNameIter,
 }
@@ -737,6 +969,7 @@ pub fn extract_quads_pattern<'a>(
 pub(crate) fn map_storage_error(error: StorageError) -> PyErr {
     match error {
         StorageError::Io(error) => PyIOError::new_err(error.to_string()),
+        StorageError::Conflict => ConflictError::new_err(error.to_string()),
         _ => PyRuntimeError::new_err(error.to_string()),
     }
 }
@@ -180,6 +180,42 @@ class TestStore(unittest.TestCase):
         results = store.query('PREFIX : <http://www.example.org/> SELECT ?p ?a WHERE { ?p :claims << :bob :age ?a >> }')
         self.assertEqual(len(list(results)), 1)
 
+    def test_transaction_commit(self):
+        store = Store()
+        with store.transaction() as transaction:
+            transaction.add(Quad(foo, bar, baz))
+            self.assertIn(Quad(foo, bar, baz), transaction)
+            self.assertTrue(transaction.query("ASK { ?s ?p ?o }"))
+            self.assertEqual(len(store), 0)
+        self.assertEqual(len(store), 1)
+
+    def test_transaction_rollback(self):
+        store = Store()
+        store.add(Quad(foo, bar, baz))
+        with self.assertRaises(ValueError):
+            with store.transaction() as transaction:
+                transaction.update("DELETE WHERE { ?s ?p ?o }")
+                self.assertEqual(len(transaction), 0)
+                raise ValueError()
+        self.assertEqual(len(store), 1)
+        transaction = store.transaction()
+        transaction.remove(Quad(foo, bar, baz))
+        transaction.rollback()
+        with self.assertRaises(RuntimeError):
+            transaction.commit()
+        self.assertEqual(len(store), 1)
+
+    def test_transaction_conflict(self):
+        store = Store()
+        transaction1 = store.transaction()
+        transaction1.add(Quad(foo, bar, baz))
+        transaction2 = store.transaction()
+        with self.assertRaises(ConflictError):
+            transaction2.add(Quad(foo, bar, baz))
+            transaction1.commit()
+            transaction2.commit()
+        self.assertEqual(len(store), 1)
+
     def test_load_ntriples_to_default_graph(self):
         store = Store()
         store.load(
@@ -0,0 +1,151 @@
+//! Backups of the default store, created on demand through `POST /backup` or periodically.
+
+use crate::logging::Logger;
+use crate::{error, internal_server_error};
+use oxhttp::model::{Request, Response, Status};
+use oxigraph::store::Store;
+use std::fs::{create_dir_all, read_dir, remove_dir_all};
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, Builder};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Prefix of the directories containing the checkpoint backups. It is followed by the creation time in milliseconds.
+const CHECKPOINT_PREFIX: &str = "backup-";
+
+/// Creates the backups of a store inside of a directory.
+pub struct Backups {
+    directory: PathBuf,
+    /// The number of backups to keep or `None` to keep all of them.
+    retention: Option<u32>,
+    /// If RocksDB backup engine is used instead of checkpoints.
+    incremental: bool,
+    /// Avoids running two backups at the same time.
+    running: Mutex<()>,
+}
+
+impl Backups {
+    pub fn new(directory: PathBuf, retention: Option<u32>, incremental: bool) -> io::Result<Self> {
+        create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            retention: retention.map(|retention| retention.max(1)),
+            incremental,
+            running: Mutex::new(()),
+        })
+    }
+
+    /// Creates a new backup of the store and then removes the oldest backups exceeding the retention.
+    ///
+    /// Checkpoints are created in their own `backup-{milliseconds}` sub-directory
+    /// whereas incremental backups all share the backup directory.
+    pub fn create(&self, store: &Store) -> io::Result<()> {
+        let _running = self.running.lock().unwrap();
+        if self.incremental {
+            store.incremental_backup(&self.directory)?;
+            if let Some(retention) = self.retention {
+                Store::purge_incremental_backups(&self.directory, retention)?;
+            }
+        } else {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?
+                .as_millis();
+            store.backup(
+                self.directory
+                    .join(format!("{}{}", CHECKPOINT_PREFIX, timestamp)),
+            )?;
+            if let Some(retention) = self.retention {
+                self.remove_old_checkpoints(retention)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_old_checkpoints(&self, retention: u32) -> io::Result<()> {
+        let mut checkpoints = Vec::new();
+        for entry in read_dir(&self.directory)? {
+            let entry = entry?;
+            if let Some(timestamp) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(CHECKPOINT_PREFIX))
+                .and_then(|timestamp| u128::from_str(timestamp).ok())
+            {
+                checkpoints.push((timestamp, entry.path()));
+            }
+        }
+        checkpoints.sort_unstable();
+        let to_remove = checkpoints
+            .len()
+            .saturating_sub(retention.try_into().unwrap_or(usize::MAX));
+        for (_, path) in checkpoints.into_iter().take(to_remove) {
+            remove_dir_all(path)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a new backup of the store every `interval` in a background thread.
+    ///
+    /// The failures are reported using the `logger`.
+    pub fn schedule(
+        self: Arc<Self>,
+        store: Store,
+        interval: Duration,
+        logger: Arc<Logger>,
+    ) -> io::Result<()> {
+        Builder::new()
+            .name("oxigraph-backup".into())
+            .spawn(move || loop {
+                sleep(interval);
+                if let Err(e) = self.create(&store) {
+                    logger.log_error(&format!("Error while creating a scheduled backup: {}", e));
+                }
+            })?;
+        Ok(())
+    }
+}
+
+/// Handles `POST /backup` requests that create a new backup of the default store.
+///
+/// `admin_required` is returned if the request is not authenticated as an administrator.
+pub fn handle_backup_request(
+    request: &Request,
+    store: &Store,
+    backups: Option<&Backups>,
+    read_only: bool,
+    admin_required: Option<Response>,
+) -> Response {
+    if request.method().as_ref() != "POST" {
+        return error(
+            Status::METHOD_NOT_ALLOWED,
+            format!(
+                "{} /backup is not supported by this server",
+                request.method()
+            ),
+        );
+    }
+    if let Some(response) = admin_required {
+        return response;
+    }
+    if read_only {
+        return error(
+            Status::FORBIDDEN,
+            "The server is read-only: backups are not allowed",
+        );
+    }
+    let backups = if let Some(backups) = backups {
+        backups
+    } else {
+        return error(
+            Status::NOT_FOUND,
+            "Backups are not enabled on this server, use the --backup-directory option to enable them",
+        );
+    };
+    match backups.create(store) {
+        Ok(()) => Response::builder(Status::NO_CONTENT).build(),
+        Err(e) => internal_server_error(e),
+    }
+}
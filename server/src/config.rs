@@ -0,0 +1,126 @@
+use oxigraph::store::{CompressionType, StoreOptions};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The server configuration.
+///
+/// It is read from a TOML file given with the `--config` option.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Directory in which the datasets created using the `/datasets` API are persisted.
+    ///
+    /// If not set, these datasets are kept in memory.
+    pub datasets_location: Option<PathBuf>,
+    /// The named datasets served in addition to the default one.
+    #[serde(default, rename = "dataset")]
+    pub datasets: Vec<DatasetConfig>,
+    /// Token to give with the `Authorization: Bearer` header to use the administration operations
+    /// like creating and deleting datasets.
+    ///
+    /// If not set, these operations are disabled.
+    pub admin_token: Option<String>,
+    /// The keys accepted in the `X-API-Key` header to identify the clients of the rate limiter.
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// If the server is behind a reverse proxy that appends the client address to the `X-Forwarded-For` header.
+    ///
+    /// The rate limiter then identifies the clients without an API key by this address.
+    #[serde(default)]
+    pub trusted_proxy: bool,
+    /// The storage tuning used by all the persistent stores of the server.
+    #[serde(default)]
+    pub storage: StorageConfig,
+}
+
+/// The configuration of a named dataset.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatasetConfig {
+    /// The dataset name, used in the `/datasets/{name}` URLs.
+    pub name: String,
+    /// Directory in which the dataset is persisted.
+    ///
+    /// If not set, the dataset is kept in memory.
+    pub location: Option<PathBuf>,
+}
+
+/// The tuning of the on-disk storage.
+///
+/// The fields that are not set keep their default values.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Size of the block cache in megabytes.
+    pub cache_size: Option<usize>,
+    /// Compression algorithm of all the column families: "none", "lz4" or "lz4hc".
+    pub compression: Option<String>,
+    /// Compression algorithm overrides by column family name.
+    #[serde(default)]
+    pub column_family_compression: HashMap<String, String>,
+    /// Maximal number of files kept open at the same time.
+    pub max_open_files: Option<u32>,
+    /// Directory in which the write-ahead log is written.
+    pub wal_directory: Option<PathBuf>,
+    /// If each write should be synced to disk before returning.
+    #[serde(default)]
+    pub sync_writes: bool,
+}
+
+impl StorageConfig {
+    /// Builds the options to open the stores with.
+    pub fn options(&self) -> io::Result<StoreOptions> {
+        let mut options = StoreOptions::default();
+        if let Some(cache_size) = self.cache_size {
+            options = options.with_cache_size_in_megabytes(cache_size);
+        }
+        if let Some(compression) = &self.compression {
+            options = options.with_compression(compression_type(compression)?);
+        }
+        for (column_family, compression) in &self.column_family_compression {
+            options = options
+                .with_column_family_compression(column_family, compression_type(compression)?);
+        }
+        if let Some(max_open_files) = self.max_open_files {
+            options = options.with_max_open_files(max_open_files);
+        }
+        if let Some(wal_directory) = &self.wal_directory {
+            options = options.with_wal_directory(wal_directory);
+        }
+        if self.sync_writes {
+            options = options.with_sync_writes();
+        }
+        Ok(options)
+    }
+}
+
+fn compression_type(name: &str) -> io::Result<CompressionType> {
+    CompressionType::from_name(name).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "The compression algorithm {} is not supported, use none, lz4 or lz4hc",
+                name
+            ),
+        )
+    })
+}
+
+impl Config {
+    /// Reads the configuration file if there is one or returns the default configuration.
+    pub fn read(file: Option<&Path>) -> io::Result<Self> {
+        if let Some(file) = file {
+            toml::from_str(&fs::read_to_string(file)?).map_err(|e| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid configuration file {}: {}", file.display(), e),
+                )
+            })
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
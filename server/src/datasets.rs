@@ -0,0 +1,227 @@
+use crate::config::Config;
+use oxigraph::store::{Store, StoreOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// The named datasets hosted by the server in addition to the default one.
+#[derive(Default)]
+pub struct Datasets {
+    datasets: RwLock<HashMap<String, Dataset>>,
+    /// Directory in which the datasets created with [`Datasets::create`] are persisted.
+    location: Option<PathBuf>,
+    /// The options used to open the persistent datasets.
+    options: StoreOptions,
+}
+
+struct Dataset {
+    store: Store,
+    /// If the dataset has been created with [`Datasets::create`] and so could be deleted with [`Datasets::delete`].
+    managed: bool,
+    lease: DatasetLease,
+}
+
+/// Marks a dataset as being used, for example by a request, a query job or a transaction.
+///
+/// A dataset could not be deleted while some leases on it are alive.
+#[derive(Clone, Default)]
+pub struct DatasetLease(Arc<()>);
+
+/// The outcome of [`Datasets::delete`].
+pub enum DatasetDeletion {
+    Deleted,
+    NotFound,
+    /// The dataset is still used and so has not been deleted.
+    InUse,
+}
+
+impl Datasets {
+    /// Opens the datasets listed in the configuration and the ones previously created in the configured datasets location.
+    pub fn open(config: &Config, read_only: bool) -> io::Result<Self> {
+        let options = config.storage.options()?;
+        let mut datasets = HashMap::new();
+        for dataset in &config.datasets {
+            check_name(&dataset.name)?;
+            let store = match (&dataset.location, read_only) {
+                (Some(location), false) => Store::open_with_options(location, &options)?,
+                (Some(location), true) => Store::open_read_only_with_options(location, &options)?,
+                (None, false) => Store::new()?,
+                (None, true) => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "The dataset {} should have a location to be opened in read-only mode",
+                            dataset.name
+                        ),
+                    ))
+                }
+            };
+            if datasets
+                .insert(
+                    dataset.name.clone(),
+                    Dataset {
+                        store,
+                        managed: false,
+                        lease: DatasetLease::default(),
+                    },
+                )
+                .is_some()
+            {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The dataset {} is declared multiple times", dataset.name),
+                ));
+            }
+        }
+        if let Some(location) = &config.datasets_location {
+            if location.exists() {
+                for entry in fs::read_dir(location)? {
+                    let entry = entry?;
+                    if !entry.file_type()?.is_dir() {
+                        continue;
+                    }
+                    let name = if let Ok(name) = entry.file_name().into_string() {
+                        name
+                    } else {
+                        continue;
+                    };
+                    if !is_valid_name(&name) {
+                        continue;
+                    }
+                    if datasets.contains_key(&name) {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidInput,
+                            format!(
+                                "The dataset {} is both declared in the configuration and stored in {}",
+                                name,
+                                location.display()
+                            ),
+                        ));
+                    }
+                    let store = if read_only {
+                        Store::open_read_only_with_options(entry.path(), &options)?
+                    } else {
+                        Store::open_with_options(entry.path(), &options)?
+                    };
+                    datasets.insert(
+                        name,
+                        Dataset {
+                            store,
+                            managed: true,
+                            lease: DatasetLease::default(),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(Self {
+            datasets: RwLock::new(datasets),
+            location: config.datasets_location.clone(),
+            options,
+        })
+    }
+
+    /// Returns the store of the dataset with the given name if it exists and a lease preventing its deletion.
+    pub fn get(&self, name: &str) -> Option<(Store, DatasetLease)> {
+        self.datasets
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|dataset| (dataset.store.clone(), dataset.lease.clone()))
+    }
+
+    /// Returns the names of all the datasets in lexicographic order.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self
+            .datasets
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Creates a new empty dataset.
+    ///
+    /// It is persisted in the datasets location if there is one and kept in memory if not.
+    ///
+    /// Returns `false` if the dataset already exists.
+    pub fn create(&self, name: &str) -> io::Result<bool> {
+        check_name(name)?;
+        let mut datasets = self.datasets.write().unwrap();
+        if datasets.contains_key(name) {
+            return Ok(false);
+        }
+        let store = if let Some(location) = &self.location {
+            fs::create_dir_all(location)?;
+            Store::open_with_options(location.join(name), &self.options)?
+        } else {
+            Store::new()?
+        };
+        datasets.insert(
+            name.to_owned(),
+            Dataset {
+                store,
+                managed: true,
+                lease: DatasetLease::default(),
+            },
+        );
+        Ok(true)
+    }
+
+    /// Deletes a dataset created with [`Datasets::create`] and its data.
+    ///
+    /// Datasets declared in the configuration file could not be deleted.
+    /// The dataset is not deleted if some [`DatasetLease`] on it are alive:
+    /// its store is then still open and its data directory could not be removed.
+    pub fn delete(&self, name: &str) -> io::Result<DatasetDeletion> {
+        let mut datasets = self.datasets.write().unwrap();
+        match datasets.get(name) {
+            Some(dataset) if !dataset.managed => {
+                return Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("The dataset {} is not deletable", name),
+                ))
+            }
+            // New leases are only given while holding the read lock so the count could not increase here
+            Some(dataset) if Arc::strong_count(&dataset.lease.0) > 1 => {
+                return Ok(DatasetDeletion::InUse)
+            }
+            Some(_) => (),
+            None => return Ok(DatasetDeletion::NotFound),
+        }
+        let dataset = datasets.remove(name);
+        // The store must be closed before its directory is removed
+        drop(dataset);
+        if let Some(location) = &self.location {
+            fs::remove_dir_all(location.join(name))?;
+        }
+        Ok(DatasetDeletion::Deleted)
+    }
+}
+
+/// Dataset names are restricted to ASCII letters, digits, `-` and `_` in order to be safely used in URLs and file names.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn check_name(name: &str) -> io::Result<()> {
+    if is_valid_name(name) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid dataset name '{}': only ASCII letters, digits, '-' and '_' are allowed",
+                name
+            ),
+        ))
+    }
+}
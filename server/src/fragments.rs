@@ -0,0 +1,363 @@
+//! Implementation of [Triple Pattern Fragments](https://linkeddatafragments.org/specification/triple-pattern-fragments/)
+//! extended with a graph parameter like [Quad Pattern Fragments](https://linkeddatafragments.org/specification/quad-pattern-fragments/).
+
+use crate::jsonld::write_jsonld;
+use crate::{bad_request, base_url, content_negotiation, internal_server_error, url_query};
+use oxhttp::model::{HeaderName, Request, Response, Status};
+use oxigraph::io::{DatasetFormat, DatasetSerializer, GraphFormat, GraphSerializer};
+use oxigraph::model::vocab::rdf;
+use oxigraph::model::{BlankNode, GraphName, Literal, NamedNode, Quad, Subject, Term};
+use oxigraph::store::Store;
+use url::form_urlencoded;
+
+/// Number of triples per fragment page.
+const PAGE_SIZE: usize = 100;
+/// Maximal number of matching triples counted to compute the fragment size estimate.
+const MAX_EXACT_COUNT: usize = 100_000;
+/// Maximal page number.
+///
+/// Each page requires to scan all the triples of the previous pages so the deep pages are not served.
+const MAX_PAGE: usize = MAX_EXACT_COUNT / PAGE_SIZE;
+
+const HYDRA: &str = "http://www.w3.org/ns/hydra/core#";
+const VOID: &str = "http://rdfs.org/ns/void#";
+const SD_GRAPH: &str = "http://www.w3.org/ns/sparql-service-description#graph";
+
+#[derive(Clone, Copy)]
+enum FragmentFormat {
+    Graph(GraphFormat),
+    Dataset(DatasetFormat),
+    JsonLd,
+}
+
+impl FragmentFormat {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/ld+json" => Some(Self::JsonLd),
+            media_type => DatasetFormat::from_media_type(media_type)
+                .map(Self::Dataset)
+                .or_else(|| GraphFormat::from_media_type(media_type).map(Self::Graph)),
+        }
+    }
+
+    fn media_type(self) -> &'static str {
+        match self {
+            Self::Graph(format) => format.media_type(),
+            Self::Dataset(format) => format.media_type(),
+            Self::JsonLd => "application/ld+json",
+        }
+    }
+}
+
+/// Returns a page of the fragment selected by the request `subject`, `predicate`, `object` and `graph` parameters.
+///
+/// Empty parameters and variables like `?s` match any term.
+/// If no graph is given the triples of all graphs are returned.
+/// The hypermedia controls are written in the default graph for triple formats
+/// and in a dedicated `{page}#metadata` graph for quad formats.
+pub fn handle_fragments_request(request: &Request, store: &Store) -> Response {
+    let mut subject = String::new();
+    let mut predicate = String::new();
+    let mut object = String::new();
+    let mut graph = String::new();
+    let mut page = 1;
+    for (k, v) in form_urlencoded::parse(url_query(request)) {
+        match k.as_ref() {
+            "subject" => subject = v.into_owned(),
+            "predicate" => predicate = v.into_owned(),
+            "object" => object = v.into_owned(),
+            "graph" => graph = v.into_owned(),
+            "page" => {
+                page = match v.parse::<usize>() {
+                    Ok(page) if page > MAX_PAGE => {
+                        return bad_request(format!(
+                            "Only the first {} pages of a fragment are available, please use a more specific pattern or a SPARQL query",
+                            MAX_PAGE
+                        ))
+                    }
+                    Ok(page) if page > 0 => page,
+                    _ => return bad_request(format!("Invalid page number: {}", v)),
+                }
+            }
+            _ => return bad_request(format!("Unexpected parameter: {}", k)),
+        }
+    }
+    let subject_pattern = match parse_pattern_term(&subject) {
+        Ok(None) => None,
+        Ok(Some(Term::NamedNode(node))) => Some(Subject::NamedNode(node)),
+        Ok(Some(Term::BlankNode(node))) => Some(Subject::BlankNode(node)),
+        Ok(Some(_)) => return bad_request("The subject should be an IRI or a blank node"),
+        Err(e) => return bad_request(e),
+    };
+    let predicate_pattern = match parse_pattern_term(&predicate) {
+        Ok(None) => None,
+        Ok(Some(Term::NamedNode(node))) => Some(node),
+        Ok(Some(_)) => return bad_request("The predicate should be an IRI"),
+        Err(e) => return bad_request(e),
+    };
+    let object_pattern = match parse_pattern_term(&object) {
+        Ok(object) => object,
+        Err(e) => return bad_request(e),
+    };
+    let graph_pattern = match parse_pattern_term(&graph) {
+        Ok(None) => None,
+        Ok(Some(Term::NamedNode(node))) => Some(GraphName::NamedNode(node)),
+        Ok(Some(Term::BlankNode(node))) => Some(GraphName::BlankNode(node)),
+        Ok(Some(_)) => return bad_request("The graph should be an IRI or a blank node"),
+        Err(e) => return bad_request(e),
+    };
+    let format = match content_negotiation(
+        request,
+        &[
+            GraphFormat::Turtle.media_type(),
+            DatasetFormat::TriG.media_type(),
+            "application/ld+json",
+            GraphFormat::NTriples.media_type(),
+            DatasetFormat::NQuads.media_type(),
+        ],
+        FragmentFormat::from_media_type,
+    ) {
+        Ok(format) => format,
+        Err(response) => return response,
+    };
+    let base_url = match base_url(request) {
+        Ok(base_url) => base_url,
+        Err(response) => return response,
+    };
+
+    let offset = (page - 1) * PAGE_SIZE;
+    let mut data = Vec::new();
+    let mut count = 0;
+    let mut is_count_exact = true;
+    for quad in store.quads_for_pattern(
+        subject_pattern.as_ref().map(Subject::as_ref),
+        predicate_pattern.as_ref().map(NamedNode::as_ref),
+        object_pattern.as_ref().map(Term::as_ref),
+        graph_pattern.as_ref().map(GraphName::as_ref),
+    ) {
+        let quad = match quad {
+            Ok(quad) => quad,
+            Err(e) => return internal_server_error(e),
+        };
+        if count >= offset && data.len() < PAGE_SIZE {
+            data.push(quad);
+        }
+        count += 1;
+        if count >= MAX_EXACT_COUNT && count > offset + PAGE_SIZE {
+            is_count_exact = false;
+            break; // We know enough to build the page and its controls
+        }
+    }
+
+    let parameters = [
+        (
+            "subject",
+            subject_pattern.is_some().then(|| subject.as_str()),
+        ),
+        (
+            "predicate",
+            predicate_pattern.is_some().then(|| predicate.as_str()),
+        ),
+        ("object", object_pattern.is_some().then(|| object.as_str())),
+        ("graph", graph_pattern.is_some().then(|| graph.as_str())),
+    ];
+    let page_url = fragment_url(&base_url, &parameters, page);
+    let page_node = NamedNode::new_unchecked(page_url.clone());
+    let metadata_graph = match format {
+        FragmentFormat::Graph(_) => GraphName::DefaultGraph,
+        _ => NamedNode::new_unchecked(format!("{}#metadata", page_url)).into(),
+    };
+    let dataset = NamedNode::new_unchecked(format!("{}#dataset", base_url));
+    let mut metadata = Vec::new();
+    let mut add = |s: Subject, p: NamedNode, o: Term| {
+        metadata.push(Quad::new(s, p, o, metadata_graph.clone()));
+    };
+    add(
+        dataset.clone().into(),
+        rdf::TYPE.into(),
+        void("Dataset").into(),
+    );
+    add(
+        dataset.clone().into(),
+        rdf::TYPE.into(),
+        hydra("Collection").into(),
+    );
+    add(
+        dataset.clone().into(),
+        void("subset"),
+        page_node.clone().into(),
+    );
+    let search = BlankNode::default();
+    add(dataset.into(), hydra("search"), search.clone().into());
+    add(
+        search.clone().into(),
+        hydra("template"),
+        Literal::new_simple_literal(format!("{}{{?subject,predicate,object,graph}}", base_url))
+            .into(),
+    );
+    add(
+        search.clone().into(),
+        hydra("variableRepresentation"),
+        hydra("ExplicitRepresentation").into(),
+    );
+    for (variable, property) in [
+        ("subject", rdf::SUBJECT.into_owned()),
+        ("predicate", rdf::PREDICATE.into_owned()),
+        ("object", rdf::OBJECT.into_owned()),
+        ("graph", NamedNode::new_unchecked(SD_GRAPH)),
+    ] {
+        let mapping = BlankNode::default();
+        add(
+            search.clone().into(),
+            hydra("mapping"),
+            mapping.clone().into(),
+        );
+        add(
+            mapping.clone().into(),
+            hydra("variable"),
+            Literal::new_simple_literal(variable).into(),
+        );
+        add(mapping.into(), hydra("property"), property.into());
+    }
+    add(
+        page_node.clone().into(),
+        rdf::TYPE.into(),
+        hydra("PartialCollectionView").into(),
+    );
+    // void:triples is an estimate following the TPF specification but hydra:totalItems is the exact number of items
+    add(
+        page_node.clone().into(),
+        void("triples"),
+        Literal::from(count as u64).into(),
+    );
+    if is_count_exact {
+        add(
+            page_node.clone().into(),
+            hydra("totalItems"),
+            Literal::from(count as u64).into(),
+        );
+    }
+    add(
+        page_node.clone().into(),
+        hydra("itemsPerPage"),
+        Literal::from(PAGE_SIZE as u64).into(),
+    );
+    add(
+        page_node.clone().into(),
+        hydra("first"),
+        NamedNode::new_unchecked(fragment_url(&base_url, &parameters, 1)).into(),
+    );
+    if page > 1 {
+        add(
+            page_node.clone().into(),
+            hydra("previous"),
+            NamedNode::new_unchecked(fragment_url(&base_url, &parameters, page - 1)).into(),
+        );
+    }
+    if count > offset + PAGE_SIZE && page < MAX_PAGE {
+        add(
+            page_node.into(),
+            hydra("next"),
+            NamedNode::new_unchecked(fragment_url(&base_url, &parameters, page + 1)).into(),
+        );
+    }
+
+    let mut body = Vec::new();
+    if let Err(e) = write_fragment(&mut body, format, &data, &metadata) {
+        return internal_server_error(e);
+    }
+    Response::builder(Status::OK)
+        .with_header(HeaderName::CONTENT_TYPE, format.media_type())
+        .unwrap()
+        .with_body(body)
+}
+
+fn write_fragment(
+    body: &mut Vec<u8>,
+    format: FragmentFormat,
+    data: &[Quad],
+    metadata: &[Quad],
+) -> std::io::Result<()> {
+    match format {
+        FragmentFormat::Graph(format) => {
+            let mut writer = GraphSerializer::from_format(format).triple_writer(body)?;
+            for quad in data.iter().chain(metadata) {
+                writer.write(quad.as_ref())?;
+            }
+            writer.finish()
+        }
+        FragmentFormat::Dataset(format) => {
+            let mut writer = DatasetSerializer::from_format(format).quad_writer(body)?;
+            for quad in data.iter().chain(metadata) {
+                writer.write(quad)?;
+            }
+            writer.finish()
+        }
+        FragmentFormat::JsonLd => write_jsonld(body, data.iter().chain(metadata).map(Quad::as_ref)),
+    }
+}
+
+/// Parses a fragment selector parameter.
+///
+/// IRIs could be given as is or between `<` and `>` and literals are written like `"foo"@en` or `"1"^^http://www.w3.org/2001/XMLSchema#integer`.
+/// Empty values and variables are returned as `None`.
+fn parse_pattern_term(value: &str) -> Result<Option<Term>, String> {
+    let value = value.trim();
+    if value.is_empty() || value.starts_with('?') {
+        return Ok(None);
+    }
+    Ok(Some(if let Some(literal) = value.strip_prefix('"') {
+        let (lexical_form, suffix) = literal
+            .rsplit_once('"')
+            .ok_or_else(|| format!("Invalid literal: {}", value))?;
+        if suffix.is_empty() {
+            Literal::new_simple_literal(lexical_form).into()
+        } else if let Some(language) = suffix.strip_prefix('@') {
+            Literal::new_language_tagged_literal(lexical_form, language)
+                .map_err(|e| e.to_string())?
+                .into()
+        } else if let Some(datatype) = suffix.strip_prefix("^^") {
+            Literal::new_typed_literal(lexical_form, parse_iri(datatype)?).into()
+        } else {
+            return Err(format!("Invalid literal: {}", value));
+        }
+    } else if let Some(id) = value.strip_prefix("_:") {
+        BlankNode::new(id).map_err(|e| e.to_string())?.into()
+    } else {
+        parse_iri(value)?.into()
+    }))
+}
+
+fn parse_iri(value: &str) -> Result<NamedNode, String> {
+    let value = value
+        .strip_prefix('<')
+        .and_then(|v| v.strip_suffix('>'))
+        .unwrap_or(value);
+    NamedNode::new(value).map_err(|e| e.to_string())
+}
+
+fn fragment_url(base_url: &str, parameters: &[(&str, Option<&str>)], page: usize) -> String {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in parameters {
+        if let Some(value) = value {
+            query.append_pair(key, value);
+        }
+    }
+    if page > 1 {
+        query.append_pair("page", &page.to_string());
+    }
+    let query = query.finish();
+    if query.is_empty() {
+        base_url.to_owned()
+    } else {
+        format!("{}?{}", base_url, query)
+    }
+}
+
+fn hydra(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("{}{}", HYDRA, name))
+}
+
+fn void(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("{}{}", VOID, name))
+}
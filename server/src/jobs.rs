@@ -0,0 +1,496 @@
+//! Asynchronous evaluation of long-running SPARQL queries.
+//!
+//! Queries are submitted with `POST /jobs`, evaluated on a bounded pool of worker threads
+//! and their results are spilled to temporary files until they are fetched or deleted.
+
+use crate::datasets::DatasetLease;
+use crate::limits::{EvaluationPermit, Limits};
+use crate::{
+    content_encoding_negotiation, error, graph_content_negotiation, internal_server_error,
+    query_results_content_negotiation, read_sparql_query_request, ReadForWrite,
+};
+use json_event_parser::{JsonEvent, JsonWriter};
+use oxhttp::model::{HeaderName, Request, Response, Status};
+use oxigraph::io::{GraphFormat, GraphParser, GraphSerializer};
+use oxigraph::sparql::{EvaluationError, Query, QueryOptions, QueryResults};
+use oxigraph::store::Store;
+use rand::random;
+use sparesults::{
+    QueryResultsFormat, QueryResultsParser, QueryResultsReader, QueryResultsSerializer,
+};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::Builder;
+use std::time::{Duration, Instant};
+
+/// Maximal number of jobs waiting for a worker.
+const MAX_QUEUED_JOBS: usize = 128;
+/// Duration during which the results of a finished job are kept.
+const JOB_RESULTS_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// Lossless formats used to spill the results to disk.
+const SPILL_RESULTS_FORMAT: QueryResultsFormat = QueryResultsFormat::Json;
+const SPILL_GRAPH_FORMAT: GraphFormat = GraphFormat::NTriples;
+
+/// The query jobs of the server and the worker pool evaluating them.
+pub struct Jobs {
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+    queue: Mutex<SyncSender<QueuedJob>>,
+    /// Directory in which the job results are spilled.
+    directory: PathBuf,
+}
+
+impl Jobs {
+    /// Starts a pool of `workers` threads spilling the results in the given directory.
+    ///
+    /// The directory content is removed: it should be dedicated to the jobs of this server.
+    pub fn new(directory: PathBuf, workers: usize) -> io::Result<Self> {
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        let (sender, receiver) = sync_channel(MAX_QUEUED_JOBS);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            Builder::new()
+                .name("oxigraph-job-worker".to_owned())
+                .spawn(move || work(&receiver))?;
+        }
+        Ok(Self {
+            jobs: Mutex::new(HashMap::new()),
+            queue: Mutex::new(sender),
+            directory,
+        })
+    }
+
+    /// Queues the evaluation of a query. The `permit` is held until the evaluation is done.
+    ///
+    /// Returns `None` if too many jobs are already waiting for a worker.
+    fn submit(
+        &self,
+        store: Store,
+        lease: Option<DatasetLease>,
+        query: Query,
+        prefix: &str,
+        permit: EvaluationPermit,
+    ) -> Option<Arc<Job>> {
+        let id = format!("{:x}", random::<u128>());
+        let job = Arc::new(Job {
+            file: self.directory.join(&id),
+            id,
+            prefix: prefix.to_owned(),
+            status: Mutex::new(JobStatus::Queued),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        });
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| !job.is_expired());
+        if self
+            .queue
+            .lock()
+            .unwrap()
+            .try_send(QueuedJob {
+                job: job.clone(),
+                store,
+                query,
+                lease,
+                permit,
+            })
+            .is_err()
+        {
+            return None;
+        }
+        jobs.insert(job.id.clone(), job.clone());
+        Some(job)
+    }
+
+    /// Returns the job with the given id if it has been submitted to the dataset served under `prefix`.
+    fn get(&self, id: &str, prefix: &str) -> Option<Arc<Job>> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|job| job.prefix == prefix && !job.is_expired())
+            .cloned()
+    }
+
+    /// Removes the directory in which the results are spilled.
+    pub fn remove_directory(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.directory) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Cancels a job and deletes its results.
+    fn delete(&self, id: &str, prefix: &str) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get(id) {
+            Some(job) if job.prefix == prefix => {
+                job.cancelled.store(true, Ordering::Relaxed);
+                jobs.remove(id);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Drop for Jobs {
+    fn drop(&mut self) {
+        let _ = self.remove_directory();
+    }
+}
+
+struct Job {
+    id: String,
+    /// Path prefix of the dataset the job has been submitted to.
+    prefix: String,
+    status: Mutex<JobStatus>,
+    /// Also checked by the query evaluation to stop as soon as possible.
+    cancelled: Arc<AtomicBool>,
+    /// File in which the results are spilled.
+    file: PathBuf,
+}
+
+impl Job {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn is_expired(&self) -> bool {
+        match &*self.status.lock().unwrap() {
+            JobStatus::Succeeded(_, end) | JobStatus::Failed(_, end) => {
+                end.elapsed() > JOB_RESULTS_RETENTION
+            }
+            JobStatus::Queued | JobStatus::Running => false,
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.file);
+    }
+}
+
+enum JobStatus {
+    Queued,
+    Running,
+    Succeeded(JobResults, Instant),
+    Failed(String, Instant),
+}
+
+#[derive(Clone, Copy)]
+enum JobResults {
+    /// Solutions spilled using [`SPILL_RESULTS_FORMAT`].
+    Solutions,
+    Boolean(bool),
+    /// Triples spilled using [`SPILL_GRAPH_FORMAT`].
+    Graph,
+}
+
+struct QueuedJob {
+    job: Arc<Job>,
+    store: Store,
+    query: Query,
+    /// Released after the store is dropped.
+    lease: Option<DatasetLease>,
+    /// The evaluation slot of the job, taken when it has been submitted.
+    permit: EvaluationPermit,
+}
+
+fn work(queue: &Mutex<Receiver<QueuedJob>>) {
+    loop {
+        let queued = queue.lock().unwrap().recv();
+        let QueuedJob {
+            job,
+            store,
+            query,
+            lease,
+            permit,
+        } = match queued {
+            Ok(queued) => queued,
+            Err(_) => return, // The server has been stopped
+        };
+        if !job.is_cancelled() {
+            *job.status.lock().unwrap() = JobStatus::Running;
+            let status = match evaluate(&job, &store, query) {
+                Ok(Some(results)) => Some(JobStatus::Succeeded(results, Instant::now())),
+                Ok(None) | Err(EvaluationError::Cancelled) => None,
+                Err(e) => Some(JobStatus::Failed(e.to_string(), Instant::now())),
+            };
+            if let Some(status) = status {
+                *job.status.lock().unwrap() = status;
+            }
+        }
+        drop(permit);
+        // The dataset could be deleted as soon as the lease is released so the store is dropped first
+        drop(store);
+        drop(lease);
+    }
+}
+
+/// Evaluates the query and spills its results to the job file.
+///
+/// Returns `None` or [`EvaluationError::Cancelled`] if the job has been cancelled during the evaluation.
+fn evaluate(job: &Job, store: &Store, query: Query) -> Result<Option<JobResults>, EvaluationError> {
+    let options = QueryOptions::default().with_cancellation_flag(job.cancelled.clone());
+    Ok(Some(match store.query_opt(query, options)? {
+        QueryResults::Solutions(solutions) => {
+            let mut writer = QueryResultsSerializer::from_format(SPILL_RESULTS_FORMAT)
+                .solutions_writer(
+                    BufWriter::new(File::create(&job.file)?),
+                    solutions.variables().to_vec(),
+                )?;
+            for solution in solutions {
+                if job.is_cancelled() {
+                    return Ok(None);
+                }
+                writer.write(&solution?)?;
+            }
+            writer.finish()?.flush()?;
+            JobResults::Solutions
+        }
+        QueryResults::Boolean(value) => JobResults::Boolean(value),
+        QueryResults::Graph(triples) => {
+            let mut writer = GraphSerializer::from_format(SPILL_GRAPH_FORMAT)
+                .triple_writer(BufWriter::new(File::create(&job.file)?))?;
+            for triple in triples {
+                if job.is_cancelled() {
+                    return Ok(None);
+                }
+                writer.write(&triple?)?;
+            }
+            writer.finish()?;
+            JobResults::Graph
+        }
+    }))
+}
+
+/// Handles the requests to `/jobs` and `/jobs/{id}[/results]` of the dataset served under `prefix`.
+pub fn handle_jobs_request(
+    request: &mut Request,
+    path: &str,
+    prefix: &str,
+    store: Store,
+    lease: Option<DatasetLease>,
+    jobs: &Jobs,
+    limits: &Limits,
+) -> Response {
+    if path == "/jobs" {
+        return if request.method().as_ref() == "POST" {
+            let query = match read_sparql_query_request(request) {
+                Ok(query) => query,
+                Err(response) => return response,
+            };
+            let permit = match limits.acquire(request) {
+                Ok(permit) => permit,
+                Err(response) => return response,
+            };
+            if let Some(job) = jobs.submit(store, lease, query, prefix, permit) {
+                match status_body(&job) {
+                    Ok(body) => Response::builder(Status::ACCEPTED)
+                        .with_header(HeaderName::CONTENT_TYPE, "application/json")
+                        .unwrap()
+                        .with_header(HeaderName::LOCATION, format!("{}/jobs/{}", prefix, job.id))
+                        .unwrap()
+                        .with_body(body),
+                    Err(e) => internal_server_error(e),
+                }
+            } else {
+                error(
+                    Status::SERVICE_UNAVAILABLE,
+                    "Too many jobs are waiting to be evaluated, please retry later",
+                )
+            }
+        } else {
+            error(
+                Status::METHOD_NOT_ALLOWED,
+                format!("{} /jobs is not supported by this server", request.method()),
+            )
+        };
+    }
+    let (id, results) = match path.trim_start_matches("/jobs/").split_once('/') {
+        None => (path.trim_start_matches("/jobs/"), false),
+        Some((id, "results")) => (id, true),
+        Some(_) => return error(Status::NOT_FOUND, format!("{} is not found", path)),
+    };
+    match (results, request.method().as_ref()) {
+        (false, "DELETE") => {
+            if jobs.delete(id, prefix) {
+                Response::builder(Status::NO_CONTENT).build()
+            } else {
+                job_not_found(id)
+            }
+        }
+        (false, "GET") => match jobs.get(id, prefix) {
+            Some(job) => match status_body(&job) {
+                Ok(body) => Response::builder(Status::OK)
+                    .with_header(HeaderName::CONTENT_TYPE, "application/json")
+                    .unwrap()
+                    .with_body(body),
+                Err(e) => internal_server_error(e),
+            },
+            None => job_not_found(id),
+        },
+        (true, "GET") => match jobs.get(id, prefix) {
+            Some(job) => results_response(request, &job),
+            None => job_not_found(id),
+        },
+        (_, method) => error(
+            Status::METHOD_NOT_ALLOWED,
+            format!("{} {} is not supported by this server", method, path),
+        ),
+    }
+}
+
+fn job_not_found(id: &str) -> Response {
+    error(
+        Status::NOT_FOUND,
+        format!("The job {} does not exist or has expired", id),
+    )
+}
+
+/// Returns a JSON object describing the job status like `{"id": "...", "status": "failed", "error": "..."}`.
+fn status_body(job: &Job) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut writer = JsonWriter::from_writer(&mut body);
+    writer.write_event(JsonEvent::StartObject)?;
+    writer.write_event(JsonEvent::ObjectKey("id"))?;
+    writer.write_event(JsonEvent::String(&job.id))?;
+    writer.write_event(JsonEvent::ObjectKey("status"))?;
+    match &*job.status.lock().unwrap() {
+        JobStatus::Queued => writer.write_event(JsonEvent::String("queued"))?,
+        JobStatus::Running => writer.write_event(JsonEvent::String("running"))?,
+        JobStatus::Succeeded(_, _) => {
+            writer.write_event(JsonEvent::String("succeeded"))?;
+            writer.write_event(JsonEvent::ObjectKey("results"))?;
+            writer.write_event(JsonEvent::String(&format!(
+                "{}/jobs/{}/results",
+                job.prefix, job.id
+            )))?;
+        }
+        JobStatus::Failed(message, _) => {
+            writer.write_event(JsonEvent::String("failed"))?;
+            writer.write_event(JsonEvent::ObjectKey("error"))?;
+            writer.write_event(JsonEvent::String(message))?;
+        }
+    }
+    writer.write_event(JsonEvent::EndObject)?;
+    Ok(body)
+}
+
+/// Serializes the spilled results of a finished job in the format requested by the client.
+fn results_response(request: &Request, job: &Job) -> Response {
+    let results = match &*job.status.lock().unwrap() {
+        JobStatus::Succeeded(results, _) => *results,
+        JobStatus::Failed(message, _) => {
+            return error(Status::CONFLICT, format!("The job failed: {}", message))
+        }
+        JobStatus::Queued | JobStatus::Running => {
+            return error(Status::CONFLICT, "The job is not finished yet")
+        }
+    };
+    match results {
+        JobResults::Solutions => {
+            let format = match query_results_content_negotiation(request) {
+                Ok(format) => format,
+                Err(response) => return response,
+            };
+            let content_encoding = match content_encoding_negotiation(request) {
+                Ok(content_encoding) => content_encoding,
+                Err(response) => return response,
+            };
+            let solutions = match File::open(&job.file)
+                .map_err(|e| e.into())
+                .and_then(|file| {
+                    QueryResultsParser::from_format(SPILL_RESULTS_FORMAT)
+                        .read_results(BufReader::new(file))
+                }) {
+                Ok(QueryResultsReader::Solutions(solutions)) => solutions,
+                Ok(QueryResultsReader::Boolean(_)) => {
+                    return internal_server_error("The spilled results should be solutions")
+                }
+                Err(e) => return internal_server_error(e),
+            };
+            ReadForWrite::build_response(
+                move |w| {
+                    Ok((
+                        QueryResultsSerializer::from_format(format)
+                            .solutions_writer(w, solutions.variables().to_vec())?,
+                        solutions,
+                    ))
+                },
+                |(mut writer, mut solutions)| {
+                    Ok(if let Some(solution) = solutions.next() {
+                        writer.write(&solution?)?;
+                        Some((writer, solutions))
+                    } else {
+                        writer.finish()?;
+                        None
+                    })
+                },
+                format.media_type(),
+                content_encoding,
+            )
+        }
+        JobResults::Boolean(value) => {
+            let format = match query_results_content_negotiation(request) {
+                Ok(format) => format,
+                Err(response) => return response,
+            };
+            let mut body = Vec::new();
+            if let Err(e) =
+                QueryResultsSerializer::from_format(format).write_boolean_result(&mut body, value)
+            {
+                return internal_server_error(e);
+            }
+            Response::builder(Status::OK)
+                .with_header(HeaderName::CONTENT_TYPE, format.media_type())
+                .unwrap()
+                .with_body(body)
+        }
+        JobResults::Graph => {
+            let format = match graph_content_negotiation(request) {
+                Ok(format) => format,
+                Err(response) => return response,
+            };
+            let content_encoding = match content_encoding_negotiation(request) {
+                Ok(content_encoding) => content_encoding,
+                Err(response) => return response,
+            };
+            let triples = match File::open(&job.file)
+                .map_err(|e| e.into())
+                .and_then(|file| {
+                    GraphParser::from_format(SPILL_GRAPH_FORMAT).read_triples(BufReader::new(file))
+                }) {
+                Ok(triples) => triples,
+                Err(e) => return internal_server_error(e),
+            };
+            ReadForWrite::build_response(
+                move |w| {
+                    Ok((
+                        GraphSerializer::from_format(format).triple_writer(w)?,
+                        triples,
+                    ))
+                },
+                |(mut writer, mut triples)| {
+                    Ok(if let Some(t) = triples.next() {
+                        writer.write(&t?)?;
+                        Some((writer, triples))
+                    } else {
+                        writer.finish()?;
+                        None
+                    })
+                },
+                format.media_type(),
+                content_encoding,
+            )
+        }
+    }
+}
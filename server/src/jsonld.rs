@@ -0,0 +1,107 @@
+//! A minimal [JSON-LD](https://www.w3.org/TR/json-ld/) serializer writing the expanded document form.
+
+use json_event_parser::{JsonEvent, JsonWriter};
+use oxigraph::model::vocab::{rdf, xsd};
+use oxigraph::model::{GraphNameRef, QuadRef, SubjectRef, TermRef};
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Write};
+
+/// Serializes the given quads as an expanded JSON-LD document.
+///
+/// Quads are grouped by graph name and then by subject.
+/// RDF-star quoted triples are not supported.
+pub fn write_jsonld<'a>(
+    sink: impl Write,
+    quads: impl IntoIterator<Item = QuadRef<'a>>,
+) -> io::Result<()> {
+    #[allow(clippy::type_complexity)]
+    let mut graphs: BTreeMap<
+        Option<String>,
+        BTreeMap<String, BTreeMap<&str, Vec<TermRef<'_>>>>,
+    > = BTreeMap::new();
+    for quad in quads {
+        let graph = match quad.graph_name {
+            GraphNameRef::NamedNode(node) => Some(node.as_str().to_owned()),
+            GraphNameRef::BlankNode(node) => Some(format!("_:{}", node.as_str())),
+            GraphNameRef::DefaultGraph => None,
+        };
+        let subject = match quad.subject {
+            SubjectRef::NamedNode(node) => node.as_str().to_owned(),
+            SubjectRef::BlankNode(node) => format!("_:{}", node.as_str()),
+            SubjectRef::Triple(_) => return Err(unsupported_quoted_triple()),
+        };
+        graphs
+            .entry(graph)
+            .or_default()
+            .entry(subject)
+            .or_default()
+            .entry(quad.predicate.as_str())
+            .or_default()
+            .push(quad.object);
+    }
+
+    let mut writer = JsonWriter::from_writer(sink);
+    writer.write_event(JsonEvent::StartArray)?;
+    for (graph, nodes) in &graphs {
+        if let Some(graph) = graph {
+            writer.write_event(JsonEvent::StartObject)?;
+            writer.write_event(JsonEvent::ObjectKey("@id"))?;
+            writer.write_event(JsonEvent::String(graph))?;
+            writer.write_event(JsonEvent::ObjectKey("@graph"))?;
+            writer.write_event(JsonEvent::StartArray)?;
+        }
+        for (subject, properties) in nodes {
+            writer.write_event(JsonEvent::StartObject)?;
+            writer.write_event(JsonEvent::ObjectKey("@id"))?;
+            writer.write_event(JsonEvent::String(subject))?;
+            for (predicate, objects) in properties {
+                writer.write_event(JsonEvent::ObjectKey(predicate))?;
+                writer.write_event(JsonEvent::StartArray)?;
+                for object in objects {
+                    write_object(&mut writer, *object)?;
+                }
+                writer.write_event(JsonEvent::EndArray)?;
+            }
+            writer.write_event(JsonEvent::EndObject)?;
+        }
+        if graph.is_some() {
+            writer.write_event(JsonEvent::EndArray)?;
+            writer.write_event(JsonEvent::EndObject)?;
+        }
+    }
+    writer.write_event(JsonEvent::EndArray)
+}
+
+fn write_object(writer: &mut JsonWriter<impl Write>, object: TermRef<'_>) -> io::Result<()> {
+    writer.write_event(JsonEvent::StartObject)?;
+    match object {
+        TermRef::NamedNode(node) => {
+            writer.write_event(JsonEvent::ObjectKey("@id"))?;
+            writer.write_event(JsonEvent::String(node.as_str()))?;
+        }
+        TermRef::BlankNode(node) => {
+            writer.write_event(JsonEvent::ObjectKey("@id"))?;
+            writer.write_event(JsonEvent::String(&format!("_:{}", node.as_str())))?;
+        }
+        TermRef::Literal(literal) => {
+            writer.write_event(JsonEvent::ObjectKey("@value"))?;
+            writer.write_event(JsonEvent::String(literal.value()))?;
+            if let Some(language) = literal.language() {
+                writer.write_event(JsonEvent::ObjectKey("@language"))?;
+                writer.write_event(JsonEvent::String(language))?;
+            } else if literal.datatype() != xsd::STRING && literal.datatype() != rdf::LANG_STRING {
+                writer.write_event(JsonEvent::ObjectKey("@type"))?;
+                writer.write_event(JsonEvent::String(literal.datatype().as_str()))?;
+            }
+        }
+        TermRef::Triple(_) => return Err(unsupported_quoted_triple()),
+    }
+    writer.write_event(JsonEvent::EndObject)
+}
+
+fn unsupported_quoted_triple() -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidInput,
+        "RDF-star quoted triples are not supported in JSON-LD",
+    )
+}
@@ -0,0 +1,69 @@
+//! Tracking of the requests being handled in order to shut down the server gracefully.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Tracks the in-flight requests and if the server is shutting down.
+#[derive(Default)]
+pub struct Lifecycle {
+    state: Mutex<LifecycleState>,
+    drained: Condvar,
+}
+
+#[derive(Default)]
+struct LifecycleState {
+    shutting_down: bool,
+    in_flight: usize,
+}
+
+impl Lifecycle {
+    /// Registers a new request being handled.
+    ///
+    /// Returns `None` if the server is shutting down and so the request should be rejected.
+    /// The request is considered done when the returned guard is dropped.
+    pub fn start_request(self: &Arc<Self>) -> Option<InFlightRequest> {
+        let mut state = self.state.lock().unwrap();
+        if state.shutting_down {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(InFlightRequest {
+            lifecycle: self.clone(),
+        })
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.state.lock().unwrap().shutting_down
+    }
+
+    /// Stops accepting new requests and waits for the in-flight ones to be done.
+    ///
+    /// Returns `false` if some requests are still in-flight after `timeout`.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.shutting_down = true;
+        let (state, _) = self
+            .drained
+            .wait_timeout_while(state, timeout, |state| state.in_flight > 0)
+            .unwrap();
+        state.in_flight == 0
+    }
+}
+
+/// A request being handled. It is considered done when dropped.
+///
+/// It should be kept until the response body is fully sent.
+#[must_use]
+pub struct InFlightRequest {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        let mut state = self.lifecycle.state.lock().unwrap();
+        state.in_flight -= 1;
+        if state.in_flight == 0 {
+            self.lifecycle.drained.notify_all();
+        }
+    }
+}
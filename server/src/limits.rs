@@ -0,0 +1,241 @@
+//! Limits on the number of queries and updates evaluated by the server.
+
+use crate::error;
+use oxhttp::model::{HeaderName, Request, Response, Status};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Maximal duration a query could wait for an evaluation slot.
+const MAX_QUEUE_WAIT: Duration = Duration::from_secs(30);
+/// Number of tracked clients above which the clients with a full bucket are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Limits the number of queries and updates evaluated concurrently and the rate at which each client could send them.
+#[derive(Default)]
+pub struct Limits {
+    concurrency: Option<Arc<ConcurrencyLimiter>>,
+    rate: Option<RateLimiter>,
+}
+
+impl Limits {
+    /// At most `max_concurrent` evaluations are run concurrently, `max_queued` others are waiting
+    /// and each client identified using `clients` could send at most `rate_limit` queries or updates per minute.
+    pub fn new(
+        max_concurrent: Option<usize>,
+        max_queued: usize,
+        rate_limit: Option<u32>,
+        clients: ClientIdentification,
+    ) -> Self {
+        Self {
+            concurrency: max_concurrent.map(|max_running| {
+                Arc::new(ConcurrencyLimiter {
+                    max_running: max_running.max(1),
+                    max_queued,
+                    state: Mutex::new(ConcurrencyState::default()),
+                    released: Condvar::new(),
+                })
+            }),
+            rate: rate_limit.map(|per_minute| RateLimiter {
+                capacity: f64::from(per_minute.max(1)),
+                refill_per_second: f64::from(per_minute.max(1)) / 60.,
+                buckets: Mutex::new(HashMap::new()),
+                clients,
+            }),
+        }
+    }
+
+    /// Waits for the right to evaluate a query or an update.
+    ///
+    /// Returns a `401 Unauthorized` response if the client could not be identified,
+    /// a `429 Too Many Requests` response if the client exceeded its rate limit
+    /// and a `503 Service Unavailable` response if too many evaluations are already waiting.
+    /// The evaluation slot is released when the returned permit is dropped.
+    pub fn acquire(&self, request: &Request) -> Result<EvaluationPermit, Response> {
+        if let Some(rate) = &self.rate {
+            if let Err(retry_after) = rate.take(rate.clients.key(request)?) {
+                return Err(Response::builder(Status::TOO_MANY_REQUESTS)
+                    .with_header(HeaderName::CONTENT_TYPE, "text/plain; charset=utf-8")
+                    .unwrap()
+                    .with_header(
+                        HeaderName::RETRY_AFTER,
+                        (retry_after.as_secs_f64().ceil() as u64).max(1).to_string(),
+                    )
+                    .unwrap()
+                    .with_body("Too many queries and updates have been sent, please slow down"));
+            }
+        }
+        Ok(EvaluationPermit {
+            limiter: if let Some(concurrency) = &self.concurrency {
+                if !concurrency.acquire() {
+                    return Err(error(
+                        Status::SERVICE_UNAVAILABLE,
+                        "The server is overloaded, please retry later",
+                    ));
+                }
+                Some(concurrency.clone())
+            } else {
+                None
+            },
+        })
+    }
+}
+
+/// The right to evaluate a query or an update. It is released when dropped.
+#[must_use]
+pub struct EvaluationPermit {
+    limiter: Option<Arc<ConcurrencyLimiter>>,
+}
+
+impl Drop for EvaluationPermit {
+    fn drop(&mut self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.release();
+        }
+    }
+}
+
+struct ConcurrencyLimiter {
+    max_running: usize,
+    max_queued: usize,
+    state: Mutex<ConcurrencyState>,
+    released: Condvar,
+}
+
+#[derive(Default)]
+struct ConcurrencyState {
+    running: usize,
+    queued: usize,
+}
+
+impl ConcurrencyLimiter {
+    /// Returns `false` if the queue is full or if no slot has been released in time.
+    fn acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.running < self.max_running {
+            state.running += 1;
+            return true;
+        }
+        if state.queued >= self.max_queued {
+            return false;
+        }
+        state.queued += 1;
+        let (mut state, _) = self
+            .released
+            .wait_timeout_while(state, MAX_QUEUE_WAIT, |state| {
+                state.running >= self.max_running
+            })
+            .unwrap();
+        state.queued -= 1;
+        if state.running < self.max_running {
+            state.running += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn release(&self) {
+        self.state.lock().unwrap().running -= 1;
+        self.released.notify_one();
+    }
+}
+
+/// A token bucket rate limiter per client.
+struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    clients: ClientIdentification,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Takes a token from the client bucket or returns the duration to wait before a token is available.
+    fn take(&self, client: String) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let capacity = self.capacity;
+            let refill_per_second = self.refill_per_second;
+            buckets.retain(|_, bucket| {
+                bucket.tokens
+                    + now.duration_since(bucket.last_refill).as_secs_f64() * refill_per_second
+                    < capacity
+            });
+        }
+        let bucket = buckets.entry(client).or_insert(TokenBucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+        bucket.tokens = (bucket.tokens
+            + now.duration_since(bucket.last_refill).as_secs_f64() * self.refill_per_second)
+            .min(self.capacity);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1. - bucket.tokens) / self.refill_per_second,
+            ))
+        }
+    }
+}
+
+/// How the clients are identified by the rate limiter.
+#[derive(Default)]
+pub struct ClientIdentification {
+    api_keys: HashSet<String>,
+    trusted_proxy: bool,
+}
+
+impl ClientIdentification {
+    /// The clients are identified by their `X-API-Key` header, that must be one of `api_keys`, or,
+    /// if `trusted_proxy` is set, by the address appended to the `X-Forwarded-For` header by the proxy.
+    pub fn new(api_keys: impl IntoIterator<Item = String>, trusted_proxy: bool) -> Self {
+        Self {
+            api_keys: api_keys.into_iter().collect(),
+            trusted_proxy,
+        }
+    }
+
+    /// If the clients that do not send an API key could still be identified.
+    pub fn identifies_anonymous_clients(&self) -> bool {
+        self.trusted_proxy
+    }
+
+    fn key(&self, request: &Request) -> Result<String, Response> {
+        if let Some(api_key) = HeaderName::from_str("x-api-key")
+            .ok()
+            .and_then(|name| request.header(&name))
+        {
+            return match api_key.to_str() {
+                Ok(api_key) if self.api_keys.contains(api_key) => Ok(format!("key:{}", api_key)),
+                _ => Err(error(Status::UNAUTHORIZED, "Invalid API key")),
+            };
+        }
+        if !self.trusted_proxy {
+            return Err(error(
+                Status::UNAUTHORIZED,
+                "An API key must be given with the X-API-Key header",
+            ));
+        }
+        // The trusted proxy appends the address of its client, the previous ones are set by the client itself
+        Ok(format!(
+            "address:{}",
+            HeaderName::from_str("x-forwarded-for")
+                .ok()
+                .and_then(|name| request.header(&name))
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .map(str::trim)
+                .unwrap_or("-")
+        ))
+    }
+}
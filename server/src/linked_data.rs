@@ -0,0 +1,229 @@
+//! [Linked Data](https://www.w3.org/DesignIssues/LinkedData.html) dereferencing of the IRIs served by the server.
+
+use crate::jsonld::write_jsonld;
+use crate::limits::Limits;
+use crate::{
+    base_url, content_encoding_negotiation, content_negotiation, error, internal_server_error,
+    ReadForWrite,
+};
+use oxhttp::model::{HeaderName, Request, Response, Status};
+use oxigraph::io::{GraphFormat, GraphSerializer};
+use oxigraph::model::{GraphNameRef, TermRef, Triple};
+use oxigraph::sparql::{Query, QueryOptions, QueryResults};
+use oxigraph::store::Store;
+use std::fmt;
+use std::iter::once;
+
+const HTML_MEDIA_TYPE: &str = "text/html";
+const JSON_LD_MEDIA_TYPE: &str = "application/ld+json";
+/// Maximal number of triples displayed by the HTML pages.
+const MAX_HTML_TRIPLES: usize = 1000;
+
+#[derive(Clone, Copy)]
+enum LinkedDataFormat {
+    Graph(GraphFormat),
+    JsonLd,
+    Html,
+}
+
+impl LinkedDataFormat {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            HTML_MEDIA_TYPE => Some(Self::Html),
+            JSON_LD_MEDIA_TYPE => Some(Self::JsonLd),
+            media_type => GraphFormat::from_media_type(media_type).map(Self::Graph),
+        }
+    }
+}
+
+/// Returns the [Concise Bounded Description](https://www.w3.org/Submission/CBD/) of the resource identified by the request URL.
+///
+/// It is computed using a SPARQL `DESCRIBE` query.
+pub fn handle_linked_data_request(request: &Request, store: &Store, limits: &Limits) -> Response {
+    let iri = match base_url(request) {
+        Ok(iri) => iri,
+        Err(response) => return response,
+    };
+    let format = match content_negotiation(
+        request,
+        &[
+            GraphFormat::Turtle.media_type(),
+            GraphFormat::NTriples.media_type(),
+            GraphFormat::RdfXml.media_type(),
+            JSON_LD_MEDIA_TYPE,
+            HTML_MEDIA_TYPE,
+        ],
+        LinkedDataFormat::from_media_type,
+    ) {
+        Ok(format) => format,
+        Err(response) => return response,
+    };
+    let query = match Query::parse(&format!("DESCRIBE <{}>", iri), None) {
+        Ok(query) => query,
+        Err(e) => return internal_server_error(e),
+    };
+    let permit = match limits.acquire(request) {
+        Ok(permit) => permit,
+        Err(response) => return response,
+    };
+    let mut triples = match store.query_opt(
+        query,
+        QueryOptions::default().with_concise_bounded_description(),
+    ) {
+        Ok(QueryResults::Graph(triples)) => triples,
+        Ok(_) => return internal_server_error("DESCRIBE queries should return a graph"),
+        Err(e) => return internal_server_error(e),
+    };
+    let first = match triples.next() {
+        Some(Ok(triple)) => triple,
+        Some(Err(e)) => return internal_server_error(e),
+        None => {
+            return error(
+                Status::NOT_FOUND,
+                format!("There is no description of <{}>", iri),
+            )
+        }
+    };
+    let triples = once(Ok(first)).chain(triples);
+    match format {
+        LinkedDataFormat::Html => html_response(&iri, triples, None),
+        LinkedDataFormat::JsonLd => {
+            // The triples are grouped by subject so they are all kept in memory
+            let triples = match triples.collect::<Result<Vec<_>, _>>() {
+                Ok(triples) => triples,
+                Err(e) => return internal_server_error(e),
+            };
+            let mut body = Vec::new();
+            if let Err(e) = write_jsonld(
+                &mut body,
+                triples
+                    .iter()
+                    .map(|t| t.as_ref().in_graph(GraphNameRef::DefaultGraph)),
+            ) {
+                return internal_server_error(e);
+            }
+            Response::builder(Status::OK)
+                .with_header(HeaderName::CONTENT_TYPE, JSON_LD_MEDIA_TYPE)
+                .unwrap()
+                .with_body(body)
+        }
+        LinkedDataFormat::Graph(format) => {
+            let content_encoding = match content_encoding_negotiation(request) {
+                Ok(content_encoding) => content_encoding,
+                Err(response) => return response,
+            };
+            ReadForWrite::build_response(
+                move |w| {
+                    Ok((
+                        GraphSerializer::from_format(format).triple_writer(w)?,
+                        triples,
+                        permit,
+                    ))
+                },
+                |(mut writer, mut triples, permit)| {
+                    Ok(if let Some(t) = triples.next() {
+                        writer.write(&t?)?;
+                        Some((writer, triples, permit))
+                    } else {
+                        writer.finish()?;
+                        None
+                    })
+                },
+                format.media_type(),
+                content_encoding,
+            )
+        }
+    }
+}
+
+/// Returns if the client prefers an HTML page to an RDF serialization.
+pub fn prefers_html(request: &Request) -> bool {
+    matches!(
+        content_negotiation(
+            request,
+            &[GraphFormat::Turtle.media_type(), HTML_MEDIA_TYPE],
+            |media_type| Some(media_type == HTML_MEDIA_TYPE)
+        ),
+        Ok(true)
+    )
+}
+
+/// Returns a minimal HTML page displaying the given triples in a table.
+///
+/// At most [`MAX_HTML_TRIPLES`] triples are displayed,
+/// if there are more of them the page links to `all_triples_url` if it is given.
+pub fn html_response<E: fmt::Display>(
+    title: &str,
+    triples: impl IntoIterator<Item = Result<Triple, E>>,
+    all_triples_url: Option<&str>,
+) -> Response {
+    let mut triples = triples.into_iter();
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    write_escaped(&mut page, title);
+    page.push_str("</title>\n<link rel=\"icon\" type=\"image/svg+xml\" href=\"/logo.svg\">\n</head>\n<body>\n<h1>");
+    write_escaped(&mut page, title);
+    page.push_str("</h1>\n<table>\n<thead><tr><th>Subject</th><th>Predicate</th><th>Object</th></tr></thead>\n<tbody>\n");
+    for triple in triples.by_ref().take(MAX_HTML_TRIPLES) {
+        let triple = match triple {
+            Ok(triple) => triple,
+            Err(e) => return internal_server_error(e),
+        };
+        page.push_str("<tr><td>");
+        write_term(&mut page, triple.subject.as_ref().into());
+        page.push_str("</td><td>");
+        write_term(&mut page, triple.predicate.as_ref().into());
+        page.push_str("</td><td>");
+        write_term(&mut page, triple.object.as_ref());
+        page.push_str("</td></tr>\n");
+    }
+    page.push_str("</tbody>\n</table>\n");
+    if triples.next().is_some() {
+        page.push_str("<p>Only the first ");
+        page.push_str(&MAX_HTML_TRIPLES.to_string());
+        page.push_str(" triples are shown. ");
+        if let Some(url) = all_triples_url {
+            page.push_str("<a href=\"");
+            write_escaped(&mut page, url);
+            page.push_str("\">More…</a>");
+        } else {
+            page.push_str("All of them are available in the RDF serializations.");
+        }
+        page.push_str("</p>\n");
+    }
+    page.push_str("</body>\n</html>\n");
+    Response::builder(Status::OK)
+        .with_header(HeaderName::CONTENT_TYPE, "text/html; charset=utf-8")
+        .unwrap()
+        .with_body(page)
+}
+
+fn write_term(page: &mut String, term: TermRef<'_>) {
+    match term {
+        // We only link HTTP(S) IRIs to avoid creating links to "javascript:" URLs
+        TermRef::NamedNode(node)
+            if node.as_str().starts_with("http://") || node.as_str().starts_with("https://") =>
+        {
+            page.push_str("<a href=\"");
+            write_escaped(page, node.as_str());
+            page.push_str("\">");
+            write_escaped(page, node.as_str());
+            page.push_str("</a>");
+        }
+        TermRef::NamedNode(node) => write_escaped(page, node.as_str()),
+        term => write_escaped(page, &term.to_string()),
+    }
+}
+
+fn write_escaped(page: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => page.push_str("&amp;"),
+            '<' => page.push_str("&lt;"),
+            '>' => page.push_str("&gt;"),
+            '"' => page.push_str("&quot;"),
+            '\'' => page.push_str("&#39;"),
+            c => page.push(c),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Access logging and slow query logging.
+
+use json_event_parser::{JsonEvent, JsonWriter};
+use oxhttp::model::{HeaderName, Request, Response};
+use std::fs::OpenOptions;
+use std::io::{self, stderr, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The format of the access log lines.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum AccessLogFormat {
+    /// The [Common Log Format](https://httpd.apache.org/docs/current/logs.html#common).
+    Common,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for AccessLogFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "common" => Ok(Self::Common),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Unknown access log format {}, 'common' and 'json' are supported",
+                name
+            )),
+        }
+    }
+}
+
+type LogSink = Mutex<Box<dyn Write + Send>>;
+
+/// Writes the server access log and slow query log.
+pub struct Logger {
+    access_log: Option<(AccessLogFormat, LogSink)>,
+    slow_query_log: Option<(Duration, LogSink)>,
+}
+
+impl Logger {
+    /// A logger that logs nothing.
+    pub fn disabled() -> Self {
+        Self {
+            access_log: None,
+            slow_query_log: None,
+        }
+    }
+
+    /// Builds a logger writing into the given files or to stderr if no file is given.
+    ///
+    /// The access log is only written if a format is given and the slow query log only if a threshold is given.
+    pub fn new(
+        access_log_format: Option<AccessLogFormat>,
+        access_log_file: Option<&Path>,
+        slow_query_threshold: Option<Duration>,
+        slow_query_log_file: Option<&Path>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            access_log: access_log_format
+                .map(|format| Ok((format, open_sink(access_log_file)?)))
+                .transpose()?,
+            slow_query_log: slow_query_threshold
+                .map(|threshold| Ok((threshold, open_sink(slow_query_log_file)?)))
+                .transpose()?,
+        })
+    }
+
+    /// Logs a request that has been handled in `duration`.
+    ///
+    /// The response body might still be streamed after the call.
+    pub fn log_access(&self, request: &Request, response: &Response, duration: Duration) {
+        let (format, sink) = if let Some(access_log) = &self.access_log {
+            access_log
+        } else {
+            return;
+        };
+        let now = SystemTime::now();
+        let mut line = Vec::new();
+        let result = match format {
+            AccessLogFormat::Common => writeln!(
+                line,
+                "{} - - [{}] \"{} {} HTTP/1.1\" {} -",
+                client_address(request),
+                clf_time(now),
+                request.method(),
+                request_target(request),
+                u16::from(response.status())
+            ),
+            AccessLogFormat::Json => write_json_line(&mut line, |writer| {
+                write_json_field(writer, "time", &rfc3339_time(now))?;
+                write_json_field(writer, "client", &client_address(request))?;
+                write_json_field(writer, "method", request.method().as_ref())?;
+                write_json_field(writer, "target", &request_target(request))?;
+                writer.write_event(JsonEvent::ObjectKey("status"))?;
+                writer.write_event(JsonEvent::Number(&u16::from(response.status()).to_string()))?;
+                writer.write_event(JsonEvent::ObjectKey("duration_ms"))?;
+                writer.write_event(JsonEvent::Number(&duration.as_millis().to_string()))
+            }),
+        };
+        if let Err(e) = result.and_then(|_| sink.lock().unwrap().write_all(&line)) {
+            eprintln!("Error while writing the access log: {}", e);
+        }
+    }
+
+    /// Logs an error that happened outside of a request, like in a background task.
+    ///
+    /// It is written to stderr as a JSON line if the access log uses JSON or as a timestamped text line otherwise.
+    pub fn log_error(&self, message: &str) {
+        let now = SystemTime::now();
+        let mut line = Vec::new();
+        let result = if matches!(self.access_log, Some((AccessLogFormat::Json, _))) {
+            write_json_line(&mut line, |writer| {
+                write_json_field(writer, "time", &rfc3339_time(now))?;
+                write_json_field(writer, "level", "error")?;
+                write_json_field(writer, "message", message)
+            })
+        } else {
+            writeln!(line, "[{}] {}", clf_time(now), message)
+        };
+        if result.is_ok() {
+            // We can't do much if stderr is not writable
+            let _ = stderr().write_all(&line);
+        }
+    }
+
+    /// Starts to track a query or an update evaluation for the slow query log.
+    ///
+    /// Returns `None` if the slow query log is disabled.
+    pub fn slow_query(
+        self: &Arc<Self>,
+        request: &Request,
+        operation: SparqlOperation,
+        sparql: impl FnOnce() -> String,
+        dataset: SlowQueryDataset,
+    ) -> Option<SlowQuery> {
+        self.slow_query_log.as_ref()?;
+        Some(SlowQuery {
+            logger: self.clone(),
+            start: Instant::now(),
+            operation,
+            sparql: sparql(),
+            dataset,
+            client: client_address(request),
+            result_count: None,
+        })
+    }
+
+    fn log_slow_query(&self, query: &SlowQuery) {
+        let (threshold, sink) = if let Some(slow_query_log) = &self.slow_query_log {
+            slow_query_log
+        } else {
+            return;
+        };
+        let duration = query.start.elapsed();
+        if duration < *threshold {
+            return;
+        }
+        let mut line = Vec::new();
+        let result = write_json_line(&mut line, |writer| {
+            write_json_field(writer, "time", &rfc3339_time(SystemTime::now()))?;
+            write_json_field(writer, "client", &query.client)?;
+            write_json_field(
+                writer,
+                "operation",
+                match query.operation {
+                    SparqlOperation::Query => "query",
+                    SparqlOperation::Update => "update",
+                },
+            )?;
+            write_json_field(writer, "sparql", &query.sparql)?;
+            writer.write_event(JsonEvent::ObjectKey("default_graph"))?;
+            if let Some(graphs) = &query.dataset.default_graph {
+                write_json_array(writer, graphs)?;
+            } else {
+                writer.write_event(JsonEvent::String("union"))?;
+            }
+            writer.write_event(JsonEvent::ObjectKey("named_graphs"))?;
+            if let Some(graphs) = &query.dataset.named_graphs {
+                write_json_array(writer, graphs)?;
+            } else {
+                writer.write_event(JsonEvent::String("all"))?;
+            }
+            writer.write_event(JsonEvent::ObjectKey("duration_ms"))?;
+            writer.write_event(JsonEvent::Number(&duration.as_millis().to_string()))?;
+            if let Some(result_count) = query.result_count {
+                writer.write_event(JsonEvent::ObjectKey("result_count"))?;
+                writer.write_event(JsonEvent::Number(&result_count.to_string()))?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result.and_then(|_| sink.lock().unwrap().write_all(&line)) {
+            eprintln!("Error while writing the slow query log: {}", e);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum SparqlOperation {
+    Query,
+    Update,
+}
+
+/// The graphs a query or an update is evaluated against.
+pub struct SlowQueryDataset {
+    /// The graphs composing the default graph or `None` for the union of all graphs.
+    pub default_graph: Option<Vec<String>>,
+    /// The available named graphs or `None` if all graphs are available.
+    pub named_graphs: Option<Vec<String>>,
+}
+
+/// A query or an update evaluation tracked by the slow query log.
+///
+/// It is logged when dropped if it took longer than the configured threshold.
+pub struct SlowQuery {
+    logger: Arc<Logger>,
+    start: Instant,
+    operation: SparqlOperation,
+    sparql: String,
+    dataset: SlowQueryDataset,
+    client: String,
+    /// The number of returned solutions or triples.
+    result_count: Option<u64>,
+}
+
+impl SlowQuery {
+    /// Records that a new solution or triple has been returned.
+    pub fn add_result(&mut self) {
+        *self.result_count.get_or_insert(0) += 1;
+    }
+}
+
+impl Drop for SlowQuery {
+    fn drop(&mut self) {
+        self.logger.log_slow_query(self)
+    }
+}
+
+fn open_sink(file: Option<&Path>) -> io::Result<LogSink> {
+    Ok(Mutex::new(if let Some(file) = file {
+        Box::new(OpenOptions::new().create(true).append(true).open(file)?)
+    } else {
+        Box::new(stderr())
+    }))
+}
+
+fn write_json_line(
+    line: &mut Vec<u8>,
+    write_fields: impl FnOnce(&mut JsonWriter<&mut Vec<u8>>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut writer = JsonWriter::from_writer(&mut *line);
+    writer.write_event(JsonEvent::StartObject)?;
+    write_fields(&mut writer)?;
+    writer.write_event(JsonEvent::EndObject)?;
+    line.push(b'\n');
+    Ok(())
+}
+
+fn write_json_field(
+    writer: &mut JsonWriter<&mut Vec<u8>>,
+    key: &str,
+    value: &str,
+) -> io::Result<()> {
+    writer.write_event(JsonEvent::ObjectKey(key))?;
+    writer.write_event(JsonEvent::String(value))
+}
+
+fn write_json_array(writer: &mut JsonWriter<&mut Vec<u8>>, values: &[String]) -> io::Result<()> {
+    writer.write_event(JsonEvent::StartArray)?;
+    for value in values {
+        writer.write_event(JsonEvent::String(value))?;
+    }
+    writer.write_event(JsonEvent::EndArray)
+}
+
+/// Returns the address of the client.
+///
+/// The HTTP server does not expose the TCP peer address so we rely on the `X-Forwarded-For` header set by reverse proxies.
+fn client_address(request: &Request) -> String {
+    HeaderName::from_str("x-forwarded-for")
+        .ok()
+        .and_then(|name| request.header(&name))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .unwrap_or("-")
+        .to_owned()
+}
+
+fn request_target(request: &Request) -> String {
+    let url = request.url();
+    if let Some(query) = url.query() {
+        format!("{}?{}", url.path(), query)
+    } else {
+        url.path().to_owned()
+    }
+}
+
+/// Formats a time like `10/Oct/2000:13:55:36 +0000`.
+fn clf_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc_date_time(time);
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[usize::from(month - 1)],
+        year,
+        hour,
+        minute,
+        second
+    )
+}
+
+/// Formats a time like `2000-10-10T13:55:36Z`.
+fn rfc3339_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc_date_time(time);
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Converts a time into its UTC year, month, day, hour, minute and second.
+fn utc_date_time(time: SystemTime) -> (i64, u8, u8, u8, u8, u8) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let days = seconds.div_euclid(86400);
+    let seconds_in_day = seconds.rem_euclid(86400);
+    // Algorithm from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        year,
+        month as u8,
+        day as u8,
+        (seconds_in_day / 3600) as u8,
+        (seconds_in_day % 3600 / 60) as u8,
+        (seconds_in_day % 60) as u8,
+    )
+}
+
+/// Parses a number of milliseconds into a duration.
+pub fn parse_milliseconds(value: &str) -> Result<Duration, String> {
+    u64::from_str(value)
+        .map(Duration::from_millis)
+        .map_err(|e| e.to_string())
+}
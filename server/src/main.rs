@@ -1,38 +1,95 @@
-use clap::{Parser, Subcommand};
+use crate::backups::{handle_backup_request, Backups};
+use crate::config::Config;
+use crate::datasets::{DatasetDeletion, DatasetLease, Datasets};
+use crate::fragments::handle_fragments_request;
+use crate::jobs::{handle_jobs_request, Jobs};
+use crate::lifecycle::{InFlightRequest, Lifecycle};
+use crate::limits::{ClientIdentification, Limits};
+use crate::linked_data::{handle_linked_data_request, html_response, prefers_html};
+use crate::logging::{
+    parse_milliseconds, AccessLogFormat, Logger, SlowQueryDataset, SparqlOperation,
+};
+use crate::rdf_patch::{parse_rdf_patch, RdfPatchChange};
+use crate::transactions::{handle_transactions_request, Transactions};
+use bzip2::read::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use clap::{Args, Parser, Subcommand};
 use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use json_event_parser::{JsonEvent, JsonWriter};
 use oxhttp::model::{Body, HeaderName, HeaderValue, Request, Response, Status};
 use oxhttp::Server;
 use oxigraph::io::{DatasetFormat, DatasetSerializer, GraphFormat, GraphSerializer};
-use oxigraph::model::{GraphName, GraphNameRef, IriParseError, NamedNode, NamedOrBlankNode};
-use oxigraph::sparql::{Query, QueryResults, Update};
-use oxigraph::store::{BulkLoader, Store};
+use oxigraph::model::{
+    GraphName, GraphNameRef, IriParseError, NamedNode, NamedOrBlankNode, QuadRef,
+};
+use oxigraph::sparql::{EvaluationError, Query, QueryResults, Update};
+use oxigraph::store::{BulkLoader, StorageError, Store, StoreOptions};
 use oxiri::Iri;
 use rand::random;
 use sparesults::{QueryResultsFormat, QueryResultsSerializer};
+use spargebra::term::GraphNamePattern;
+use spargebra::GraphUpdateOperation;
 use std::cell::RefCell;
 use std::cmp::min;
+use std::env::temp_dir;
 use std::fmt;
-use std::fs::File;
-use std::io::{self, BufReader, ErrorKind, Read, Write};
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::iter::once;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::thread::{spawn, JoinHandle};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use url::form_urlencoded;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
 
+mod backups;
+mod config;
+mod datasets;
+mod fragments;
+mod jobs;
+mod jsonld;
+mod lifecycle;
+mod limits;
+mod linked_data;
+mod logging;
+mod rdf_patch;
+mod transactions;
+
+/// The file name used to read data from stdin.
+const STDIN_FILE: &str = "-";
+/// The extensions of the compressed files supported by the load command.
+const COMPRESSION_EXTENSIONS: [&str; 4] = ["gz", "bz2", "zst", "xz"];
 const MAX_SPARQL_BODY_SIZE: u64 = 1_048_576;
+/// RDF patches are applied in a single transaction so they are read fully in memory.
+const MAX_RDF_PATCH_BODY_SIZE: u64 = 16 * 1_048_576;
 const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+/// Maximal duration to wait for the in-flight requests on shutdown.
+///
+/// It is lower than the default 30s Kubernetes termination grace period to leave time to flush the stores.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(25);
 const HTML_ROOT_PAGE: &str = include_str!("../templates/query.html");
 const LOGO: &str = include_str!("../logo.svg");
 
 #[derive(Parser)]
 #[clap(about, version)]
 /// Oxigraph SPARQL server.
-struct Args {
+struct Arguments {
     /// Directory in which persist the data.
     #[clap(short, long, parse(from_os_str), global = true)]
     location: Option<PathBuf>,
+    /// TOML configuration file of the server.
+    ///
+    /// It allows to declare named datasets served under /datasets/{name} and to tune the storage.
+    #[clap(short, long, parse(from_os_str), global = true)]
+    config: Option<PathBuf>,
     #[clap(subcommand)]
     command: Command,
 }
@@ -44,65 +101,364 @@ enum Command {
         /// Host and port to listen to.
         #[clap(short, long, default_value = "localhost:7878", global = true)]
         bind: String,
+        #[clap(flatten)]
+        options: ServeOptions,
+    },
+    /// Start Oxigraph HTTP server in read-only mode.
+    ///
+    /// It allows to read the database while other processes are also reading it.
+    /// Opening as read-only while having another process writing the database is not supported.
+    /// The SPARQL update endpoint and the mutating Graph Store Protocol operations are disabled.
+    ServeReadOnly {
+        /// Host and port to listen to.
+        #[clap(short, long, default_value = "localhost:7878")]
+        bind: String,
+        #[clap(flatten)]
+        options: ServeOptions,
     },
     /// Load file(s) into the store.
     Load {
         /// file(s) to load.
         ///
-        /// If multiple files are provided they are loaded in parallel.
-        #[clap(short, long, global = true)]
-        file: Vec<String>,
+        /// If multiple files are provided they are loaded in parallel, see --parallel-loads.
+        /// Directories are loaded recursively and "-" reads the data from stdin.
+        /// Files with a ".gz", ".bz2", ".zst" or ".xz" extension are decompressed.
+        #[clap(short, long, global = true, parse(from_os_str))]
+        file: Vec<PathBuf>,
         /// Attempt to keep loading even if the data file is invalid.
         ///
         /// Only works with N-Triples and N-Quads for now.
         #[clap(long, global = true)]
         lenient: bool,
+        /// The format of the file(s) to load.
+        ///
+        /// Can be an extension like "nt" or a media type like "application/n-triples".
+        /// By default the format is guessed from the file extension.
+        /// It is required when reading from stdin.
+        #[clap(long, global = true)]
+        format: Option<String>,
+        /// Base IRI of the file(s) to load.
+        #[clap(long, global = true)]
+        base: Option<String>,
+        /// Name of the graph to load the data into.
+        ///
+        /// By default the default graph is used.
+        /// It is only allowed with graph formats like Turtle, not with dataset formats like N-Quads.
+        #[clap(long, global = true)]
+        graph: Option<String>,
+        /// Record in the database the progress of the load to be able to resume it with --resume if it is interrupted.
+        ///
+        /// It is not supported when reading from stdin.
+        #[clap(long, global = true)]
+        checkpoint: bool,
+        /// Resume an interrupted load started with --checkpoint.
+        ///
+        /// The parts of the files already loaded by a previous run of this command with the same arguments are skipped.
+        /// The files must not have been modified since.
+        #[clap(long, global = true)]
+        resume: bool,
+        /// Maximal number of files loaded at the same time.
+        ///
+        /// Each file loaded in parallel uses its own memory buffers.
+        #[clap(long, global = true, default_value = "4")]
+        parallel_loads: usize,
+    },
+    /// Dump the store content into a file.
+    Dump {
+        /// File to dump to.
+        ///
+        /// If no file is given, stdout is used.
+        /// If the file name ends with ".gz", ".bz2", ".zst" or ".xz", the output is compressed accordingly.
+        #[clap(short, long, parse(from_os_str))]
+        file: Option<PathBuf>,
+        /// The format of the dump.
+        ///
+        /// Can be a file extension like "nq" or a media type like "application/n-quads".
+        /// By default the format is guessed from the file extension.
+        #[clap(long)]
+        format: Option<String>,
+        /// Name of the graph to dump.
+        ///
+        /// By default the whole dataset is dumped if a dataset format is used and the default graph if a graph format is used.
+        #[clap(long)]
+        graph: Option<String>,
+    },
+    /// Prints statistics about the store content as JSON.
+    ///
+    /// It includes the number of quads of each graph, the number of distinct subjects, predicates and objects,
+    /// the number of strings in the store dictionary and the size on disk of each column family.
+    /// The numbers of quads are maintained by the store but the other statistics require to scan the store indexes.
+    Stats,
+    /// Executes a SPARQL query against the store.
+    Query {
+        /// The SPARQL query to execute.
+        ///
+        /// If no query and no query file are given, the query is read from stdin.
+        #[clap(short, long)]
+        query: Option<String>,
+        /// File containing the SPARQL query to execute.
+        ///
+        /// Files with a ".gz", ".bz2", ".zst" or ".xz" extension are decompressed.
+        #[clap(long, parse(from_os_str))]
+        query_file: Option<PathBuf>,
+        /// Base IRI of the query.
+        #[clap(long)]
+        query_base: Option<String>,
+        /// File in which the query results will be stored.
+        ///
+        /// If no file is given, stdout is used.
+        /// If the file name ends with ".gz", ".bz2", ".zst" or ".xz", the output is compressed accordingly.
+        #[clap(long, parse(from_os_str))]
+        results_file: Option<PathBuf>,
+        /// The format of the results.
+        ///
+        /// Can be a file extension like "srj" or a media type like "application/sparql-results+json".
+        /// By default the format is guessed from the results file extension.
+        /// If the results are written to stdout, SPARQL JSON is used for SELECT and ASK queries and N-Triples for CONSTRUCT and DESCRIBE queries.
+        #[clap(long)]
+        results_format: Option<String>,
+    },
+    /// Executes a SPARQL update against the store.
+    Update {
+        /// The SPARQL update to execute.
+        ///
+        /// If no update and no update file are given, the update is read from stdin.
+        #[clap(short, long)]
+        update: Option<String>,
+        /// File containing the SPARQL update to execute.
+        ///
+        /// Files with a ".gz", ".bz2", ".zst" or ".xz" extension are decompressed.
+        #[clap(long, parse(from_os_str))]
+        update_file: Option<PathBuf>,
+        /// Base IRI of the update.
+        #[clap(long)]
+        update_base: Option<String>,
+    },
+    /// Creates a backup of the store.
+    ///
+    /// The store must not be opened by another process.
+    /// Use the POST /backup endpoint to create backups of a running server.
+    Backup {
+        /// Directory in which the backup is created.
+        ///
+        /// It must not exist yet unless --incremental is set.
+        #[clap(short, long, parse(from_os_str))]
+        directory: PathBuf,
+        /// Adds an incremental backup to the directory using RocksDB backup engine instead of creating a full checkpoint.
+        ///
+        /// Only the files that changed since the previous backup in the directory are copied.
+        #[clap(long)]
+        incremental: bool,
+        /// Number of incremental backups to keep in the directory.
+        ///
+        /// The oldest ones are removed. By default all backups are kept.
+        #[clap(long, requires = "incremental")]
+        retention: Option<u32>,
+    },
+    /// Restores a backup into the directory given by --location.
+    ///
+    /// The directory must not exist yet.
+    Restore {
+        /// Directory containing the backup to restore.
+        ///
+        /// If it contains incremental backups, the most recent one is restored.
+        #[clap(short, long, parse(from_os_str))]
+        directory: PathBuf,
     },
 }
 
+/// Options shared by the serve commands.
+#[derive(Args)]
+struct ServeOptions {
+    /// Allows to dereference the IRIs served by the server.
+    ///
+    /// A GET request on a path not used by the server returns the Concise Bounded Description of the request URL.
+    #[clap(long)]
+    linked_data: bool,
+    /// Number of threads evaluating the queries submitted to the /jobs endpoint.
+    #[clap(long, default_value = "2")]
+    job_workers: usize,
+    /// Maximal number of transactions open at the same time.
+    ///
+    /// The server returns 503 Service Unavailable to POST /transactions if it is reached.
+    #[clap(long, default_value = "64")]
+    max_transactions: usize,
+    /// Enables the access log using the given format ("common" for the Common Log Format or "json" for JSON lines).
+    #[clap(long)]
+    access_log: Option<AccessLogFormat>,
+    /// File to which the access log is appended.
+    ///
+    /// If no file is given, stderr is used.
+    #[clap(long, parse(from_os_str))]
+    access_log_file: Option<PathBuf>,
+    /// Logs the queries and updates taking more than the given number of milliseconds.
+    #[clap(long, parse(try_from_str = parse_milliseconds))]
+    slow_query_threshold: Option<Duration>,
+    /// File to which the slow query log is appended as JSON lines.
+    ///
+    /// If no file is given, stderr is used.
+    #[clap(long, parse(from_os_str))]
+    slow_query_log_file: Option<PathBuf>,
+    /// Maximal number of queries and updates evaluated at the same time.
+    ///
+    /// By default there is no limit.
+    #[clap(long)]
+    max_concurrent_queries: Option<usize>,
+    /// Maximal number of queries and updates waiting for an evaluation slot if --max-concurrent-queries is set.
+    ///
+    /// The server returns 503 Service Unavailable if the queue is full.
+    #[clap(long, default_value = "64")]
+    max_queued_queries: usize,
+    /// Maximal number of queries and updates per minute for each client.
+    ///
+    /// Clients are identified by their X-API-Key header, that must be one of the api_keys of the configuration file,
+    /// or, if trusted_proxy is set in the configuration file, by the address given by the proxy.
+    /// The server returns 429 Too Many Requests if the limit is exceeded.
+    #[clap(long)]
+    rate_limit: Option<u32>,
+    /// Directory in which the backups of the default store are created.
+    ///
+    /// It enables the POST /backup endpoint.
+    /// Each backup is created in its own "backup-{timestamp}" sub-directory unless --incremental-backups is set.
+    #[clap(long, parse(from_os_str))]
+    backup_directory: Option<PathBuf>,
+    /// Creates a backup every given number of seconds.
+    #[clap(long, requires = "backup-directory")]
+    backup_interval: Option<u64>,
+    /// Number of backups to keep.
+    ///
+    /// The oldest backups are removed after each new backup. By default all backups are kept.
+    #[clap(long, requires = "backup-directory")]
+    backup_retention: Option<u32>,
+    /// Creates incremental backups using RocksDB backup engine.
+    ///
+    /// Only the files that changed since the previous backup are copied.
+    #[clap(long, requires = "backup-directory")]
+    incremental_backups: bool,
+}
+
+impl ServeOptions {
+    fn logger(&self) -> io::Result<Logger> {
+        Logger::new(
+            self.access_log,
+            self.access_log_file.as_deref(),
+            self.slow_query_threshold,
+            self.slow_query_log_file.as_deref(),
+        )
+    }
+
+    fn limits(&self, config: &Config) -> io::Result<Limits> {
+        let clients = ClientIdentification::new(config.api_keys.clone(), config.trusted_proxy);
+        if self.rate_limit.is_some()
+            && config.api_keys.is_empty()
+            && !clients.identifies_anonymous_clients()
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "--rate-limit requires api_keys or trusted_proxy to be set in the configuration file to identify the clients",
+            ));
+        }
+        Ok(Limits::new(
+            self.max_concurrent_queries,
+            self.max_queued_queries,
+            self.rate_limit,
+            clients,
+        ))
+    }
+
+    fn backups(&self, location: Option<&Path>) -> io::Result<Option<Arc<Backups>>> {
+        let directory = if let Some(directory) = &self.backup_directory {
+            directory
+        } else {
+            return Ok(None);
+        };
+        if location.is_none() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Backups are only possible for stores persisted using the --location argument",
+            ));
+        }
+        Ok(Some(Arc::new(Backups::new(
+            directory.clone(),
+            self.backup_retention,
+            self.incremental_backups,
+        )?)))
+    }
+}
+
 pub fn main() -> std::io::Result<()> {
-    let matches = Args::parse();
-    let store = if let Some(path) = &matches.location {
-        Store::open(path)
-    } else {
-        Store::new()
-    }?;
+    run(Arguments::parse())
+}
 
+fn run(matches: Arguments) -> io::Result<()> {
     match matches.command {
-        Command::Load { file, lenient } => {
-            let handles = file
-                .iter()
-                .map(|file| {
-                    let store = store.clone();
-                    let file = file.to_string();
-                    spawn(move || {
-                        let f = file.clone();
-                        let start = Instant::now();
-                        let mut loader = store.bulk_loader().on_progress(move |size| {
-                            let elapsed = start.elapsed();
-                            eprintln!(
-                                "{} triples loaded in {}s ({} t/s) from {}",
-                                size,
-                                elapsed.as_secs(),
-                                size / elapsed.as_secs(),
-                                f
-                            )
-                        });
-                        if lenient {
-                            loader = loader.on_parse_error(|e| {
-                                eprintln!("Parsing error: {}", e);
-                                Ok(())
-                            })
-                        }
-                        if file.ends_with(".gz") {
-                            bulk_load(
-                                loader,
-                                &file[..file.len() - 3],
-                                MultiGzDecoder::new(File::open(&file)?),
-                            )
+        Command::Load {
+            file,
+            lenient,
+            format,
+            base,
+            graph,
+            checkpoint,
+            resume,
+            parallel_loads,
+        } => {
+            let store = open_store(matches.location, &store_options(matches.config.as_deref())?)?;
+            let format = format
+                .map(|format| load_format_from_name(&format))
+                .transpose()?;
+            let graph = graph
+                .map(NamedNode::new)
+                .transpose()
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+            let files = files_to_load(file, format.is_some())?;
+            let workers = parallel_loads.max(1).min(files.len());
+            let files = Arc::new(Mutex::new(files.into_iter()));
+            let load_one = Arc::new(move |file: PathBuf| -> io::Result<()> {
+                let f = file.display().to_string();
+                let start = Instant::now();
+                let mut loader = store.bulk_loader().on_progress(move |size| {
+                    let elapsed = start.elapsed();
+                    eprintln!(
+                        "{} triples loaded in {}s ({} t/s) from {}",
+                        size,
+                        elapsed.as_secs(),
+                        size / elapsed.as_secs(),
+                        f
+                    )
+                });
+                if lenient {
+                    loader = loader.on_parse_error(|e| {
+                        eprintln!("Parsing error: {}", e);
+                        Ok(())
+                    })
+                }
+                if checkpoint || resume {
+                    if file == Path::new(STDIN_FILE) {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidInput,
+                            "The --checkpoint and --resume arguments are not supported when loading from stdin",
+                        ));
+                    }
+                    let checkpoint_id = checkpoint_id(&file, graph.as_ref())?;
+                    loader = if resume {
+                        loader.resume_from_checkpoint(checkpoint_id)
+                    } else {
+                        loader.with_checkpoint(checkpoint_id)
+                    };
+                }
+                load_file(loader, &file, format, graph.as_ref(), base.as_deref())
+            });
+            let handles = (0..workers)
+                .map(|_| {
+                    let files = files.clone();
+                    let load_one = load_one.clone();
+                    spawn(move || loop {
+                        let file = if let Some(file) = files.lock().unwrap().next() {
+                            file
                         } else {
-                            bulk_load(loader, &file, File::open(&file)?)
-                        }
+                            return Ok(());
+                        };
+                        load_one(file)?;
                     })
                 })
                 .collect::<Vec<JoinHandle<io::Result<()>>>>();
@@ -111,213 +467,1063 @@ pub fn main() -> std::io::Result<()> {
             }
             Ok(())
         }
-        Command::Serve { bind } => {
-            let mut server = Server::new(move |request| handle_request(request, store.clone()));
-            server.set_global_timeout(HTTP_TIMEOUT);
-            server
-                .set_server_name(concat!("Oxigraph/", env!("CARGO_PKG_VERSION")))
-                .unwrap();
-            eprintln!("Listening for requests at http://{}", &bind);
-            server.listen(bind)?;
+        Command::Serve { bind, options } => {
+            let config = Config::read(matches.config.as_deref())?;
+            let jobs = Jobs::new(
+                jobs_directory(matches.location.as_deref(), false),
+                options.job_workers,
+            )?;
+            let backups = options.backups(matches.location.as_deref())?;
+            let store = open_store(matches.location, &config.storage.options()?)?;
+            let logger = Arc::new(options.logger()?);
+            if let (Some(backups), Some(interval)) = (&backups, options.backup_interval) {
+                backups.clone().schedule(
+                    store.clone(),
+                    Duration::from_secs(interval.max(1)),
+                    logger.clone(),
+                )?;
+            }
+            serve(
+                ServerState {
+                    store,
+                    datasets: Datasets::open(&config, false)?,
+                    read_only: false,
+                    linked_data: options.linked_data,
+                    jobs,
+                    transactions: Transactions::new(options.max_transactions),
+                    logger,
+                    limits: options.limits(&config)?,
+                    backups,
+                    lifecycle: Arc::default(),
+                    admin_token: config.admin_token,
+                },
+                bind,
+            )
+        }
+        Command::ServeReadOnly { bind, options } => {
+            if options.backup_directory.is_some() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "Backups are not possible in read-only mode",
+                ));
+            }
+            let config = Config::read(matches.config.as_deref())?;
+            let jobs = Jobs::new(
+                jobs_directory(matches.location.as_deref(), true),
+                options.job_workers,
+            )?;
+            serve(
+                ServerState {
+                    store: Store::open_read_only_with_options(
+                        required_location(matches.location)?,
+                        &config.storage.options()?,
+                    )?,
+                    datasets: Datasets::open(&config, true)?,
+                    read_only: true,
+                    linked_data: options.linked_data,
+                    jobs,
+                    transactions: Transactions::new(options.max_transactions),
+                    logger: Arc::new(options.logger()?),
+                    limits: options.limits(&config)?,
+                    backups: None,
+                    lifecycle: Arc::default(),
+                    admin_token: config.admin_token,
+                },
+                bind,
+            )
+        }
+        Command::Dump {
+            file,
+            format,
+            graph,
+        } => {
+            let store = Store::open_read_only_with_options(
+                required_location(matches.location)?,
+                &store_options(matches.config.as_deref())?,
+            )?;
+            let format = if let Some(format) = format {
+                format
+            } else if let Some(file) = &file {
+                file_extension(file)?
+            } else {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "The --format argument is required when dumping to stdout",
+                ));
+            };
+            let graph = graph
+                .map(NamedNode::new)
+                .transpose()
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+            write_to_file_or_stdout(file.as_deref(), |writer| {
+                dump(&store, writer, &format, graph.as_ref())
+            })
+        }
+        Command::Stats => {
+            let store = Store::open_read_only_with_options(
+                required_location(matches.location)?,
+                &store_options(matches.config.as_deref())?,
+            )?;
+            write_to_file_or_stdout(None, |writer| write_statistics(&store, writer))
+        }
+        Command::Query {
+            query,
+            query_file,
+            query_base,
+            results_file,
+            results_format,
+        } => {
+            let query = read_text_argument(query, query_file.as_deref(), "query")?;
+            let query = Query::parse(&query, query_base.as_deref())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+            let store = Store::open_read_only_with_options(
+                required_location(matches.location)?,
+                &store_options(matches.config.as_deref())?,
+            )?;
+            let results = store.query(query)?;
+            let results_format = if let Some(results_format) = results_format {
+                Some(results_format)
+            } else if let Some(results_file) = &results_file {
+                Some(file_extension(results_file)?)
+            } else {
+                None
+            };
+            write_to_file_or_stdout(results_file.as_deref(), |writer| {
+                if let QueryResults::Graph(_) = results {
+                    let format = if let Some(format) = results_format {
+                        format_from_name(
+                            &format,
+                            GraphFormat::from_extension,
+                            GraphFormat::from_media_type,
+                        )?
+                    } else {
+                        GraphFormat::NTriples
+                    };
+                    results.write_graph(writer, format)?;
+                } else {
+                    let format = if let Some(format) = results_format {
+                        format_from_name(
+                            &format,
+                            QueryResultsFormat::from_extension,
+                            QueryResultsFormat::from_media_type,
+                        )?
+                    } else {
+                        QueryResultsFormat::Json
+                    };
+                    results.write(writer, format)?;
+                }
+                Ok(())
+            })
+        }
+        Command::Update {
+            update,
+            update_file,
+            update_base,
+        } => {
+            let update = read_text_argument(update, update_file.as_deref(), "update")?;
+            let update = Update::parse(&update, update_base.as_deref())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+            let store = Store::open_with_options(
+                required_location(matches.location)?,
+                &store_options(matches.config.as_deref())?,
+            )?;
+            store.update(update)?;
+            store.flush()?;
+            Ok(())
+        }
+        Command::Backup {
+            directory,
+            incremental,
+            retention,
+        } => {
+            let store = Store::open_with_options(
+                required_location(matches.location)?,
+                &store_options(matches.config.as_deref())?,
+            )?;
+            if incremental {
+                store.incremental_backup(&directory)?;
+                if let Some(retention) = retention {
+                    Store::purge_incremental_backups(&directory, retention.max(1))?;
+                }
+            } else {
+                store.backup(&directory)?;
+            }
             Ok(())
         }
+        Command::Restore { directory } => Ok(Store::restore_from_backup(
+            directory,
+            required_location(matches.location)?,
+        )?),
     }
 }
 
-fn bulk_load(loader: BulkLoader, file: &str, reader: impl Read) -> io::Result<()> {
-    let (_, extension) = file.rsplit_once('.').ok_or_else(|| io::Error::new(
-        ErrorKind::InvalidInput,
-        format!("The server is not able to guess the file format of {} because the file name as no extension", file)))?;
-    let reader = BufReader::new(reader);
-    if let Some(format) = DatasetFormat::from_extension(extension) {
-        loader.load_dataset(reader, format, None)?;
-        Ok(())
-    } else if let Some(format) = GraphFormat::from_extension(extension) {
-        loader.load_graph(reader, format, GraphNameRef::DefaultGraph, None)?;
-        Ok(())
+/// Returns the storage options set in the configuration file if there is one.
+fn store_options(config: Option<&Path>) -> io::Result<StoreOptions> {
+    Config::read(config)?.storage.options()
+}
+
+fn open_store(location: Option<PathBuf>, options: &StoreOptions) -> io::Result<Store> {
+    Ok(if let Some(path) = location {
+        Store::open_with_options(path, options)
+    } else {
+        Store::new()
+    }?)
+}
+
+/// Returns the directory in which the results of the query jobs are spilled.
+///
+/// It is inside of the store directory if the store is persistent and writable and in the system temporary directory if not.
+/// The directory is removed when the server is shut down.
+fn jobs_directory(location: Option<&Path>, read_only: bool) -> PathBuf {
+    match location {
+        Some(location) if !read_only => location.join("jobs"),
+        _ => temp_dir().join(format!("oxigraph-jobs-{}", process::id())),
+    }
+}
+
+fn required_location(location: Option<PathBuf>) -> io::Result<PathBuf> {
+    location.ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            "The --location argument is required for this command",
+        )
+    })
+}
+
+fn dump(
+    store: &Store,
+    writer: &mut dyn Write,
+    format: &str,
+    graph: Option<&NamedNode>,
+) -> io::Result<()> {
+    if let Some(graph) = graph {
+        let format = format_from_name(
+            format,
+            GraphFormat::from_extension,
+            GraphFormat::from_media_type,
+        )?;
+        if !store.contains_named_graph(graph)? {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("The graph {} does not exist", graph),
+            ));
+        }
+        store.dump_graph(writer, format, graph)?;
+    } else if let Ok(format) = format_from_name(
+        format,
+        DatasetFormat::from_extension,
+        DatasetFormat::from_media_type,
+    ) {
+        store.dump_dataset(writer, format)?;
     } else {
-        Err(io::Error::new(
+        let format = format_from_name(
+            format,
+            GraphFormat::from_extension,
+            GraphFormat::from_media_type,
+        )?;
+        store.dump_graph(writer, format, GraphNameRef::DefaultGraph)?;
+    }
+    Ok(())
+}
+
+/// Reads a text given either directly, from a file or from stdin
+fn read_text_argument(text: Option<String>, file: Option<&Path>, name: &str) -> io::Result<String> {
+    match (text, file) {
+        (Some(_), Some(_)) => Err(io::Error::new(
             ErrorKind::InvalidInput,
             format!(
-                "The server is not able to guess the file format from the extension {}",
-                extension
+                "The --{} and --{}-file arguments should not be set at the same time",
+                name, name
             ),
-        ))
+        )),
+        (Some(text), None) => Ok(text),
+        (None, Some(file)) => {
+            let mut text = String::new();
+            open_decompressed(file)?.1.read_to_string(&mut text)?;
+            Ok(text)
+        }
+        (None, None) => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
     }
 }
 
-fn handle_request(request: &mut Request, store: Store) -> Response {
-    match (request.url().path(), request.method().as_ref()) {
-        ("/", "HEAD") => Response::builder(Status::OK)
-            .with_header(HeaderName::CONTENT_TYPE, "text_html")
-            .unwrap()
-            .build(),
-        ("/", "GET") => Response::builder(Status::OK)
-            .with_header(HeaderName::CONTENT_TYPE, "text_html")
-            .unwrap()
-            .with_body(HTML_ROOT_PAGE),
-        ("/logo.svg", "HEAD") => Response::builder(Status::OK)
-            .with_header(HeaderName::CONTENT_TYPE, "image/svg+xml")
-            .unwrap()
-            .build(),
-        ("/logo.svg", "GET") => Response::builder(Status::OK)
-            .with_header(HeaderName::CONTENT_TYPE, "image/svg+xml")
-            .unwrap()
-            .with_body(LOGO),
-        ("/query", "GET") => {
-            configure_and_evaluate_sparql_query(store, &[url_query(request)], None, request)
+/// Writes the store statistics as a JSON object.
+fn write_statistics(store: &Store, writer: &mut dyn Write) -> io::Result<()> {
+    let statistics = store.statistics()?;
+    let total_size = statistics
+        .graph_sizes
+        .iter()
+        .map(|(_, size)| size)
+        .sum::<usize>();
+    {
+        let mut json = JsonWriter::from_writer(&mut *writer);
+        json.write_event(JsonEvent::StartObject)?;
+        json.write_event(JsonEvent::ObjectKey("quads"))?;
+        json.write_event(JsonEvent::Number(&total_size.to_string()))?;
+        json.write_event(JsonEvent::ObjectKey("graphs"))?;
+        json.write_event(JsonEvent::StartObject)?;
+        for (graph_name, size) in &statistics.graph_sizes {
+            json.write_event(JsonEvent::ObjectKey(&graph_name.to_string()))?;
+            json.write_event(JsonEvent::Number(&size.to_string()))?;
         }
-        ("/query", "POST") => {
-            if let Some(content_type) = content_type(request) {
-                if content_type == "application/sparql-query" {
-                    let mut buffer = String::new();
-                    if let Err(e) = request
-                        .body_mut()
-                        .take(MAX_SPARQL_BODY_SIZE)
-                        .read_to_string(&mut buffer)
-                    {
-                        return bad_request(e);
-                    }
-                    configure_and_evaluate_sparql_query(
-                        store,
-                        &[url_query(request)],
-                        Some(buffer),
-                        request,
-                    )
-                } else if content_type == "application/x-www-form-urlencoded" {
-                    let mut buffer = Vec::new();
-                    if let Err(e) = request
-                        .body_mut()
-                        .take(MAX_SPARQL_BODY_SIZE)
-                        .read_to_end(&mut buffer)
-                    {
-                        return bad_request(e);
-                    }
-                    configure_and_evaluate_sparql_query(
-                        store,
-                        &[url_query(request), &buffer],
-                        None,
-                        request,
-                    )
-                } else {
-                    unsupported_media_type(&content_type)
-                }
-            } else {
-                bad_request("No Content-Type given")
-            }
+        json.write_event(JsonEvent::EndObject)?;
+        for (key, value) in [
+            ("distinct_subjects", statistics.distinct_subjects),
+            ("distinct_predicates", statistics.distinct_predicates),
+            ("distinct_objects", statistics.distinct_objects),
+            ("dictionary_size", statistics.dictionary_size),
+        ] {
+            json.write_event(JsonEvent::ObjectKey(key))?;
+            json.write_event(JsonEvent::Number(&value.to_string()))?;
         }
-        ("/update", "POST") => {
-            if let Some(content_type) = content_type(request) {
-                if content_type == "application/sparql-update" {
-                    let mut buffer = String::new();
-                    if let Err(e) = request
-                        .body_mut()
-                        .take(MAX_SPARQL_BODY_SIZE)
-                        .read_to_string(&mut buffer)
-                    {
-                        return bad_request(e);
-                    }
-                    configure_and_evaluate_sparql_update(
-                        store,
-                        &[url_query(request)],
-                        Some(buffer),
-                        request,
-                    )
-                } else if content_type == "application/x-www-form-urlencoded" {
-                    let mut buffer = Vec::new();
-                    if let Err(e) = request
-                        .body_mut()
-                        .take(MAX_SPARQL_BODY_SIZE)
-                        .read_to_end(&mut buffer)
-                    {
-                        return bad_request(e);
-                    }
-                    configure_and_evaluate_sparql_update(
-                        store,
-                        &[url_query(request), &buffer],
-                        None,
-                        request,
-                    )
-                } else {
-                    unsupported_media_type(&content_type)
-                }
+        json.write_event(JsonEvent::ObjectKey("disk_sizes"))?;
+        json.write_event(JsonEvent::StartObject)?;
+        for (column_family, size) in &statistics.disk_sizes {
+            json.write_event(JsonEvent::ObjectKey(column_family))?;
+            if let Some(size) = size {
+                json.write_event(JsonEvent::Number(&size.to_string()))?;
             } else {
-                bad_request("No Content-Type given")
+                json.write_event(JsonEvent::Null)?;
             }
         }
-        (path, "GET") if path.starts_with("/store") => {
-            if let Some(target) = match store_target(request) {
-                Ok(target) => target,
-                Err(error) => return error,
-            } {
-                if !match &target {
-                    NamedGraphName::DefaultGraph => true,
-                    NamedGraphName::NamedNode(target) => match store.contains_named_graph(target) {
-                        Ok(r) => r,
-                        Err(e) => return internal_server_error(e),
-                    },
-                } {
-                    return error(
-                        Status::NOT_FOUND,
-                        format!("The graph {} does not exists", GraphName::from(target)),
-                    );
-                }
-                let format = match graph_content_negotiation(request) {
-                    Ok(format) => format,
-                    Err(response) => return response,
-                };
-                let triples = store.quads_for_pattern(
-                    None,
-                    None,
-                    None,
-                    Some(GraphName::from(target).as_ref()),
-                );
-                ReadForWrite::build_response(
-                    move |w| {
-                        Ok((
-                            GraphSerializer::from_format(format).triple_writer(w)?,
-                            triples,
-                        ))
-                    },
-                    |(mut writer, mut triples)| {
-                        Ok(if let Some(t) = triples.next() {
-                            writer.write(&t?.into())?;
-                            Some((writer, triples))
-                        } else {
-                            writer.finish()?;
-                            None
-                        })
-                    },
-                    format.media_type(),
-                )
-            } else {
-                let format = match dataset_content_negotiation(request) {
-                    Ok(format) => format,
-                    Err(response) => return response,
-                };
-                ReadForWrite::build_response(
-                    move |w| {
-                        Ok((
-                            DatasetSerializer::from_format(format).quad_writer(w)?,
-                            store.iter(),
-                        ))
-                    },
-                    |(mut writer, mut quads)| {
-                        Ok(if let Some(q) = quads.next() {
-                            writer.write(&q?)?;
-                            Some((writer, quads))
-                        } else {
-                            writer.finish()?;
-                            None
-                        })
-                    },
-                    format.media_type(),
-                )
+        json.write_event(JsonEvent::EndObject)?;
+        json.write_event(JsonEvent::EndObject)?;
+    }
+    writeln!(writer)
+}
+
+fn write_to_file_or_stdout(
+    file: Option<&Path>,
+    callback: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    if let Some(file) = file {
+        let writer = BufWriter::new(File::create(file)?);
+        match split_compression_extension(file).1 {
+            Some("gz") => {
+                let mut writer = GzEncoder::new(writer, Compression::default());
+                callback(&mut writer)?;
+                writer.finish()?.flush()
+            }
+            Some("bz2") => {
+                let mut writer = BzEncoder::new(writer, bzip2::Compression::default());
+                callback(&mut writer)?;
+                writer.finish()?.flush()
+            }
+            Some("zst") => {
+                let mut writer = zstd::stream::write::Encoder::new(writer, 0)?;
+                callback(&mut writer)?;
+                writer.finish()?.flush()
+            }
+            Some("xz") => {
+                let mut writer = XzEncoder::new(writer, 6);
+                callback(&mut writer)?;
+                writer.finish()?.flush()
+            }
+            _ => {
+                let mut writer = writer;
+                callback(&mut writer)?;
+                writer.flush()
             }
         }
-        (path, "PUT") if path.starts_with("/store") => {
-            if let Some(content_type) = content_type(request) {
-                if let Some(target) = match store_target(request) {
+    } else {
+        let stdout = io::stdout();
+        let mut writer = stdout.lock();
+        callback(&mut writer)?;
+        writer.flush()
+    }
+}
+
+/// Returns the extension of a file ignoring the compression extension
+fn file_extension(file: &Path) -> io::Result<String> {
+    let (file, _) = split_compression_extension(file);
+    let (_, extension) = file.rsplit_once('.').ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Not able to guess the file format of {} because the file name as no extension",
+                file
+            ),
+        )
+    })?;
+    Ok(extension.to_owned())
+}
+
+/// Parses a format given either as a file extension or as a media type
+fn format_from_name<F>(
+    name: &str,
+    from_extension: impl FnOnce(&str) -> Option<F>,
+    from_media_type: impl FnOnce(&str) -> Option<F>,
+) -> io::Result<F> {
+    if name.contains('/') {
+        from_media_type(name)
+    } else {
+        from_extension(name)
+    }
+    .ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("The format {} is not supported", name),
+        )
+    })
+}
+
+/// The state shared by all the request handlers.
+struct ServerState {
+    /// The default dataset, served at the server root.
+    store: Store,
+    datasets: Datasets,
+    read_only: bool,
+    /// If GET requests on unknown paths return the description of the request URL.
+    linked_data: bool,
+    jobs: Jobs,
+    transactions: Transactions,
+    logger: Arc<Logger>,
+    limits: Limits,
+    /// The backups of the default store if enabled.
+    backups: Option<Arc<Backups>>,
+    lifecycle: Arc<Lifecycle>,
+    /// The token required by the administration operations. They are disabled if not set.
+    admin_token: Option<String>,
+}
+
+fn serve(state: ServerState, bind: String) -> io::Result<()> {
+    let state = Arc::new(state);
+    let shutdown_state = state.clone();
+    ctrlc::set_handler(move || {
+        shutdown(&shutdown_state);
+        process::exit(0)
+    })
+    .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+    let mut server = Server::new(move |request| handle_request(request, &state));
+    server.set_global_timeout(HTTP_TIMEOUT);
+    server
+        .set_server_name(concat!("Oxigraph/", env!("CARGO_PKG_VERSION")))
+        .unwrap();
+    eprintln!("Listening for requests at http://{}", &bind);
+    server.listen(bind)?;
+    Ok(())
+}
+
+/// Stops accepting requests, waits for the in-flight ones to be done and flushes the stores.
+fn shutdown(state: &ServerState) {
+    eprintln!("Shutting down: waiting for the in-flight requests to be done");
+    if !state.lifecycle.shutdown(SHUTDOWN_TIMEOUT) {
+        eprintln!(
+            "Some requests are still running after {}s, they are going to be aborted",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+    }
+    // The process exits without dropping the jobs
+    if let Err(e) = state.jobs.remove_directory() {
+        eprintln!("Error while removing the query job results: {}", e);
+    }
+    if state.read_only {
+        return;
+    }
+    let datasets = state.datasets.names();
+    for store in once(state.store.clone()).chain(
+        datasets
+            .iter()
+            .filter_map(|name| Some(state.datasets.get(name)?.0)),
+    ) {
+        if let Err(e) = store.flush() {
+            eprintln!("Error while flushing the store: {}", e);
+        }
+    }
+}
+
+/// The format of a file to load.
+#[derive(Clone, Copy)]
+enum LoadFormat {
+    Graph(GraphFormat),
+    Dataset(DatasetFormat),
+}
+
+/// Parses a graph or dataset format given either as a file extension or as a media type
+fn load_format_from_name(name: &str) -> io::Result<LoadFormat> {
+    if let Ok(format) = format_from_name(
+        name,
+        DatasetFormat::from_extension,
+        DatasetFormat::from_media_type,
+    ) {
+        Ok(LoadFormat::Dataset(format))
+    } else {
+        Ok(LoadFormat::Graph(format_from_name(
+            name,
+            GraphFormat::from_extension,
+            GraphFormat::from_media_type,
+        )?))
+    }
+}
+
+/// Guesses the format of a file from its extension
+fn guess_load_format(file_name: &str) -> io::Result<LoadFormat> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The server is not able to guess the file format of {} because the file name as no extension",
+                    file_name
+                ),
+            )
+        })?;
+    load_format_from_name(extension)
+}
+
+/// Lists the files to load, exploring the directories recursively.
+///
+/// Hidden files are ignored in directories and so are the files whose format could not be guessed if no format is given.
+fn files_to_load(paths: Vec<PathBuf>, has_format: bool) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            add_directory_files_to_load(&path, has_format, &mut files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn add_directory_files_to_load(
+    directory: &Path,
+    has_format: bool,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            add_directory_files_to_load(&path, has_format, files)?;
+        } else if has_format || guess_load_format(&split_compression_extension(&path).0).is_ok() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the file name without its compression extension and the compression extension if any
+fn split_compression_extension(file: &Path) -> (String, Option<&'static str>) {
+    let name = file.to_string_lossy();
+    for extension in COMPRESSION_EXTENSIONS {
+        if let Some(name) = name
+            .strip_suffix(extension)
+            .and_then(|name| name.strip_suffix('.'))
+        {
+            return (name.to_owned(), Some(extension));
+        }
+    }
+    (name.into_owned(), None)
+}
+
+/// Opens a file, decompressing it according to its extension.
+///
+/// Returns the file name without the compression extension and a reader of the decompressed content.
+fn open_decompressed(file: &Path) -> io::Result<(String, Box<dyn Read>)> {
+    let (name, compression) = split_compression_extension(file);
+    let file = File::open(file)?;
+    let reader: Box<dyn Read> = match compression {
+        Some("gz") => Box::new(MultiGzDecoder::new(file)),
+        Some("bz2") => Box::new(MultiBzDecoder::new(file)),
+        Some("zst") => Box::new(zstd::stream::read::Decoder::new(file)?),
+        Some("xz") => Box::new(XzDecoder::new_multi_decoder(file)),
+        _ => Box::new(file),
+    };
+    Ok((name, reader))
+}
+
+/// Identifies the loading of a file into a graph in order to be able to resume it.
+///
+/// The file size and modification time are included to not resume the loading of a modified file.
+fn checkpoint_id(file: &Path, to_graph_name: Option<&NamedNode>) -> io::Result<String> {
+    let metadata = fs::metadata(file)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    Ok(format!(
+        "{} {} {} {}",
+        file.canonicalize()?.display(),
+        metadata.len(),
+        modified,
+        to_graph_name.map_or_else(String::new, ToString::to_string)
+    ))
+}
+
+/// Loads a file or stdin if the file name is "-"
+fn load_file(
+    loader: BulkLoader,
+    file: &Path,
+    format: Option<LoadFormat>,
+    to_graph_name: Option<&NamedNode>,
+    base_iri: Option<&str>,
+) -> io::Result<()> {
+    if file == Path::new(STDIN_FILE) {
+        let format = format.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "The --format argument is required when loading from stdin",
+            )
+        })?;
+        return bulk_load(loader, io::stdin(), format, to_graph_name, base_iri);
+    }
+    let (name, compression) = split_compression_extension(file);
+    let format = if let Some(format) = format {
+        format
+    } else {
+        guess_load_format(&name)?
+    };
+    if compression == Some("gz")
+        && matches!(
+            format,
+            LoadFormat::Graph(GraphFormat::NTriples) | LoadFormat::Dataset(DatasetFormat::NQuads)
+        )
+    {
+        // The bulk loader decompresses the gzip members of line based files in parallel itself
+        return bulk_load(loader, File::open(file)?, format, to_graph_name, base_iri);
+    }
+    let (_, reader) = open_decompressed(file)?;
+    bulk_load(loader, reader, format, to_graph_name, base_iri)
+}
+
+fn bulk_load(
+    loader: BulkLoader,
+    reader: impl Read,
+    format: LoadFormat,
+    to_graph_name: Option<&NamedNode>,
+    base_iri: Option<&str>,
+) -> io::Result<()> {
+    let reader = BufReader::new(reader);
+    match format {
+        LoadFormat::Dataset(format) => {
+            if to_graph_name.is_some() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "The --graph argument is not supported with the {} dataset format",
+                        format.media_type()
+                    ),
+                ));
+            }
+            loader.load_dataset(reader, format, base_iri)?;
+        }
+        LoadFormat::Graph(format) => {
+            loader.load_graph(
+                reader,
+                format,
+                to_graph_name.map_or(GraphNameRef::DefaultGraph, |graph| graph.into()),
+                base_iri,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// The resources held while handling a request.
+///
+/// The streamed response bodies keep a copy of them so that they are only released when the body is fully sent.
+#[derive(Default, Clone)]
+struct RequestResources {
+    /// The lease on the dataset targeted by the request so that it is not deleted.
+    dataset_lease: Option<DatasetLease>,
+    /// The request registration so that the shutdown waits for it.
+    in_flight: Option<Arc<InFlightRequest>>,
+}
+
+thread_local! {
+    /// The resources of the request being handled by the current thread.
+    static REQUEST_RESOURCES: RefCell<RequestResources> = RefCell::default();
+}
+
+fn handle_request(request: &mut Request, state: &ServerState) -> Response {
+    let start = Instant::now();
+    let response = match request.url().path() {
+        // The probes are still answered during shutdown
+        "/health" | "/ready" => route_request(request, state),
+        _ => {
+            if let Some(in_flight) = state.lifecycle.start_request() {
+                REQUEST_RESOURCES.with(|resources| {
+                    resources.borrow_mut().in_flight = Some(Arc::new(in_flight));
+                });
+                route_request(request, state)
+            } else {
+                error(Status::SERVICE_UNAVAILABLE, "The server is shutting down")
+            }
+        }
+    };
+    REQUEST_RESOURCES.with(RefCell::take);
+    state.logger.log_access(request, &response, start.elapsed());
+    response
+}
+
+/// Checks that the request is authenticated using the admin token with the `Authorization: Bearer` scheme.
+fn is_admin(request: &Request, state: &ServerState) -> bool {
+    let admin_token = if let Some(admin_token) = &state.admin_token {
+        admin_token
+    } else {
+        return false;
+    };
+    request
+        .header(&HeaderName::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map_or(false, |token| {
+            // Constant time comparison to not leak the token through timing
+            token.len() == admin_token.len()
+                && token
+                    .bytes()
+                    .zip(admin_token.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        })
+}
+
+/// The response to an administration request that is not authenticated with the admin token.
+fn admin_required(operation: &str, state: &ServerState) -> Response {
+    if state.admin_token.is_some() {
+        Response::builder(Status::UNAUTHORIZED)
+            .with_header(HeaderName::WWW_AUTHENTICATE, "Bearer")
+            .unwrap()
+            .with_header(HeaderName::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .unwrap()
+            .with_body(format!(
+                "{} requires the admin token given with the Authorization: Bearer header",
+                operation
+            ))
+    } else {
+        error(
+            Status::FORBIDDEN,
+            format!(
+                "{} is disabled, set admin_token in the configuration file to enable it",
+                operation
+            ),
+        )
+    }
+}
+
+fn route_request(request: &mut Request, state: &ServerState) -> Response {
+    let path = request.url().path().to_owned();
+    if path == "/health" {
+        // The server is alive if it is able to answer
+        return probe_response(request, "/health", Ok(()));
+    }
+    if path == "/ready" {
+        return probe_response(request, "/ready", readiness(state));
+    }
+    if path == "/backup" {
+        return handle_backup_request(
+            request,
+            &state.store,
+            state.backups.as_deref(),
+            state.read_only,
+            (!is_admin(request, state)).then(|| admin_required("Creating backups", state)),
+        );
+    }
+    if path == "/datasets" {
+        return match request.method().as_ref() {
+            "HEAD" => Response::builder(Status::OK)
+                .with_header(HeaderName::CONTENT_TYPE, "application/json")
+                .unwrap()
+                .build(),
+            "GET" => Response::builder(Status::OK)
+                .with_header(HeaderName::CONTENT_TYPE, "application/json")
+                .unwrap()
+                .with_body(format!(
+                    "[{}]",
+                    state
+                        .datasets
+                        .names()
+                        .iter()
+                        .map(|name| format!("\"{}\"", name))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )),
+            _ => error(
+                Status::METHOD_NOT_ALLOWED,
+                format!(
+                    "{} /datasets is not supported by this server",
+                    request.method()
+                ),
+            ),
+        };
+    }
+    if let Some(suffix) = path.strip_prefix("/datasets/") {
+        let name = suffix.split('/').next().unwrap_or_default();
+        if name.len() == suffix.len() {
+            match request.method().as_ref() {
+                "PUT" | "DELETE" if state.read_only => {
+                    return error(
+                        Status::FORBIDDEN,
+                        "The server is read-only: datasets can not be created or deleted",
+                    )
+                }
+                "PUT" | "DELETE" if !is_admin(request, state) => {
+                    return admin_required("Creating and deleting datasets", state)
+                }
+                "PUT" => {
+                    return match state.datasets.create(name) {
+                        Ok(true) => Response::builder(Status::CREATED).build(),
+                        Ok(false) => Response::builder(Status::NO_CONTENT).build(),
+                        Err(e) if e.kind() == ErrorKind::InvalidInput => bad_request(e),
+                        Err(e) => internal_server_error(e),
+                    }
+                }
+                "DELETE" => {
+                    // The expired transactions would prevent the deletion
+                    state.transactions.remove_expired();
+                    return match state.datasets.delete(name) {
+                        Ok(DatasetDeletion::Deleted) => Response::builder(Status::NO_CONTENT).build(),
+                        Ok(DatasetDeletion::NotFound) => error(
+                            Status::NOT_FOUND,
+                            format!("The dataset {} does not exist", name),
+                        ),
+                        Ok(DatasetDeletion::InUse) => error(
+                            Status::CONFLICT,
+                            format!(
+                                "The dataset {} is in use by running requests, query jobs or transactions, please retry later",
+                                name
+                            ),
+                        ),
+                        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                            error(Status::FORBIDDEN, e)
+                        }
+                        Err(e) => internal_server_error(e),
+                    };
+                }
+                _ => (),
+            }
+        }
+        return if let Some((store, lease)) = state.datasets.get(name) {
+            REQUEST_RESOURCES.with(|resources| {
+                resources.borrow_mut().dataset_lease = Some(lease.clone());
+            });
+            handle_dataset_request(
+                request,
+                state,
+                &format!("/datasets/{}", name),
+                store,
+                Some(lease),
+            )
+        } else {
+            error(
+                Status::NOT_FOUND,
+                format!("The dataset {} does not exist", name),
+            )
+        };
+    }
+    handle_dataset_request(request, state, "", state.store.clone(), None)
+}
+
+/// Returns if the server is ready to handle requests.
+///
+/// The stores are opened (and migrated if needed) before the server starts listening
+/// so it is ready if it is not shutting down and the default store could be read.
+fn readiness(state: &ServerState) -> Result<(), String> {
+    if state.lifecycle.is_shutting_down() {
+        return Err("The server is shutting down".into());
+    }
+    if let Some(Err(e)) = state.store.iter().next() {
+        return Err(format!("The store could not be read: {}", e));
+    }
+    Ok(())
+}
+
+fn probe_response(request: &Request, path: &str, status: Result<(), String>) -> Response {
+    if !matches!(request.method().as_ref(), "GET" | "HEAD") {
+        return error(
+            Status::METHOD_NOT_ALLOWED,
+            format!(
+                "{} {} is not supported by this server",
+                request.method(),
+                path
+            ),
+        );
+    }
+    match status {
+        Ok(()) => Response::builder(Status::OK)
+            .with_header(HeaderName::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .unwrap()
+            .with_body("OK"),
+        Err(message) => error(Status::SERVICE_UNAVAILABLE, message),
+    }
+}
+
+/// Handles a request against a dataset served under the given path prefix.
+///
+/// The lease is given to the jobs and transactions that outlive the request.
+/// It is `None` for the default dataset that could not be deleted.
+fn handle_dataset_request(
+    request: &mut Request,
+    state: &ServerState,
+    prefix: &str,
+    store: Store,
+    lease: Option<DatasetLease>,
+) -> Response {
+    let path = match &request.url().path()[prefix.len()..] {
+        "" => "/".to_owned(),
+        path => path.to_owned(),
+    };
+    if let Err(response) = decode_request_body(request) {
+        return response;
+    }
+    match (path.as_str(), request.method().as_ref()) {
+        ("/update", _) if state.read_only => error(
+            Status::FORBIDDEN,
+            "The server is read-only: updates are not allowed",
+        ),
+        (path, "PUT" | "POST" | "DELETE" | "PATCH")
+            if state.read_only && path.starts_with("/store") =>
+        {
+            error(
+                Status::FORBIDDEN,
+                "The server is read-only: the store can not be modified",
+            )
+        }
+        (path, _) if state.read_only && path.starts_with("/transactions") => error(
+            Status::FORBIDDEN,
+            "The server is read-only: transactions are not allowed",
+        ),
+        ("/", "HEAD") => Response::builder(Status::OK)
+            .with_header(HeaderName::CONTENT_TYPE, "text_html")
+            .unwrap()
+            .build(),
+        ("/", "GET") => Response::builder(Status::OK)
+            .with_header(HeaderName::CONTENT_TYPE, "text_html")
+            .unwrap()
+            .with_body(HTML_ROOT_PAGE),
+        ("/logo.svg", "HEAD") => Response::builder(Status::OK)
+            .with_header(HeaderName::CONTENT_TYPE, "image/svg+xml")
+            .unwrap()
+            .build(),
+        ("/logo.svg", "GET") => Response::builder(Status::OK)
+            .with_header(HeaderName::CONTENT_TYPE, "image/svg+xml")
+            .unwrap()
+            .with_body(LOGO),
+        ("/fragments", "GET") => handle_fragments_request(request, &store),
+        ("/query", "GET") => match configure_sparql_query(&[url_query(request)], None, request) {
+            Ok(query) => evaluate_sparql_query(query, request, state, |query| store.query(query)),
+            Err(response) => response,
+        },
+        ("/query", "POST") => match read_sparql_query_request(request) {
+            Ok(query) => evaluate_sparql_query(query, request, state, |query| store.query(query)),
+            Err(response) => response,
+        },
+        ("/update", "POST") => {
+            handle_sparql_update_request(request, state, |update| store.update(update))
+        }
+        (path, "GET") if path.starts_with("/store") => {
+            if let Some(target) = match store_target(request, &path) {
+                Ok(target) => target,
+                Err(error) => return error,
+            } {
+                if !match &target {
+                    NamedGraphName::DefaultGraph => true,
+                    NamedGraphName::NamedNode(target) => match store.contains_named_graph(target) {
+                        Ok(r) => r,
+                        Err(e) => return internal_server_error(e),
+                    },
+                } {
+                    return error(
+                        Status::NOT_FOUND,
+                        format!("The graph {} does not exists", GraphName::from(target)),
+                    );
+                }
+                if state.linked_data && path != "/store" && prefers_html(request) {
+                    let graph_name = GraphName::from(target);
+                    let all_triples_url = match &graph_name {
+                        GraphName::NamedNode(graph_name) => format!(
+                            "{}/store?{}",
+                            prefix,
+                            form_urlencoded::Serializer::new(String::new())
+                                .append_pair("graph", graph_name.as_str())
+                                .finish()
+                        ),
+                        _ => format!("{}/store?default", prefix),
+                    };
+                    return html_response(
+                        &graph_name.to_string(),
+                        store
+                            .quads_for_pattern(None, None, None, Some(graph_name.as_ref()))
+                            .map(|q| q.map(Into::into)),
+                        Some(&all_triples_url),
+                    );
+                }
+                let format = match graph_content_negotiation(request) {
+                    Ok(format) => format,
+                    Err(response) => return response,
+                };
+                let content_encoding = match content_encoding_negotiation(request) {
+                    Ok(content_encoding) => content_encoding,
+                    Err(response) => return response,
+                };
+                let triples = store.quads_for_pattern(
+                    None,
+                    None,
+                    None,
+                    Some(GraphName::from(target).as_ref()),
+                );
+                ReadForWrite::build_response(
+                    move |w| {
+                        Ok((
+                            GraphSerializer::from_format(format).triple_writer(w)?,
+                            triples,
+                        ))
+                    },
+                    |(mut writer, mut triples)| {
+                        Ok(if let Some(t) = triples.next() {
+                            writer.write(&t?.into())?;
+                            Some((writer, triples))
+                        } else {
+                            writer.finish()?;
+                            None
+                        })
+                    },
+                    format.media_type(),
+                    content_encoding,
+                )
+            } else {
+                let format = match dataset_content_negotiation(request) {
+                    Ok(format) => format,
+                    Err(response) => return response,
+                };
+                let content_encoding = match content_encoding_negotiation(request) {
+                    Ok(content_encoding) => content_encoding,
+                    Err(response) => return response,
+                };
+                ReadForWrite::build_response(
+                    move |w| {
+                        Ok((
+                            DatasetSerializer::from_format(format).quad_writer(w)?,
+                            store.iter(),
+                        ))
+                    },
+                    |(mut writer, mut quads)| {
+                        Ok(if let Some(q) = quads.next() {
+                            writer.write(&q?)?;
+                            Some((writer, quads))
+                        } else {
+                            writer.finish()?;
+                            None
+                        })
+                    },
+                    format.media_type(),
+                    content_encoding,
+                )
+            }
+        }
+        (path, "PUT") if path.starts_with("/store") => {
+            if let Some(content_type) = content_type(request) {
+                if let Some(target) = match store_target(request, &path) {
                     Ok(target) => target,
                     Err(error) => return error,
                 } {
@@ -381,7 +1587,7 @@ fn handle_request(request: &mut Request, store: Store) -> Response {
             }
         }
         (path, "DELETE") if path.starts_with("/store") => {
-            if let Some(target) = match store_target(request) {
+            if let Some(target) = match store_target(request, &path) {
                 Ok(target) => target,
                 Err(error) => return error,
             } {
@@ -414,7 +1620,7 @@ fn handle_request(request: &mut Request, store: Store) -> Response {
         }
         (path, "POST") if path.starts_with("/store") => {
             if let Some(content_type) = content_type(request) {
-                if let Some(target) = match store_target(request) {
+                if let Some(target) = match store_target(request, &path) {
                     Ok(target) => target,
                     Err(error) => return error,
                 } {
@@ -453,12 +1659,13 @@ fn handle_request(request: &mut Request, store: Store) -> Response {
                     }
                     Response::builder(Status::NO_CONTENT).build()
                 } else if let Some(format) = GraphFormat::from_media_type(&content_type) {
-                    let graph =
-                        match resolve_with_base(request, &format!("/store/{:x}", random::<u128>()))
-                        {
-                            Ok(graph) => graph,
-                            Err(e) => return e,
-                        };
+                    let graph = match resolve_with_base(
+                        request,
+                        &format!("{}/store/{:x}", prefix, random::<u128>()),
+                    ) {
+                        Ok(graph) => graph,
+                        Err(e) => return e,
+                    };
                     if let Err(e) =
                         store.load_graph(BufReader::new(request.body_mut()), format, &graph, None)
                     {
@@ -475,13 +1682,69 @@ fn handle_request(request: &mut Request, store: Store) -> Response {
                 bad_request("No Content-Type given")
             }
         }
-        (path, "HEAD") if path.starts_with("/store") => {
-            if let Some(target) = match store_target(request) {
-                Ok(target) => target,
+        (path, "PATCH") if path.starts_with("/store") => {
+            let target = match store_target(request, &path) {
+                Ok(Some(target)) => target,
+                Ok(None) => return bad_request("PATCH requests should target a graph"),
                 Err(error) => return error,
-            } {
-                if !match &target {
-                    NamedGraphName::DefaultGraph => true,
+            };
+            if let Some(content_type) = content_type(request) {
+                if content_type == "application/sparql-update" {
+                    let mut buffer = String::new();
+                    if let Err(e) = request
+                        .body_mut()
+                        .take(MAX_SPARQL_BODY_SIZE)
+                        .read_to_string(&mut buffer)
+                    {
+                        return bad_request(e);
+                    }
+                    let base_url = match base_url(request) {
+                        Ok(url) => url,
+                        Err(e) => return e,
+                    };
+                    let update = match parse_graph_patch_update(&buffer, &base_url, &target) {
+                        Ok(update) => update,
+                        Err(e) => return bad_request(e),
+                    };
+                    let _permit = match state.limits.acquire(request) {
+                        Ok(permit) => permit,
+                        Err(response) => return response,
+                    };
+                    if let Err(e) = store.update(update) {
+                        return internal_server_error(e);
+                    }
+                    Response::builder(Status::NO_CONTENT).build()
+                } else if content_type == "application/rdf-patch" {
+                    let mut buffer = String::new();
+                    // We read one more byte to detect the too big patches instead of silently truncating them
+                    if let Err(e) = request
+                        .body_mut()
+                        .take(MAX_RDF_PATCH_BODY_SIZE + 1)
+                        .read_to_string(&mut buffer)
+                    {
+                        return bad_request(e);
+                    }
+                    if buffer.len() as u64 > MAX_RDF_PATCH_BODY_SIZE {
+                        return bad_request(format!(
+                            "RDF patches are limited to {} bytes",
+                            MAX_RDF_PATCH_BODY_SIZE
+                        ));
+                    }
+                    apply_rdf_patch(&store, &buffer, target)
+                } else {
+                    unsupported_media_type(&content_type)
+                }
+            } else {
+                bad_request("No Content-Type given")
+            }
+        }
+        (path, "HEAD") if path.starts_with("/store") => {
+            if let Some(target) = match store_target(request, &path) {
+                Ok(target) => target,
+                Err(error) => return error,
+            } {
+                if !match &target {
+                    NamedGraphName::DefaultGraph => true,
                     NamedGraphName::NamedNode(target) => match store.contains_named_graph(target) {
                         Ok(r) => r,
                         Err(e) => return internal_server_error(e),
@@ -497,6 +1760,21 @@ fn handle_request(request: &mut Request, store: Store) -> Response {
                 Response::builder(Status::OK).build()
             }
         }
+        (path, _) if path == "/jobs" || path.starts_with("/jobs/") => handle_jobs_request(
+            request,
+            path,
+            prefix,
+            store,
+            lease,
+            &state.jobs,
+            &state.limits,
+        ),
+        (path, _) if path == "/transactions" || path.starts_with("/transactions/") => {
+            handle_transactions_request(request, path, prefix, store, lease, state)
+        }
+        (_, "GET") if state.linked_data => {
+            handle_linked_data_request(request, &store, &state.limits)
+        }
         _ => error(
             Status::NOT_FOUND,
             format!(
@@ -532,12 +1810,11 @@ fn url_query(request: &Request) -> &[u8] {
     request.url().query().unwrap_or("").as_bytes()
 }
 
-fn configure_and_evaluate_sparql_query(
-    store: Store,
+fn configure_sparql_query(
     encoded: &[&[u8]],
     mut query: Option<String>,
     request: &Request,
-) -> Response {
+) -> Result<Query, Response> {
     let mut default_graph_uris = Vec::new();
     let mut named_graph_uris = Vec::new();
     let mut use_default_graph_as_union = false;
@@ -546,7 +1823,7 @@ fn configure_and_evaluate_sparql_query(
             match k.as_ref() {
                 "query" => {
                     if query.is_some() {
-                        return bad_request("Multiple query parameters provided");
+                        return Err(bad_request("Multiple query parameters provided"));
                     }
                     query = Some(v.into_owned())
                 }
@@ -558,8 +1835,7 @@ fn configure_and_evaluate_sparql_query(
         }
     }
     if let Some(query) = query {
-        evaluate_sparql_query(
-            store,
+        parse_sparql_query(
             query,
             use_default_graph_as_union,
             default_graph_uris,
@@ -567,62 +1843,98 @@ fn configure_and_evaluate_sparql_query(
             request,
         )
     } else {
-        bad_request("You should set the 'query' parameter")
+        Err(bad_request("You should set the 'query' parameter"))
     }
 }
 
-fn evaluate_sparql_query(
-    store: Store,
+fn parse_sparql_query(
     query: String,
     use_default_graph_as_union: bool,
     default_graph_uris: Vec<String>,
     named_graph_uris: Vec<String>,
     request: &Request,
-) -> Response {
-    let mut query = match Query::parse(
-        &query,
-        Some(&match base_url(request) {
-            Ok(url) => url,
-            Err(r) => return r,
-        }),
-    ) {
-        Ok(query) => query,
-        Err(e) => return bad_request(e),
-    };
+) -> Result<Query, Response> {
+    let mut query = Query::parse(&query, Some(&base_url(request)?)).map_err(bad_request)?;
 
     if use_default_graph_as_union {
         if !default_graph_uris.is_empty() || !named_graph_uris.is_empty() {
-            return bad_request(
+            return Err(bad_request(
                 "default-graph-uri or named-graph-uri and union-default-graph should not be set at the same time"
-            );
+            ));
         }
         query.dataset_mut().set_default_graph_as_union()
     } else if !default_graph_uris.is_empty() || !named_graph_uris.is_empty() {
         query.dataset_mut().set_default_graph(
-            match default_graph_uris
+            default_graph_uris
                 .into_iter()
                 .map(|e| Ok(NamedNode::new(e)?.into()))
                 .collect::<Result<Vec<GraphName>, IriParseError>>()
-            {
-                Ok(default_graph_uris) => default_graph_uris,
-                Err(e) => return bad_request(e),
-            },
+                .map_err(bad_request)?,
         );
         query.dataset_mut().set_available_named_graphs(
-            match named_graph_uris
+            named_graph_uris
                 .into_iter()
                 .map(|e| Ok(NamedNode::new(e)?.into()))
                 .collect::<Result<Vec<NamedOrBlankNode>, IriParseError>>()
-            {
-                Ok(named_graph_uris) => named_graph_uris,
-                Err(e) => return bad_request(e),
-            },
+                .map_err(bad_request)?,
         );
     }
+    Ok(query)
+}
+
+/// Reads a SPARQL query sent in a POST request body following the SPARQL 1.1 Protocol.
+fn read_sparql_query_request(request: &mut Request) -> Result<Query, Response> {
+    let content_type = content_type(request).ok_or_else(|| bad_request("No Content-Type given"))?;
+    if content_type == "application/sparql-query" {
+        let mut buffer = String::new();
+        request
+            .body_mut()
+            .take(MAX_SPARQL_BODY_SIZE)
+            .read_to_string(&mut buffer)
+            .map_err(bad_request)?;
+        configure_sparql_query(&[url_query(request)], Some(buffer), request)
+    } else if content_type == "application/x-www-form-urlencoded" {
+        let mut buffer = Vec::new();
+        request
+            .body_mut()
+            .take(MAX_SPARQL_BODY_SIZE)
+            .read_to_end(&mut buffer)
+            .map_err(bad_request)?;
+        configure_sparql_query(&[url_query(request), &buffer], None, request)
+    } else {
+        Err(unsupported_media_type(&content_type))
+    }
+}
 
-    let results = match store.query(query) {
+/// Evaluates a query using `evaluate` and serializes its results in the format requested by the client.
+fn evaluate_sparql_query(
+    query: Query,
+    request: &Request,
+    state: &ServerState,
+    evaluate: impl FnOnce(Query) -> Result<QueryResults, EvaluationError>,
+) -> Response {
+    let permit = match state.limits.acquire(request) {
+        Ok(permit) => permit,
+        Err(response) => return response,
+    };
+    let slow_query = state.logger.slow_query(
+        request,
+        SparqlOperation::Query,
+        || query.to_string(),
+        SlowQueryDataset {
+            default_graph: query
+                .dataset()
+                .default_graph_graphs()
+                .map(|graphs| graphs.iter().map(|g| g.to_string()).collect()),
+            named_graphs: query
+                .dataset()
+                .available_named_graphs()
+                .map(|graphs| graphs.iter().map(|g| g.to_string()).collect()),
+        },
+    );
+    let results = match evaluate(query) {
         Ok(results) => results,
-        Err(e) => return internal_server_error(e),
+        Err(e) => return evaluation_error(e),
     };
     match results {
         QueryResults::Solutions(solutions) => {
@@ -630,24 +1942,34 @@ fn evaluate_sparql_query(
                 Ok(format) => format,
                 Err(response) => return response,
             };
+            let content_encoding = match content_encoding_negotiation(request) {
+                Ok(content_encoding) => content_encoding,
+                Err(response) => return response,
+            };
             ReadForWrite::build_response(
                 move |w| {
                     Ok((
                         QueryResultsSerializer::from_format(format)
                             .solutions_writer(w, solutions.variables().to_vec())?,
                         solutions,
+                        slow_query,
+                        permit,
                     ))
                 },
-                |(mut writer, mut solutions)| {
+                |(mut writer, mut solutions, mut slow_query, permit)| {
                     Ok(if let Some(solution) = solutions.next() {
                         writer.write(&solution?)?;
-                        Some((writer, solutions))
+                        if let Some(slow_query) = &mut slow_query {
+                            slow_query.add_result();
+                        }
+                        Some((writer, solutions, slow_query, permit))
                     } else {
                         writer.finish()?;
                         None
                     })
                 },
                 format.media_type(),
+                content_encoding,
             )
         }
         QueryResults::Boolean(result) => {
@@ -671,33 +1993,91 @@ fn evaluate_sparql_query(
                 Ok(format) => format,
                 Err(response) => return response,
             };
+            let content_encoding = match content_encoding_negotiation(request) {
+                Ok(content_encoding) => content_encoding,
+                Err(response) => return response,
+            };
             ReadForWrite::build_response(
                 move |w| {
                     Ok((
                         GraphSerializer::from_format(format).triple_writer(w)?,
                         triples,
+                        slow_query,
+                        permit,
                     ))
                 },
-                |(mut writer, mut triples)| {
+                |(mut writer, mut triples, mut slow_query, permit)| {
                     Ok(if let Some(t) = triples.next() {
                         writer.write(&t?)?;
-                        Some((writer, triples))
+                        if let Some(slow_query) = &mut slow_query {
+                            slow_query.add_result();
+                        }
+                        Some((writer, triples, slow_query, permit))
                     } else {
                         writer.finish()?;
                         None
                     })
                 },
                 format.media_type(),
+                content_encoding,
+            )
+        }
+    }
+}
+
+/// Reads a SPARQL update from the request and evaluates it using `evaluate`.
+fn handle_sparql_update_request(
+    request: &mut Request,
+    state: &ServerState,
+    evaluate: impl FnOnce(Update) -> Result<(), EvaluationError>,
+) -> Response {
+    if let Some(content_type) = content_type(request) {
+        if content_type == "application/sparql-update" {
+            let mut buffer = String::new();
+            if let Err(e) = request
+                .body_mut()
+                .take(MAX_SPARQL_BODY_SIZE)
+                .read_to_string(&mut buffer)
+            {
+                return bad_request(e);
+            }
+            configure_and_evaluate_sparql_update(
+                state,
+                &[url_query(request)],
+                Some(buffer),
+                request,
+                evaluate,
+            )
+        } else if content_type == "application/x-www-form-urlencoded" {
+            let mut buffer = Vec::new();
+            if let Err(e) = request
+                .body_mut()
+                .take(MAX_SPARQL_BODY_SIZE)
+                .read_to_end(&mut buffer)
+            {
+                return bad_request(e);
+            }
+            configure_and_evaluate_sparql_update(
+                state,
+                &[url_query(request), &buffer],
+                None,
+                request,
+                evaluate,
             )
+        } else {
+            unsupported_media_type(&content_type)
         }
+    } else {
+        bad_request("No Content-Type given")
     }
 }
 
 fn configure_and_evaluate_sparql_update(
-    store: Store,
+    state: &ServerState,
     encoded: &[&[u8]],
     mut update: Option<String>,
     request: &Request,
+    evaluate: impl FnOnce(Update) -> Result<(), EvaluationError>,
 ) -> Response {
     let mut use_default_graph_as_union = false;
     let mut default_graph_uris = Vec::new();
@@ -720,12 +2100,13 @@ fn configure_and_evaluate_sparql_update(
     }
     if let Some(update) = update {
         evaluate_sparql_update(
-            store,
+            state,
             update,
             use_default_graph_as_union,
             default_graph_uris,
             named_graph_uris,
             request,
+            evaluate,
         )
     } else {
         bad_request("You should set the 'update' parameter")
@@ -733,12 +2114,13 @@ fn configure_and_evaluate_sparql_update(
 }
 
 fn evaluate_sparql_update(
-    store: Store,
+    state: &ServerState,
     update: String,
     use_default_graph_as_union: bool,
     default_graph_uris: Vec<String>,
     named_graph_uris: Vec<String>,
     request: &Request,
+    evaluate: impl FnOnce(Update) -> Result<(), EvaluationError>,
 ) -> Response {
     let mut update = match Update::parse(
         &update,
@@ -754,6 +2136,32 @@ fn evaluate_sparql_update(
         Err(e) => return bad_request(e),
     };
 
+    let slow_query_dataset = if use_default_graph_as_union {
+        SlowQueryDataset {
+            default_graph: None,
+            named_graphs: None,
+        }
+    } else if !default_graph_uris.is_empty() || !named_graph_uris.is_empty() {
+        SlowQueryDataset {
+            default_graph: Some(
+                default_graph_uris
+                    .iter()
+                    .map(|g| format!("<{}>", g))
+                    .collect(),
+            ),
+            named_graphs: Some(
+                named_graph_uris
+                    .iter()
+                    .map(|g| format!("<{}>", g))
+                    .collect(),
+            ),
+        }
+    } else {
+        SlowQueryDataset {
+            default_graph: Some(vec![GraphName::DefaultGraph.to_string()]),
+            named_graphs: None,
+        }
+    };
     if use_default_graph_as_union {
         if !default_graph_uris.is_empty() || !named_graph_uris.is_empty() {
             return bad_request(
@@ -795,14 +2203,157 @@ fn evaluate_sparql_update(
             using.set_available_named_graphs(named_graph_uris.clone());
         }
     }
-    if let Err(e) = store.update(update) {
+    let _permit = match state.limits.acquire(request) {
+        Ok(permit) => permit,
+        Err(response) => return response,
+    };
+    let _slow_query = state.logger.slow_query(
+        request,
+        SparqlOperation::Update,
+        || update.to_string(),
+        slow_query_dataset,
+    );
+    if let Err(e) = evaluate(update) {
+        return evaluation_error(e);
+    }
+    Response::builder(Status::NO_CONTENT).build()
+}
+
+/// Parses a SPARQL update sent using PATCH to a Graph Store Protocol graph.
+///
+/// The update default graph is replaced by the target graph and the update is not allowed to modify other graphs.
+fn parse_graph_patch_update(
+    update: &str,
+    base_url: &str,
+    target: &NamedGraphName,
+) -> Result<Update, String> {
+    let mut update = spargebra::Update::parse(update, Some(base_url)).map_err(|e| e.to_string())?;
+    let target_graph = match target {
+        NamedGraphName::NamedNode(target) => spargebra::term::GraphName::NamedNode(target.clone()),
+        NamedGraphName::DefaultGraph => spargebra::term::GraphName::DefaultGraph,
+    };
+    let target_pattern = match target {
+        NamedGraphName::NamedNode(target) => GraphNamePattern::NamedNode(target.clone()),
+        NamedGraphName::DefaultGraph => GraphNamePattern::DefaultGraph,
+    };
+    for operation in &mut update.operations {
+        match operation {
+            GraphUpdateOperation::InsertData { data } => {
+                for quad in data {
+                    scope_graph_name(&mut quad.graph_name, &target_graph)?;
+                }
+            }
+            GraphUpdateOperation::DeleteData { data } => {
+                for quad in data {
+                    scope_graph_name(&mut quad.graph_name, &target_graph)?;
+                }
+            }
+            GraphUpdateOperation::DeleteInsert { delete, insert, .. } => {
+                for quad in delete {
+                    scope_graph_name_pattern(&mut quad.graph_name, &target_pattern)?;
+                }
+                for quad in insert {
+                    scope_graph_name_pattern(&mut quad.graph_name, &target_pattern)?;
+                }
+            }
+            _ => {
+                return Err(
+                    "Only INSERT DATA, DELETE DATA and DELETE/INSERT operations are allowed in PATCH requests"
+                        .to_owned(),
+                )
+            }
+        }
+    }
+    let mut update = Update::parse(&update.to_string(), None).map_err(|e| e.to_string())?;
+    if let NamedGraphName::NamedNode(target) = target {
+        for using in update.using_datasets_mut() {
+            if using.is_default_dataset() {
+                using.set_default_graph(vec![target.clone().into()]);
+            }
+        }
+    }
+    Ok(update)
+}
+
+fn scope_graph_name(
+    graph_name: &mut spargebra::term::GraphName,
+    target: &spargebra::term::GraphName,
+) -> Result<(), String> {
+    if *graph_name == spargebra::term::GraphName::DefaultGraph {
+        *graph_name = target.clone();
+    } else if graph_name != target {
+        return Err(format!(
+            "The update is not allowed to modify the graph {}",
+            graph_name
+        ));
+    }
+    Ok(())
+}
+
+fn scope_graph_name_pattern(
+    graph_name: &mut GraphNamePattern,
+    target: &GraphNamePattern,
+) -> Result<(), String> {
+    if *graph_name == GraphNamePattern::DefaultGraph {
+        *graph_name = target.clone();
+    } else if graph_name != target {
+        return Err(format!(
+            "The update is not allowed to modify the graph {}",
+            graph_name
+        ));
+    }
+    Ok(())
+}
+
+/// Applies an RDF Patch to a Graph Store Protocol graph in a single transaction.
+///
+/// The triples are added to or removed from the target graph and the patch is not allowed to modify other graphs.
+fn apply_rdf_patch(store: &Store, patch: &str, target: NamedGraphName) -> Response {
+    let changes = match parse_rdf_patch(patch) {
+        Ok(changes) => changes,
+        Err(e) => return bad_request(e),
+    };
+    let target = GraphName::from(target);
+    for change in &changes {
+        let (RdfPatchChange::Add(quad) | RdfPatchChange::Delete(quad)) = change;
+        if !quad.graph_name.is_default_graph() && quad.graph_name != target {
+            return bad_request(format!(
+                "The patch is not allowed to modify the graph {}",
+                quad.graph_name
+            ));
+        }
+    }
+    let result = store.transaction(|mut transaction| {
+        for change in &changes {
+            match change {
+                RdfPatchChange::Add(quad) => {
+                    transaction.insert(QuadRef::new(
+                        &quad.subject,
+                        &quad.predicate,
+                        &quad.object,
+                        &target,
+                    ))?;
+                }
+                RdfPatchChange::Delete(quad) => {
+                    transaction.remove(QuadRef::new(
+                        &quad.subject,
+                        &quad.predicate,
+                        &quad.object,
+                        &target,
+                    ))?;
+                }
+            }
+        }
+        Result::<_, StorageError>::Ok(())
+    });
+    if let Err(e) = result {
         return internal_server_error(e);
     }
     Response::builder(Status::NO_CONTENT).build()
 }
 
-fn store_target(request: &Request) -> Result<Option<NamedGraphName>, Response> {
-    if request.url().path() == "/store" {
+fn store_target(request: &Request, path: &str) -> Result<Option<NamedGraphName>, Response> {
+    if path == "/store" {
         let mut graph = None;
         let mut default = false;
         for (k, v) in form_urlencoded::parse(request.url().query().unwrap_or("").as_bytes()) {
@@ -964,6 +2515,112 @@ fn content_type(request: &Request) -> Option<String> {
     )
 }
 
+/// The supported HTTP content codings.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+enum ContentEncoding {
+    Identity,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+impl ContentEncoding {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "identity" => Some(Self::Identity),
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "br" => Some(Self::Brotli),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn encode(self, read: impl Read + 'static) -> io::Result<Box<dyn Read>> {
+        Ok(match self {
+            Self::Identity => Box::new(read),
+            Self::Gzip => Box::new(flate2::read::GzEncoder::new(read, Compression::default())),
+            Self::Brotli => Box::new(brotli::CompressorReader::new(read, 4096, 5, 22)),
+            Self::Zstd => Box::new(zstd::stream::read::Encoder::new(read, 0)?),
+        })
+    }
+
+    fn decode(self, read: impl Read + 'static) -> io::Result<Box<dyn Read>> {
+        Ok(match self {
+            Self::Identity => Box::new(read),
+            Self::Gzip => Box::new(MultiGzDecoder::new(read)),
+            Self::Brotli => Box::new(brotli::Decompressor::new(read, 4096)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(read)?),
+        })
+    }
+}
+
+/// Picks the response content coding from the Accept-Encoding header.
+///
+/// The identity coding is used if no supported coding is accepted.
+fn content_encoding_negotiation(request: &Request) -> Result<ContentEncoding, Response> {
+    let header = if let Some(header) = request.header(&HeaderName::ACCEPT_ENCODING) {
+        header
+            .to_str()
+            .map_err(|_| bad_request("The Accept-Encoding header should be a valid ASCII string"))?
+    } else {
+        return Ok(ContentEncoding::Identity);
+    };
+    let mut result = ContentEncoding::Identity;
+    let mut result_score = 0f32;
+    for possible in header.split(',') {
+        let (possible, parameters) = possible.split_once(';').unwrap_or((possible, ""));
+        let mut score = 1.;
+        for parameter in parameters.split(';') {
+            let parameter = parameter.trim();
+            if let Some(s) = parameter.strip_prefix("q=") {
+                score = f32::from_str(s.trim()).map_err(|_| {
+                    bad_request(format!("Invalid Accept-Encoding coding score: {}", s))
+                })?
+            }
+        }
+        if score <= result_score {
+            continue;
+        }
+        if let Some(candidate) = ContentEncoding::from_name(possible.trim()) {
+            result = candidate;
+            result_score = score;
+        }
+    }
+    Ok(result)
+}
+
+/// Replaces the request body by its decoded version according to the Content-Encoding header.
+fn decode_request_body(request: &mut Request) -> Result<(), Response> {
+    let content_encoding = if let Some(header) = request.header(&HeaderName::CONTENT_ENCODING) {
+        let header = header
+            .to_str()
+            .map_err(|_| bad_request("The Content-Encoding header should be a valid ASCII string"))?
+            .trim();
+        ContentEncoding::from_name(header).ok_or_else(|| {
+            error(
+                Status::UNSUPPORTED_MEDIA_TYPE,
+                format!("Not supported Content-Encoding: {}", header),
+            )
+        })?
+    } else {
+        return Ok(());
+    };
+    if content_encoding != ContentEncoding::Identity {
+        let body = mem::replace(request.body_mut(), Body::from(Vec::new()));
+        *request.body_mut() = Body::from_read(content_encoding.decode(body).map_err(bad_request)?);
+    }
+    Ok(())
+}
+
 fn error(status: Status, message: impl fmt::Display) -> Response {
     Response::builder(status)
         .with_header(HeaderName::CONTENT_TYPE, "text/plain; charset=utf-8")
@@ -987,12 +2644,30 @@ fn internal_server_error(message: impl fmt::Display) -> Response {
     error(Status::INTERNAL_SERVER_ERROR, message)
 }
 
+/// Returns 409 Conflict if the operation conflicts with a concurrent transaction and 500 Internal Server Error if not.
+fn storage_error(e: StorageError) -> Response {
+    if matches!(e, StorageError::Conflict) {
+        error(Status::CONFLICT, e)
+    } else {
+        internal_server_error(e)
+    }
+}
+
+fn evaluation_error(e: EvaluationError) -> Response {
+    match e {
+        EvaluationError::Storage(e) => storage_error(e),
+        e => internal_server_error(e),
+    }
+}
+
 /// Hacky tool to allow implementing read on top of a write loop
 struct ReadForWrite<O, U: (Fn(O) -> std::io::Result<Option<O>>)> {
     buffer: Rc<RefCell<Vec<u8>>>,
     position: usize,
     add_more_data: U,
     state: Option<O>,
+    /// The resources of the request. They are released after the state is dropped.
+    _resources: RequestResources,
 }
 
 impl<O: 'static, U: (Fn(O) -> std::io::Result<Option<O>>) + 'static> ReadForWrite<O, U> {
@@ -1000,22 +2675,36 @@ impl<O: 'static, U: (Fn(O) -> std::io::Result<Option<O>>) + 'static> ReadForWrit
         initial_state_builder: impl FnOnce(ReadForWriteWriter) -> std::io::Result<O>,
         add_more_data: U,
         content_type: &'static str,
+        content_encoding: ContentEncoding,
     ) -> Response {
         let buffer = Rc::new(RefCell::new(Vec::new()));
-        match initial_state_builder(ReadForWriteWriter {
+        let state = match initial_state_builder(ReadForWriteWriter {
             buffer: buffer.clone(),
         }) {
-            Ok(state) => Response::builder(Status::OK)
-                .with_header(HeaderName::CONTENT_TYPE, content_type)
-                .unwrap()
-                .with_body(Body::from_read(Self {
-                    buffer,
-                    position: 0,
-                    add_more_data,
-                    state: Some(state),
-                })),
-            Err(e) => internal_server_error(e),
+            Ok(state) => state,
+            Err(e) => return internal_server_error(e),
+        };
+        let body = match content_encoding.encode(Self {
+            buffer,
+            position: 0,
+            add_more_data,
+            state: Some(state),
+            _resources: REQUEST_RESOURCES.with(|resources| resources.borrow().clone()),
+        }) {
+            Ok(body) => body,
+            Err(e) => return internal_server_error(e),
+        };
+        let mut response = Response::builder(Status::OK)
+            .with_header(HeaderName::CONTENT_TYPE, content_type)
+            .unwrap()
+            .with_header(HeaderName::VARY, "Accept-Encoding")
+            .unwrap();
+        if content_encoding != ContentEncoding::Identity {
+            response = response
+                .with_header(HeaderName::CONTENT_ENCODING, content_encoding.name())
+                .unwrap();
         }
+        response.with_body(Body::from_read(body))
     }
 }
 
@@ -1070,6 +2759,7 @@ impl Write for ReadForWriteWriter {
 mod tests {
     use super::*;
     use oxhttp::model::Method;
+    use std::ffi::OsString;
 
     #[test]
     fn get_ui() {
@@ -1124,200 +2814,1255 @@ mod tests {
         )
         .with_header(HeaderName::ACCEPT, "text/csv")
         .unwrap()
-        .build();
+        .build();
+        server.test_body(
+            request,
+            "s,p,o\r\nhttp://example.com,http://example.com,http://example.com",
+        );
+    }
+
+    #[test]
+    fn get_query_accept_star() {
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::ACCEPT, "*/*")
+        .unwrap()
+        .build();
+        ServerTest::new().test_body(
+            request,
+            "{\"head\":{\"vars\":[\"s\",\"p\",\"o\"]},\"results\":{\"bindings\":[]}}",
+        );
+    }
+
+    #[test]
+    fn get_query_accept_good() {
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(
+            HeaderName::ACCEPT,
+            "application/sparql-results+json;charset=utf-8",
+        )
+        .unwrap()
+        .build();
+        ServerTest::new().test_body(
+            request,
+            "{\"head\":{\"vars\":[\"s\",\"p\",\"o\"]},\"results\":{\"bindings\":[]}}",
+        );
+    }
+
+    #[test]
+    fn get_query_accept_bad() {
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/query?query=SELECT%20*%20WHERE%20{%20?s%20?p%20?o%20}"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::ACCEPT, "application/foo")
+        .unwrap()
+        .build();
+        ServerTest::new().test_status(request, Status::NOT_ACCEPTABLE);
+    }
+
+    #[test]
+    fn get_bad_query() {
+        ServerTest::new().test_status(
+            Request::builder(
+                Method::GET,
+                "http://localhost/query?query=SELECT".parse().unwrap(),
+            )
+            .build(),
+            Status::BAD_REQUEST,
+        );
+    }
+
+    #[test]
+    fn get_query_union_graph() {
+        let server = ServerTest::new();
+
+        let request = Request::builder(Method::PUT, "http://localhost/store/1".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
+            .unwrap()
+            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
+        server.test_status(request, Status::CREATED);
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}&union-default-graph"
+                .parse()
+                .unwrap(),
+        ).with_header(HeaderName::ACCEPT, "text/csv")
+            .unwrap()
+            .build();
+        server.test_body(
+            request,
+            "s,p,o\r\nhttp://example.com,http://example.com,http://example.com",
+        );
+    }
+
+    #[test]
+    fn get_query_union_graph_in_url_and_urlencoded() {
+        let server = ServerTest::new();
+
+        let request = Request::builder(Method::PUT, "http://localhost/store/1".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
+            .unwrap()
+            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
+        server.test_status(request, Status::CREATED);
+
+        let request = Request::builder(
+            Method::POST,
+            "http://localhost/query?union-default-graph"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(
+            HeaderName::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .unwrap()
+        .with_header(HeaderName::ACCEPT, "text/csv")
+        .unwrap()
+        .with_body("query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}");
+        server.test_body(
+            request,
+            "s,p,o\r\nhttp://example.com,http://example.com,http://example.com",
+        );
+    }
+
+    #[test]
+    fn get_query_union_graph_and_default_graph() {
+        ServerTest::new().test_status(Request::builder(
+            Method::GET,
+            "http://localhost/query?query=SELECT%20*%20WHERE%20{%20?s%20?p%20?o%20}&union-default-graph&default-graph-uri=http://example.com".parse()
+                .unwrap(),
+        ).build(), Status::BAD_REQUEST);
+    }
+
+    #[test]
+    fn get_without_query() {
+        ServerTest::new().test_status(
+            Request::builder(Method::GET, "http://localhost/query".parse().unwrap()).build(),
+            Status::BAD_REQUEST,
+        );
+    }
+
+    #[test]
+    fn post_query() {
+        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap()
+            .with_body("SELECT * WHERE { ?s ?p ?o }");
+        ServerTest::new().test_status(request, Status::OK)
+    }
+
+    #[test]
+    fn post_bad_query() {
+        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap()
+            .with_body("SELECT");
+        ServerTest::new().test_status(request, Status::BAD_REQUEST)
+    }
+
+    #[test]
+    fn post_unknown_query() {
+        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-todo")
+            .unwrap()
+            .with_body("SELECT");
+        ServerTest::new().test_status(request, Status::UNSUPPORTED_MEDIA_TYPE)
+    }
+
+    #[test]
+    fn post_federated_query() {
+        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap().with_body("SELECT * WHERE { SERVICE <https://query.wikidata.org/sparql> { <https://en.wikipedia.org/wiki/Paris> ?p ?o } }");
+        ServerTest::new().test_status(request, Status::OK)
+    }
+
+    #[test]
+    fn post_update() {
+        let request = Request::builder(Method::POST, "http://localhost/update".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+            .unwrap()
+            .with_body(
+                "INSERT DATA { <http://example.com> <http://example.com> <http://example.com> }",
+            );
+        ServerTest::new().test_status(request, Status::NO_CONTENT)
+    }
+
+    #[test]
+    fn post_bad_update() {
+        let request = Request::builder(Method::POST, "http://localhost/update".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+            .unwrap()
+            .with_body("INSERT");
+        ServerTest::new().test_status(request, Status::BAD_REQUEST)
+    }
+
+    #[test]
+    fn read_only_rejects_writes() {
+        let server = ServerTest::new_read_only();
+
+        let request = Request::builder(Method::POST, "http://localhost/update".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+            .unwrap()
+            .with_body("LOAD <http://example.com/data.nt>");
+        server.test_status(request, Status::FORBIDDEN);
+
+        let request = Request::builder(Method::PUT, "http://localhost/store/1".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
+            .unwrap()
+            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
+        server.test_status(request, Status::FORBIDDEN);
+
+        server.test_status(
+            Request::builder(Method::DELETE, "http://localhost/store".parse().unwrap()).build(),
+            Status::FORBIDDEN,
+        );
+
+        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap()
+            .with_body("SELECT * WHERE { ?s ?p ?o }");
+        server.test_status(request, Status::OK);
+    }
+
+    #[test]
+    fn named_datasets() {
+        let server = ServerTest::new();
+
+        // The administration requires the admin token
+        server.test_status(
+            Request::builder(
+                Method::PUT,
+                "http://localhost/datasets/foo".parse().unwrap(),
+            )
+            .build(),
+            Status::UNAUTHORIZED,
+        );
+        server.test_status(
+            Request::builder(
+                Method::PUT,
+                "http://localhost/datasets/foo".parse().unwrap(),
+            )
+            .with_header(HeaderName::AUTHORIZATION, "Bearer foo")
+            .unwrap()
+            .build(),
+            Status::UNAUTHORIZED,
+        );
+        let mut without_token = ServerTest::new();
+        without_token.state.admin_token = None;
+        without_token.test_status(
+            admin_request(Method::PUT, "http://localhost/datasets/foo"),
+            Status::FORBIDDEN,
+        );
+
+        server.test_status(
+            admin_request(Method::PUT, "http://localhost/datasets/foo"),
+            Status::CREATED,
+        );
+        server.test_status(
+            admin_request(Method::PUT, "http://localhost/datasets/foo"),
+            Status::NO_CONTENT,
+        );
+        server.test_status(
+            admin_request(Method::PUT, "http://localhost/datasets/f%20o"),
+            Status::BAD_REQUEST,
+        );
+        server.test_body(
+            Request::builder(Method::GET, "http://localhost/datasets".parse().unwrap()).build(),
+            "[\"foo\"]",
+        );
+
+        let request = Request::builder(
+            Method::POST,
+            "http://localhost/datasets/foo/store".parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
+        .unwrap()
+        .with_body("<http://example.com> <http://example.com> <http://example.com> .");
+        let response = server.exec(request);
+        assert_eq!(response.status(), Status::CREATED);
+        assert!(response
+            .header(&HeaderName::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("http://localhost/datasets/foo/store/"));
+
+        let request = Request::builder(
+            Method::POST,
+            "http://localhost/datasets/foo/update".parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+        .unwrap()
+        .with_body(
+            "INSERT DATA { <http://example.com/s> <http://example.com/p> <http://example.com/o> }",
+        );
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(
+            Method::POST,
+            "http://localhost/datasets/foo/query".parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+        .unwrap()
+        .with_body("ASK { <http://example.com/s> ?p ?o }");
+        server.test_body(request, "{\"head\":{},\"boolean\":true}");
+
+        // The default dataset is not modified
+        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap()
+            .with_body("ASK { ?s ?p ?o }");
+        server.test_body(request, "{\"head\":{},\"boolean\":false}");
+
+        // The dataset could not be deleted while a transaction is using it
+        let response = server.exec(
+            Request::builder(
+                Method::POST,
+                "http://localhost/datasets/foo/transactions"
+                    .parse()
+                    .unwrap(),
+            )
+            .build(),
+        );
+        assert_eq!(response.status(), Status::CREATED);
+        let transaction = response
+            .header(&HeaderName::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        server.test_status(
+            admin_request(Method::DELETE, "http://localhost/datasets/foo"),
+            Status::CONFLICT,
+        );
+        server.test_status(
+            Request::builder(
+                Method::DELETE,
+                format!("http://localhost{}", transaction).parse().unwrap(),
+            )
+            .build(),
+            Status::NO_CONTENT,
+        );
+
+        server.test_status(
+            admin_request(Method::DELETE, "http://localhost/datasets/foo"),
+            Status::NO_CONTENT,
+        );
+        server.test_status(
+            Request::builder(
+                Method::GET,
+                "http://localhost/datasets/foo/store".parse().unwrap(),
+            )
+            .build(),
+            Status::NOT_FOUND,
+        );
+        server.test_status(
+            admin_request(Method::DELETE, "http://localhost/datasets/foo"),
+            Status::NOT_FOUND,
+        );
+    }
+
+    #[test]
+    fn content_encoding() {
+        let server = ServerTest::new();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(b"<http://example.com> <http://example.com> <http://example.com> .")
+            .unwrap();
+        let request = Request::builder(
+            Method::POST,
+            "http://localhost/store?default".parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
+        .unwrap()
+        .with_header(HeaderName::CONTENT_ENCODING, "gzip")
+        .unwrap()
+        .with_body(encoder.finish().unwrap());
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/store?default".parse().unwrap(),
+        )
+        .with_header(HeaderName::ACCEPT, "application/n-triples")
+        .unwrap()
+        .with_header(HeaderName::ACCEPT_ENCODING, "br;q=0.5, gzip, zstd;q=0")
+        .unwrap()
+        .build();
+        let mut response = server.exec(request);
+        assert_eq!(response.status(), Status::OK);
+        assert_eq!(
+            response
+                .header(&HeaderName::CONTENT_ENCODING)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "gzip"
+        );
+        let mut buf = String::new();
+        MultiGzDecoder::new(response.body_mut())
+            .read_to_string(&mut buf)
+            .unwrap();
+        assert_eq!(
+            buf,
+            "<http://example.com> <http://example.com> <http://example.com> .\n"
+        );
+
+        let request = Request::builder(Method::POST, "http://localhost/update".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+            .unwrap()
+            .with_header(HeaderName::CONTENT_ENCODING, "foo")
+            .unwrap()
+            .with_body("CLEAR ALL");
+        server.test_status(request, Status::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn graph_store_patch() {
+        let server = ServerTest::new();
+
+        let request = Request::builder(
+            Method::PATCH,
+            "http://localhost/store?graph=http://example.com/g"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+        .unwrap()
+        .with_body("INSERT DATA { <http://example.com/s> <http://example.com/p> \"o\" }");
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(
+            Method::PATCH,
+            "http://localhost/store?graph=http://example.com/g"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+        .unwrap()
+        .with_body("DELETE { ?s ?p \"o\" } INSERT { ?s ?p \"o2\" } WHERE { ?s ?p \"o\" }");
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(
+            Method::PATCH,
+            "http://localhost/store?graph=http://example.com/g"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
+        .unwrap()
+        .with_body("INSERT DATA { GRAPH <http://example.com/g2> { <http://example.com/s> <http://example.com/p> \"o\" } }");
+        server.test_status(request, Status::BAD_REQUEST);
+
+        let request = Request::builder(
+            Method::PATCH,
+            "http://localhost/store?graph=http://example.com/g"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/rdf-patch")
+        .unwrap()
+        .with_body(
+            "TX .\nPA ex <http://example.com/> .\nD ex:s ex:p \"o2\" .\nA ex:s ex:p \"o3\"@en .\nTC .\nTX .\nA ex:s ex:p \"aborted\" .\nTA .\n",
+        );
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/store?graph=http://example.com/g"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::ACCEPT, "application/n-triples")
+        .unwrap()
+        .build();
+        server.test_body(
+            request,
+            "<http://example.com/s> <http://example.com/p> \"o3\"@en .\n",
+        );
+
+        let request = Request::builder(
+            Method::PATCH,
+            "http://localhost/store?graph=http://example.com/g"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/rdf-patch")
+        .unwrap()
+        .with_body("A <http://example.com/s> <http://example.com/p> .\n");
+        server.test_status(request, Status::BAD_REQUEST);
+    }
+
+    #[test]
+    fn triple_pattern_fragments() {
+        let server = ServerTest::new();
+
+        let request = Request::builder(Method::POST, "http://localhost/store".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/n-quads")
+            .unwrap()
+            .with_body("<http://example.com/s> <http://example.com/p> \"o\"@en <http://example.com/g> .\n<http://example.com/s2> <http://example.com/p> \"o\" .");
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/fragments?subject=http%3A%2F%2Fexample.com%2Fs&object=%22o%22%40en"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::ACCEPT, "application/n-quads")
+        .unwrap()
+        .build();
+        let mut response = server.exec(request);
+        assert_eq!(response.status(), Status::OK);
+        let mut buf = String::new();
+        response.body_mut().read_to_string(&mut buf).unwrap();
+        assert!(buf.contains(
+            "<http://example.com/s> <http://example.com/p> \"o\"@en <http://example.com/g> .\n"
+        ));
+        assert!(!buf.contains("<http://example.com/s2>"));
+        assert!(buf.contains(
+            "<http://rdfs.org/ns/void#triples> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+        ));
+        assert!(!buf.contains("<http://www.w3.org/ns/hydra/core#next>"));
+        assert!(buf.contains(
+            "<http://www.w3.org/ns/hydra/core#totalItems> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+        ));
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/fragments?predicate=http%3A%2F%2Fexample.com%2Fp"
+                .parse()
+                .unwrap(),
+        )
+        .with_header(HeaderName::ACCEPT, "application/ld+json")
+        .unwrap()
+        .build();
+        server.test_status(request, Status::OK);
+
+        server.test_status(
+            Request::builder(
+                Method::GET,
+                "http://localhost/fragments?subject=%22o%22"
+                    .parse()
+                    .unwrap(),
+            )
+            .build(),
+            Status::BAD_REQUEST,
+        );
+
+        // The deep pages are not served
+        server.test_status(
+            Request::builder(
+                Method::GET,
+                "http://localhost/fragments?page=1000".parse().unwrap(),
+            )
+            .build(),
+            Status::OK,
+        );
+        server.test_status(
+            Request::builder(
+                Method::GET,
+                "http://localhost/fragments?page=1001".parse().unwrap(),
+            )
+            .build(),
+            Status::BAD_REQUEST,
+        );
+    }
+
+    #[test]
+    fn linked_data() {
+        let server = ServerTest::new_linked_data();
+
+        let request = Request::builder(Method::POST, "http://localhost/store".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/n-quads")
+            .unwrap()
+            .with_body("<http://localhost/foo> <http://example.com/p> _:b .\n_:b <http://example.com/p> \"<b>\" <http://localhost/store/g> .");
+        server.test_status(request, Status::NO_CONTENT);
+
+        let request = Request::builder(Method::GET, "http://localhost/foo".parse().unwrap())
+            .with_header(HeaderName::ACCEPT, "application/n-triples")
+            .unwrap()
+            .build();
+        let mut response = server.exec(request);
+        assert_eq!(response.status(), Status::OK);
+        let mut buf = String::new();
+        response.body_mut().read_to_string(&mut buf).unwrap();
+        assert_eq!(buf.lines().count(), 2);
+
+        let request = Request::builder(Method::GET, "http://localhost/foo".parse().unwrap())
+            .with_header(HeaderName::ACCEPT, "text/html,*/*;q=0.8")
+            .unwrap()
+            .build();
+        let mut response = server.exec(request);
+        assert_eq!(response.status(), Status::OK);
+        let mut buf = String::new();
+        response.body_mut().read_to_string(&mut buf).unwrap();
+        assert!(buf.contains("<a href=\"http://localhost/foo\">"));
+        assert!(buf.contains("&quot;&lt;b&gt;&quot;"));
+
+        let request = Request::builder(Method::GET, "http://localhost/store/g".parse().unwrap())
+            .with_header(HeaderName::ACCEPT, "text/html")
+            .unwrap()
+            .build();
+        server.test_status(request, Status::OK);
+
+        let request = Request::builder(Method::PUT, "http://localhost/store/big".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/n-triples")
+            .unwrap()
+            .with_body(
+                (0..1001)
+                    .map(|i| {
+                        format!(
+                            "<http://localhost/foo> <http://example.com/p> \"{}\" .\n",
+                            i
+                        )
+                    })
+                    .collect::<String>(),
+            );
+        server.test_status(request, Status::CREATED);
+        let request = Request::builder(Method::GET, "http://localhost/store/big".parse().unwrap())
+            .with_header(HeaderName::ACCEPT, "text/html")
+            .unwrap()
+            .build();
+        let mut response = server.exec(request);
+        assert_eq!(response.status(), Status::OK);
+        let mut buf = String::new();
+        response.body_mut().read_to_string(&mut buf).unwrap();
+        assert_eq!(buf.matches("<tr><td>").count(), 1000);
+        assert!(buf.contains("<a href=\"/store?graph=http%3A%2F%2Flocalhost%2Fstore%2Fbig\">"));
+
+        server.test_status(
+            Request::builder(Method::GET, "http://localhost/bar".parse().unwrap()).build(),
+            Status::NOT_FOUND,
+        );
+        ServerTest::new().test_status(
+            Request::builder(Method::GET, "http://localhost/foo".parse().unwrap()).build(),
+            Status::NOT_FOUND,
+        );
+    }
+
+    #[test]
+    fn query_jobs() {
+        let server = ServerTest::new();
+        let request = Request::builder(Method::POST, "http://localhost/jobs".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap()
+            .with_body("SELECT ?s WHERE { VALUES ?s { 1 2 } }");
+        let response = server.exec(request);
+        assert_eq!(response.status(), Status::ACCEPTED);
+        let location = response
+            .header(&HeaderName::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let job_url = format!("http://localhost{}", location);
+
+        // We wait for the job to be done
+        let mut status = String::new();
+        for _ in 0..100 {
+            let mut response =
+                server.exec(Request::builder(Method::GET, job_url.parse().unwrap()).build());
+            assert_eq!(response.status(), Status::OK);
+            status.clear();
+            response.body_mut().read_to_string(&mut status).unwrap();
+            if status.contains("succeeded") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(status.contains("\"succeeded\""), "{}", status);
+
+        let request =
+            Request::builder(Method::GET, format!("{}/results", job_url).parse().unwrap())
+                .with_header(HeaderName::ACCEPT, "text/csv")
+                .unwrap()
+                .build();
+        server.test_body(request, "s\r\n1\r\n2\r\n");
+
+        server.test_status(
+            Request::builder(Method::DELETE, job_url.parse().unwrap()).build(),
+            Status::NO_CONTENT,
+        );
+        server.test_status(
+            Request::builder(Method::GET, job_url.parse().unwrap()).build(),
+            Status::NOT_FOUND,
+        );
+        server.test_status(
+            Request::builder(
+                Method::GET,
+                format!("http://localhost/datasets/foo{}", location)
+                    .parse()
+                    .unwrap(),
+            )
+            .build(),
+            Status::NOT_FOUND,
+        );
+
+        let request = Request::builder(Method::POST, "http://localhost/jobs".parse().unwrap())
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+            .unwrap()
+            .with_body("SELECT");
+        server.test_status(request, Status::BAD_REQUEST);
+    }
+
+    #[test]
+    fn interactive_transactions() {
+        let server = ServerTest::new();
+        let start_transaction = || {
+            let response = server.exec(
+                Request::builder(
+                    Method::POST,
+                    "http://localhost/transactions".parse().unwrap(),
+                )
+                .build(),
+            );
+            assert_eq!(response.status(), Status::CREATED);
+            format!(
+                "http://localhost{}",
+                response
+                    .header(&HeaderName::LOCATION)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+            )
+        };
+        let count_request = || {
+            Request::builder(
+                Method::GET,
+                "http://localhost/query?query=SELECT%20(COUNT(*)%20AS%20?c)%20WHERE%20%7B%20?s%20?p%20?o%20%7D"
+                    .parse()
+                    .unwrap(),
+            )
+            .with_header(HeaderName::ACCEPT, "text/csv")
+            .unwrap()
+            .build()
+        };
+
+        // Committed transaction
+        let transaction = start_transaction();
+        let request = Request::builder(
+            Method::PUT,
+            format!("{}?action=ADD", transaction).parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/n-triples")
+        .unwrap()
+        .with_body("<http://example.com/s> <http://example.com/p> <http://example.com/o> .");
+        server.test_status(request, Status::NO_CONTENT);
+        server.test_body(
+            Request::builder(
+                Method::PUT,
+                format!("{}?action=SIZE", transaction).parse().unwrap(),
+            )
+            .build(),
+            "1",
+        );
+        let request = Request::builder(
+            Method::PUT,
+            format!("{}?action=QUERY", transaction).parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+        .unwrap()
+        .with_header(HeaderName::ACCEPT, "text/csv")
+        .unwrap()
+        .with_body("SELECT ?s WHERE { ?s ?p ?o }");
+        server.test_body(request, "s\r\nhttp://example.com/s\r\n");
+        let request = Request::builder(
+            Method::PUT,
+            format!("{}?action=QUERY", transaction).parse().unwrap(),
+        )
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+        .unwrap()
+        .with_header(HeaderName::ACCEPT, "application/n-triples")
+        .unwrap()
+        .with_body("CONSTRUCT WHERE { ?s ?p ?o }");
         server.test_body(
             request,
-            "s,p,o\r\nhttp://example.com,http://example.com,http://example.com",
+            "<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n",
+        );
+        server.test_body(count_request(), "c\r\n0\r\n");
+        server.test_status(
+            Request::builder(
+                Method::PUT,
+                format!("{}?action=COMMIT", transaction).parse().unwrap(),
+            )
+            .build(),
+            Status::OK,
+        );
+        server.test_body(count_request(), "c\r\n1\r\n");
+        server.test_status(
+            Request::builder(
+                Method::PUT,
+                format!("{}?action=SIZE", transaction).parse().unwrap(),
+            )
+            .build(),
+            Status::NOT_FOUND,
         );
-    }
 
-    #[test]
-    fn get_query_accept_star() {
+        // Rolled back transaction
+        let transaction = start_transaction();
         let request = Request::builder(
-            Method::GET,
-            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}"
-                .parse()
-                .unwrap(),
+            Method::PUT,
+            format!("{}?action=UPDATE", transaction).parse().unwrap(),
         )
-        .with_header(HeaderName::ACCEPT, "*/*")
+        .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
         .unwrap()
-        .build();
-        ServerTest::new().test_body(
-            request,
-            "{\"head\":{\"vars\":[\"s\",\"p\",\"o\"]},\"results\":{\"bindings\":[]}}",
+        .with_body("DELETE WHERE { ?s ?p ?o }");
+        server.test_status(request, Status::NO_CONTENT);
+        server.test_status(
+            Request::builder(Method::DELETE, transaction.parse().unwrap()).build(),
+            Status::NO_CONTENT,
+        );
+        server.test_body(count_request(), "c\r\n1\r\n");
+        server.test_status(
+            Request::builder(Method::DELETE, transaction.parse().unwrap()).build(),
+            Status::NOT_FOUND,
         );
-    }
 
-    #[test]
-    fn get_query_accept_good() {
-        let request = Request::builder(
-            Method::GET,
-            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}"
-                .parse()
-                .unwrap(),
-        )
-        .with_header(
-            HeaderName::ACCEPT,
-            "application/sparql-results+json;charset=utf-8",
-        )
-        .unwrap()
-        .build();
-        ServerTest::new().test_body(
-            request,
-            "{\"head\":{\"vars\":[\"s\",\"p\",\"o\"]},\"results\":{\"bindings\":[]}}",
+        // Bad requests
+        let transaction = start_transaction();
+        server.test_status(
+            Request::builder(Method::PUT, transaction.parse().unwrap()).build(),
+            Status::BAD_REQUEST,
+        );
+        server.test_status(
+            Request::builder(
+                Method::PUT,
+                format!("{}?action=FOO", transaction).parse().unwrap(),
+            )
+            .build(),
+            Status::BAD_REQUEST,
+        );
+        server.test_status(
+            Request::builder(
+                Method::PUT,
+                "http://localhost/transactions/foo?action=SIZE"
+                    .parse()
+                    .unwrap(),
+            )
+            .build(),
+            Status::NOT_FOUND,
+        );
+
+        // Too many open transactions
+        start_transaction();
+        server.test_status(
+            Request::builder(
+                Method::POST,
+                "http://localhost/transactions".parse().unwrap(),
+            )
+            .build(),
+            Status::SERVICE_UNAVAILABLE,
+        );
+        ServerTest::new_read_only().test_status(
+            Request::builder(
+                Method::POST,
+                "http://localhost/transactions".parse().unwrap(),
+            )
+            .build(),
+            Status::FORBIDDEN,
         );
     }
 
     #[test]
-    fn get_query_accept_bad() {
+    fn access_and_slow_query_logs() {
+        let access_log_file = temp_dir().join(format!("oxigraph-access-{}.log", random::<u128>()));
+        let slow_query_log_file =
+            temp_dir().join(format!("oxigraph-slow-query-{}.log", random::<u128>()));
+        let mut server = ServerTest::new();
+        server.state.logger = Arc::new(
+            Logger::new(
+                Some(AccessLogFormat::Common),
+                Some(&access_log_file),
+                Some(Duration::from_millis(0)),
+                Some(&slow_query_log_file),
+            )
+            .unwrap(),
+        );
         let request = Request::builder(
             Method::GET,
-            "http://localhost/query?query=SELECT%20*%20WHERE%20{%20?s%20?p%20?o%20}"
+            "http://localhost/query?query=SELECT%20?s%20WHERE%20%7B%20VALUES%20?s%20%7B%201%202%20%7D%20%7D&default-graph-uri=http://example.com/g"
                 .parse()
                 .unwrap(),
         )
-        .with_header(HeaderName::ACCEPT, "application/foo")
+        .with_header(HeaderName::from_str("x-forwarded-for").unwrap(), "192.0.2.1, 10.0.0.1")
         .unwrap()
         .build();
-        ServerTest::new().test_status(request, Status::NOT_ACCEPTABLE);
+        server.test_status(request, Status::OK);
+        drop(server);
+
+        let access_log = fs::read_to_string(&access_log_file).unwrap();
+        assert!(access_log.starts_with("192.0.2.1 - - ["), "{}", access_log);
+        assert!(
+            access_log.ends_with(" HTTP/1.1\" 200 -\n"),
+            "{}",
+            access_log
+        );
+        let slow_query_log = fs::read_to_string(&slow_query_log_file).unwrap();
+        assert!(
+            slow_query_log.contains("\"client\":\"192.0.2.1\""),
+            "{}",
+            slow_query_log
+        );
+        assert!(
+            slow_query_log.contains("\"default_graph\":[\"<http://example.com/g>\"]"),
+            "{}",
+            slow_query_log
+        );
+        assert!(
+            slow_query_log.contains("\"result_count\":2"),
+            "{}",
+            slow_query_log
+        );
+        fs::remove_file(access_log_file).unwrap();
+        fs::remove_file(slow_query_log_file).unwrap();
     }
 
     #[test]
-    fn get_bad_query() {
-        ServerTest::new().test_status(
+    fn query_limits() {
+        let query = || {
             Request::builder(
                 Method::GET,
-                "http://localhost/query?query=SELECT".parse().unwrap(),
+                "http://localhost/query?query=SELECT%20*%20WHERE%20%7B%20?s%20?p%20?o%20%7D"
+                    .parse()
+                    .unwrap(),
             )
-            .build(),
-            Status::BAD_REQUEST,
-        );
-    }
-
-    #[test]
-    fn get_query_union_graph() {
-        let server = ServerTest::new();
+            .build()
+        };
 
-        let request = Request::builder(Method::PUT, "http://localhost/store/1".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
+        // Concurrency
+        let mut server = ServerTest::new();
+        server.state.limits = Limits::new(Some(1), 0, None, ClientIdentification::default());
+        let running = server.exec(query());
+        assert_eq!(running.status(), Status::OK);
+        server.test_status(query(), Status::SERVICE_UNAVAILABLE);
+        server.test_status(
+            Request::builder(Method::POST, "http://localhost/jobs".parse().unwrap())
+                .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
+                .unwrap()
+                .with_body("ASK {}"),
+            Status::SERVICE_UNAVAILABLE,
+        );
+        server.test_status(
+            Request::builder(
+                Method::PATCH,
+                "http://localhost/store?default".parse().unwrap(),
+            )
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
             .unwrap()
-            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
-        server.test_status(request, Status::CREATED);
+            .with_body("INSERT DATA { <http://example.com> <http://example.com> 1 }"),
+            Status::SERVICE_UNAVAILABLE,
+        );
+        drop(running);
+        server.test_status(query(), Status::OK);
 
-        let request = Request::builder(
-            Method::GET,
-            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}&union-default-graph"
-                .parse()
-                .unwrap(),
-        ).with_header(HeaderName::ACCEPT, "text/csv")
+        // Rate
+        let mut server = ServerTest::new();
+        server.state.limits = Limits::new(
+            None,
+            0,
+            Some(2),
+            ClientIdentification::new(vec!["foo".to_owned()], true),
+        );
+        let forwarded_query = |forwarded_for: &str| {
+            Request::builder(
+                Method::GET,
+                "http://localhost/query?query=ASK%20%7B%7D".parse().unwrap(),
+            )
+            .with_header(
+                HeaderName::from_str("x-forwarded-for").unwrap(),
+                forwarded_for,
+            )
             .unwrap()
-            .build();
-        server.test_body(
-            request,
-            "s,p,o\r\nhttp://example.com,http://example.com,http://example.com",
+            .build()
+        };
+        server.test_status(forwarded_query("192.0.2.1"), Status::OK);
+        server.test_status(forwarded_query("192.0.2.2, 192.0.2.1"), Status::OK);
+        let response = server.exec(forwarded_query("192.0.2.3, 192.0.2.1"));
+        assert_eq!(response.status(), Status::TOO_MANY_REQUESTS);
+        assert!(response.header(&HeaderName::RETRY_AFTER).is_some());
+        server.test_status(forwarded_query("192.0.2.2"), Status::OK);
+        let api_key_query = |api_key: &str| {
+            Request::builder(
+                Method::GET,
+                "http://localhost/query?query=ASK%20%7B%7D".parse().unwrap(),
+            )
+            .with_header(HeaderName::from_str("x-api-key").unwrap(), api_key)
+            .unwrap()
+            .build()
+        };
+        server.test_status(api_key_query("foo"), Status::OK);
+        server.test_status(api_key_query("bar"), Status::UNAUTHORIZED);
+
+        // Without trusted proxy
+        let mut server = ServerTest::new();
+        server.state.limits = Limits::new(
+            None,
+            0,
+            Some(2),
+            ClientIdentification::new(vec!["foo".to_owned()], false),
         );
+        server.test_status(query(), Status::UNAUTHORIZED);
+        server.test_status(api_key_query("foo"), Status::OK);
     }
 
     #[test]
-    fn get_query_union_graph_in_url_and_urlencoded() {
-        let server = ServerTest::new();
+    fn backups() {
+        let backup = || admin_request(Method::POST, "http://localhost/backup");
 
-        let request = Request::builder(Method::PUT, "http://localhost/store/1".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "text/turtle")
-            .unwrap()
-            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
-        server.test_status(request, Status::CREATED);
+        // Disabled
+        ServerTest::new().test_status(backup(), Status::NOT_FOUND);
+        ServerTest::new_read_only().test_status(backup(), Status::FORBIDDEN);
 
-        let request = Request::builder(
-            Method::POST,
-            "http://localhost/query?union-default-graph"
-                .parse()
-                .unwrap(),
-        )
-        .with_header(
-            HeaderName::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
-        )
-        .unwrap()
-        .with_header(HeaderName::ACCEPT, "text/csv")
-        .unwrap()
-        .with_body("query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}");
-        server.test_body(
-            request,
-            "s,p,o\r\nhttp://example.com,http://example.com,http://example.com",
+        // Checkpoints with retention
+        let store_directory = temp_dir().join(format!("oxigraph-store-{}", random::<u128>()));
+        let backup_directory = temp_dir().join(format!("oxigraph-backups-{}", random::<u128>()));
+        let mut server = ServerTest::new();
+        server.state.store = Store::open(&store_directory).unwrap();
+        server.state.backups = Some(Arc::new(
+            Backups::new(backup_directory.clone(), Some(1), false).unwrap(),
+        ));
+        server.test_status(
+            Request::builder(Method::GET, "http://localhost/backup".parse().unwrap()).build(),
+            Status::METHOD_NOT_ALLOWED,
+        );
+        server.test_status(
+            Request::builder(Method::POST, "http://localhost/backup".parse().unwrap()).build(),
+            Status::UNAUTHORIZED,
         );
+        server.test_status(backup(), Status::NO_CONTENT);
+        std::thread::sleep(Duration::from_millis(2));
+        server.test_status(backup(), Status::NO_CONTENT);
+        assert_eq!(fs::read_dir(&backup_directory).unwrap().count(), 1);
+        fs::remove_dir_all(&backup_directory).unwrap();
+        drop(server);
+        fs::remove_dir_all(&store_directory).unwrap();
     }
 
     #[test]
-    fn get_query_union_graph_and_default_graph() {
-        ServerTest::new().test_status(Request::builder(
-            Method::GET,
-            "http://localhost/query?query=SELECT%20*%20WHERE%20{%20?s%20?p%20?o%20}&union-default-graph&default-graph-uri=http://example.com".parse()
-                .unwrap(),
-        ).build(), Status::BAD_REQUEST);
+    fn shutdown_waits_for_streamed_responses() {
+        let server = ServerTest::new();
+        let response = server.exec(
+            Request::builder(
+                Method::GET,
+                "http://localhost/query?query=SELECT%20*%20WHERE%20%7B%20?s%20?p%20?o%20%7D"
+                    .parse()
+                    .unwrap(),
+            )
+            .build(),
+        );
+        assert_eq!(response.status(), Status::OK);
+        assert!(!server.state.lifecycle.shutdown(Duration::from_millis(10)));
+        drop(response);
+        assert!(server.state.lifecycle.shutdown(Duration::from_secs(1)));
     }
 
     #[test]
-    fn get_without_query() {
-        ServerTest::new().test_status(
-            Request::builder(Method::GET, "http://localhost/query".parse().unwrap()).build(),
-            Status::BAD_REQUEST,
-        );
+    fn shutdown_removes_the_job_results() {
+        let directory = temp_dir().join(format!("oxigraph-jobs-{:x}", random::<u128>()));
+        let mut server = ServerTest::new();
+        server.state.jobs = Jobs::new(directory.clone(), 1).unwrap();
+        assert!(directory.exists());
+        shutdown(&server.state);
+        assert!(!directory.exists());
     }
 
     #[test]
-    fn post_query() {
-        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
-            .unwrap()
-            .with_body("SELECT * WHERE { ?s ?p ?o }");
-        ServerTest::new().test_status(request, Status::OK)
+    fn health_and_readiness() {
+        let server = ServerTest::new();
+        let probe = |path: &str| {
+            Request::builder(
+                Method::GET,
+                format!("http://localhost{}", path).parse().unwrap(),
+            )
+            .build()
+        };
+        server.test_status(probe("/health"), Status::OK);
+        server.test_status(probe("/ready"), Status::OK);
+
+        assert!(server.state.lifecycle.shutdown(Duration::from_secs(1)));
+        server.test_status(probe("/health"), Status::OK);
+        server.test_status(probe("/ready"), Status::SERVICE_UNAVAILABLE);
+        server.test_status(
+            probe("/query?query=ASK%20%7B%7D"),
+            Status::SERVICE_UNAVAILABLE,
+        );
     }
 
     #[test]
-    fn post_bad_query() {
-        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
-            .unwrap()
-            .with_body("SELECT");
-        ServerTest::new().test_status(request, Status::BAD_REQUEST)
+    fn load_files_listing() -> io::Result<()> {
+        let directory = temp_dir().join(format!("oxigraph-load-{}", random::<u128>()));
+        fs::create_dir_all(directory.join("sub"))?;
+        fs::write(directory.join("a.nt.bz2"), "")?;
+        fs::write(directory.join("sub").join("b.ttl"), "")?;
+        fs::write(directory.join("README"), "")?;
+        fs::write(directory.join(".hidden.nt"), "")?;
+        assert_eq!(
+            files_to_load(vec![directory.clone(), PathBuf::from(STDIN_FILE)], false)?,
+            vec![
+                directory.join("a.nt.bz2"),
+                directory.join("sub").join("b.ttl"),
+                PathBuf::from(STDIN_FILE)
+            ]
+        );
+        assert_eq!(files_to_load(vec![directory.clone()], true)?.len(), 3);
+        assert!(matches!(
+            guess_load_format(&split_compression_extension(&directory.join("a.nq.zst")).0)?,
+            LoadFormat::Dataset(DatasetFormat::NQuads)
+        ));
+        fs::remove_dir_all(&directory)
     }
 
     #[test]
-    fn post_unknown_query() {
-        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-todo")
-            .unwrap()
-            .with_body("SELECT");
-        ServerTest::new().test_status(request, Status::UNSUPPORTED_MEDIA_TYPE)
+    fn cli_update_query_and_dump() -> io::Result<()> {
+        let directory = temp_dir().join(format!("oxigraph-cli-{}", random::<u128>()));
+        let location = directory.join("store");
+        fs::create_dir_all(&directory)?;
+        let triple = "<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n";
+
+        // Update
+        cli(
+            &location,
+            &["update", "--update", "INSERT DATA { GRAPH <http://example.com/g> { <http://example.com/s> <http://example.com/p> <http://example.com/o> } }"],
+        )?;
+        let update_file = directory.join("update.ru.gz");
+        let mut encoder = GzEncoder::new(File::create(&update_file)?, Compression::default());
+        encoder.write_all(b"ADD <http://example.com/g> TO DEFAULT")?;
+        encoder.finish()?;
+        cli(
+            &location,
+            &["update", "--update-file", update_file.to_str().unwrap()],
+        )?;
+        assert!(cli(&location, &["update", "--update", "INSERT"]).is_err());
+
+        // Query
+        let results_file = directory.join("results.csv");
+        cli(
+            &location,
+            &[
+                "query",
+                "--query",
+                "SELECT ?s WHERE { ?s ?p ?o }",
+                "--results-file",
+                results_file.to_str().unwrap(),
+            ],
+        )?;
+        assert_eq!(
+            fs::read_to_string(&results_file)?,
+            "s\r\nhttp://example.com/s"
+        );
+        let results_file = directory.join("results.nt.zst");
+        cli(
+            &location,
+            &[
+                "query",
+                "--query",
+                "CONSTRUCT WHERE { ?s ?p ?o }",
+                "--results-file",
+                results_file.to_str().unwrap(),
+            ],
+        )?;
+        assert_eq!(read_decompressed(&results_file)?, triple);
+
+        // Dump
+        let dump_file = directory.join("dump.nq.bz2");
+        cli(&location, &["dump", "--file", dump_file.to_str().unwrap()])?;
+        assert_eq!(
+            read_decompressed(&dump_file)?,
+            format!(
+                "{}<http://example.com/s> <http://example.com/p> <http://example.com/o> <http://example.com/g> .\n",
+                triple
+            )
+        );
+        let dump_file = directory.join("dump.nt.xz");
+        cli(
+            &location,
+            &[
+                "dump",
+                "--file",
+                dump_file.to_str().unwrap(),
+                "--graph",
+                "http://example.com/g",
+            ],
+        )?;
+        assert_eq!(read_decompressed(&dump_file)?, triple);
+        assert!(cli(
+            &location,
+            &[
+                "dump",
+                "--file",
+                dump_file.to_str().unwrap(),
+                "--graph",
+                "http://example.com/unknown",
+            ],
+        )
+        .is_err());
+        assert!(cli(&location, &["dump"]).is_err());
+
+        fs::remove_dir_all(&directory)
     }
 
     #[test]
-    fn post_federated_query() {
-        let request = Request::builder(Method::POST, "http://localhost/query".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-query")
-            .unwrap().with_body("SELECT * WHERE { SERVICE <https://query.wikidata.org/sparql> { <https://en.wikipedia.org/wiki/Paris> ?p ?o } }");
-        ServerTest::new().test_status(request, Status::OK)
+    fn cli_load_with_checkpoint() -> io::Result<()> {
+        let directory = temp_dir().join(format!("oxigraph-cli-{}", random::<u128>()));
+        let location = directory.join("store");
+        fs::create_dir_all(&directory)?;
+        let file = directory.join("data.nt");
+        fs::write(
+            &file,
+            "<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n",
+        )?;
+        let file = file.to_str().unwrap();
+
+        cli(&location, &["load", "--file", file])?;
+        cli(&location, &["load", "--file", file, "--checkpoint"])?;
+        cli(&location, &["load", "--file", file, "--resume"])?;
+        assert!(cli(
+            &location,
+            &["load", "--file", "-", "--format", "nt", "--checkpoint"]
+        )
+        .is_err());
+        let dump_file = directory.join("dump.nt");
+        cli(&location, &["dump", "--file", dump_file.to_str().unwrap()])?;
+        assert_eq!(
+            fs::read_to_string(&dump_file)?,
+            "<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n"
+        );
+
+        fs::remove_dir_all(&directory)
     }
 
-    #[test]
-    fn post_update() {
-        let request = Request::builder(Method::POST, "http://localhost/update".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
-            .unwrap()
-            .with_body(
-                "INSERT DATA { <http://example.com> <http://example.com> <http://example.com> }",
-            );
-        ServerTest::new().test_status(request, Status::NO_CONTENT)
+    /// Runs the command line with the given arguments against the store at the given location
+    fn cli(location: &Path, arguments: &[&str]) -> io::Result<()> {
+        let mut all_arguments = vec![
+            OsString::from("oxigraph_server"),
+            OsString::from("--location"),
+            OsString::from(location),
+        ];
+        all_arguments.extend(arguments.iter().map(OsString::from));
+        run(Arguments::try_parse_from(all_arguments)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?)
     }
 
-    #[test]
-    fn post_bad_update() {
-        let request = Request::builder(Method::POST, "http://localhost/update".parse().unwrap())
-            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")
-            .unwrap()
-            .with_body("INSERT");
-        ServerTest::new().test_status(request, Status::BAD_REQUEST)
+    fn read_decompressed(file: &Path) -> io::Result<String> {
+        let mut content = String::new();
+        open_decompressed(file)?.1.read_to_string(&mut content)?;
+        Ok(content)
     }
 
     #[test]
@@ -1632,19 +4377,54 @@ mod tests {
         server.test_status(request, Status::OK);
     }
 
+    fn admin_request(method: Method, url: &str) -> Request {
+        Request::builder(method, url.parse().unwrap())
+            .with_header(HeaderName::AUTHORIZATION, "Bearer admin")
+            .unwrap()
+            .build()
+    }
+
     struct ServerTest {
-        store: Store,
+        state: ServerState,
     }
 
     impl ServerTest {
         fn new() -> Self {
+            Self::with_options(false, false)
+        }
+
+        fn new_read_only() -> Self {
+            Self::with_options(true, false)
+        }
+
+        fn new_linked_data() -> Self {
+            Self::with_options(false, true)
+        }
+
+        fn with_options(read_only: bool, linked_data: bool) -> Self {
             Self {
-                store: Store::new().unwrap(),
+                state: ServerState {
+                    store: Store::new().unwrap(),
+                    datasets: Datasets::default(),
+                    read_only,
+                    linked_data,
+                    jobs: Jobs::new(
+                        temp_dir().join(format!("oxigraph-jobs-{:x}", random::<u128>())),
+                        1,
+                    )
+                    .unwrap(),
+                    transactions: Transactions::new(2),
+                    logger: Arc::new(Logger::disabled()),
+                    limits: Limits::default(),
+                    backups: None,
+                    lifecycle: Arc::default(),
+                    admin_token: Some("admin".to_owned()),
+                },
             }
         }
 
         fn exec(&self, mut request: Request) -> Response {
-            handle_request(&mut request, self.store.clone())
+            handle_request(&mut request, &self.state)
         }
 
         fn test_status(&self, request: Request, expected_status: Status) {
@@ -0,0 +1,324 @@
+//! A parser for the [RDF Patch](https://afs.github.io/rdf-delta/rdf-patch.html) format.
+
+use oxigraph::model::{BlankNode, GraphName, Literal, NamedNode, Quad, Subject, Term};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A change described by an RDF Patch row.
+pub enum RdfPatchChange {
+    Add(Quad),
+    Delete(Quad),
+}
+
+/// An error raised while parsing an RDF Patch document.
+#[derive(Debug)]
+pub struct RdfPatchSyntaxError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for RdfPatchSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RDF Patch syntax error on line {}: {}",
+            self.line, self.message
+        )
+    }
+}
+
+impl Error for RdfPatchSyntaxError {}
+
+/// Parses an RDF Patch document into the list of changes it describes.
+///
+/// Headers are ignored and the changes done in an aborted transaction (i.e. between `TX` and `TA`) are dropped.
+/// Triples are added to or removed from the default graph.
+pub fn parse_rdf_patch(data: &str) -> Result<Vec<RdfPatchChange>, RdfPatchSyntaxError> {
+    let mut parser = RdfPatchParser {
+        input: data,
+        line: 1,
+        prefixes: HashMap::new(),
+    };
+    let mut changes = Vec::new();
+    let mut transaction_start = None;
+    while let Some((code, mut arguments)) = parser.parse_row()? {
+        match code.as_str() {
+            "H" => (),
+            "TX" => transaction_start = Some(changes.len()),
+            "TC" => transaction_start = None,
+            "TA" => {
+                if let Some(start) = transaction_start.take() {
+                    changes.truncate(start);
+                } else {
+                    return Err(parser.error("TA row without a matching TX row"));
+                }
+            }
+            "PA" => {
+                if arguments.len() != 2 {
+                    return Err(parser.error("PA rows should have two arguments"));
+                }
+                let iri = match arguments.pop() {
+                    Some(Token::Iri(iri)) => iri,
+                    Some(Token::Literal(literal)) => literal.value().to_owned(),
+                    _ => return Err(parser.error("The PA row second argument should be an IRI")),
+                };
+                let prefix = parser.prefix_label(arguments.pop())?;
+                parser.prefixes.insert(prefix, iri);
+            }
+            "PD" => {
+                if arguments.len() != 1 {
+                    return Err(parser.error("PD rows should have one argument"));
+                }
+                let prefix = parser.prefix_label(arguments.pop())?;
+                parser.prefixes.remove(&prefix);
+            }
+            "A" => changes.push(RdfPatchChange::Add(parser.quad(arguments)?)),
+            "D" => changes.push(RdfPatchChange::Delete(parser.quad(arguments)?)),
+            _ => return Err(parser.error(format!("Unknown row code {}", code))),
+        }
+    }
+    Ok(changes)
+}
+
+enum Token {
+    Iri(String),
+    BlankNode(String),
+    Literal(Literal),
+    Word(String),
+    Dot,
+}
+
+struct RdfPatchParser<'a> {
+    input: &'a str,
+    line: usize,
+    prefixes: HashMap<String, String>,
+}
+
+impl<'a> RdfPatchParser<'a> {
+    fn error(&self, message: impl Into<String>) -> RdfPatchSyntaxError {
+        RdfPatchSyntaxError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the row code and its arguments or `None` at the end of the document.
+    fn parse_row(&mut self) -> Result<Option<(String, Vec<Token>)>, RdfPatchSyntaxError> {
+        let code = match self.next_token()? {
+            Some(Token::Word(code)) => code,
+            Some(_) => return Err(self.error("Each row should start with a code like A or D")),
+            None => return Ok(None),
+        };
+        let mut arguments = Vec::new();
+        loop {
+            match self.next_token()? {
+                Some(Token::Dot) => return Ok(Some((code, arguments))),
+                Some(token) => arguments.push(token),
+                None => return Err(self.error("Rows should end with a dot")),
+            }
+        }
+    }
+
+    fn prefix_label(&self, token: Option<Token>) -> Result<String, RdfPatchSyntaxError> {
+        match token {
+            Some(Token::Word(label)) => Ok(label.strip_suffix(':').unwrap_or(&label).to_owned()),
+            Some(Token::Literal(literal)) => Ok(literal.value().to_owned()),
+            _ => Err(self.error("Invalid prefix label")),
+        }
+    }
+
+    fn quad(&self, arguments: Vec<Token>) -> Result<Quad, RdfPatchSyntaxError> {
+        if arguments.len() != 3 && arguments.len() != 4 {
+            return Err(self.error("A and D rows should contain a triple or a quad"));
+        }
+        let mut arguments = arguments.into_iter();
+        let subject = match self.term(arguments.next().unwrap())? {
+            Term::NamedNode(node) => Subject::NamedNode(node),
+            Term::BlankNode(node) => Subject::BlankNode(node),
+            _ => return Err(self.error("Literals are not allowed in subject position")),
+        };
+        let predicate = match self.term(arguments.next().unwrap())? {
+            Term::NamedNode(node) => node,
+            _ => return Err(self.error("The predicate should be an IRI")),
+        };
+        let object = self.term(arguments.next().unwrap())?;
+        let graph_name = if let Some(graph_name) = arguments.next() {
+            match self.term(graph_name)? {
+                Term::NamedNode(node) => GraphName::NamedNode(node),
+                Term::BlankNode(node) => GraphName::BlankNode(node),
+                _ => return Err(self.error("Literals are not allowed in graph name position")),
+            }
+        } else {
+            GraphName::DefaultGraph
+        };
+        Ok(Quad::new(subject, predicate, object, graph_name))
+    }
+
+    fn term(&self, token: Token) -> Result<Term, RdfPatchSyntaxError> {
+        Ok(match token {
+            Token::Iri(iri) => NamedNode::new(iri)
+                .map_err(|e| self.error(e.to_string()))?
+                .into(),
+            Token::BlankNode(id) => BlankNode::new(id)
+                .map_err(|e| self.error(e.to_string()))?
+                .into(),
+            Token::Literal(literal) => literal.into(),
+            Token::Word(name) => self.prefixed_name(&name)?.into(),
+            Token::Dot => return Err(self.error("Unexpected dot")),
+        })
+    }
+
+    fn prefixed_name(&self, name: &str) -> Result<NamedNode, RdfPatchSyntaxError> {
+        let (prefix, local) = name
+            .split_once(':')
+            .ok_or_else(|| self.error(format!("Unexpected word {}", name)))?;
+        let namespace = self
+            .prefixes
+            .get(prefix)
+            .ok_or_else(|| self.error(format!("The prefix {} is not defined", prefix)))?;
+        NamedNode::new(format!("{}{}", namespace, local)).map_err(|e| self.error(e.to_string()))
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, RdfPatchSyntaxError> {
+        self.skip_whitespaces_and_comments();
+        let c = if let Some(c) = self.input.chars().next() {
+            c
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(match c {
+            '<' => {
+                let (iri, rest) = self.input[1..]
+                    .split_once('>')
+                    .ok_or_else(|| self.error("Unterminated IRI"))?;
+                let iri = unescape(iri).map_err(|e| self.error(e))?;
+                self.input = rest;
+                Token::Iri(iri)
+            }
+            '"' | '\'' => {
+                let value = self.read_string(c)?;
+                if let Some(rest) = self.input.strip_prefix('@') {
+                    let end = rest
+                        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                        .unwrap_or(rest.len());
+                    let language = &rest[..end];
+                    self.input = &rest[end..];
+                    Token::Literal(
+                        Literal::new_language_tagged_literal(value, language)
+                            .map_err(|e| self.error(e.to_string()))?,
+                    )
+                } else if let Some(rest) = self.input.strip_prefix("^^") {
+                    self.input = rest;
+                    let datatype = match self.next_token()? {
+                        Some(Token::Iri(iri)) => {
+                            NamedNode::new(iri).map_err(|e| self.error(e.to_string()))?
+                        }
+                        Some(Token::Word(name)) => self.prefixed_name(&name)?,
+                        _ => return Err(self.error("Invalid literal datatype")),
+                    };
+                    Token::Literal(Literal::new_typed_literal(value, datatype))
+                } else {
+                    Token::Literal(Literal::new_simple_literal(value))
+                }
+            }
+            '_' if self.input.starts_with("_:") => {
+                let word = self.read_word();
+                Token::BlankNode(word[2..].to_owned())
+            }
+            '.' => {
+                self.input = &self.input[1..];
+                Token::Dot
+            }
+            _ => Token::Word(self.read_word().to_owned()),
+        }))
+    }
+
+    fn skip_whitespaces_and_comments(&mut self) {
+        loop {
+            let trimmed = self.input.trim_start();
+            self.line += self.input[..self.input.len() - trimmed.len()]
+                .matches('\n')
+                .count();
+            self.input = trimmed;
+            if let Some(comment) = self.input.strip_prefix('#') {
+                self.input = comment.find('\n').map_or("", |end| &comment[end..]);
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Reads a word i.e. a row code, a blank node or a prefixed name.
+    ///
+    /// A final dot is not considered as part of the word.
+    fn read_word(&mut self) -> &'a str {
+        let mut end = self
+            .input
+            .find(|c: char| c.is_whitespace() || c == '<' || c == '"' || c == '\'')
+            .unwrap_or(self.input.len());
+        while end > 0 && self.input[..end].ends_with('.') {
+            end -= 1;
+        }
+        if end == 0 {
+            end = self.input.chars().next().map_or(0, char::len_utf8);
+        }
+        let (word, rest) = self.input.split_at(end);
+        self.input = rest;
+        word
+    }
+
+    fn read_string(&mut self, delimiter: char) -> Result<String, RdfPatchSyntaxError> {
+        let mut escaped = false;
+        for (i, c) in self.input.char_indices().skip(1) {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '\n' {
+                break;
+            } else if c == delimiter {
+                let value = unescape(&self.input[1..i]).map_err(|e| self.error(e))?;
+                self.input = &self.input[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(self.error("Unterminated string"))
+    }
+}
+
+/// Decodes the `ECHAR` and `UCHAR` escape sequences.
+fn unescape(value: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('b') => result.push('\u{8}'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('f') => result.push('\u{C}'),
+            Some('"') => result.push('"'),
+            Some('\'') => result.push('\''),
+            Some('\\') => result.push('\\'),
+            Some(c @ ('u' | 'U')) => {
+                let len = if c == 'u' { 4 } else { 8 };
+                let code: String = chars.by_ref().take(len).collect();
+                result.push(
+                    u32::from_str_radix(&code, 16)
+                        .ok()
+                        .filter(|_| code.len() == len)
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("Invalid escape sequence \\{}{}", c, code))?,
+                );
+            }
+            Some(c) => return Err(format!("Invalid escape sequence \\{}", c)),
+            None => return Err("Unterminated escape sequence".to_owned()),
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,351 @@
+//! Interactive transactions following the RDF4J REST API.
+//!
+//! A transaction is started with `POST /transactions` and is then driven with
+//! `PUT /transactions/{id}?action=ADD|DELETE|UPDATE|QUERY|SIZE|COMMIT` until it is committed
+//! or rolled back with `DELETE /transactions/{id}`.
+//! Transactions that are not used for [`TRANSACTION_TIMEOUT`] are rolled back.
+//! If a transaction conflicts with a concurrent one it is rolled back and `409 Conflict` is returned.
+
+use crate::datasets::DatasetLease;
+use crate::{
+    bad_request, content_type, error, evaluate_sparql_query, handle_sparql_update_request,
+    read_sparql_query_request, resolve_with_base, storage_error, unsupported_media_type,
+    ServerState,
+};
+use oxhttp::model::{HeaderName, Request, Response, Status};
+use oxigraph::io::read::ParseError;
+use oxigraph::io::{DatasetFormat, DatasetParser, GraphFormat, GraphParser};
+use oxigraph::model::{GraphName, Quad};
+use oxigraph::sparql::{EvaluationError, QueryResults, QuerySolutionIter, QueryTripleIter};
+use oxigraph::store::{InteractiveTransaction, LoaderError, StorageError, Store, Transaction};
+use rand::random;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::form_urlencoded;
+
+/// Duration after which an unused transaction is rolled back.
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The open transactions of the server.
+pub struct Transactions {
+    transactions: Mutex<HashMap<String, Arc<Transaction>>>,
+    /// Maximal number of open transactions, each of them keeps a snapshot of the store.
+    max_open: usize,
+}
+
+impl Transactions {
+    pub fn new(max_open: usize) -> Self {
+        Self {
+            transactions: Mutex::default(),
+            max_open,
+        }
+    }
+
+    /// Starts a new transaction on the dataset served under `prefix` and returns its id.
+    ///
+    /// Returns `None` if too many transactions are already open.
+    fn start(
+        &self,
+        store: &Store,
+        lease: Option<DatasetLease>,
+        prefix: &str,
+    ) -> Result<Option<String>, StorageError> {
+        let mut transactions = self.transactions.lock().unwrap();
+        transactions.retain(|_, transaction| !transaction.is_expired());
+        if transactions.len() >= self.max_open {
+            return Ok(None);
+        }
+        let id = format!("{:x}", random::<u128>());
+        let transaction = Arc::new(Transaction {
+            prefix: prefix.to_owned(),
+            inner: Mutex::new(Some(store.start_transaction()?)),
+            last_use: Mutex::new(Instant::now()),
+            _lease: lease,
+        });
+        transactions.insert(id.clone(), transaction);
+        Ok(Some(id))
+    }
+
+    /// Returns the transaction with the given id if it has been started on the dataset served under `prefix`.
+    fn get(&self, id: &str, prefix: &str) -> Option<Arc<Transaction>> {
+        let transaction = self
+            .transactions
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|transaction| transaction.prefix == prefix && !transaction.is_expired())
+            .cloned()?;
+        *transaction.last_use.lock().unwrap() = Instant::now();
+        Some(transaction)
+    }
+
+    /// Rolls back the transactions that have not been used for [`TRANSACTION_TIMEOUT`].
+    pub fn remove_expired(&self) {
+        self.transactions
+            .lock()
+            .unwrap()
+            .retain(|_, transaction| !transaction.is_expired());
+    }
+
+    /// Removes a transaction and returns it if it has not ended yet.
+    fn remove(&self, id: &str, prefix: &str) -> Option<InteractiveTransaction> {
+        let mut transactions = self.transactions.lock().unwrap();
+        match transactions.get(id) {
+            Some(transaction) if transaction.prefix == prefix => {
+                let transaction = transactions.remove(id)?;
+                let inner = transaction.inner.lock().unwrap().take();
+                inner
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Transaction {
+    /// Path prefix of the dataset the transaction has been started on.
+    prefix: String,
+    /// `None` if the transaction has ended.
+    inner: Mutex<Option<InteractiveTransaction>>,
+    last_use: Mutex<Instant>,
+    /// Released after the transaction is dropped.
+    _lease: Option<DatasetLease>,
+}
+
+impl Transaction {
+    fn is_expired(&self) -> bool {
+        self.last_use.lock().unwrap().elapsed() > TRANSACTION_TIMEOUT
+    }
+}
+
+/// Handles the requests to `/transactions` and `/transactions/{id}` of the dataset served under `prefix`.
+pub fn handle_transactions_request(
+    request: &mut Request,
+    path: &str,
+    prefix: &str,
+    store: Store,
+    lease: Option<DatasetLease>,
+    state: &ServerState,
+) -> Response {
+    let transactions = &state.transactions;
+    if path == "/transactions" {
+        return if request.method().as_ref() == "POST" {
+            let _permit = match state.limits.acquire(request) {
+                Ok(permit) => permit,
+                Err(response) => return response,
+            };
+            match transactions.start(&store, lease, prefix) {
+                Ok(Some(id)) => Response::builder(Status::CREATED)
+                    .with_header(
+                        HeaderName::LOCATION,
+                        format!("{}/transactions/{}", prefix, id),
+                    )
+                    .unwrap()
+                    .build(),
+                Ok(None) => error(
+                    Status::SERVICE_UNAVAILABLE,
+                    "Too many transactions are open, please retry later",
+                ),
+                Err(e) => storage_error(e),
+            }
+        } else {
+            error(
+                Status::METHOD_NOT_ALLOWED,
+                format!(
+                    "{} /transactions is not supported by this server",
+                    request.method()
+                ),
+            )
+        };
+    }
+    let id = path.trim_start_matches("/transactions/");
+    match request.method().as_ref() {
+        "DELETE" => match transactions.remove(id, prefix) {
+            Some(transaction) => match transaction.rollback() {
+                Ok(()) => Response::builder(Status::NO_CONTENT).build(),
+                Err(e) => storage_error(e),
+            },
+            None => transaction_not_found(id),
+        },
+        "PUT" | "POST" => {
+            let action = match action(request) {
+                Ok(action) => action,
+                Err(response) => return response,
+            };
+            if action == "COMMIT" {
+                return match transactions.remove(id, prefix) {
+                    Some(transaction) => match transaction.commit() {
+                        Ok(()) => Response::builder(Status::OK).build(),
+                        Err(e) => storage_error(e),
+                    },
+                    None => transaction_not_found(id),
+                };
+            }
+            let transaction = match transactions.get(id, prefix) {
+                Some(transaction) => transaction,
+                None => return transaction_not_found(id),
+            };
+            let mut guard = transaction.inner.lock().unwrap();
+            let mut inner = match guard.as_ref() {
+                Some(inner) => inner.transaction(),
+                None => return transaction_not_found(id),
+            };
+            let response = match action.as_str() {
+                "ADD" => add(request, &mut inner),
+                "DELETE" => delete(request, &mut inner),
+                "UPDATE" => {
+                    handle_sparql_update_request(request, state, |update| inner.update(update))
+                }
+                "QUERY" => match read_sparql_query_request(request) {
+                    Ok(query) => evaluate_sparql_query(query, request, state, |query| {
+                        materialize(inner.query(query)?)
+                    }),
+                    Err(response) => response,
+                },
+                "SIZE" => match inner.len() {
+                    Ok(size) => Response::builder(Status::OK)
+                        .with_header(HeaderName::CONTENT_TYPE, "text/plain; charset=utf-8")
+                        .unwrap()
+                        .with_body(size.to_string()),
+                    Err(e) => storage_error(e),
+                },
+                action => bad_request(format!("Unsupported transaction action: {}", action)),
+            };
+            drop(inner);
+            if response.status() == Status::CONFLICT {
+                // The transaction can't be committed anymore
+                *guard = None;
+                drop(guard);
+                transactions.remove(id, prefix);
+            }
+            response
+        }
+        method => error(
+            Status::METHOD_NOT_ALLOWED,
+            format!("{} {} is not supported by this server", method, path),
+        ),
+    }
+}
+
+/// Evaluates the query results completely.
+///
+/// The results of a transaction query can only be read while the transaction is locked.
+/// They would fail if the transaction is committed or rolled back while they are streamed.
+fn materialize(results: QueryResults) -> Result<QueryResults, EvaluationError> {
+    Ok(match results {
+        QueryResults::Solutions(solutions) => {
+            let variables = Arc::new(solutions.variables().to_vec());
+            let solutions = solutions
+                .map(|solution| Ok(solution?.values().to_vec()))
+                .collect::<Result<Vec<_>, EvaluationError>>()?;
+            QueryResults::Solutions(QuerySolutionIter::new(
+                variables,
+                solutions.into_iter().map(Ok),
+            ))
+        }
+        QueryResults::Boolean(result) => QueryResults::Boolean(result),
+        QueryResults::Graph(triples) => QueryResults::Graph(QueryTripleIter::new(
+            triples.collect::<Result<Vec<_>, _>>()?.into_iter().map(Ok),
+        )),
+    })
+}
+
+/// Reads the mandatory `action` URL query parameter.
+fn action(request: &Request) -> Result<String, Response> {
+    form_urlencoded::parse(request.url().query().unwrap_or("").as_bytes())
+        .find(|(k, _)| k == "action")
+        .map(|(_, v)| v.to_ascii_uppercase())
+        .ok_or_else(|| bad_request("The action parameter is required"))
+}
+
+/// Reads the optional `graph` URL query parameter used to target a graph with ADD and DELETE.
+fn target_graph(request: &Request) -> Result<GraphName, Response> {
+    match form_urlencoded::parse(request.url().query().unwrap_or("").as_bytes())
+        .find(|(k, _)| k == "graph")
+    {
+        Some((_, graph)) => Ok(resolve_with_base(request, &graph)?.into()),
+        None => Ok(GraphName::DefaultGraph),
+    }
+}
+
+/// Adds the RDF data in the request body to the transaction.
+fn add(request: &mut Request, transaction: &mut Transaction<'_>) -> Response {
+    let content_type = match content_type(request) {
+        Some(content_type) => content_type,
+        None => return bad_request("No Content-Type given"),
+    };
+    let result = if let Some(format) = GraphFormat::from_media_type(&content_type) {
+        let graph_name = match target_graph(request) {
+            Ok(graph_name) => graph_name,
+            Err(response) => return response,
+        };
+        transaction.load_graph(
+            BufReader::new(request.body_mut()),
+            format,
+            &graph_name,
+            None,
+        )
+    } else if let Some(format) = DatasetFormat::from_media_type(&content_type) {
+        transaction.load_dataset(BufReader::new(request.body_mut()), format, None)
+    } else {
+        return unsupported_media_type(&content_type);
+    };
+    match result {
+        Ok(()) => Response::builder(Status::NO_CONTENT).build(),
+        Err(e) => loader_error(e),
+    }
+}
+
+/// Removes the RDF data in the request body from the transaction.
+fn delete(request: &mut Request, transaction: &mut Transaction<'_>) -> Response {
+    let content_type = match content_type(request) {
+        Some(content_type) => content_type,
+        None => return bad_request("No Content-Type given"),
+    };
+    let quads: Box<dyn Iterator<Item = Result<Quad, ParseError>> + '_> = if let Some(format) =
+        GraphFormat::from_media_type(&content_type)
+    {
+        let graph_name = match target_graph(request) {
+            Ok(graph_name) => graph_name,
+            Err(response) => return response,
+        };
+        match GraphParser::from_format(format).read_triples(BufReader::new(request.body_mut())) {
+            Ok(triples) => {
+                Box::new(triples.map(move |t| t.map(|t| t.in_graph(graph_name.clone()))))
+            }
+            Err(e) => return bad_request(e),
+        }
+    } else if let Some(format) = DatasetFormat::from_media_type(&content_type) {
+        match DatasetParser::from_format(format).read_quads(BufReader::new(request.body_mut())) {
+            Ok(quads) => Box::new(quads),
+            Err(e) => return bad_request(e),
+        }
+    } else {
+        return unsupported_media_type(&content_type);
+    };
+    for quad in quads {
+        let quad = match quad {
+            Ok(quad) => quad,
+            Err(e) => return bad_request(e),
+        };
+        if let Err(e) = transaction.remove(&quad) {
+            return storage_error(e);
+        }
+    }
+    Response::builder(Status::NO_CONTENT).build()
+}
+
+fn loader_error(e: LoaderError) -> Response {
+    match e {
+        LoaderError::Parsing(e) => bad_request(e),
+        LoaderError::Storage(e) => storage_error(e),
+    }
+}
+
+fn transaction_not_found(id: &str) -> Response {
+    error(
+        Status::NOT_FOUND,
+        format!("The transaction {} does not exist or has ended", id),
+    )
+}